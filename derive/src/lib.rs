@@ -0,0 +1,328 @@
+//! Derive macros for code-agent-sdk.
+//!
+//! `#[derive(ToolSchema)]` implements `code_agent_sdk::mcp::ToolSchema`,
+//! generating the JSON Schema of a type from its definition. See that trait
+//! for the supported shapes and attributes.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, Fields, GenericParam, LitStr, Type, parse_macro_input,
+    parse_quote,
+};
+
+/// Derive `code_agent_sdk::mcp::ToolSchema`.
+#[proc_macro_derive(ToolSchema, attributes(schema))]
+pub fn derive_tool_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let doc = doc_comment(&input.attrs);
+    let body = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let mut props = Vec::new();
+                for field in &fields.named {
+                    let attrs = SerdeAttrs::parse(&field.attrs)?;
+                    if attrs.skip {
+                        continue;
+                    }
+                    let ident = field.ident.as_ref().expect("named field");
+                    let name = attrs.rename.clone().unwrap_or_else(|| {
+                        container.rename_rule(ident.to_string().trim_start_matches("r#"))
+                    });
+                    let ty = &field.ty;
+                    if attrs.flatten {
+                        if container.deny_unknown_fields {
+                            return Err(syn::Error::new_spanned(
+                                field,
+                                "serde does not support `flatten` together with `deny_unknown_fields`",
+                            ));
+                        }
+                        props.push(quote! {
+                            let inner = <#ty as __sdk::mcp::ToolSchema>::schema();
+                            if let Some(__sdk::__private::serde_json::Value::Object(p)) =
+                                inner.get("properties")
+                            {
+                                properties.extend(p.clone());
+                            }
+                            if let Some(__sdk::__private::serde_json::Value::Array(r)) =
+                                inner.get("required")
+                            {
+                                required.extend(r.iter().filter_map(|v| v.as_str()).map(str::to_string));
+                            }
+                        });
+                        continue;
+                    }
+                    let optional = container.default || attrs.default || is_option(ty);
+                    let keywords = schema_keywords(&field.attrs)?;
+                    let describe = doc_comment(&field.attrs).map(|doc| {
+                        quote! { field["description"] = __sdk::__private::serde_json::json!(#doc); }
+                    });
+                    let require = (!optional).then(|| quote! { required.push(#name.to_string()); });
+                    props.push(quote! {
+                        let mut field = <#ty as __sdk::mcp::ToolSchema>::schema();
+                        #describe
+                        #(#keywords)*
+                        properties.insert(#name.to_string(), field);
+                        #require
+                    });
+                }
+                let deny_unknown = container.deny_unknown_fields.then(|| {
+                    quote! {
+                        schema["additionalProperties"] = __sdk::__private::serde_json::json!(false);
+                    }
+                });
+                quote! {
+                    let mut properties = __sdk::__private::serde_json::Map::new();
+                    let mut required: Vec<String> = Vec::new();
+                    #(#props)*
+                    let mut schema = __sdk::__private::serde_json::json!({
+                        "type": "object",
+                        "properties": properties,
+                    });
+                    if !required.is_empty() {
+                        schema["required"] = __sdk::__private::serde_json::json!(required);
+                    }
+                    #deny_unknown
+                }
+            }
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote! { let mut schema = <#ty as __sdk::mcp::ToolSchema>::schema(); }
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "ToolSchema can be derived for structs with named fields, newtype structs and enums of unit variants",
+                ));
+            }
+        },
+        Data::Enum(ref data) => {
+            let mut names = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "ToolSchema can only be derived for enums of unit variants",
+                    ));
+                }
+                let attrs = SerdeAttrs::parse(&variant.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                names.push(
+                    attrs
+                        .rename
+                        .unwrap_or_else(|| container.rename_rule(&variant.ident.to_string())),
+                );
+            }
+            quote! {
+                let mut schema = __sdk::__private::serde_json::json!({
+                    "type": "string",
+                    "enum": [#(#names),*],
+                });
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "ToolSchema cannot be derived for unions",
+            ));
+        }
+    };
+    let describe = doc.map(|doc| {
+        quote! { schema["description"] = __sdk::__private::serde_json::json!(#doc); }
+    });
+
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(param) = param {
+            param
+                .bounds
+                .push(parse_quote!(::code_agent_sdk::mcp::ToolSchema));
+        }
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        const _: () = {
+            use ::code_agent_sdk as __sdk;
+
+            impl #impl_generics __sdk::mcp::ToolSchema for #ident #ty_generics #where_clause {
+                #[allow(unused_mut)]
+                fn schema() -> __sdk::__private::serde_json::Value {
+                    #body
+                    #describe
+                    schema
+                }
+            }
+        };
+    })
+}
+
+/// The serde attributes that shape the schema.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+    deny_unknown_fields: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("rename_all") {
+                    parsed.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    parsed.default = true;
+                    skip_meta(&meta)?;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    parsed.flatten = true;
+                } else if meta.path.is_ident("deny_unknown_fields") {
+                    parsed.deny_unknown_fields = true;
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+
+    /// `name` under the container's `rename_all` rule.
+    fn rename_rule(&self, name: &str) -> String {
+        let Some(ref rule) = self.rename_all else {
+            return name.to_string();
+        };
+        let words = split_words(name);
+        let capitalize = |w: &String| {
+            let mut chars = w.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        };
+        match rule.as_str() {
+            "lowercase" => words.concat(),
+            "UPPERCASE" => words.concat().to_uppercase(),
+            "PascalCase" => words.iter().map(capitalize).collect(),
+            "camelCase" => words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+                .collect(),
+            "snake_case" => words.join("_"),
+            "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+            "kebab-case" => words.join("-"),
+            "SCREAMING-KEBAB-CASE" => words.join("-").to_uppercase(),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// Lowercase words of a snake_case or PascalCase identifier.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for c in name.chars() {
+        if c == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else if c.is_uppercase() && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+            current.extend(c.to_lowercase());
+        } else {
+            current.extend(c.to_lowercase());
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Consume the value or nested list of an attribute we don't interpret.
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested| skip_meta(&nested))?;
+    }
+    Ok(())
+}
+
+/// `#[schema(key = value)]` keywords added to a field's schema, with the
+/// key converted to camelCase: `#[schema(min_length = 1)]` sets
+/// `minLength`.
+fn schema_keywords(attrs: &[Attribute]) -> syn::Result<Vec<TokenStream2>> {
+    let mut keywords = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("schema")) {
+        attr.parse_nested_meta(|meta| {
+            let key = meta
+                .path
+                .get_ident()
+                .ok_or_else(|| meta.error("expected a keyword"))?
+                .to_string();
+            let key = SerdeAttrs {
+                rename_all: Some("camelCase".to_string()),
+                ..Default::default()
+            }
+            .rename_rule(&key);
+            let value: Expr = meta.value()?.parse()?;
+            keywords.push(quote! {
+                field[#key] = __sdk::__private::serde_json::json!(#value);
+            });
+            Ok(())
+        })?;
+    }
+    Ok(keywords)
+}
+
+/// The `///` comments of an item, or `None` if it has none.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match a.meta {
+            syn::Meta::NameValue(ref nv) => match nv.value {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(ref s),
+                    ..
+                }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
@@ -0,0 +1,265 @@
+//! Claude Code stream-json + control protocol.
+//!
+//! Answers the SDK's `initialize` and runtime control requests, starts a
+//! turn for every `user` message and issues `can_use_tool`, `hook_callback`
+//! and `mcp_message` control requests of its own when a step asks for them.
+
+use crate::scenario::{Scenario, Step};
+use crate::{Inbound, Io, merge};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const MODEL: &str = "fake-model";
+
+struct Claude<'a> {
+    scenario: &'a mut Scenario,
+    io: &'a mut Io,
+    session_id: String,
+    hooks: Value,
+    pending_users: VecDeque<Value>,
+    next_request: u64,
+    sent_init: bool,
+    interrupted: bool,
+}
+
+pub fn run(scenario: &mut Scenario, io: &mut Io) -> i32 {
+    let session_id = scenario.session_id_or("fake-session");
+    let mut claude = Claude {
+        scenario,
+        io,
+        session_id,
+        hooks: Value::Null,
+        pending_users: VecDeque::new(),
+        next_request: 0,
+        sent_init: false,
+        interrupted: false,
+    };
+    claude.serve()
+}
+
+impl Claude<'_> {
+    fn serve(&mut self) -> i32 {
+        loop {
+            let msg = match self.pending_users.pop_front() {
+                Some(m) => m,
+                None => match self.io.recv() {
+                    Some(m) => m,
+                    None => return 0,
+                },
+            };
+            match msg.get("type").and_then(Value::as_str) {
+                Some("control_request") => self.answer_control(&msg),
+                Some("user") => {
+                    if let Some(code) = self.run_turn(&msg) {
+                        return code;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn answer_control(&mut self, msg: &Value) {
+        let request_id = msg.get("request_id").cloned().unwrap_or(Value::Null);
+        let request = msg.get("request").cloned().unwrap_or(Value::Null);
+        let subtype = request.get("subtype").and_then(Value::as_str).unwrap_or("");
+
+        let response = match subtype {
+            "initialize" => {
+                self.hooks = request.get("hooks").cloned().unwrap_or(Value::Null);
+                self.scenario.init.clone().unwrap_or_else(
+                    || json!({"commands": [], "output_style": "default", "models": [MODEL]}),
+                )
+            }
+            "interrupt" => {
+                self.interrupted = true;
+                self.control_or_empty(subtype)
+            }
+            _ => self.control_or_empty(subtype),
+        };
+
+        self.io.send(&json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": request_id,
+                "response": response,
+            }
+        }));
+    }
+
+    fn control_or_empty(&self, subtype: &str) -> Value {
+        self.scenario
+            .control
+            .get(subtype)
+            .cloned()
+            .unwrap_or_else(|| json!({}))
+    }
+
+    /// Run one turn. Returns `Some(code)` when a step asks the CLI to exit.
+    fn run_turn(&mut self, user: &Value) -> Option<i32> {
+        let prompt = prompt_text(user);
+        let steps = self.scenario.take_turn(&prompt);
+        self.interrupted = false;
+
+        if !self.sent_init {
+            self.sent_init = true;
+            self.io.send(&json!({
+                "type": "system",
+                "subtype": "init",
+                "session_id": self.session_id,
+                "model": MODEL,
+                "tools": [],
+                "mcp_servers": [],
+            }));
+        }
+
+        let mut last_text = String::new();
+        for step in steps {
+            if self.interrupted {
+                break;
+            }
+            match step {
+                Step::Text(text) => {
+                    self.assistant(json!([{"type": "text", "text": text}]));
+                    last_text = text;
+                }
+                Step::Thinking(thinking) => self.assistant(json!([{
+                    "type": "thinking",
+                    "thinking": thinking,
+                    "signature": "fake-signature",
+                }])),
+                Step::ToolUse { id, name, input } => self.assistant(json!([{
+                    "type": "tool_use",
+                    "id": id,
+                    "name": name,
+                    "input": input,
+                }])),
+                Step::Request(request) => self.request(request),
+                Step::Hook {
+                    event,
+                    input,
+                    tool_use_id,
+                } => {
+                    for callback_id in self.hook_ids(&event) {
+                        self.request(json!({
+                            "subtype": "hook_callback",
+                            "callback_id": callback_id,
+                            "input": input,
+                            "tool_use_id": tool_use_id,
+                        }));
+                    }
+                }
+                Step::Result(overrides) => self.result(&last_text, &overrides),
+                Step::Emit(value) => self.io.send(&value),
+                Step::SleepMs(ms) => self.sleep(ms),
+                Step::Stderr(line) => eprintln!("{}", line),
+                Step::Exit(code) => return Some(code),
+            }
+        }
+
+        if self.interrupted {
+            let mut overrides = serde_json::Map::new();
+            overrides.insert("subtype".into(), json!("error_during_execution"));
+            overrides.insert("is_error".into(), json!(true));
+            self.result(&last_text, &overrides);
+        }
+        None
+    }
+
+    fn assistant(&self, content: Value) {
+        self.io.send(&json!({
+            "type": "assistant",
+            "message": {"role": "assistant", "model": MODEL, "content": content},
+            "session_id": self.session_id,
+            "parent_tool_use_id": null,
+        }));
+    }
+
+    fn result(&self, last_text: &str, overrides: &serde_json::Map<String, Value>) {
+        let base = json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": self.session_id,
+            "result": last_text,
+            "total_cost_usd": 0.0,
+            "usage": {"input_tokens": 10, "output_tokens": 5},
+        });
+        self.io.send(&merge(base, overrides));
+    }
+
+    fn hook_ids(&self, event: &str) -> Vec<String> {
+        self.hooks
+            .get(event)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.get("hookCallbackIds").and_then(Value::as_array))
+            .flatten()
+            .filter_map(|id| id.as_str().map(String::from))
+            .collect()
+    }
+
+    /// Send a control request and wait for the SDK's response, answering
+    /// the SDK's own control requests in the meantime.
+    fn request(&mut self, request: Value) {
+        let request_id = format!("cli_req_{}", self.next_request);
+        self.next_request += 1;
+        self.io.send(&json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": request,
+        }));
+
+        loop {
+            let Some(msg) = self.io.recv() else { return };
+            match msg.get("type").and_then(Value::as_str) {
+                Some("control_response") => {
+                    let answered = msg
+                        .get("response")
+                        .and_then(|r| r.get("request_id"))
+                        .and_then(Value::as_str)
+                        == Some(request_id.as_str());
+                    if answered {
+                        return;
+                    }
+                }
+                Some("control_request") => self.answer_control(&msg),
+                Some("user") => self.pending_users.push_back(msg),
+                _ => {}
+            }
+        }
+    }
+
+    /// Sleep while still answering control requests (so interrupts land).
+    fn sleep(&mut self, ms: u64) {
+        let deadline = Instant::now() + Duration::from_millis(ms);
+        while !self.interrupted {
+            match self.io.recv_until(Some(deadline)) {
+                Inbound::Line(msg) => match msg.get("type").and_then(Value::as_str) {
+                    Some("control_request") => self.answer_control(&msg),
+                    Some("user") => self.pending_users.push_back(msg),
+                    _ => {}
+                },
+                Inbound::Timeout | Inbound::Closed => break,
+            }
+        }
+    }
+}
+
+fn prompt_text(user: &Value) -> String {
+    match user.get("message").and_then(|m| m.get("content")) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,309 @@
+//! Codex `app-server` JSON-RPC 2.0 and `exec --json` protocols.
+
+use crate::scenario::{Scenario, Step};
+use crate::{Inbound, Io, merge, sleep_ms, trailing_prompt};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+struct AppServer<'a> {
+    scenario: &'a mut Scenario,
+    io: &'a mut Io,
+    thread_id: String,
+    pending: VecDeque<Value>,
+    next_request: u64,
+    next_item: u64,
+    next_turn: u64,
+    interrupted: bool,
+}
+
+pub fn run_app_server(scenario: &mut Scenario, io: &mut Io) -> i32 {
+    let thread_id = scenario.session_id_or("fake-thread");
+    let mut server = AppServer {
+        scenario,
+        io,
+        thread_id,
+        pending: VecDeque::new(),
+        next_request: 0,
+        next_item: 0,
+        next_turn: 0,
+        interrupted: false,
+    };
+    server.serve()
+}
+
+impl AppServer<'_> {
+    fn serve(&mut self) -> i32 {
+        loop {
+            let msg = match self.pending.pop_front() {
+                Some(m) => m,
+                None => match self.io.recv() {
+                    Some(m) => m,
+                    None => return 0,
+                },
+            };
+            if msg.get("id").is_none() || msg.get("method").is_none() {
+                // Notifications (`initialized`) and stray responses.
+                continue;
+            }
+            if msg.get("method").and_then(Value::as_str) == Some("turn/start") {
+                self.reply(
+                    &msg,
+                    json!({"turn": {"id": self.turn_id(), "status": "inProgress"}}),
+                );
+                if let Some(code) = self.run_turn(&msg) {
+                    return code;
+                }
+            } else {
+                self.answer(&msg);
+            }
+        }
+    }
+
+    fn turn_id(&self) -> String {
+        format!("turn-{}", self.next_turn)
+    }
+
+    fn reply(&self, request: &Value, result: Value) {
+        self.io.send(&json!({
+            "jsonrpc": "2.0",
+            "id": request.get("id").cloned().unwrap_or(Value::Null),
+            "result": result,
+        }));
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.io
+            .send(&json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+
+    /// Answer a client request other than `turn/start`.
+    fn answer(&mut self, msg: &Value) {
+        let method = msg.get("method").and_then(Value::as_str).unwrap_or("");
+        match method {
+            "initialize" => {
+                let init = self
+                    .scenario
+                    .init
+                    .clone()
+                    .unwrap_or_else(|| json!({"userAgent": "fake-agent-cli"}));
+                self.reply(msg, init);
+            }
+            "thread/start" => {
+                let thread = json!({"id": self.thread_id});
+                self.reply(msg, json!({"thread": thread, "threadId": self.thread_id}));
+                self.notify(
+                    "thread/started",
+                    json!({"thread": thread, "threadId": self.thread_id}),
+                );
+            }
+            "thread/resume" | "thread/fork" => {
+                let id = msg
+                    .pointer("/params/threadId")
+                    .and_then(Value::as_str)
+                    .unwrap_or("");
+                self.thread_id = if method == "thread/fork" {
+                    format!("{}-fork", id)
+                } else {
+                    id.to_string()
+                };
+                let thread = json!({"id": self.thread_id});
+                self.reply(msg, json!({"thread": thread, "threadId": self.thread_id}));
+                self.notify(
+                    "thread/started",
+                    json!({"thread": thread, "threadId": self.thread_id}),
+                );
+            }
+            "thread/list" if !self.scenario.control.contains_key(method) => {
+                let thread = json!({"id": self.thread_id, "preview": "", "updatedAt": 0});
+                self.reply(msg, json!({"data": [thread], "nextCursor": null}));
+            }
+            "turn/interrupt" => {
+                self.interrupted = true;
+                self.reply(msg, json!({}));
+            }
+            _ => match self.scenario.control.get(method) {
+                Some(result) => self.reply(msg, result.clone()),
+                None => self.io.send(&json!({
+                    "jsonrpc": "2.0",
+                    "id": msg.get("id").cloned().unwrap_or(Value::Null),
+                    "error": {"code": -32601, "message": format!("Method not found: {}", method)},
+                })),
+            },
+        }
+    }
+
+    fn run_turn(&mut self, msg: &Value) -> Option<i32> {
+        let prompt = turn_input_text(msg.get("params").unwrap_or(&Value::Null));
+        let steps = self.scenario.take_turn(&prompt);
+        let turn = json!({"id": self.turn_id()});
+        self.next_turn += 1;
+        self.interrupted = false;
+
+        self.notify(
+            "turn/started",
+            json!({"threadId": self.thread_id, "turn": turn}),
+        );
+
+        for step in steps {
+            if self.interrupted {
+                break;
+            }
+            match step {
+                Step::Text(text) => self.item(json!({"type": "agent_message", "text": text})),
+                Step::Thinking(text) => self.item(json!({"type": "reasoning", "text": text})),
+                Step::ToolUse { id, input, .. } => self.item(json!({
+                    "type": "command_execution",
+                    "id": id,
+                    "command": input.get("command").cloned().unwrap_or(Value::Null),
+                    "output": input.get("output").cloned().unwrap_or(json!("")),
+                    "exitCode": 0,
+                })),
+                Step::Request(request) => self.request(request),
+                Step::Hook { .. } => eprintln!("fake-agent-cli: hooks are not part of Codex"),
+                Step::Result(overrides) => self.turn_completed(&turn, "completed", &overrides),
+                Step::Emit(value) => self.io.send(&value),
+                Step::SleepMs(ms) => self.sleep(ms),
+                Step::Stderr(line) => eprintln!("{}", line),
+                Step::Exit(code) => return Some(code),
+            }
+        }
+
+        if self.interrupted {
+            self.turn_completed(&turn, "interrupted", &serde_json::Map::new());
+        }
+        None
+    }
+
+    fn item(&mut self, mut item: Value) {
+        if item.get("id").is_none() {
+            item["id"] = json!(format!("item-{}", self.next_item));
+            self.next_item += 1;
+        }
+        self.notify(
+            "item/completed",
+            json!({"threadId": self.thread_id, "item": item}),
+        );
+    }
+
+    fn turn_completed(
+        &self,
+        turn: &Value,
+        status: &str,
+        overrides: &serde_json::Map<String, Value>,
+    ) {
+        let mut turn = turn.clone();
+        turn["status"] = json!(status);
+        let base = json!({
+            "threadId": self.thread_id,
+            "turn": turn,
+            "usage": {"input_tokens": 10, "cached_input_tokens": 0, "output_tokens": 5},
+        });
+        self.notify("turn/completed", merge(base, overrides));
+    }
+
+    /// Send a server request (`{"method", "params"}`) and wait for its response.
+    fn request(&mut self, request: Value) {
+        let id = self.next_request;
+        self.next_request += 1;
+        self.io.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": request.get("method").cloned().unwrap_or(Value::Null),
+            "params": request.get("params").cloned().unwrap_or_else(|| json!({})),
+        }));
+
+        loop {
+            let Some(msg) = self.io.recv() else { return };
+            if msg.get("method").is_none() {
+                if msg.get("id").and_then(Value::as_u64) == Some(id) {
+                    return;
+                }
+                continue;
+            }
+            self.dispatch_during_turn(msg);
+        }
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        let deadline = Instant::now() + Duration::from_millis(ms);
+        while !self.interrupted {
+            match self.io.recv_until(Some(deadline)) {
+                Inbound::Line(msg) => self.dispatch_during_turn(msg),
+                Inbound::Timeout | Inbound::Closed => break,
+            }
+        }
+    }
+
+    /// Handle client traffic that arrives while a turn is running.
+    fn dispatch_during_turn(&mut self, msg: Value) {
+        match msg.get("method").and_then(Value::as_str) {
+            Some("turn/start") => self.pending.push_back(msg),
+            Some(_) if msg.get("id").is_some() => self.answer(&msg),
+            _ => {}
+        }
+    }
+}
+
+fn turn_input_text(params: &Value) -> String {
+    params
+        .get("input")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            item.get("text")
+                .or_else(|| item.get("content"))
+                .and_then(Value::as_str)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `codex exec --json <prompt>`: one turn, then exit.
+pub fn run_exec(scenario: &mut Scenario, args: &[String], io: &Io) -> i32 {
+    let thread_id = scenario.session_id_or("fake-thread");
+    let steps = scenario.take_turn(&trailing_prompt(args));
+
+    io.send(&json!({"type": "thread.started", "thread_id": thread_id}));
+    io.send(&json!({"type": "turn.started"}));
+
+    for (n, step) in steps.into_iter().enumerate() {
+        let id = format!("item-{}", n);
+        match step {
+            Step::Text(text) => io.send(&json!({
+                "type": "item.completed",
+                "item": {"id": id, "type": "agent_message", "text": text},
+            })),
+            Step::Thinking(text) => io.send(&json!({
+                "type": "item.completed",
+                "item": {"id": id, "type": "reasoning", "text": text},
+            })),
+            Step::ToolUse { id, input, .. } => io.send(&json!({
+                "type": "item.completed",
+                "item": {
+                    "id": id,
+                    "type": "command_execution",
+                    "command": input.get("command").cloned().unwrap_or(Value::Null),
+                    "output": input.get("output").cloned().unwrap_or(json!("")),
+                    "exitCode": 0,
+                },
+            })),
+            Step::Result(overrides) => {
+                let base = json!({
+                    "type": "turn.completed",
+                    "usage": {"input_tokens": 10, "cached_input_tokens": 0, "output_tokens": 5},
+                });
+                io.send(&merge(base, &overrides));
+            }
+            Step::Emit(value) => io.send(&value),
+            Step::SleepMs(ms) => sleep_ms(ms),
+            Step::Stderr(line) => eprintln!("{}", line),
+            Step::Exit(code) => return code,
+            Step::Request(_) | Step::Hook { .. } => {
+                eprintln!("fake-agent-cli: codex exec cannot send requests");
+            }
+        }
+    }
+    0
+}
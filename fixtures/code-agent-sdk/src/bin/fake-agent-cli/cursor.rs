@@ -0,0 +1,85 @@
+//! Cursor Agent `--print --output-format stream-json` protocol.
+//!
+//! Cursor spawns one process per turn, so each invocation plays a single
+//! turn. Give multi-turn scenarios a `match` per turn; `--resume <chatId>`
+//! is echoed back as the session id.
+
+use crate::scenario::{Scenario, Step};
+use crate::{Io, merge, sleep_ms, trailing_prompt};
+use serde_json::json;
+
+pub fn run(scenario: &mut Scenario, args: &[String], io: &Io) -> i32 {
+    let chat_id = args
+        .iter()
+        .position(|a| a == "--resume")
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+        .unwrap_or_else(|| scenario.session_id_or("fake-chat"));
+    let steps = scenario.take_turn(&trailing_prompt(args));
+
+    io.send(&json!({
+        "type": "system",
+        "subtype": "init",
+        "session_id": chat_id,
+        "model": "fake-model",
+        "cwd": std::env::current_dir().ok(),
+    }));
+
+    let mut last_text = String::new();
+    for step in steps {
+        match step {
+            Step::Text(text) => {
+                io.send(&json!({
+                    "type": "assistant",
+                    "message": {"role": "assistant", "content": [{"type": "text", "text": text}]},
+                    "session_id": chat_id,
+                }));
+                last_text = text;
+            }
+            Step::Thinking(text) => io.send(&json!({
+                "type": "thinking",
+                "text": text,
+                "session_id": chat_id,
+            })),
+            Step::ToolUse { id, name, input } => {
+                io.send(&json!({
+                    "type": "tool_call",
+                    "subtype": "started",
+                    "id": id,
+                    "name": name,
+                    "input": input,
+                    "session_id": chat_id,
+                }));
+                io.send(&json!({
+                    "type": "tool_call",
+                    "subtype": "completed",
+                    "id": id,
+                    "output": input.get("output").cloned().unwrap_or(json!("")),
+                    "is_error": false,
+                    "session_id": chat_id,
+                }));
+            }
+            Step::Result(overrides) => {
+                let base = json!({
+                    "type": "result",
+                    "subtype": "success",
+                    "is_error": false,
+                    "duration_ms": 1,
+                    "duration_api_ms": 1,
+                    "session_id": chat_id,
+                    "result": last_text,
+                    "usage": {"inputTokens": 10, "outputTokens": 5},
+                });
+                io.send(&merge(base, &overrides));
+            }
+            Step::Emit(value) => io.send(&value),
+            Step::SleepMs(ms) => sleep_ms(ms),
+            Step::Stderr(line) => eprintln!("{}", line),
+            Step::Exit(code) => return code,
+            Step::Request(_) | Step::Hook { .. } => {
+                eprintln!("fake-agent-cli: cursor cannot send requests");
+            }
+        }
+    }
+    0
+}
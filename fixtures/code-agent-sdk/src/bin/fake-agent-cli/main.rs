@@ -0,0 +1,173 @@
+//! Fake agent CLI for offline end-to-end tests.
+//!
+//! Speaks the Claude stream-json control protocol, the Codex `app-server`
+//! JSON-RPC flow, `codex exec --json` and Cursor `--print` stream-json,
+//! replaying a scenario file instead of calling a model. Point
+//! `AgentOptions::cli_path` at this binary and pass the scenario through
+//! `AgentOptions::env`:
+//!
+//! - `FAKE_AGENT_SCENARIO`: path to the scenario JSON (see [`scenario`]).
+//! - `FAKE_AGENT_LOG`: optional path; argv, cwd and every stdin line are
+//!   appended as JSON lines so tests can assert on what the SDK sent.
+//!
+//! The protocol is detected from argv (`app-server`, `exec`, `--print`,
+//! otherwise Claude) unless the scenario sets `protocol`.
+
+mod claude;
+mod codex;
+mod cursor;
+mod scenario;
+
+use scenario::{Protocol, Scenario};
+use serde_json::{Value, json};
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Claude's version probe runs without the scenario environment.
+    if args.iter().any(|a| a == "-v" || a == "--version") {
+        println!("2.1.0 (Fake Agent CLI)");
+        return;
+    }
+
+    let mut scenario = match Scenario::load() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let log = Log::open();
+    log.record(json!({
+        "argv": args,
+        "cwd": std::env::current_dir().ok(),
+    }));
+
+    let mut io = Io::new(log);
+    let protocol = scenario.protocol.unwrap_or_else(|| Protocol::detect(&args));
+    let code = match protocol {
+        Protocol::Claude => claude::run(&mut scenario, &mut io),
+        Protocol::CodexAppServer => codex::run_app_server(&mut scenario, &mut io),
+        Protocol::CodexExec => codex::run_exec(&mut scenario, &args, &io),
+        Protocol::Cursor => cursor::run(&mut scenario, &args, &io),
+    };
+    std::process::exit(code);
+}
+
+/// Append-only JSONL log of what the simulator observed.
+struct Log {
+    file: Option<std::fs::File>,
+}
+
+impl Log {
+    fn open() -> Self {
+        let file = std::env::var("FAKE_AGENT_LOG")
+            .ok()
+            .and_then(|path| OpenOptions::new().create(true).append(true).open(path).ok());
+        Self { file }
+    }
+
+    fn record(&self, entry: Value) {
+        if let Some(mut file) = self.file.as_ref() {
+            let _ = writeln!(file, "{}", entry);
+        }
+    }
+}
+
+/// Result of waiting on stdin.
+pub enum Inbound {
+    Line(Value),
+    Timeout,
+    Closed,
+}
+
+/// Line-oriented stdin/stdout shared by all protocol drivers.
+pub struct Io {
+    rx: mpsc::Receiver<Value>,
+    log: Log,
+}
+
+impl Io {
+    fn new(log: Log) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(line) {
+                    Ok(v) => {
+                        if tx.send(v).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => eprintln!("fake-agent-cli: ignoring invalid JSON: {}", e),
+                }
+            }
+        });
+        Self { rx, log }
+    }
+
+    /// Write one JSON line to stdout.
+    pub fn send(&self, value: &Value) {
+        let mut out = std::io::stdout().lock();
+        let _ = writeln!(out, "{}", value);
+        let _ = out.flush();
+    }
+
+    /// Block until the next stdin line (or EOF).
+    pub fn recv(&mut self) -> Option<Value> {
+        match self.recv_until(None) {
+            Inbound::Line(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Wait for the next stdin line until `deadline`.
+    pub fn recv_until(&mut self, deadline: Option<Instant>) -> Inbound {
+        let received = match deadline {
+            None => self
+                .rx
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            Some(d) => self
+                .rx
+                .recv_timeout(d.saturating_duration_since(Instant::now())),
+        };
+        match received {
+            Ok(v) => {
+                self.log.record(json!({ "stdin": v }));
+                Inbound::Line(v)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => Inbound::Timeout,
+            Err(mpsc::RecvTimeoutError::Disconnected) => Inbound::Closed,
+        }
+    }
+}
+
+/// Sleep without reading stdin (for one-shot protocols).
+pub fn sleep_ms(ms: u64) {
+    std::thread::sleep(Duration::from_millis(ms));
+}
+
+/// Merge `overrides` into the object `base`.
+pub fn merge(mut base: Value, overrides: &serde_json::Map<String, Value>) -> Value {
+    if let Some(obj) = base.as_object_mut() {
+        for (k, v) in overrides {
+            obj.insert(k.clone(), v.clone());
+        }
+    }
+    base
+}
+
+/// The prompt is the final positional argument for one-shot CLIs.
+pub fn trailing_prompt(args: &[String]) -> String {
+    args.last().cloned().unwrap_or_default()
+}
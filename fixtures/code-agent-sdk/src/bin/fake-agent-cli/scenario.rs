@@ -0,0 +1,156 @@
+//! Scenario file format for the fake agent CLI.
+//!
+//! A scenario is a JSON document:
+//!
+//! ```json
+//! {
+//!   "session_id": "fake-session",
+//!   "init": {"commands": []},
+//!   "control": {"mcp_status": {"mcpServers": []}},
+//!   "turns": [
+//!     {"match": "weather", "steps": [
+//!       {"request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {}}},
+//!       {"text": "It is sunny"},
+//!       {"result": {"total_cost_usd": 0.01}}
+//!     ]}
+//!   ]
+//! }
+//! ```
+//!
+//! Each prompt consumes the first unused turn whose `match` (if any) is a
+//! substring of the prompt. When no turn fits, the CLI echoes the prompt.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Wire protocol spoken by the simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    /// Claude stream-json with the control protocol.
+    Claude,
+    /// `codex app-server` JSON-RPC 2.0.
+    CodexAppServer,
+    /// `codex exec --json` JSONL events.
+    CodexExec,
+    /// Cursor `--print --output-format stream-json`.
+    Cursor,
+}
+
+impl Protocol {
+    /// Pick the protocol from the arguments the SDK launched us with.
+    pub fn detect(args: &[String]) -> Self {
+        match args.first().map(String::as_str) {
+            Some("app-server") => Self::CodexAppServer,
+            Some("exec") => Self::CodexExec,
+            _ if args.iter().any(|a| a == "--print") => Self::Cursor,
+            _ => Self::Claude,
+        }
+    }
+}
+
+/// One scripted action within a turn.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Assistant text.
+    Text(String),
+    /// Assistant thinking/reasoning.
+    Thinking(String),
+    /// Assistant tool use.
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    /// A request sent to the SDK (Claude: control request body;
+    /// Codex: `{"method", "params"}`). Blocks until the SDK answers.
+    Request(Value),
+    /// Invoke every hook callback the SDK registered for `event` (Claude only).
+    Hook {
+        event: String,
+        #[serde(default)]
+        input: Value,
+        #[serde(default)]
+        tool_use_id: Option<String>,
+    },
+    /// Final result; fields override the protocol defaults.
+    Result(serde_json::Map<String, Value>),
+    /// A raw line written verbatim to stdout.
+    Emit(Value),
+    /// Pause; Claude and Codex app-server still answer control traffic.
+    SleepMs(u64),
+    /// A line written to stderr.
+    Stderr(String),
+    /// Exit immediately with this code.
+    Exit(i32),
+}
+
+/// A scripted turn.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Turn {
+    /// Only use this turn for prompts containing this string.
+    #[serde(default, rename = "match")]
+    pub matches: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+/// A complete scenario file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// Force a protocol instead of detecting it from argv.
+    pub protocol: Option<Protocol>,
+    /// Session / thread / chat id reported to the SDK.
+    pub session_id: Option<String>,
+    /// Response to the SDK's `initialize` request.
+    pub init: Option<Value>,
+    /// Responses to SDK-originated requests keyed by subtype (Claude) or
+    /// method (Codex).
+    pub control: HashMap<String, Value>,
+    pub turns: Vec<Turn>,
+    #[serde(skip)]
+    used: Vec<bool>,
+}
+
+impl Scenario {
+    /// Load the scenario named by `FAKE_AGENT_SCENARIO`, or an empty one.
+    pub fn load() -> Result<Self, String> {
+        let Ok(path) = std::env::var("FAKE_AGENT_SCENARIO") else {
+            return Ok(Self::default());
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read scenario {}: {}", path, e))?;
+        let mut scenario: Self =
+            serde_json::from_str(&text).map_err(|e| format!("invalid scenario {}: {}", path, e))?;
+        scenario.used = vec![false; scenario.turns.len()];
+        Ok(scenario)
+    }
+
+    /// Session id, falling back to `default`.
+    pub fn session_id_or(&self, default: &str) -> String {
+        self.session_id
+            .clone()
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// Consume the steps for `prompt`, echoing it back when no turn fits.
+    pub fn take_turn(&mut self, prompt: &str) -> Vec<Step> {
+        let found = self.turns.iter().enumerate().position(|(i, turn)| {
+            !self.used[i] && turn.matches.as_deref().is_none_or(|m| prompt.contains(m))
+        });
+        match found {
+            Some(i) => {
+                self.used[i] = true;
+                self.turns[i].steps.clone()
+            }
+            None => vec![
+                Step::Text(format!("Echo: {}", prompt)),
+                Step::Result(serde_json::Map::new()),
+            ],
+        }
+    }
+}
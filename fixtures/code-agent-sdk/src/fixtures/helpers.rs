@@ -95,10 +95,10 @@ pub fn backend_name(kind: BackendKind) -> &'static str {
 /// Find a CLI binary path from environment variable or PATH search.
 fn find_cli_path(env_var: &str, binary_name: &str) -> Option<String> {
     // Check environment variable first
-    if let Ok(path) = std::env::var(env_var) {
-        if Path::new(&path).is_file() {
-            return Some(path);
-        }
+    if let Ok(path) = std::env::var(env_var)
+        && Path::new(&path).is_file()
+    {
+        return Some(path);
     }
 
     // Search PATH
@@ -6,6 +6,8 @@
 
 use std::env;
 
+// The live-CLI fixtures predate workspace formatting and are kept as-is.
+#[rustfmt::skip]
 mod fixtures;
 
 fn main() {
@@ -41,6 +43,7 @@ fn main() {
 }
 
 /// Run offline tests that do not require any CLI to be installed.
+#[rustfmt::skip]
 async fn run_offline() -> Result<(), anyhow::Error> {
     macro_rules! run {
         ($name:expr, $fn:path) => {
@@ -58,6 +61,7 @@ async fn run_offline() -> Result<(), anyhow::Error> {
 }
 
 /// Run all fixtures. Online tests are skipped if CLI is not available.
+#[rustfmt::skip]
 async fn run_all() -> Result<(), anyhow::Error> {
     macro_rules! run {
         ($name:expr, $fn:path) => {
@@ -0,0 +1,431 @@
+//! End-to-end tests of the SDK backends against the fake agent CLI.
+
+#![cfg(unix)]
+
+use code_agent_sdk::backend::codex::list_threads;
+use code_agent_sdk::options::HookJSONOutput;
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, HookEvent, HookMatcher, McpServerConfig, Message,
+    PermissionResult, PermissionResultAllow, PermissionResultDeny, create_sdk_mcp_server, query,
+    sdk_mcp_tool,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FAKE_CLI: &str = env!("CARGO_BIN_EXE_fake-agent-cli");
+
+struct TempTestDir {
+    path: PathBuf,
+}
+
+impl TempTestDir {
+    fn new(prefix: &str) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock before UNIX_EPOCH")
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("{prefix}-{}-{nanos}", std::process::id()));
+        std::fs::create_dir_all(&path).expect("failed to create temp directory");
+        Self { path }
+    }
+
+    fn join(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempTestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Write `scenario` into `dir` and return options pointing at the fake CLI.
+fn fake_cli_options(dir: &TempTestDir, backend: BackendKind, scenario: Value) -> AgentOptions {
+    let scenario_path = dir.join("scenario.json");
+    std::fs::write(&scenario_path, scenario.to_string()).expect("failed to write scenario");
+    AgentOptions::builder()
+        .backend(backend)
+        .cli_path(FAKE_CLI)
+        .env("FAKE_AGENT_SCENARIO", scenario_path.to_string_lossy())
+        .env("FAKE_AGENT_LOG", dir.join("log.jsonl").to_string_lossy())
+        .build()
+}
+
+fn read_log(path: &Path) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+fn stdin_lines(dir: &TempTestDir) -> Vec<Value> {
+    read_log(&dir.join("log.jsonl"))
+        .into_iter()
+        .filter_map(|entry| entry.get("stdin").cloned())
+        .collect()
+}
+
+fn argv_lines(dir: &TempTestDir) -> Vec<Vec<String>> {
+    read_log(&dir.join("log.jsonl"))
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry.get("argv")?.clone()).ok())
+        .collect()
+}
+
+fn assistant_texts(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .filter_map(|m| match m {
+            Message::Assistant(a) => Some(a.content.iter().filter_map(|b| match b {
+                code_agent_sdk::ContentBlock::Text(t) => Some(t.text.clone()),
+                _ => None,
+            })),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+async fn collect_response(client: &AgentSdkClient) -> Vec<Message> {
+    client
+        .receive_response()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("receive_response failed")
+}
+
+#[tokio::test]
+async fn test_should_echo_prompt_for_claude_one_shot_query() {
+    let dir = TempTestDir::new("fake-cli-claude-query");
+    let options = fake_cli_options(&dir, BackendKind::Claude, json!({}));
+
+    let messages: Vec<Message> = query("ping", Some(options))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("query failed");
+
+    assert_eq!(assistant_texts(&messages), vec!["Echo: ping"]);
+    assert!(matches!(messages.last(), Some(Message::Result(r)) if r.session_id == "fake-session"));
+}
+
+#[tokio::test]
+async fn test_should_route_claude_callbacks_through_control_protocol() {
+    let dir = TempTestDir::new("fake-cli-claude-callbacks");
+    let scenario = json!({
+        "turns": [{"steps": [
+            {"request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {"command": "ls"}}},
+            {"hook": {"event": "PreToolUse", "input": {"tool_name": "Bash"}, "tool_use_id": "tu-1"}},
+            {"request": {"subtype": "mcp_message", "server_name": "calc", "message": {
+                "jsonrpc": "2.0", "id": 7, "method": "tools/call",
+                "params": {"name": "add", "arguments": {"a": 2, "b": 3}}
+            }}},
+            {"text": "done"},
+            {"result": {}}
+        ]}]
+    });
+
+    let mut options = fake_cli_options(&dir, BackendKind::Claude, scenario);
+    options.can_use_tool = Some(Arc::new(|tool, _input, _ctx| {
+        Box::pin(async move {
+            if tool == "Bash" {
+                PermissionResult::Allow(PermissionResultAllow {
+                    updated_input: Some(json!({"command": "ls -la"})),
+                    updated_permissions: None,
+                })
+            } else {
+                PermissionResult::Deny(PermissionResultDeny {
+                    message: "no".to_string(),
+                    interrupt: false,
+                })
+            }
+        })
+    }));
+    let hook: code_agent_sdk::options::HookCallback = Arc::new(|_input, tool_use_id, _ctx| {
+        Box::pin(async move {
+            Ok(HookJSONOutput::Sync {
+                continue_: Some(true),
+                suppress_output: None,
+                stop_reason: None,
+                decision: None,
+                system_message: tool_use_id,
+                reason: None,
+                hook_specific_output: None,
+            })
+        })
+    });
+    options.hooks = Some(HashMap::from([(
+        HookEvent::PreToolUse,
+        vec![HookMatcher {
+            matcher: Some("Bash".to_string()),
+            hooks: vec![hook],
+            timeout: None,
+        }],
+    )]));
+    let add = sdk_mcp_tool(
+        "add",
+        "Add two numbers",
+        json!({"type": "object"}),
+        |args| {
+            Box::pin(async move {
+                let sum = args["a"].as_i64().unwrap_or(0) + args["b"].as_i64().unwrap_or(0);
+                Ok(json!({"content": [{"type": "text", "text": sum.to_string()}]}))
+            })
+        },
+    );
+    options.mcp_servers = Some(
+        HashMap::from([(
+            "calc".to_string(),
+            McpServerConfig::Sdk(create_sdk_mcp_server("calc", "1.0.0", vec![add])),
+        )])
+        .into(),
+    );
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    client.query("go", "default").await.expect("query failed");
+    let messages = collect_response(&client).await;
+    client.disconnect().await.expect("disconnect failed");
+
+    assert_eq!(assistant_texts(&messages), vec!["done"]);
+
+    let responses: HashMap<String, Value> = stdin_lines(&dir)
+        .into_iter()
+        .filter(|l| l["type"] == "control_response")
+        .filter_map(|l| {
+            let response = l["response"].clone();
+            Some((response["request_id"].as_str()?.to_string(), response))
+        })
+        .collect();
+
+    let permission = &responses["cli_req_0"];
+    assert_eq!(permission["subtype"], "success");
+    assert_eq!(permission["response"]["behavior"], "allow");
+    assert_eq!(permission["response"]["updatedInput"]["command"], "ls -la");
+
+    let hook = &responses["cli_req_1"];
+    assert_eq!(hook["response"]["continue"], true);
+    assert_eq!(hook["response"]["systemMessage"], "tu-1");
+
+    let mcp = &responses["cli_req_2"]["response"]["mcp_response"];
+    assert_eq!(mcp["id"], 7);
+    assert_eq!(mcp["result"]["content"][0]["text"], "5");
+}
+
+#[tokio::test]
+async fn test_should_answer_claude_runtime_control_requests() {
+    let dir = TempTestDir::new("fake-cli-claude-control");
+    let scenario = json!({
+        "init": {"commands": [{"name": "fake"}]},
+        "control": {"mcp_status": {"mcpServers": [{"name": "x", "status": "connected"}]}},
+    });
+    let options = fake_cli_options(&dir, BackendKind::Claude, scenario);
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+
+    let info = client
+        .get_server_info()
+        .await
+        .unwrap()
+        .expect("server info");
+    assert_eq!(info["commands"][0]["name"], "fake");
+    let status = client.get_mcp_status().await.expect("mcp_status failed");
+    assert_eq!(status["mcpServers"][0]["status"], "connected");
+    client
+        .set_model(Some("other"))
+        .await
+        .expect("set_model failed");
+    client.disconnect().await.expect("disconnect failed");
+
+    let subtypes: Vec<String> = stdin_lines(&dir)
+        .into_iter()
+        .filter(|l| l["type"] == "control_request")
+        .filter_map(|l| l["request"]["subtype"].as_str().map(String::from))
+        .collect();
+    assert_eq!(subtypes, vec!["initialize", "mcp_status", "set_model"]);
+}
+
+#[tokio::test]
+async fn test_should_drive_codex_app_server_turns_and_approvals() {
+    let dir = TempTestDir::new("fake-cli-codex");
+    let scenario = json!({
+        "session_id": "thread-42",
+        "turns": [
+            {"match": "first", "steps": [
+                {"request": {"method": "item/commandExecution/requestApproval", "params": {"command": "rm -rf /"}}},
+                {"text": "declined"},
+                {"result": {}}
+            ]},
+            {"match": "second", "steps": [{"text": "again"}, {"result": {}}]}
+        ]
+    });
+    let mut options = fake_cli_options(&dir, BackendKind::Codex, scenario);
+    options.can_use_tool = Some(Arc::new(|_tool, input, _ctx| {
+        Box::pin(async move {
+            if input["command"].as_str().unwrap_or("").contains("rm") {
+                PermissionResult::Deny(PermissionResultDeny {
+                    message: "dangerous".to_string(),
+                    interrupt: false,
+                })
+            } else {
+                PermissionResult::Allow(PermissionResultAllow {
+                    updated_input: None,
+                    updated_permissions: None,
+                })
+            }
+        })
+    }));
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    assert_eq!(
+        client.get_server_info().await.unwrap(),
+        Some(json!({"threadId": "thread-42"}))
+    );
+
+    client.query("first turn", "default").await.unwrap();
+    let first = collect_response(&client).await;
+    assert_eq!(assistant_texts(&first), vec!["declined"]);
+    assert!(matches!(first.last(), Some(Message::Result(r)) if r.session_id == "thread-42"));
+
+    client.query("second turn", "default").await.unwrap();
+    let second = collect_response(&client).await;
+    assert_eq!(assistant_texts(&second), vec!["again"]);
+    client.disconnect().await.expect("disconnect failed");
+
+    let stdin = stdin_lines(&dir);
+    let methods: Vec<&str> = stdin.iter().filter_map(|l| l["method"].as_str()).collect();
+    assert_eq!(
+        methods,
+        vec![
+            "initialize",
+            "initialized",
+            "thread/start",
+            "turn/start",
+            "turn/start"
+        ]
+    );
+    let approval = stdin
+        .iter()
+        .find(|l| l.get("method").is_none() && l.get("result").is_some())
+        .expect("approval response");
+    assert_eq!(approval["result"]["decision"], "decline");
+}
+
+#[tokio::test]
+async fn test_should_list_and_resume_codex_threads() {
+    let dir = TempTestDir::new("fake-cli-codex-threads");
+    let scenario = json!({
+        "session_id": "thread-1",
+        "control": {"thread/list": {"data": [
+            {"id": "thread-8", "preview": "second", "modelProvider": "openai", "updatedAt": 20},
+            {"id": "thread-3", "preview": "first", "updatedAt": 10}
+        ]}}
+    });
+    let options = fake_cli_options(&dir, BackendKind::Codex, scenario);
+
+    let threads = list_threads(&options, Some(2)).await.expect("list failed");
+    let ids: Vec<_> = threads.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["thread-8", "thread-3"]);
+    assert_eq!(threads[0].preview.as_deref(), Some("second"));
+    assert_eq!(threads[0].model_provider.as_deref(), Some("openai"));
+
+    let mut options = options;
+    options.resume = Some(threads[1].id.clone());
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    client.query("hello", "default").await.unwrap();
+    let messages = collect_response(&client).await;
+    client.disconnect().await.expect("disconnect failed");
+    assert!(matches!(messages.last(), Some(Message::Result(r)) if r.session_id == "thread-3"));
+
+    let methods: Vec<String> = stdin_lines(&dir)
+        .iter()
+        .filter_map(|l| l["method"].as_str().map(String::from))
+        .collect();
+    assert_eq!(
+        methods,
+        [
+            "initialize",
+            "initialized",
+            "thread/list",
+            "initialize",
+            "initialized",
+            "thread/resume",
+            "turn/start"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_should_resume_cursor_chat_between_turns() {
+    let dir = TempTestDir::new("fake-cli-cursor");
+    let scenario = json!({
+        "session_id": "chat-7",
+        "turns": [
+            {"match": "hello", "steps": [{"text": "hi"}, {"result": {}}]},
+            {"match": "bye", "steps": [{"text": "see you"}, {"result": {}}]}
+        ]
+    });
+    let options = fake_cli_options(&dir, BackendKind::Cursor, scenario);
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+
+    client.query("hello", "default").await.unwrap();
+    let first = collect_response(&client).await;
+    assert_eq!(assistant_texts(&first), vec!["hi"]);
+
+    client.query("bye", "default").await.unwrap();
+    let second = collect_response(&client).await;
+    assert_eq!(assistant_texts(&second), vec!["see you"]);
+    client.disconnect().await.expect("disconnect failed");
+
+    let argv = argv_lines(&dir);
+    assert_eq!(argv.len(), 2);
+    assert!(!argv[0].contains(&"--resume".to_string()));
+    let resume = argv[1]
+        .iter()
+        .position(|a| a == "--resume")
+        .expect("--resume");
+    assert_eq!(argv[1][resume + 1], "chat-7");
+}
+
+#[tokio::test]
+async fn test_should_stream_codex_exec_and_cursor_one_shot_queries() {
+    for backend in [BackendKind::Codex, BackendKind::Cursor] {
+        let dir = TempTestDir::new("fake-cli-one-shot");
+        let scenario = json!({"turns": [{"steps": [
+            {"thinking": "hmm"},
+            {"tool_use": {"id": "t1", "name": "Bash", "input": {"command": "ls", "output": "a.txt"}}},
+            {"text": "listed"},
+            {"result": {}}
+        ]}]});
+        let options = fake_cli_options(&dir, backend, scenario);
+
+        let messages: Vec<Message> = query("list files", Some(options))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| panic!("{backend} query failed: {e}"));
+
+        assert_eq!(assistant_texts(&messages), vec!["listed"], "{backend}");
+        assert!(
+            matches!(messages.last(), Some(Message::Result(_))),
+            "{backend}: {messages:?}"
+        );
+        let argv = argv_lines(&dir);
+        assert_eq!(argv[0].last().map(String::as_str), Some("list files"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Assembly of partial-message deltas into [`AssistantMessage`] snapshots.
+//!
+//! With `include_partial_messages`, every backend emits
+//! [`Message::StreamEvent`]s carrying [`StreamDelta`]s. [`accumulate`] wraps a
+//! message stream (typically [`receive_messages`](crate::AgentSdkClient::receive_messages)
+//! or [`receive_response`](crate::AgentSdkClient::receive_response)) and turns
+//! those deltas into [`PartialEvent`]s: a fresh snapshot of the in-progress
+//! message after every delta, and each content block once it is complete.
+//!
+//! ```no_run
+//! use code_agent_sdk::accumulator::{PartialEvent, accumulate};
+//! use code_agent_sdk::{AgentOptions, AgentSdkClient};
+//! use futures::StreamExt;
+//!
+//! # async fn example() -> code_agent_sdk::Result<()> {
+//! let options = AgentOptions::builder().include_partial_messages(true).build();
+//! let mut client = AgentSdkClient::new(Some(options), None);
+//! client.connect(None).await?;
+//! client.query("Explain this repo", "default").await?;
+//!
+//! let mut events = accumulate(client.receive_response());
+//! while let Some(event) = events.next().await {
+//!     match event? {
+//!         PartialEvent::Snapshot(message) => println!("{:?}", message.content),
+//!         PartialEvent::BlockComplete { block, .. } => println!("done: {:?}", block),
+//!         PartialEvent::Message(_) => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Result;
+use crate::types::{
+    AssistantMessage, ContentBlock, Message, StreamBlockStart, StreamDelta, StreamEvent, TextBlock,
+    ThinkingBlock, ToolUseBlock,
+};
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+
+/// Output of [`accumulate`].
+#[derive(Debug, Clone)]
+pub enum PartialEvent {
+    /// The in-progress assistant message after applying a delta.
+    Snapshot(AssistantMessage),
+    /// A content block finished streaming.
+    BlockComplete { index: usize, block: ContentBlock },
+    /// Any message that is not a recognized stream event, passed through.
+    Message(Message),
+}
+
+#[derive(Debug, Clone)]
+enum BlockState {
+    Text(String),
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        json: String,
+        input: Value,
+    },
+}
+
+impl BlockState {
+    fn to_block(&self) -> ContentBlock {
+        match self {
+            Self::Text(text) => ContentBlock::Text(TextBlock { text: text.clone() }),
+            Self::Thinking {
+                thinking,
+                signature,
+            } => ContentBlock::Thinking(ThinkingBlock {
+                thinking: thinking.clone(),
+                signature: signature.clone(),
+            }),
+            Self::ToolUse {
+                id, name, input, ..
+            } => ContentBlock::ToolUse(ToolUseBlock {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+            }),
+        }
+    }
+}
+
+/// Incrementally assembles one assistant message from [`StreamDelta`]s.
+///
+/// Use this directly when driving your own loop; [`accumulate`] wraps it as
+/// a stream adapter. Deltas for blocks that were never started open an
+/// implicit block of the matching kind, so partial streams still assemble.
+#[derive(Debug, Clone, Default)]
+pub struct MessageAccumulator {
+    model: String,
+    parent_tool_use_id: Option<String>,
+    blocks: BTreeMap<usize, BlockState>,
+    active: bool,
+}
+
+impl MessageAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The in-progress message, or `None` between messages.
+    pub fn snapshot(&self) -> Option<AssistantMessage> {
+        self.active.then(|| AssistantMessage {
+            content: self.blocks.values().map(BlockState::to_block).collect(),
+            model: self.model.clone(),
+            parent_tool_use_id: self.parent_tool_use_id.clone(),
+            error: None,
+        })
+    }
+
+    /// Apply a delta and return the resulting events.
+    pub fn apply(&mut self, delta: &StreamDelta) -> Vec<PartialEvent> {
+        match delta {
+            StreamDelta::MessageStart { model, .. } => {
+                self.reset();
+                self.active = true;
+                self.model = model.clone().unwrap_or_default();
+                return Vec::new();
+            }
+            StreamDelta::ContentBlockStart { index, block } => {
+                let state = match block {
+                    StreamBlockStart::Text { text } => BlockState::Text(text.clone()),
+                    StreamBlockStart::Thinking { thinking } => BlockState::Thinking {
+                        thinking: thinking.clone(),
+                        signature: String::new(),
+                    },
+                    StreamBlockStart::ToolUse { id, name } => BlockState::ToolUse {
+                        id: id.clone(),
+                        name: name.clone(),
+                        json: String::new(),
+                        input: Value::Object(Default::default()),
+                    },
+                };
+                self.active = true;
+                self.blocks.insert(*index, state);
+            }
+            StreamDelta::TextDelta { index, text } => {
+                if let BlockState::Text(buf) =
+                    self.block(*index, || BlockState::Text(String::new()))
+                {
+                    buf.push_str(text);
+                }
+            }
+            StreamDelta::ThinkingDelta { index, thinking } => {
+                if let BlockState::Thinking { thinking: buf, .. } =
+                    self.block(*index, || BlockState::Thinking {
+                        thinking: String::new(),
+                        signature: String::new(),
+                    })
+                {
+                    buf.push_str(thinking);
+                }
+            }
+            StreamDelta::SignatureDelta { index, signature } => {
+                if let Some(BlockState::Thinking { signature: buf, .. }) =
+                    self.blocks.get_mut(index)
+                {
+                    buf.push_str(signature);
+                }
+                return Vec::new();
+            }
+            StreamDelta::InputJsonDelta {
+                index,
+                partial_json,
+            } => {
+                if let BlockState::ToolUse { json, input, .. } =
+                    self.block(*index, || BlockState::ToolUse {
+                        id: String::new(),
+                        name: String::new(),
+                        json: String::new(),
+                        input: Value::Object(Default::default()),
+                    })
+                {
+                    json.push_str(partial_json);
+                    if let Some(parsed) = parse_partial_json(json) {
+                        *input = parsed;
+                    }
+                }
+            }
+            StreamDelta::ContentBlockStop { index } => {
+                return self
+                    .blocks
+                    .get(index)
+                    .map(|state| PartialEvent::BlockComplete {
+                        index: *index,
+                        block: state.to_block(),
+                    })
+                    .into_iter()
+                    .collect();
+            }
+            StreamDelta::MessageDelta { .. } => return Vec::new(),
+            StreamDelta::MessageStop => {
+                self.reset();
+                return Vec::new();
+            }
+        }
+        self.snapshot()
+            .map(PartialEvent::Snapshot)
+            .into_iter()
+            .collect()
+    }
+
+    /// Apply a stream event, or return `None` if it carries no known delta.
+    pub fn apply_event(&mut self, event: &StreamEvent) -> Option<Vec<PartialEvent>> {
+        let delta = event.delta()?;
+        let starts_message = matches!(delta, StreamDelta::MessageStart { .. });
+        if !starts_message && !self.active {
+            self.parent_tool_use_id = event.parent_tool_use_id.clone();
+        }
+        let events = self.apply(&delta);
+        if starts_message {
+            self.parent_tool_use_id = event.parent_tool_use_id.clone();
+        }
+        Some(events)
+    }
+
+    fn block(&mut self, index: usize, default: impl FnOnce() -> BlockState) -> &mut BlockState {
+        self.active = true;
+        self.blocks.entry(index).or_insert_with(default)
+    }
+
+    fn reset(&mut self) {
+        self.model.clear();
+        self.parent_tool_use_id = None;
+        self.blocks.clear();
+        self.active = false;
+    }
+}
+
+/// Adapt a message stream into [`PartialEvent`]s.
+///
+/// Stream events with a recognized [`StreamDelta`] are consumed and turned
+/// into snapshots and completed blocks; everything else (including the
+/// backend's own final `AssistantMessage`) is passed through as
+/// [`PartialEvent::Message`]. Errors are passed through unchanged.
+pub fn accumulate<'a>(
+    messages: impl Stream<Item = Result<Message>> + Send + 'a,
+) -> Pin<Box<dyn Stream<Item = Result<PartialEvent>> + Send + 'a>> {
+    let mut accumulator = MessageAccumulator::new();
+    Box::pin(
+        messages
+            .map(move |item| -> Vec<Result<PartialEvent>> {
+                let message = match item {
+                    Ok(message) => message,
+                    Err(e) => return vec![Err(e)],
+                };
+                let Message::StreamEvent(ref event) = message else {
+                    return vec![Ok(PartialEvent::Message(message))];
+                };
+                match accumulator.apply_event(event) {
+                    Some(events) => events.into_iter().map(Ok).collect(),
+                    None => vec![Ok(PartialEvent::Message(message))],
+                }
+            })
+            .flat_map(futures::stream::iter),
+    )
+}
+
+/// Best-effort parse of a JSON document that may be cut off mid-way.
+///
+/// Unterminated strings and containers are closed; a trailing member that
+/// cannot be completed (e.g. `"key":` or `tr`) is dropped.
+fn parse_partial_json(input: &str) -> Option<Value> {
+    if let Ok(value) = serde_json::from_str(input) {
+        return Some(value);
+    }
+
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    // Prefix lengths that end just before a `,` or just after a `{`/`[`,
+    // paired with the closers needed at that point.
+    let mut cuts: Vec<(usize, String)> = Vec::new();
+    for (i, c) in input.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                stack.push(if c == '{' { '}' } else { ']' });
+                cuts.push((i + 1, stack.iter().rev().collect()));
+            }
+            '}' | ']' => {
+                stack.pop();
+            }
+            ',' => cuts.push((i, stack.iter().rev().collect())),
+            _ => {}
+        }
+    }
+
+    let mut completed = input.to_string();
+    if in_string {
+        completed.push('"');
+    }
+    completed.extend(stack.iter().rev());
+    if let Ok(value) = serde_json::from_str(&completed) {
+        return Some(value);
+    }
+    cuts.iter().rev().find_map(|(end, closers)| {
+        serde_json::from_str(&format!("{}{}", &input[..*end], closers)).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_should_complete_truncated_json() {
+        let cases = [
+            (r#"{"command": "ls -"#, json!({"command": "ls -"})),
+            (r#"{"command": "ls", "#, json!({"command": "ls"})),
+            (r#"{"command": "ls", "timeout":"#, json!({"command": "ls"})),
+            (r#"{"a": [1, 2, {"b": tr"#, json!({"a": [1, 2, {}]})),
+            (r#"{"path": "C:\\dir\"#, json!({})),
+            (r#"{"n": 12"#, json!({"n": 12})),
+            (r#"{"#, json!({})),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_partial_json(input), Some(expected), "{input}");
+        }
+        assert_eq!(parse_partial_json(""), None);
+    }
+}
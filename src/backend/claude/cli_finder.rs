@@ -13,9 +13,10 @@ const MINIMUM_CLAUDE_CODE_VERSION: &str = "2.0.0";
 ///
 /// Search order:
 /// 1. Explicit `cli_path` from options
-/// 2. Bundled CLI in executable directory (`_bundled/claude`)
-/// 3. PATH environment variable
-/// 4. Common installation paths
+/// 2. For a remote [`launcher`](AgentOptions::launcher), the bare name `claude`
+/// 3. Bundled CLI in executable directory (`_bundled/claude`)
+/// 4. PATH environment variable
+/// 5. Common installation paths
 ///
 /// # Errors
 ///
@@ -25,6 +26,10 @@ pub fn find_cli(options: &AgentOptions) -> Result<String> {
         return Ok(p.to_string_lossy().to_string());
     }
 
+    if crate::transport::is_remote(options) {
+        return Ok("claude".to_string());
+    }
+
     if let Some(bundled) = find_bundled_cli() {
         return Ok(bundled);
     }
@@ -0,0 +1,336 @@
+//! The Claude CLI's on-disk session history.
+//!
+//! The CLI appends every session to
+//! `~/.claude/projects/<encoded cwd>/<session id>.jsonl` (under
+//! `$CLAUDE_CONFIG_DIR` instead of `~/.claude` when set). [`ClaudeHistory`]
+//! lists those sessions, summarizes them and reads their messages back,
+//! e.g. to offer a picker whose choice becomes
+//! [`AgentOptions::resume`](crate::options::AgentOptions::resume):
+//!
+//! ```no_run
+//! use code_agent_sdk::{AgentOptions, ClaudeHistory};
+//!
+//! # async fn run() -> code_agent_sdk::Result<()> {
+//! let history = ClaudeHistory::new();
+//! let sessions = history.list_sessions("/path/to/project").await?;
+//! if let Some(latest) = sessions.first() {
+//!     println!("{:?}", latest.first_prompt);
+//!     let options = AgentOptions::builder()
+//!         .cwd("/path/to/project")
+//!         .resume(latest.session_id.clone())
+//!         .build();
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::message_parser::{parse_message, parse_usage};
+use crate::error::{Error, Result};
+use crate::types::{ContentBlock, Message, UserContent};
+use crate::usage::{PriceTable, Usage};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Summary of one session in the Claude CLI's history.
+#[derive(Debug, Clone)]
+pub struct ClaudeSessionInfo {
+    /// Pass to [`AgentOptionsBuilder::resume`](crate::options::AgentOptionsBuilder::resume)
+    /// to continue the session.
+    pub session_id: String,
+    pub path: PathBuf,
+    /// Title the CLI generated for the session, if any.
+    pub summary: Option<String>,
+    /// Text of the first prompt the user typed.
+    pub first_prompt: Option<String>,
+    /// Timestamp of the last entry, as written by the CLI (RFC 3339).
+    pub last_activity: Option<String>,
+    /// When the session file was last written.
+    pub modified: SystemTime,
+    /// Number of user and assistant messages.
+    pub message_count: usize,
+    /// Model of the last assistant message.
+    pub model: Option<String>,
+    pub git_branch: Option<String>,
+    /// Tokens used by the session's assistant messages.
+    pub usage: Usage,
+    /// Cost the CLI recorded for the session, or else estimated with the
+    /// [`price_table`](ClaudeHistory::price_table). `None` if neither is
+    /// available.
+    pub total_cost_usd: Option<f64>,
+}
+
+/// Reader for the Claude CLI's session history.
+#[derive(Debug, Clone)]
+pub struct ClaudeHistory {
+    projects_dir: PathBuf,
+    price_table: Option<PriceTable>,
+}
+
+impl ClaudeHistory {
+    /// History in the CLI's default location.
+    pub fn new() -> Self {
+        let config_dir = match std::env::var_os("CLAUDE_CONFIG_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+                Path::new(&home).join(".claude")
+            }
+        };
+        Self::with_projects_dir(config_dir.join("projects"))
+    }
+
+    /// History under `dir`, which holds one directory per project.
+    pub fn with_projects_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            projects_dir: dir.into(),
+            price_table: None,
+        }
+    }
+
+    /// Estimate the cost of assistant messages the CLI recorded no cost
+    /// for; recent CLI versions record none.
+    pub fn price_table(mut self, prices: PriceTable) -> Self {
+        self.price_table = Some(prices);
+        self
+    }
+
+    /// Directory holding the sessions started in `cwd`. The CLI replaces
+    /// every character other than an ASCII letter or digit with `-`.
+    pub fn project_dir(&self, cwd: impl AsRef<Path>) -> PathBuf {
+        let encoded: String = cwd
+            .as_ref()
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        self.projects_dir.join(encoded)
+    }
+
+    /// Sessions started in `cwd`, most recently active first.
+    ///
+    /// Files that cannot be read are skipped with a warning.
+    pub async fn list_sessions(&self, cwd: impl AsRef<Path>) -> Result<Vec<ClaudeSessionInfo>> {
+        let dir = self.project_dir(cwd);
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut sessions = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(session_id) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|_| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+            else {
+                continue;
+            };
+            match self.summarize(session_id, &path).await {
+                Ok(info) => sessions.push(info),
+                Err(e) => tracing::warn!("Skipping session {}: {}", path.display(), e),
+            }
+        }
+        sessions.sort_by(|a, b| {
+            b.last_activity
+                .cmp(&a.last_activity)
+                .then(b.modified.cmp(&a.modified))
+        });
+        Ok(sessions)
+    }
+
+    /// Summary of one session started in `cwd`.
+    pub async fn session_info(
+        &self,
+        cwd: impl AsRef<Path>,
+        session_id: &str,
+    ) -> Result<ClaudeSessionInfo> {
+        let path = self.session_path(cwd.as_ref(), session_id)?;
+        self.summarize(session_id, &path).await
+    }
+
+    /// The user and assistant messages of a session started in `cwd`, in
+    /// order, as [`parse_message`] reads them from the CLI's output.
+    ///
+    /// Subagent (sidechain) and CLI-internal (meta) entries are left out,
+    /// as are entries that are not messages, such as summaries.
+    pub async fn read_session(
+        &self,
+        cwd: impl AsRef<Path>,
+        session_id: &str,
+    ) -> Result<Vec<Message>> {
+        let path = self.session_path(cwd.as_ref(), session_id)?;
+        let mut messages = Vec::new();
+        for (line, entry) in read_entries(&path).await? {
+            if !is_conversation(&entry) {
+                continue;
+            }
+            let parsed = parse_message(&to_stream_json(entry))
+                .map_err(|e| Error::MessageParse(format!("{}:{}: {}", path.display(), line, e)))?;
+            messages.extend(parsed);
+        }
+        Ok(messages)
+    }
+
+    fn session_path(&self, cwd: &Path, session_id: &str) -> Result<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if !valid {
+            return Err(Error::Other(format!(
+                "Invalid session id: {:?}",
+                session_id
+            )));
+        }
+        let path = self.project_dir(cwd).join(format!("{}.jsonl", session_id));
+        if !path.exists() {
+            return Err(Error::Other(format!(
+                "Claude session {} not found in {}",
+                session_id,
+                path.parent().unwrap_or(&path).display()
+            )));
+        }
+        Ok(path)
+    }
+
+    async fn summarize(&self, session_id: &str, path: &Path) -> Result<ClaudeSessionInfo> {
+        let modified = tokio::fs::metadata(path).await?.modified()?;
+        let mut info = ClaudeSessionInfo {
+            session_id: session_id.to_string(),
+            path: path.to_path_buf(),
+            summary: None,
+            first_prompt: None,
+            last_activity: None,
+            modified,
+            message_count: 0,
+            model: None,
+            git_branch: None,
+            usage: Usage::default(),
+            total_cost_usd: None,
+        };
+        // Recent CLI versions keep a running total; older ones record each
+        // assistant message's cost.
+        let mut recorded_total = None;
+        let mut message_costs: Option<f64> = None;
+        // Assistant messages are written once per content block, each line
+        // repeating the message's usage.
+        let mut counted = HashSet::new();
+        for (_, entry) in read_entries(path).await? {
+            let text = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(String::from);
+            match entry.get("type").and_then(|v| v.as_str()) {
+                Some("summary") => info.summary = text("summary").or(info.summary.take()),
+                Some("cost-state") => {
+                    recorded_total = entry.get("totalCostUSD").and_then(|v| v.as_f64());
+                }
+                _ => {}
+            }
+            info.last_activity = text("timestamp").or(info.last_activity.take());
+            info.git_branch = text("gitBranch").or(info.git_branch.take());
+            if !is_conversation(&entry) {
+                continue;
+            }
+            info.message_count += 1;
+
+            let message = entry.get("message").unwrap_or(&Value::Null);
+            if entry.get("type").and_then(|v| v.as_str()) == Some("user") {
+                if info.first_prompt.is_none() {
+                    info.first_prompt = prompt_text(message);
+                }
+                continue;
+            }
+            // Messages the CLI makes up itself, e.g. for API errors, have
+            // the model `<synthetic>`.
+            let model = message
+                .get("model")
+                .and_then(|v| v.as_str())
+                .filter(|model| *model != "<synthetic>");
+            if let Some(model) = model {
+                info.model = Some(model.to_string());
+            }
+            let id = message.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            if !id.is_empty() && !counted.insert(id.to_string()) {
+                continue;
+            }
+            let usage = message.get("usage").map(parse_usage).unwrap_or_default();
+            info.usage += usage;
+            let cost = entry.get("costUSD").and_then(|v| v.as_f64()).or_else(|| {
+                self.price_table
+                    .as_ref()
+                    .zip(model)
+                    .and_then(|(table, model)| table.cost_usd(model, &usage))
+            });
+            if let Some(cost) = cost {
+                *message_costs.get_or_insert(0.0) += cost;
+            }
+        }
+        info.total_cost_usd = recorded_total.or(message_costs);
+        Ok(info)
+    }
+}
+
+impl Default for ClaudeHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The JSON entries of a session file with their line numbers.
+async fn read_entries(path: &Path) -> Result<Vec<(usize, Value)>> {
+    let text = tokio::fs::read_to_string(path).await?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map(|entry| (i + 1, entry))
+                .map_err(|e| Error::MessageParse(format!("{}:{}: {}", path.display(), i + 1, e)))
+        })
+        .collect()
+}
+
+/// Whether `entry` is a user or assistant message of the main
+/// conversation.
+fn is_conversation(entry: &Value) -> bool {
+    let flag = |key: &str| entry.get(key).and_then(|v| v.as_bool()) == Some(true);
+    matches!(
+        entry.get("type").and_then(|v| v.as_str()),
+        Some("user" | "assistant")
+    ) && !flag("isSidechain")
+        && !flag("isMeta")
+}
+
+/// Rename the keys the history spells differently from `stream-json`.
+fn to_stream_json(mut entry: Value) -> Value {
+    if let Some(obj) = entry.as_object_mut()
+        && let Some(result) = obj.remove("toolUseResult")
+    {
+        obj.entry("tool_use_result").or_insert(result);
+    }
+    entry
+}
+
+/// The text of a user message typed by the user, as opposed to tool
+/// results and slash-command output.
+fn prompt_text(message: &Value) -> Option<String> {
+    let parsed = parse_message(&serde_json::json!({"type": "user", "message": message})).ok()??;
+    let Message::User(user) = parsed else {
+        return None;
+    };
+    let text = match user.content {
+        UserContent::String(text) => text,
+        UserContent::Blocks(blocks) => blocks
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(t) => Some(t.text),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    let text = text.trim();
+    let is_command = text.starts_with("<command-") || text.starts_with("<local-command-");
+    (!text.is_empty() && !is_command).then(|| text.to_string())
+}
@@ -1,11 +1,12 @@
 //! Message parser for Claude CLI output.
 //!
 //! Parses JSON messages from the Claude CLI's `stream-json` output format
-//! into the SDK's [`Message`] types.
+//! into the SDK's [`Message`] types, and serializes them back.
 
 use crate::error::{Error, Result};
 use crate::types::*;
-use serde_json::Value;
+use crate::usage::Usage;
+use serde_json::{Value, json};
 
 /// Parse a JSON message from Claude CLI output into a typed [`Message`].
 ///
@@ -38,6 +39,75 @@ pub fn parse_message(data: &serde_json::Value) -> Result<Option<Message>> {
     }
 }
 
+/// Serialize a [`Message`] in the Claude CLI's `stream-json` format.
+///
+/// The inverse of [`parse_message`]: parsing the result yields the same
+/// message. This is the serde representation of [`Message`].
+pub fn message_to_json(message: &Message) -> Value {
+    let mut obj = serde_json::Map::new();
+    match message {
+        Message::User(m) => {
+            obj.insert("type".into(), "user".into());
+            obj.insert(
+                "message".into(),
+                json!({"role": "user", "content": m.content}),
+            );
+            insert_some(&mut obj, "uuid", &m.uuid);
+            obj.insert("parent_tool_use_id".into(), json!(m.parent_tool_use_id));
+            insert_some(&mut obj, "tool_use_result", &m.tool_use_result);
+        }
+        Message::Assistant(m) => {
+            obj.insert("type".into(), "assistant".into());
+            obj.insert(
+                "message".into(),
+                json!({"content": m.content, "model": m.model}),
+            );
+            obj.insert("parent_tool_use_id".into(), json!(m.parent_tool_use_id));
+            insert_some(&mut obj, "error", &m.error);
+        }
+        Message::System(m) => {
+            if let Value::Object(data) = &m.data {
+                obj.extend(data.clone());
+            }
+            obj.insert("type".into(), "system".into());
+            obj.insert("subtype".into(), json!(m.subtype));
+        }
+        Message::Result(m) => {
+            obj.insert("type".into(), "result".into());
+            obj.insert("subtype".into(), json!(m.subtype));
+            obj.insert("duration_ms".into(), json!(m.duration_ms));
+            obj.insert("duration_api_ms".into(), json!(m.duration_api_ms));
+            obj.insert("is_error".into(), json!(m.is_error));
+            obj.insert("num_turns".into(), json!(m.num_turns));
+            obj.insert("session_id".into(), json!(m.session_id));
+            insert_some(&mut obj, "total_cost_usd", &m.total_cost_usd);
+            insert_some(&mut obj, "usage", &m.usage);
+            insert_some(&mut obj, "result", &m.result);
+            insert_some(&mut obj, "structured_output", &m.structured_output);
+        }
+        Message::StreamEvent(m) => {
+            obj.insert("type".into(), "stream_event".into());
+            obj.insert("uuid".into(), json!(m.uuid));
+            obj.insert("session_id".into(), json!(m.session_id));
+            obj.insert("event".into(), m.event.clone());
+            obj.insert("parent_tool_use_id".into(), json!(m.parent_tool_use_id));
+        }
+    }
+    Value::Object(obj)
+}
+
+/// Optional fields are omitted rather than written as `null`, which the
+/// parser would read back as a value for `serde_json::Value` fields.
+fn insert_some<T: serde::Serialize>(
+    obj: &mut serde_json::Map<String, Value>,
+    key: &str,
+    value: &Option<T>,
+) {
+    if let Some(value) = value {
+        obj.insert(key.to_string(), json!(value));
+    }
+}
+
 fn type_name(v: &Value) -> &'static str {
     match v {
         Value::Null => "null",
@@ -260,12 +330,25 @@ fn parse_result_message(obj: &serde_json::Map<String, Value>) -> Result<Option<M
         num_turns,
         session_id,
         total_cost_usd: obj.get("total_cost_usd").and_then(|v| v.as_f64()),
-        usage: obj.get("usage").cloned(),
+        usage: obj.get("usage").map(parse_usage),
         result: obj.get("result").and_then(|v| v.as_str()).map(String::from),
         structured_output: obj.get("structured_output").cloned(),
     })))
 }
 
+/// Anthropic `usage` object. Claude reports no reasoning token count; it is
+/// only present in usage written by [`message_to_json`].
+pub(crate) fn parse_usage(usage: &Value) -> Usage {
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    Usage {
+        input_tokens: count("input_tokens"),
+        output_tokens: count("output_tokens"),
+        cache_read_input_tokens: count("cache_read_input_tokens"),
+        cache_creation_input_tokens: count("cache_creation_input_tokens"),
+        reasoning_output_tokens: count("reasoning_output_tokens"),
+    }
+}
+
 fn parse_stream_event(obj: &serde_json::Map<String, Value>) -> Result<Option<Message>> {
     let uuid = obj
         .get("uuid")
@@ -6,10 +6,11 @@
 
 pub mod cli_finder;
 pub mod command_builder;
+pub mod history;
 pub mod message_parser;
 pub mod transport;
 
-use crate::backend::{Backend, Capabilities, Session};
+use crate::backend::{Backend, Capabilities, InterruptHandle, Session};
 use crate::error::{Error, Result};
 use crate::internal::query::Query;
 use crate::options::AgentOptions;
@@ -174,22 +175,21 @@ impl Backend for ClaudeBackend {
         options: &AgentOptions,
         prompt: Option<Prompt>,
     ) -> Result<Box<dyn Session + Send>> {
-        let mut configured_options = options.clone();
-        if configured_options.can_use_tool.is_some() {
-            if configured_options.permission_prompt_tool_name.is_some() {
-                return Err(Error::Other(
-                    "can_use_tool callback cannot be used with permission_prompt_tool_name. \
-                     Please use one or the other."
-                        .to_string(),
-                ));
-            }
-            configured_options.permission_prompt_tool_name = Some("stdio".to_string());
-        }
-
+        let configured_options = configure_permission_prompt(options)?;
         let mut transport = transport::ClaudeCliTransport::new(configured_options.clone())?;
         transport.connect().await?;
 
-        let transport: Box<dyn Transport + Send> = Box::new(transport);
+        self.create_session_with_transport(options, prompt, Box::new(transport))
+            .await
+    }
+
+    async fn create_session_with_transport(
+        &self,
+        options: &AgentOptions,
+        prompt: Option<Prompt>,
+        transport: Box<dyn Transport + Send>,
+    ) -> Result<Box<dyn Session + Send>> {
+        let configured_options = configure_permission_prompt(options)?;
         let mut query = Query::new(transport, &configured_options);
         query.initialize(&configured_options).await?;
 
@@ -201,6 +201,22 @@ impl Backend for ClaudeBackend {
     }
 }
 
+/// Route `can_use_tool` through the stdio permission prompt tool.
+fn configure_permission_prompt(options: &AgentOptions) -> Result<AgentOptions> {
+    let mut configured_options = options.clone();
+    if configured_options.can_use_tool.is_some() {
+        if configured_options.permission_prompt_tool_name.is_some() {
+            return Err(Error::Other(
+                "can_use_tool callback cannot be used with permission_prompt_tool_name. \
+                 Please use one or the other."
+                    .to_string(),
+            ));
+        }
+        configured_options.permission_prompt_tool_name = Some("stdio".to_string());
+    }
+    Ok(configured_options)
+}
+
 /// Multi-turn session for the Claude backend, wrapping [`Query`].
 struct ClaudeSession {
     query: Query,
@@ -234,6 +250,14 @@ impl Session for ClaudeSession {
         self.query.get_server_info().await
     }
 
+    fn interrupt_handle(&self) -> Option<InterruptHandle> {
+        Some(self.query.interrupt_handle())
+    }
+
+    fn subscribe(&self) -> Option<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        Some(self.query.receive_messages())
+    }
+
     async fn close(&mut self) -> Result<()> {
         self.query.close().await
     }
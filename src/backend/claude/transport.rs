@@ -2,7 +2,7 @@
 
 use crate::error::{Error, Result};
 use crate::options::AgentOptions;
-use crate::transport::Transport;
+use crate::transport::{JsonLines, LaunchSpec, Transport, configured_launcher};
 use async_stream::stream;
 use std::path::Path;
 use std::process::Stdio;
@@ -12,12 +12,12 @@ use tokio::process::{Child, ChildStdin, ChildStdout, Command};
 use super::cli_finder;
 use super::command_builder;
 
-const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024; // 1MB
-
 /// Subprocess-based transport for Claude Code CLI.
 ///
 /// Spawns the Claude CLI as a child process and communicates via stdin/stdout
-/// using the `stream-json` output format.
+/// using the `stream-json` output format. When
+/// [`AgentOptions::launcher`] is set, the CLI is started through it instead
+/// and all I/O is delegated to the transport it returns.
 pub struct ClaudeCliTransport {
     options: AgentOptions,
     cli_path: String,
@@ -26,7 +26,8 @@ pub struct ClaudeCliTransport {
     stdin: Option<ChildStdin>,
     stdout: Option<ChildStdout>,
     ready: bool,
-    max_buffer_size: usize,
+    max_buffer_size: Option<usize>,
+    launched: Option<Box<dyn Transport + Send>>,
 }
 
 impl ClaudeCliTransport {
@@ -41,7 +42,7 @@ impl ClaudeCliTransport {
             .cwd
             .as_ref()
             .map(|p| p.to_string_lossy().to_string());
-        let max_buffer_size = options.max_buffer_size.unwrap_or(DEFAULT_MAX_BUFFER_SIZE);
+        let max_buffer_size = options.max_buffer_size;
 
         Ok(Self {
             options,
@@ -52,6 +53,7 @@ impl ClaudeCliTransport {
             stdout: None,
             ready: false,
             max_buffer_size,
+            launched: None,
         })
     }
 }
@@ -59,7 +61,29 @@ impl ClaudeCliTransport {
 #[async_trait::async_trait]
 impl Transport for ClaudeCliTransport {
     async fn connect(&mut self) -> Result<()> {
-        if self.process.is_some() {
+        if self.process.is_some() || self.launched.is_some() {
+            return Ok(());
+        }
+
+        if let Some(launcher) = configured_launcher(&self.options)? {
+            if self.options.user.is_some() {
+                return Err(Error::Other(
+                    "AgentOptions::user is not supported with a custom launcher".to_string(),
+                ));
+            }
+            let cmd = command_builder::build_command(&self.cli_path, &self.options);
+            let mut spec = LaunchSpec::from_options(cmd[0].clone(), &self.options)
+                .args(cmd[1..].to_vec())
+                .env("CLAUDE_CODE_ENTRYPOINT", "sdk-rs")
+                .env("CLAUDE_AGENT_SDK_VERSION", env!("CARGO_PKG_VERSION"));
+            if self.options.enable_file_checkpointing {
+                spec = spec.env("CLAUDE_CODE_ENABLE_SDK_FILE_CHECKPOINTING", "true");
+            }
+            if let Some(ref cwd) = self.cwd {
+                spec = spec.env("PWD", cwd.clone());
+            }
+            self.launched = Some(launcher.launch(spec).await?);
+            self.ready = true;
             return Ok(());
         }
 
@@ -149,6 +173,9 @@ impl Transport for ClaudeCliTransport {
     }
 
     async fn write(&mut self, data: &str) -> Result<()> {
+        if let Some(ref mut launched) = self.launched {
+            return launched.write(data).await;
+        }
         if !self.ready {
             return Err(Error::Other("Transport not ready for writing".to_string()));
         }
@@ -165,8 +192,11 @@ impl Transport for ClaudeCliTransport {
     fn read_messages(
         &mut self,
     ) -> std::pin::Pin<Box<dyn futures::Stream<Item = Result<serde_json::Value>> + Send>> {
+        if let Some(ref mut launched) = self.launched {
+            return launched.read_messages();
+        }
         let stdout = self.stdout.take();
-        let max_buffer_size = self.max_buffer_size;
+        let mut json_lines = JsonLines::new(self.max_buffer_size);
 
         let stream = stream! {
             let stdout = match stdout {
@@ -177,42 +207,18 @@ impl Transport for ClaudeCliTransport {
                 }
             };
 
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            let mut json_buffer = String::new();
+            let mut lines = BufReader::new(stdout).lines();
 
             loop {
                 match lines.next_line().await {
-                    Ok(Some(line)) => {
-                        let line = line.trim();
-                        if line.is_empty() {
-                            continue;
-                        }
-                        for json_line in line.split('\n') {
-                            let json_line = json_line.trim();
-                            if json_line.is_empty() {
-                                continue;
-                            }
-                            json_buffer.push_str(json_line);
-
-                            if json_buffer.len() > max_buffer_size {
-                                json_buffer.clear();
-                                yield Err(Error::Other(format!(
-                                    "JSON message exceeded maximum buffer size of {} bytes",
-                                    max_buffer_size
-                                )));
-                                return;
-                            }
-
-                            match serde_json::from_str(&json_buffer) {
-                                Ok(data) => {
-                                    json_buffer.clear();
-                                    yield Ok(data);
-                                }
-                                Err(_) => continue,
-                            }
+                    Ok(Some(line)) => match json_lines.push(&line) {
+                        Ok(Some(data)) => yield Ok(data),
+                        Ok(None) => {}
+                        Err(e) => {
+                            yield Err(e);
+                            return;
                         }
-                    }
+                    },
                     Ok(None) => break,
                     Err(e) => {
                         yield Err(Error::Connection(e));
@@ -227,6 +233,9 @@ impl Transport for ClaudeCliTransport {
 
     async fn close(&mut self) -> Result<()> {
         self.ready = false;
+        if let Some(mut launched) = self.launched.take() {
+            return launched.close().await;
+        }
         self.stdin = None;
         self.stdout = None;
         if let Some(mut process) = self.process.take() {
@@ -241,6 +250,9 @@ impl Transport for ClaudeCliTransport {
     }
 
     async fn end_input(&mut self) -> Result<()> {
+        if let Some(ref mut launched) = self.launched {
+            return launched.end_input().await;
+        }
         if let Some(stdin) = self.stdin.take() {
             drop(stdin);
         }
@@ -1,39 +1,53 @@
 //! Codex app-server multi-turn session using JSON-RPC 2.0.
 //!
-//! Spawns `codex app-server` as a long-lived subprocess and communicates
-//! via stdin/stdout using the JSON-RPC 2.0 protocol.
+//! Launches `codex app-server` as a long-lived subprocess (or uses a custom
+//! transport) and communicates over JSON lines using the JSON-RPC 2.0
+//! protocol.
 //!
 //! ## Protocol Flow
 //!
 //! 1. Client sends `initialize` request -> server responds with capabilities
 //! 2. Client sends `initialized` notification
 //! 3. Client sends `thread/start` request -> server responds with `threadId`
+//!    (`thread/resume` or `thread/fork` for an existing thread, see
+//!    [`AgentOptions::resume`]; `thread/list` lists threads)
 //! 4. Server sends `thread/started` notification
 //! 5. Client sends `turn/start` request with user input
 //! 6. Server sends `item/*` notifications and `turn/completed` notification
 //! 7. For approval: server sends `item/commandExecution/requestApproval` request,
 //!    client responds with `{decision: "accept"|"decline"}`
+//! 8. For SDK MCP tools, registered as `dynamicTools` with `thread/start`:
+//!    server sends `item/tool/call`, client runs the tool and responds with
+//!    its `contentItems`
 
-use crate::backend::Session;
+use crate::backend::{InterruptHandle, Session};
 use crate::error::{Error, Result};
-use crate::options::AgentOptions;
+use crate::internal::cancel::CancelScope;
+use crate::internal::cost::CostEstimator;
+use crate::internal::fanout::Fanout;
+use crate::internal::stream_delta::DeltaEmitter;
+use crate::options::{AgentOptions, TimeoutConfig};
+use crate::transport::{LaunchSpec, Transport, launch};
 use crate::types::{Message, Prompt};
 use async_stream::stream;
 use futures::Stream;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use std::collections::VecDeque;
+use std::path::Path;
 use std::pin::Pin;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::Child;
-use tokio::sync::{broadcast, mpsc};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
-use super::exec_transport::find_codex_cli;
+use super::dynamic_tools::DynamicTools;
+use super::exec_transport::{extra_cli_args, find_codex_cli, reasoning_effort};
 use super::jsonrpc;
 use super::message_parser;
 
-const MESSAGE_BUFFER_SIZE: usize = 100;
-const INITIALIZE_TIMEOUT_SECS: u64 = 60;
-const CLOSE_TIMEOUT_SECS: u64 = 5;
+/// Most server requests answered at once; further ones wait for a slot.
+const MAX_CONCURRENT_SERVER_REQUESTS: usize = 16;
 
 /// Internal message type for the app-server protocol.
 #[derive(Debug, Clone)]
@@ -48,16 +62,54 @@ enum AppServerMessage {
     Error(String),
 }
 
+/// Summary of a Codex thread, as listed by `thread/list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodexThreadInfo {
+    /// Pass to [`AgentOptionsBuilder::resume`](crate::options::AgentOptionsBuilder::resume)
+    /// to continue the thread.
+    pub id: String,
+    /// Start of the thread's first user message.
+    pub preview: Option<String>,
+    pub model_provider: Option<String>,
+    pub cwd: Option<String>,
+    /// Unix timestamps in seconds.
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    /// Where the CLI stores the thread's rollout.
+    pub path: Option<String>,
+}
+
+impl CodexThreadInfo {
+    fn from_json(thread: &serde_json::Value) -> Option<Self> {
+        let text = |key: &str| thread.get(key).and_then(|v| v.as_str()).map(String::from);
+        let time = |key: &str| thread.get(key).and_then(|v| v.as_i64());
+        Some(Self {
+            id: text("id").filter(|id| !id.is_empty())?,
+            preview: text("preview").filter(|p| !p.is_empty()),
+            model_provider: text("modelProvider"),
+            cwd: text("cwd"),
+            created_at: time("createdAt"),
+            updated_at: time("updatedAt"),
+            path: text("path"),
+        })
+    }
+}
+
 /// Multi-turn session for the Codex app-server.
 pub struct CodexSession {
     write_tx: Option<mpsc::Sender<String>>,
-    message_tx: broadcast::Sender<AppServerMessage>,
-    id_gen: jsonrpc::RequestIdGenerator,
+    message_tx: Fanout<AppServerMessage>,
+    id_gen: Arc<jsonrpc::RequestIdGenerator>,
     thread_id: Option<String>,
+    /// Settings sent with every `turn/start`.
+    turn_settings: serde_json::Map<String, serde_json::Value>,
+    /// SDK MCP tools registered with new threads.
+    dynamic_tools: Option<Arc<DynamicTools>>,
     can_use_tool: Option<crate::options::CanUseToolCallback>,
+    timeouts: TimeoutConfig,
+    cancel: Arc<CancelScope>,
     write_task: Option<JoinHandle<()>>,
     read_task: Option<JoinHandle<()>>,
-    process: Option<Child>,
 }
 
 impl std::fmt::Debug for CodexSession {
@@ -72,111 +124,133 @@ impl std::fmt::Debug for CodexSession {
     }
 }
 
+impl Drop for CodexSession {
+    fn drop(&mut self) {
+        self.cancel.close();
+    }
+}
+
 impl CodexSession {
     /// Create and initialize a new Codex app-server session.
+    ///
+    /// The app-server is started through
+    /// [`AgentOptions::launcher`](crate::options::AgentOptions::launcher).
     pub async fn new(options: &AgentOptions, prompt: Option<Prompt>) -> Result<Self> {
-        let cli_path = find_codex_cli(options)?;
-
-        let mut cmd_args = vec!["app-server".to_string()];
-
-        if let Some(ref codex_opts) = options.codex
-            && let Some(ref policy) = codex_opts.approval_policy
-        {
-            cmd_args.push("-c".to_string());
-            cmd_args.push(format!("approval_policy=\"{}\"", policy));
-        }
-
-        let mut child_cmd = tokio::process::Command::new(&cli_path);
-        child_cmd
-            .args(&cmd_args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null());
+        let transport = launch_app_server(options).await?;
+        Self::with_transport(transport, options, prompt).await
+    }
 
-        if let Some(ref cwd) = options.cwd {
-            child_cmd.current_dir(cwd);
-        }
+    /// Create and initialize a session over an already connected transport
+    /// speaking the app-server JSON-RPC protocol.
+    pub async fn with_transport(
+        transport: Box<dyn Transport + Send>,
+        options: &AgentOptions,
+        prompt: Option<Prompt>,
+    ) -> Result<Self> {
+        let mut session = Self::connect(transport, options).await?;
+        session.thread_id = Some(session.open_thread(options).await?);
 
-        for (k, v) in &options.env {
-            child_cmd.env(k, v);
+        // Keep connect semantics consistent across backends:
+        // Prompt::Text is accepted but not auto-sent.
+        if let Some(prompt) = prompt {
+            match prompt {
+                Prompt::Text(_) => {}
+                Prompt::Stream(_) => {
+                    return Err(Error::Other(
+                        "Codex session does not support stream prompts for initial message"
+                            .to_string(),
+                    ));
+                }
+            }
         }
 
-        let mut process = child_cmd.spawn().map_err(|e| {
-            Error::CliNotFound(format!("Codex CLI not found at: {} - {}", cli_path, e))
-        })?;
-
-        let stdin = process
-            .stdin
-            .take()
-            .ok_or_else(|| Error::Other("Failed to capture stdin".to_string()))?;
-        let stdout = process
-            .stdout
-            .take()
-            .ok_or_else(|| Error::Other("Failed to capture stdout".to_string()))?;
+        Ok(session)
+    }
 
-        let (message_tx, _) = broadcast::channel(MESSAGE_BUFFER_SIZE);
+    /// Start the app-server's I/O tasks and complete the initialize
+    /// handshake, without opening a thread.
+    async fn connect(
+        mut transport: Box<dyn Transport + Send>,
+        options: &AgentOptions,
+    ) -> Result<Self> {
+        let message_tx = Fanout::new();
         let (write_tx, mut write_rx) = mpsc::channel::<String>(64);
+        let (read_done_tx, read_done_rx) = oneshot::channel::<()>();
+        let mut read_stream = transport.read_messages();
+        let timeouts = options.timeouts;
 
-        // Write task
-        let mut stdin_writer = stdin;
+        // Write task; owns the transport and shuts it down once the session
+        // drops its sender.
         let write_task = tokio::spawn(async move {
             while let Some(msg) = write_rx.recv().await {
-                if stdin_writer
-                    .write_all(format!("{}\n", msg).as_bytes())
-                    .await
-                    .is_err()
-                {
+                if transport.write(&format!("{}\n", msg)).await.is_err() {
                     break;
                 }
-                let _ = stdin_writer.flush().await;
             }
+            let _ = transport.end_input().await;
+            // Give the app-server a chance to exit on EOF before killing it.
+            let _ = tokio::time::timeout(timeouts.shutdown_grace, read_done_rx).await;
+            let _ = transport.close().await;
         });
 
         // Read task
         let msg_tx = message_tx.clone();
         let can_use_tool_for_read = options.can_use_tool.clone();
-        let write_tx_for_read = write_tx.clone();
+        let dynamic_tools = DynamicTools::from_options(options).map(Arc::new);
+        let dynamic_tools_for_read = dynamic_tools.clone();
+        let write_tx_for_read = write_tx.downgrade();
 
-        let read_task = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
+        let include_partial_messages = options.include_partial_messages;
+        let mut cost = CostEstimator::new(options);
+        let cancel = Arc::new(CancelScope::new());
+        let cancel_for_read = Arc::clone(&cancel);
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
+        let read_task = tokio::spawn(async move {
+            use futures::StreamExt;
+
+            let mut deltas = DeltaEmitter::default();
+            let mut requests = ServerRequests {
+                can_use_tool: can_use_tool_for_read,
+                dynamic_tools: dynamic_tools_for_read,
+                write_tx: write_tx_for_read,
+                cancel: Arc::clone(&cancel_for_read),
+                in_flight: FuturesUnordered::new(),
+                queued: VecDeque::new(),
+            };
 
-                let data: serde_json::Value = match serde_json::from_str(line) {
+            loop {
+                let item = tokio::select! {
+                    item = read_stream.next() => item,
+                    _ = requests.next_answered() => continue,
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                let data = match item {
                     Ok(d) => d,
-                    Err(_) => continue,
+                    Err(e) => {
+                        msg_tx.send(AppServerMessage::Error(e.to_string()));
+                        break;
+                    }
                 };
 
                 if jsonrpc::is_response(&data) {
-                    let _ = msg_tx.send(AppServerMessage::Response(data));
+                    // thread/start reports the model the thread runs on.
+                    if let Some(model) = data
+                        .get("result")
+                        .and_then(|r| r.get("model"))
+                        .and_then(|v| v.as_str())
+                    {
+                        cost.set_model(model);
+                    }
+                    msg_tx.send(AppServerMessage::Response(data));
                     continue;
                 }
 
                 if jsonrpc::is_request(&data) {
-                    // Handle server requests (approval, tool calls)
-                    let method = jsonrpc::get_method(&data).unwrap_or("").to_string();
-                    let id = data.get("id").cloned().unwrap_or(serde_json::Value::Null);
-                    let params = data
-                        .get("params")
-                        .cloned()
-                        .unwrap_or(serde_json::Value::Null);
-
-                    let response = handle_server_request(
-                        &method,
-                        &id,
-                        &params,
-                        can_use_tool_for_read.as_ref(),
-                    )
-                    .await;
-
-                    if let Ok(resp_str) = serde_json::to_string(&response) {
-                        let _ = write_tx_for_read.send(resp_str).await;
-                    }
+                    // Server requests (approvals, tool calls) run alongside
+                    // the notifications that follow them.
+                    requests.submit(data);
                     continue;
                 }
 
@@ -187,152 +261,215 @@ impl CodexSession {
                         .cloned()
                         .unwrap_or(serde_json::Value::Null);
 
+                    if include_partial_messages {
+                        for msg in
+                            message_parser::app_server_stream_events(method, &params, &mut deltas)
+                        {
+                            msg_tx.send(AppServerMessage::SdkMessage(msg));
+                        }
+                    }
                     match message_parser::parse_app_server_notification(method, &params) {
-                        Ok(Some(msg)) => {
-                            let _ = msg_tx.send(AppServerMessage::SdkMessage(msg));
+                        Ok(Some(mut msg)) => {
+                            cost.apply(&mut msg);
+                            msg_tx.send(AppServerMessage::SdkMessage(msg));
                         }
                         Ok(None) => {}
                         Err(e) => {
-                            let _ =
-                                msg_tx.send(AppServerMessage::Error(format!("Parse error: {}", e)));
+                            msg_tx.send(AppServerMessage::Error(format!("Parse error: {}", e)));
                         }
                     }
                     continue;
                 }
             }
 
-            let _ = msg_tx.send(AppServerMessage::End);
+            cancel_for_read.close();
+            let _ = read_done_tx.send(());
+            msg_tx.send(AppServerMessage::End);
         });
 
-        let id_gen = jsonrpc::RequestIdGenerator::new();
-
-        // Initialize the app-server
-        let init_id = id_gen.next_id();
-        let init_request = jsonrpc::build_request(
-            init_id,
-            "initialize",
-            serde_json::json!({
-                "clientName": "code-agent-sdk",
-                "clientVersion": env!("CARGO_PKG_VERSION"),
-            }),
-        );
-
-        // Subscribe before sending to avoid missing fast responses.
-        let mut rx = message_tx.subscribe();
-        write_tx
-            .send(serde_json::to_string(&init_request)?)
-            .await
-            .map_err(|_| Error::Other("Write channel closed".to_string()))?;
-
-        // Wait for initialize response
-        let init_result = tokio::time::timeout(
-            std::time::Duration::from_secs(INITIALIZE_TIMEOUT_SECS),
-            async {
-                loop {
-                    match rx.recv().await {
-                        Ok(AppServerMessage::Response(resp)) => {
-                            if jsonrpc::get_id(&resp) == Some(init_id) {
-                                return Ok(resp);
-                            }
-                        }
-                        Ok(AppServerMessage::End) | Ok(AppServerMessage::Error(_)) => {
-                            return Err(Error::Other(
-                                "App-server stream ended before initialize response".to_string(),
-                            ));
-                        }
-                        Err(_) => {
-                            return Err(Error::Other("Channel closed".to_string()));
-                        }
-                        _ => continue,
-                    }
-                }
-            },
-        )
-        .await
-        .map_err(|_| Error::Other("Initialize timeout".to_string()))??;
-
-        let _ = init_result; // Could extract server capabilities here
-
-        // Send initialized notification
-        let initialized_notif = jsonrpc::build_notification("initialized", serde_json::json!({}));
-        write_tx
-            .send(serde_json::to_string(&initialized_notif)?)
-            .await
-            .map_err(|_| Error::Other("Write channel closed".to_string()))?;
-
-        let mut session = Self {
+        let session = Self {
             write_tx: Some(write_tx),
             message_tx,
-            id_gen,
+            id_gen: Arc::new(jsonrpc::RequestIdGenerator::new()),
             thread_id: None,
+            turn_settings: turn_settings(options),
+            dynamic_tools,
             can_use_tool: options.can_use_tool.clone(),
+            timeouts,
+            cancel,
             write_task: Some(write_task),
             read_task: Some(read_task),
-            process: Some(process),
         };
 
-        // Start a thread
-        let thread_start_id = session.id_gen.next_id();
-        let thread_start_req =
-            jsonrpc::build_request(thread_start_id, "thread/start", serde_json::json!({}));
+        let mut init_params = serde_json::json!({
+            "clientName": "code-agent-sdk",
+            "clientVersion": env!("CARGO_PKG_VERSION"),
+        });
+        // Dynamic tools are part of the app-server's experimental API.
+        if session.dynamic_tools.is_some() {
+            init_params["capabilities"] = serde_json::json!({"experimentalApi": true});
+        }
+        // Could extract server capabilities from the result here.
+        session
+            .request("initialize", init_params, timeouts.initialize)
+            .await?;
 
-        // Subscribe before sending to avoid missing fast responses.
-        let mut rx = session.message_tx.subscribe();
+        let initialized_notif = jsonrpc::build_notification("initialized", serde_json::json!({}));
         session
-            .send_raw(&serde_json::to_string(&thread_start_req)?)
+            .send_raw(&serde_json::to_string(&initialized_notif)?)
             .await?;
+        Ok(session)
+    }
 
-        // Wait for thread/start response to get threadId
-        let thread_resp = tokio::time::timeout(
-            std::time::Duration::from_secs(INITIALIZE_TIMEOUT_SECS),
-            async {
-                loop {
-                    match rx.recv().await {
-                        Ok(AppServerMessage::Response(resp)) => {
-                            if jsonrpc::get_id(&resp) == Some(thread_start_id) {
-                                return Ok(resp);
-                            }
-                        }
-                        Ok(AppServerMessage::End) | Ok(AppServerMessage::Error(_)) => {
-                            return Err(Error::Other(
-                                "App-server stream ended before thread/start response".to_string(),
-                            ));
-                        }
-                        Err(_) => {
-                            return Err(Error::Other("Channel closed".to_string()));
-                        }
-                        _ => continue,
-                    }
+    /// Start, resume or fork the session's thread with the options' model,
+    /// `cwd` and sandbox settings:
+    ///
+    /// - [`resume`](AgentOptions::resume) continues the thread with that id
+    ///   (`thread/resume`), or branches off a copy of it with
+    ///   [`fork_session`](AgentOptions::fork_session) (`thread/fork`).
+    /// - [`continue_conversation`](AgentOptions::continue_conversation) does
+    ///   the same with the most recently updated thread started in the
+    ///   session's `cwd`, and starts a new thread if there is none.
+    /// - Otherwise a new thread is started (`thread/start`).
+    async fn open_thread(&self, options: &AgentOptions) -> Result<String> {
+        let mut target = options.resume.clone();
+        if target.is_none() && options.continue_conversation {
+            let workspace = match options.cwd {
+                Some(ref cwd) => std::path::absolute(cwd)?,
+                None => std::env::current_dir()?,
+            };
+            target = self
+                .latest_thread_in(&workspace)
+                .await?
+                .map(|thread| thread.id);
+        }
+        let mut params = thread_settings(options);
+        let method = match target {
+            Some(id) => {
+                params.insert("threadId".to_string(), id.into());
+                if options.fork_session {
+                    "thread/fork"
+                } else {
+                    "thread/resume"
                 }
-            },
-        )
-        .await
-        .map_err(|_| Error::Other("thread/start timeout".to_string()))??;
+            }
+            None => {
+                if let Some(ref tools) = self.dynamic_tools {
+                    params.insert("dynamicTools".to_string(), tools.specs());
+                }
+                "thread/start"
+            }
+        };
+        let result = self
+            .request(method, params.into(), self.timeouts.initialize)
+            .await?;
+        thread_id_of(&result)
+            .map(String::from)
+            .ok_or_else(|| Error::Other(format!("{} response has no thread id", method)))
+    }
 
-        let thread_id = thread_resp
-            .get("result")
-            .and_then(|r| r.get("threadId"))
+    /// Threads of the app-server, most recently updated first, or the
+    /// first `limit` of them.
+    pub async fn list_threads(&self, limit: Option<u32>) -> Result<Vec<CodexThreadInfo>> {
+        Ok(self.thread_page(limit, None).await?.0)
+    }
+
+    /// The most recently updated thread whose `cwd` is `workspace`, going
+    /// through `thread/list` a page at a time.
+    async fn latest_thread_in(&self, workspace: &Path) -> Result<Option<CodexThreadInfo>> {
+        let mut cursor = None;
+        loop {
+            let (threads, next) = self.thread_page(None, cursor).await?;
+            let found = threads
+                .into_iter()
+                .find(|thread| thread.cwd.as_deref().map(Path::new) == Some(workspace));
+            match (found, next) {
+                (Some(thread), _) => return Ok(Some(thread)),
+                (None, Some(next)) => cursor = Some(next),
+                (None, None) => return Ok(None),
+            }
+        }
+    }
+
+    /// One page of `thread/list` and the cursor of the next, if any.
+    async fn thread_page(
+        &self,
+        limit: Option<u32>,
+        cursor: Option<String>,
+    ) -> Result<(Vec<CodexThreadInfo>, Option<String>)> {
+        let mut params = serde_json::json!({});
+        if let Some(limit) = limit {
+            params["limit"] = limit.into();
+        }
+        if let Some(cursor) = cursor {
+            params["cursor"] = cursor.into();
+        }
+        let result = self
+            .request("thread/list", params, self.timeouts.control_request)
+            .await?;
+        let threads = result
+            .get("data")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(CodexThreadInfo::from_json)
+            .collect();
+        let next = result
+            .get("nextCursor")
             .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
+            .map(String::from);
+        Ok((threads, next))
+    }
 
-        session.thread_id = Some(thread_id.clone());
+    /// Send a JSON-RPC request and wait up to `timeout` for its result.
+    async fn request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        timeout: std::time::Duration,
+    ) -> Result<serde_json::Value> {
+        let id = self.id_gen.next_id();
+        let request = jsonrpc::build_request(id, method, params);
 
-        // Keep connect semantics consistent across backends:
-        // Prompt::Text is accepted but not auto-sent.
-        if let Some(prompt) = prompt {
-            match prompt {
-                Prompt::Text(_) => {}
-                Prompt::Stream(_) => {
-                    return Err(Error::Other(
-                        "Codex session does not support stream prompts for initial message"
-                            .to_string(),
-                    ));
+        // Subscribe before sending to avoid missing fast responses.
+        let mut rx = self.message_tx.subscribe_new();
+        self.send_raw(&serde_json::to_string(&request)?).await?;
+
+        let response = tokio::time::timeout(timeout, async {
+            loop {
+                match rx.recv().await {
+                    Ok(Some(AppServerMessage::Response(resp))) => {
+                        if jsonrpc::get_id(&resp) == Some(id) {
+                            return Ok(resp);
+                        }
+                    }
+                    Ok(Some(AppServerMessage::End))
+                    | Ok(Some(AppServerMessage::Error(_)))
+                    | Ok(None) => {
+                        return Err(Error::Other(format!(
+                            "App-server stream ended before {} response",
+                            method
+                        )));
+                    }
+                    Err(e) => return Err(e),
+                    _ => continue,
                 }
             }
+        })
+        .await
+        .map_err(|_| Error::ControlTimeout(method.to_string()))??;
+
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown error");
+            return Err(Error::Other(format!("{} failed: {}", method, message)));
         }
-
-        Ok(session)
+        Ok(response
+            .get("result")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
     }
 
     async fn send_raw(&self, data: &str) -> Result<()> {
@@ -350,21 +487,181 @@ impl CodexSession {
             .as_ref()
             .ok_or_else(|| Error::Other("No active thread".to_string()))?;
 
+        self.message_tx.begin_turn();
         let turn_id = self.id_gen.next_id();
-        let turn_request = jsonrpc::build_request(
-            turn_id,
-            "turn/start",
-            serde_json::json!({
-                "threadId": thread_id,
-                "input": [{
-                    "role": "user",
-                    "content": prompt,
-                }],
-            }),
+        let mut params = self.turn_settings.clone();
+        params.insert("threadId".to_string(), thread_id.as_str().into());
+        params.insert(
+            "input".to_string(),
+            serde_json::json!([{
+                "role": "user",
+                "content": prompt,
+            }]),
         );
+        let turn_request = jsonrpc::build_request(turn_id, "turn/start", params.into());
 
         self.send_raw(&serde_json::to_string(&turn_request)?).await
     }
+
+    fn interrupt_request(&self) -> Result<serde_json::Value> {
+        let thread_id = self
+            .thread_id
+            .as_ref()
+            .ok_or_else(|| Error::Other("No active thread".to_string()))?;
+        Ok(interrupt_request(&self.id_gen, thread_id))
+    }
+}
+
+/// Launch `codex app-server` through the options' launcher.
+async fn launch_app_server(options: &AgentOptions) -> Result<Box<dyn Transport + Send>> {
+    let cli_path = find_codex_cli(options)?;
+
+    let mut cmd_args = vec!["app-server".to_string()];
+
+    if let Some(ref codex_opts) = options.codex
+        && let Some(ref policy) = codex_opts.approval_policy
+    {
+        cmd_args.push("-c".to_string());
+        cmd_args.push(format!("approval_policy=\"{}\"", policy));
+    }
+
+    cmd_args.extend(extra_cli_args(options));
+
+    let spec = LaunchSpec::from_options(cli_path, options).args(cmd_args);
+    launch(options, spec).await
+}
+
+/// Per-thread settings for `thread/start`, `thread/resume` and
+/// `thread/fork`, matching what `codex exec` gets on its command line.
+fn thread_settings(options: &AgentOptions) -> serde_json::Map<String, serde_json::Value> {
+    let mut settings = serde_json::Map::new();
+    if let Some(ref model) = options.model {
+        settings.insert("model".to_string(), model.as_str().into());
+    }
+    if let Some(ref cwd) = options.cwd {
+        settings.insert("cwd".to_string(), cwd.to_string_lossy().into());
+    }
+    if let Some(sandbox) = options.codex.as_ref().and_then(|c| c.sandbox_mode.as_ref()) {
+        settings.insert("sandbox".to_string(), sandbox.as_str().into());
+    }
+    if !options.add_dirs.is_empty() {
+        let roots: Vec<_> = options
+            .add_dirs
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect();
+        settings.insert(
+            "config".to_string(),
+            serde_json::json!({"sandbox_workspace_write.writable_roots": roots}),
+        );
+    }
+    settings
+}
+
+/// Per-turn settings for `turn/start`.
+fn turn_settings(options: &AgentOptions) -> serde_json::Map<String, serde_json::Value> {
+    let mut settings = serde_json::Map::new();
+    if let Some(ref effort) = options.effort {
+        settings.insert("effort".to_string(), reasoning_effort(effort).into());
+    }
+    settings
+}
+
+/// Threads recorded by the Codex CLI, most recently updated first, or the
+/// first `limit` of them.
+///
+/// Launches a short-lived `codex app-server` with `options`; pass an id to
+/// [`AgentOptions::resume`] to continue that thread.
+pub async fn list_threads(
+    options: &AgentOptions,
+    limit: Option<u32>,
+) -> Result<Vec<CodexThreadInfo>> {
+    let transport = launch_app_server(options).await?;
+    let mut session = CodexSession::connect(transport, options).await?;
+    let threads = session.list_threads(limit).await;
+    session.close().await?;
+    threads
+}
+
+/// The thread id in a `thread/start`, `thread/resume` or `thread/fork`
+/// result.
+fn thread_id_of(result: &serde_json::Value) -> Option<&str> {
+    result
+        .get("threadId")
+        .or_else(|| result.get("thread").and_then(|t| t.get("id")))
+        .and_then(|v| v.as_str())
+        .filter(|id| !id.is_empty())
+}
+
+fn interrupt_request(id_gen: &jsonrpc::RequestIdGenerator, thread_id: &str) -> serde_json::Value {
+    jsonrpc::build_request(
+        id_gen.next_id(),
+        "turn/interrupt",
+        serde_json::json!({"threadId": thread_id}),
+    )
+}
+
+/// Answers the app-server's requests concurrently, up to
+/// [`MAX_CONCURRENT_SERVER_REQUESTS`] at a time, writing each response as
+/// soon as its callback or tool finishes.
+struct ServerRequests {
+    can_use_tool: Option<crate::options::CanUseToolCallback>,
+    dynamic_tools: Option<Arc<DynamicTools>>,
+    /// Weak so that dropping the session's sender in close() still ends
+    /// the write task and closes the app-server's stdin.
+    write_tx: mpsc::WeakSender<String>,
+    cancel: Arc<CancelScope>,
+    in_flight: FuturesUnordered<BoxFuture<'static, ()>>,
+    queued: VecDeque<serde_json::Value>,
+}
+
+impl ServerRequests {
+    /// Start answering `data`, or queue it if every slot is taken.
+    fn submit(&mut self, data: serde_json::Value) {
+        if self.in_flight.len() >= MAX_CONCURRENT_SERVER_REQUESTS {
+            self.queued.push_back(data);
+            return;
+        }
+        let can_use_tool = self.can_use_tool.clone();
+        let dynamic_tools = self.dynamic_tools.clone();
+        let write_tx = self.write_tx.clone();
+        let signal = self.cancel.callback_token();
+        self.in_flight.push(Box::pin(async move {
+            let method = jsonrpc::get_method(&data).unwrap_or("");
+            let id = data.get("id").cloned().unwrap_or(serde_json::Value::Null);
+            let params = data
+                .get("params")
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+            let response = handle_server_request(
+                method,
+                &id,
+                &params,
+                can_use_tool.as_ref(),
+                dynamic_tools.as_deref(),
+                signal,
+            )
+            .await;
+            if let Ok(resp_str) = serde_json::to_string(&response)
+                && let Some(tx) = write_tx.upgrade()
+            {
+                let _ = tx.send(resp_str).await;
+            }
+        }));
+    }
+
+    /// Wait for a request to be answered and hand its slot to the next
+    /// queued one. Never resolves while nothing is in flight.
+    async fn next_answered(&mut self) {
+        use futures::StreamExt;
+
+        if self.in_flight.next().await.is_none() {
+            return std::future::pending().await;
+        }
+        if let Some(data) = self.queued.pop_front() {
+            self.submit(data);
+        }
+    }
 }
 
 async fn handle_server_request(
@@ -372,6 +669,8 @@ async fn handle_server_request(
     id: &serde_json::Value,
     params: &serde_json::Value,
     can_use_tool: Option<&crate::options::CanUseToolCallback>,
+    dynamic_tools: Option<&DynamicTools>,
+    signal: CancellationToken,
 ) -> serde_json::Value {
     match method {
         "item/commandExecution/requestApproval" => {
@@ -383,7 +682,7 @@ async fn handle_server_request(
                     .to_string();
                 let input = serde_json::json!({"command": command});
                 let ctx = crate::options::ToolPermissionContext {
-                    signal: None,
+                    signal,
                     suggestions: vec![],
                 };
                 let result = cb("Bash".to_string(), input, ctx).await;
@@ -406,7 +705,7 @@ async fn handle_server_request(
                     .to_string();
                 let input = serde_json::json!({"file_path": file_path});
                 let ctx = crate::options::ToolPermissionContext {
-                    signal: None,
+                    signal,
                     suggestions: vec![],
                 };
                 let result = cb("Edit".to_string(), input, ctx).await;
@@ -419,14 +718,12 @@ async fn handle_server_request(
                 jsonrpc::build_response(id.clone(), serde_json::json!({"decision": "accept"}))
             }
         }
-        "item/tool/call" => {
-            // Dynamic tool calls - not supported yet, decline
-            jsonrpc::build_error_response(
-                id.clone(),
-                -32601,
-                "Dynamic tool calls not supported by SDK",
-            )
-        }
+        "item/tool/call" => match dynamic_tools {
+            Some(tools) => jsonrpc::build_response(id.clone(), tools.call(params).await),
+            None => {
+                jsonrpc::build_error_response(id.clone(), -32601, "No SDK MCP tools are registered")
+            }
+        },
         _ => jsonrpc::build_error_response(
             id.clone(),
             -32601,
@@ -435,6 +732,35 @@ async fn handle_server_request(
     }
 }
 
+impl CodexSession {
+    /// Messages from the start of the current turn until the session ends.
+    fn messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+        let mut rx = self.message_tx.subscribe();
+
+        let stream = stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(Some(AppServerMessage::SdkMessage(msg))) => yield Ok(msg),
+                    Ok(Some(AppServerMessage::End)) | Ok(None) => break,
+                    Ok(Some(AppServerMessage::Error(e))) => {
+                        yield Err(Error::Other(e));
+                        break;
+                    }
+                    Ok(Some(AppServerMessage::Response(_))) => {
+                        continue;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
+            }
+        };
+
+        Box::pin(stream)
+    }
+}
+
 #[async_trait::async_trait]
 impl Session for CodexSession {
     async fn send_message(&mut self, prompt: Prompt, _session_id: &str) -> Result<()> {
@@ -450,26 +776,11 @@ impl Session for CodexSession {
     }
 
     fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
-        let mut rx = self.message_tx.subscribe();
-
-        let stream = stream! {
-            loop {
-                match rx.recv().await {
-                    Ok(AppServerMessage::SdkMessage(msg)) => yield Ok(msg),
-                    Ok(AppServerMessage::End) => break,
-                    Ok(AppServerMessage::Error(e)) => {
-                        yield Err(Error::Other(e));
-                        break;
-                    }
-                    Ok(AppServerMessage::Response(_)) => {
-                        continue;
-                    }
-                    Err(_) => break,
-                }
-            }
-        };
+        self.messages()
+    }
 
-        Box::pin(stream)
+    fn subscribe(&self) -> Option<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        Some(self.messages())
     }
 
     fn receive_response(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
@@ -478,22 +789,28 @@ impl Session for CodexSession {
         let stream = stream! {
             loop {
                 match rx.recv().await {
-                    Ok(AppServerMessage::SdkMessage(msg)) => {
+                    Ok(Some(AppServerMessage::SdkMessage(msg))) => {
                         let is_result = matches!(&msg, Message::Result(_));
+                        if is_result {
+                            rx.end_replay();
+                        }
                         yield Ok(msg);
                         if is_result {
                             break;
                         }
                     }
-                    Ok(AppServerMessage::End) => break,
-                    Ok(AppServerMessage::Error(e)) => {
+                    Ok(Some(AppServerMessage::End)) | Ok(None) => break,
+                    Ok(Some(AppServerMessage::Error(e))) => {
                         yield Err(Error::Other(e));
                         break;
                     }
-                    Ok(AppServerMessage::Response(_)) => {
+                    Ok(Some(AppServerMessage::Response(_))) => {
                         continue;
                     }
-                    Err(_) => break,
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
                 }
             }
         };
@@ -512,17 +829,9 @@ impl Session for CodexSession {
 
         match subtype {
             "interrupt" => {
-                let thread_id = self
-                    .thread_id
-                    .as_ref()
-                    .ok_or_else(|| Error::Other("No active thread".to_string()))?;
-                let id = self.id_gen.next_id();
-                let req = jsonrpc::build_request(
-                    id,
-                    "turn/interrupt",
-                    serde_json::json!({"threadId": thread_id}),
-                );
+                let req = self.interrupt_request()?;
                 self.send_raw(&serde_json::to_string(&req)?).await?;
+                self.cancel.cancel_turn();
                 Ok(serde_json::Value::Null)
             }
             _ => Err(Error::UnsupportedFeature {
@@ -532,6 +841,25 @@ impl Session for CodexSession {
         }
     }
 
+    fn interrupt_handle(&self) -> Option<InterruptHandle> {
+        let write_tx = self.write_tx.as_ref()?.downgrade();
+        let id_gen = Arc::clone(&self.id_gen);
+        let thread_id = self.thread_id.clone()?;
+        let cancel = Arc::clone(&self.cancel);
+        Some(InterruptHandle::new(move || {
+            let cancel = Arc::clone(&cancel);
+            let write_tx = write_tx.upgrade();
+            let req = interrupt_request(&id_gen, &thread_id);
+            Box::pin(async move {
+                if let Some(tx) = write_tx {
+                    let _ = tx.send(serde_json::to_string(&req)?).await;
+                }
+                cancel.cancel_turn();
+                Ok(())
+            })
+        }))
+    }
+
     async fn get_server_info(&self) -> Option<serde_json::Value> {
         self.thread_id
             .as_ref()
@@ -539,54 +867,29 @@ impl Session for CodexSession {
     }
 
     async fn close(&mut self) -> Result<()> {
+        self.cancel.close();
+        // Dropping the sender ends the write task, which closes stdin, waits
+        // briefly for the app-server to exit and then closes the transport.
         drop(self.write_tx.take());
 
-        if let Some(mut process) = self.process.take() {
-            let wait_result = tokio::time::timeout(
-                std::time::Duration::from_secs(CLOSE_TIMEOUT_SECS),
-                process.wait(),
-            )
-            .await;
-
-            match wait_result {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
-                    return Err(Error::Other(format!(
-                        "Failed waiting for Codex app-server shutdown: {}",
-                        e
-                    )));
-                }
-                Err(_) => {
-                    let _ = process.kill().await;
-                    let _ = process.wait().await;
-                }
-            }
-        }
-
-        if let Some(mut handle) = self.read_task.take()
-            && tokio::time::timeout(
-                std::time::Duration::from_secs(CLOSE_TIMEOUT_SECS),
-                &mut handle,
-            )
-            .await
-            .is_err()
+        if let Some(mut handle) = self.write_task.take()
+            && tokio::time::timeout(2 * self.timeouts.shutdown_grace, &mut handle)
+                .await
+                .is_err()
         {
             handle.abort();
             let _ = handle.await;
         }
-        if let Some(mut handle) = self.write_task.take()
-            && tokio::time::timeout(
-                std::time::Duration::from_secs(CLOSE_TIMEOUT_SECS),
-                &mut handle,
-            )
-            .await
-            .is_err()
+        if let Some(mut handle) = self.read_task.take()
+            && tokio::time::timeout(self.timeouts.shutdown_grace, &mut handle)
+                .await
+                .is_err()
         {
             handle.abort();
             let _ = handle.await;
         }
 
-        let _ = self.message_tx.send(AppServerMessage::End);
+        self.message_tx.send(AppServerMessage::End);
         Ok(())
     }
 }
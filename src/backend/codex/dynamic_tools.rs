@@ -0,0 +1,126 @@
+//! SDK MCP tools exposed to Codex as app-server dynamic tools.
+//!
+//! Every tool of an [`McpServerConfig::Sdk`](crate::options::McpServerConfig::Sdk) server is registered with
+//! `thread/start` under the name Claude gives it, `mcp__<server>__<tool>`.
+//! When the model calls one, the app-server sends an `item/tool/call`
+//! request, which runs the tool's handler in-process and answers with the
+//! tool's content.
+//!
+//! The thread's tool list cannot change once started, so the servers'
+//! registries are pinned for the session's lifetime: `add_tool` and
+//! `remove_tool` fail instead of silently not applying. Calls still look
+//! tools up in the live registry.
+
+use crate::internal::sdk_mcp::{call_tool, sdk_mcp_servers};
+use crate::mcp::RegistryPin;
+use crate::options::{AgentOptions, McpSdkConfig, SdkMcpTool};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The SDK MCP tools of a session, by the name Codex calls them.
+#[derive(Debug)]
+pub(crate) struct DynamicTools {
+    servers: Arc<HashMap<String, McpSdkConfig>>,
+    _pins: Vec<RegistryPin>,
+}
+
+impl DynamicTools {
+    /// Tools of the SDK MCP servers in [`AgentOptions::mcp_servers`], or
+    /// `None` if there are no such servers. A server without tools is
+    /// still pinned, since tools added to it would not reach the thread.
+    pub(crate) fn from_options(options: &AgentOptions) -> Option<Self> {
+        let servers = sdk_mcp_servers(options)?;
+        let pins = servers
+            .values()
+            .map(|server| server.registry.pin("Codex"))
+            .collect();
+        Some(Self {
+            servers,
+            _pins: pins,
+        })
+    }
+
+    /// The servers' current tools by Codex name, in a stable order.
+    fn tools(&self) -> Vec<(String, SdkMcpTool)> {
+        let mut names: Vec<_> = self.servers.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .flat_map(|server| {
+                self.servers[server]
+                    .active_tools()
+                    .into_iter()
+                    .map(move |tool| (format!("mcp__{}__{}", server, tool.name), tool))
+            })
+            .collect()
+    }
+
+    /// The `dynamicTools` parameter of `thread/start`.
+    pub(crate) fn specs(&self) -> Value {
+        self.tools()
+            .iter()
+            .map(|(name, tool)| {
+                json!({
+                    "name": name,
+                    "description": tool.description,
+                    "inputSchema": tool.input_schema,
+                })
+            })
+            .collect()
+    }
+
+    /// Run the tool an `item/tool/call` request names and return the
+    /// request's result.
+    pub(crate) async fn call(&self, params: &Value) -> Value {
+        let name = params.get("tool").and_then(|v| v.as_str()).unwrap_or("");
+        let Some((_, tool)) = self.tools().into_iter().find(|(n, _)| n == name) else {
+            return failure(&format!("Unknown tool: {}", name));
+        };
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+        let result = call_tool(&tool, arguments).await;
+        let is_error = result.get("isError").and_then(|v| v.as_bool()) == Some(true);
+        let content_items: Vec<_> = result
+            .get("content")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .map(content_item)
+            .collect();
+        json!({"contentItems": content_items, "success": !is_error})
+    }
+}
+
+fn failure(message: &str) -> Value {
+    json!({
+        "contentItems": [{"type": "inputText", "text": message}],
+        "success": false,
+    })
+}
+
+/// An MCP content block as a Codex content item. Images, including
+/// embedded image resources, become data URLs; text resources their text;
+/// other blocks are passed as their JSON text.
+fn content_item(block: &Value) -> Value {
+    let field = |key: &str| block.get(key).and_then(|v| v.as_str());
+    let resource = |key: &str| block["resource"].get(key).and_then(|v| v.as_str());
+    match field("type") {
+        Some("text") => json!({"type": "inputText", "text": field("text").unwrap_or("")}),
+        Some("image") => image_item(field("mimeType"), field("data").unwrap_or("")),
+        Some("resource") => match (resource("text"), resource("blob")) {
+            (Some(text), _) => json!({"type": "inputText", "text": text}),
+            (None, Some(blob)) if resource("mimeType").is_some_and(|m| m.starts_with("image/")) => {
+                image_item(resource("mimeType"), blob)
+            }
+            _ => json!({"type": "inputText", "text": block.to_string()}),
+        },
+        _ => json!({"type": "inputText", "text": block.to_string()}),
+    }
+}
+
+fn image_item(mime_type: Option<&str>, data: &str) -> Value {
+    json!({
+        "type": "inputImage",
+        "imageUrl": format!("data:{};base64,{}", mime_type.unwrap_or("image/png"), data),
+    })
+}
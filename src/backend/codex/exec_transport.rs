@@ -1,17 +1,18 @@
 //! One-shot transport for `codex exec --json`.
 //!
-//! Spawns `codex exec --json <prompt>`, reads JSONL events from stdout,
+//! Launches `codex exec --json <prompt>`, reads JSONL events from stdout,
 //! and maps them to SDK [`Message`] types.
 
 use crate::error::{Error, Result};
-use crate::options::AgentOptions;
+use crate::internal::cost::CostEstimator;
+use crate::internal::mcp_bridge::{BRIDGE_TOKEN_ENV, McpBridge};
+use crate::internal::sdk_mcp::sdk_mcp_servers;
+use crate::options::{AgentOptions, Effort};
+use crate::transport::{LaunchSpec, is_remote, launch};
 use crate::types::{Message, Prompt};
 use async_stream::stream;
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use std::pin::Pin;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
 
 use super::message_parser;
 
@@ -21,6 +22,10 @@ pub fn find_codex_cli(options: &AgentOptions) -> Result<String> {
         return Ok(p.to_string_lossy().to_string());
     }
 
+    if is_remote(options) {
+        return Ok("codex".to_string());
+    }
+
     if let Ok(path) = std::env::var("CODEX_CLI_PATH") {
         return Ok(path);
     }
@@ -43,8 +48,14 @@ pub fn find_codex_cli(options: &AgentOptions) -> Result<String> {
     ))
 }
 
-/// Build command-line arguments for `codex exec`.
-fn build_exec_command(cli_path: &str, prompt: &str, options: &AgentOptions) -> Vec<String> {
+/// Build command-line arguments for `codex exec`. SDK MCP servers are
+/// configured as streamable HTTP servers at the `bridge`.
+fn build_exec_command(
+    cli_path: &str,
+    prompt: &str,
+    options: &AgentOptions,
+    bridge: Option<&McpBridge>,
+) -> Vec<String> {
     let mut cmd = vec![
         cli_path.to_string(),
         "exec".to_string(),
@@ -86,20 +97,98 @@ fn build_exec_command(cli_path: &str, prompt: &str, options: &AgentOptions) -> V
         }
     }
 
-    for (key, value) in &options.extra_args {
-        if let Some(v) = value {
-            cmd.push(format!("--{}", key));
-            cmd.push(v.clone());
-        } else {
-            cmd.push(format!("--{}", key));
+    if let Some(ref effort) = options.effort {
+        cmd.push("-c".to_string());
+        cmd.push(format!(
+            "model_reasoning_effort=\"{}\"",
+            reasoning_effort(effort)
+        ));
+    }
+
+    for dir in &options.add_dirs {
+        cmd.push("--add-dir".to_string());
+        cmd.push(dir.to_string_lossy().to_string());
+    }
+
+    if let Some(bridge) = bridge {
+        for server in bridge.servers() {
+            let key = toml_quoted(server);
+            cmd.push("-c".to_string());
+            cmd.push(format!(
+                "mcp_servers.{}.url=\"{}\"",
+                key,
+                bridge.url(server)
+            ));
+            cmd.push("-c".to_string());
+            cmd.push(format!(
+                "mcp_servers.{}.bearer_token_env_var=\"{}\"",
+                key, BRIDGE_TOKEN_ENV
+            ));
         }
     }
 
+    cmd.extend(extra_cli_args(options));
+
     cmd.push(prompt.to_string());
 
     cmd
 }
 
+/// `s` as a TOML basic string, usable as a quoted key in `-c` overrides.
+fn toml_quoted(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Codex's name for an effort level.
+pub(crate) fn reasoning_effort(effort: &Effort) -> &'static str {
+    match effort {
+        Effort::Low => "low",
+        Effort::Medium => "medium",
+        Effort::High => "high",
+        Effort::Max => "xhigh",
+    }
+}
+
+/// [`AgentOptions::extra_args`] that `codex app-server` accepts as well as
+/// `codex exec`.
+const APP_SERVER_FLAGS: &[&str] = &["config", "enable", "disable"];
+
+/// [`AgentOptions::extra_args`] as `--key [value]` flags, for both
+/// `codex exec` and `codex app-server`.
+pub(crate) fn extra_cli_args(options: &AgentOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    for (key, value) in &options.extra_args {
+        args.push(format!("--{}", key));
+        if let Some(v) = value {
+            args.push(v.clone());
+        }
+    }
+    args
+}
+
+/// Sorted [`AgentOptions::extra_args`] keys that only `codex exec` accepts;
+/// `codex app-server` fails to start with them.
+pub(crate) fn exec_only_extra_args(options: &AgentOptions) -> Vec<&str> {
+    let mut keys: Vec<&str> = options
+        .extra_args
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !APP_SERVER_FLAGS.contains(key))
+        .collect();
+    keys.sort_unstable();
+    keys
+}
+
 /// Execute a one-shot Codex query, returning a stream of messages.
 pub fn one_shot_query(
     prompt: Prompt,
@@ -128,104 +217,58 @@ pub fn one_shot_query(
             }
         };
 
-        let cmd = build_exec_command(&cli_path, &prompt_text, &options);
-
-        let mut child_cmd = Command::new(&cmd[0]);
-        child_cmd
-            .args(&cmd[1..])
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        if let Some(ref cwd) = options.cwd {
-            child_cmd.current_dir(cwd);
-        }
+        // Serves the SDK MCP servers until the stream ends.
+        let bridge = match sdk_mcp_servers(&options) {
+            Some(servers) => match McpBridge::start(servers).await {
+                Ok(bridge) => Some(bridge),
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            },
+            None => None,
+        };
 
-        for (k, v) in &options.env {
-            child_cmd.env(k, v);
+        let cmd = build_exec_command(&cli_path, &prompt_text, &options, bridge.as_ref());
+        let mut spec = LaunchSpec::from_options(cmd[0].clone(), &options).args(cmd[1..].to_vec());
+        if let Some(ref bridge) = bridge {
+            spec = spec.env(BRIDGE_TOKEN_ENV, bridge.token());
         }
 
-        let mut process = match child_cmd.spawn() {
-            Ok(p) => p,
+        let mut transport = match launch(&options, spec).await {
+            Ok(t) => t,
             Err(e) => {
-                yield Err(Error::CliNotFound(format!(
-                    "Codex CLI not found at: {} - {}",
-                    cli_path, e
-                )));
-                return;
-            }
-        };
-
-        let stdout = match process.stdout.take() {
-            Some(s) => s,
-            None => {
-                yield Err(Error::Other("Failed to capture stdout".to_string()));
+                yield Err(e);
                 return;
             }
         };
-
-        // Read stderr in background for error reporting
-        let stderr = process.stderr.take();
-        let stderr_handle = stderr.map(|s| {
-            tokio::spawn(async move {
-                let reader = BufReader::new(s);
-                let mut lines = reader.lines();
-                let mut output = String::new();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    if !output.is_empty() {
-                        output.push('\n');
-                    }
-                    output.push_str(&line);
+        let _ = transport.end_input().await;
+
+        // A non-zero exit arrives as `Error::Process` at the end of the stream.
+        let mut events = transport.read_messages();
+        let cost = CostEstimator::new(&options);
+        while let Some(item) = events.next().await {
+            let data = match item {
+                Ok(data) => data,
+                Err(e) => {
+                    yield Err(e);
+                    let _ = transport.close().await;
+                    return;
                 }
-                output
-            })
-        });
-
-        let reader = BufReader::new(stdout);
-        let mut lines = reader.lines();
-
-        while let Ok(Some(line)) = lines.next_line().await {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(data) => {
-                    match message_parser::parse_exec_event(&data) {
-                        Ok(Some(msg)) => yield Ok(msg),
-                        Ok(None) => continue,
-                        Err(e) => {
-                            yield Err(e);
-                            continue;
-                        }
-                    }
+            };
+            match message_parser::parse_exec_event(&data) {
+                Ok(Some(mut msg)) => {
+                    cost.apply(&mut msg);
+                    yield Ok(msg);
                 }
-                Err(_) => continue,
-            }
-        }
-
-        // Wait for process to complete
-        match process.wait().await {
-            Ok(status) => {
-                if !status.success() {
-                    let stderr_output = if let Some(handle) = stderr_handle {
-                        handle.await.ok()
-                    } else {
-                        None
-                    };
-                    yield Err(Error::Process {
-                        exit_code: status.code().unwrap_or(-1),
-                        stderr: stderr_output,
-                    });
-                    return;
+                Ok(None) => continue,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
                 }
             }
-            Err(e) => {
-                yield Err(Error::Other(format!("Failed to wait for process: {}", e)));
-                return;
-            }
         }
+        let _ = transport.close().await;
 
         // Emit a synthetic result message for completion
         yield Ok(Message::Result(crate::types::ResultMessage {
@@ -252,7 +295,7 @@ mod tests {
     #[test]
     fn test_should_build_exec_command_basic() {
         let options = AgentOptions::default();
-        let cmd = build_exec_command("/usr/bin/codex", "hello world", &options);
+        let cmd = build_exec_command("/usr/bin/codex", "hello world", &options, None);
         assert_eq!(cmd[0], "/usr/bin/codex");
         assert_eq!(cmd[1], "exec");
         assert_eq!(cmd[2], "--json");
@@ -265,7 +308,7 @@ mod tests {
             model: Some("o4-mini".to_string()),
             ..Default::default()
         };
-        let cmd = build_exec_command("/usr/bin/codex", "test", &options);
+        let cmd = build_exec_command("/usr/bin/codex", "test", &options, None);
         assert!(cmd.contains(&"--model".to_string()));
         assert!(cmd.contains(&"o4-mini".to_string()));
     }
@@ -279,9 +322,49 @@ mod tests {
             }),
             ..Default::default()
         };
-        let cmd = build_exec_command("/usr/bin/codex", "test", &options);
+        let cmd = build_exec_command("/usr/bin/codex", "test", &options, None);
         assert!(cmd.contains(&"-c".to_string()));
         assert!(cmd.iter().any(|s| s.contains("approval_policy")));
         assert!(cmd.iter().any(|s| s.contains("sandbox_permissions")));
     }
+
+    #[test]
+    fn test_should_build_exec_command_with_effort_and_add_dirs() {
+        let options = AgentOptions {
+            effort: Some(Effort::Max),
+            add_dirs: vec!["/data".into()],
+            ..Default::default()
+        };
+        let cmd = build_exec_command("/usr/bin/codex", "test", &options, None);
+        assert!(cmd.contains(&"model_reasoning_effort=\"xhigh\"".to_string()));
+        let add_dir = cmd.iter().position(|a| a == "--add-dir").unwrap();
+        assert_eq!(cmd[add_dir + 1], "/data");
+    }
+
+    #[tokio::test]
+    async fn test_should_quote_bridged_server_names_as_toml_keys() {
+        let servers = ["docs.v2", "my \"tools\""]
+            .into_iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    crate::create_sdk_mcp_server(name, "1.0.0", vec![]),
+                )
+            })
+            .collect();
+        let bridge = McpBridge::start(std::sync::Arc::new(servers))
+            .await
+            .unwrap();
+        let cmd = build_exec_command("codex", "hi", &AgentOptions::default(), Some(&bridge));
+
+        let url = format!("mcp_servers.\"docs.v2\".url=\"{}\"", bridge.url("docs.v2"));
+        assert!(cmd.contains(&url), "{cmd:?}");
+        assert!(
+            cmd.contains(
+                &"mcp_servers.\"my \\\"tools\\\"\".bearer_token_env_var=\"AGENT_SDK_MCP_TOKEN\""
+                    .to_string()
+            )
+        );
+        assert_eq!(toml_quoted("a\\b\n"), "\"a\\\\b\\u000A\"");
+    }
 }
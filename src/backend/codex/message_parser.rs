@@ -13,10 +13,15 @@
 //! | `item/completed` (command_execution) | `AssistantMessage { content: [ToolUseBlock, ToolResultBlock] }` |
 //! | `item/completed` (file_change) | `AssistantMessage { content: [ToolUseBlock, ToolResultBlock] }` |
 //! | `turn/completed` | `ResultMessage { usage, duration_ms, ... }` |
-//! | `item/agentMessage/delta` | `AssistantMessage { content: [TextBlock] }` (partial) |
+//!
+//! Partial output (`item/agentMessage/delta`, `item/reasoning/*Delta`) is not
+//! mapped here; with `include_partial_messages` it is turned into
+//! [`StreamEvent`]s by [`app_server_stream_events`].
 
 use crate::error::Result;
+use crate::internal::stream_delta::{BlockKind, DeltaEmitter};
 use crate::types::*;
+use crate::usage::Usage;
 use serde_json::Value;
 
 /// Parse a Codex `exec --json` output event into a [`Message`].
@@ -187,10 +192,9 @@ pub fn parse_app_server_notification(method: &str, params: &Value) -> Result<Opt
             })))
         }
         "item/completed" => parse_item_completed(params),
-        "item/agentMessage/delta" => parse_agent_message_delta(params),
-        "item/reasoning/summaryTextDelta" | "item/reasoning/textDelta" => {
-            parse_reasoning_delta(params)
-        }
+        "item/agentMessage/delta"
+        | "item/reasoning/summaryTextDelta"
+        | "item/reasoning/textDelta" => Ok(None),
         "item/commandExecution/outputDelta" => parse_command_output_delta(params),
         "item/fileChange/outputDelta" => parse_file_change_delta(params),
         "turn/completed" => parse_turn_completed(params),
@@ -348,6 +352,40 @@ fn parse_item_completed(params: &Value) -> Result<Option<Message>> {
                 error: None,
             })))
         }
+        "dynamic_tool_call" | "dynamicToolCall" => {
+            let id = item
+                .get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let name = item
+                .get("tool")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let input = item.get("arguments").cloned().unwrap_or(Value::Null);
+            let success = item.get("success").and_then(|v| v.as_bool());
+
+            let blocks = vec![
+                ContentBlock::ToolUse(ToolUseBlock {
+                    id: id.clone(),
+                    name,
+                    input,
+                }),
+                ContentBlock::ToolResult(ToolResultBlock {
+                    tool_use_id: id,
+                    content: item.get("contentItems").cloned(),
+                    is_error: success.map(|s| !s),
+                }),
+            ];
+
+            Ok(Some(Message::Assistant(AssistantMessage {
+                content: blocks,
+                model: String::new(),
+                parent_tool_use_id: None,
+                error: None,
+            })))
+        }
         _ => {
             tracing::debug!("Skipping unknown Codex item type: {}", item_type);
             Ok(None)
@@ -355,41 +393,6 @@ fn parse_item_completed(params: &Value) -> Result<Option<Message>> {
     }
 }
 
-fn parse_agent_message_delta(params: &Value) -> Result<Option<Message>> {
-    let text = params.get("delta").and_then(|v| v.as_str()).unwrap_or("");
-
-    if text.is_empty() {
-        return Ok(None);
-    }
-
-    Ok(Some(Message::Assistant(AssistantMessage {
-        content: vec![ContentBlock::Text(TextBlock {
-            text: text.to_string(),
-        })],
-        model: String::new(),
-        parent_tool_use_id: None,
-        error: None,
-    })))
-}
-
-fn parse_reasoning_delta(params: &Value) -> Result<Option<Message>> {
-    let text = params.get("delta").and_then(|v| v.as_str()).unwrap_or("");
-
-    if text.is_empty() {
-        return Ok(None);
-    }
-
-    Ok(Some(Message::Assistant(AssistantMessage {
-        content: vec![ContentBlock::Thinking(ThinkingBlock {
-            thinking: text.to_string(),
-            signature: String::new(),
-        })],
-        model: String::new(),
-        parent_tool_use_id: None,
-        error: None,
-    })))
-}
-
 fn parse_command_output_delta(params: &Value) -> Result<Option<Message>> {
     let delta = params.get("delta").and_then(|v| v.as_str()).unwrap_or("");
 
@@ -424,8 +427,45 @@ fn parse_file_change_delta(params: &Value) -> Result<Option<Message>> {
     })))
 }
 
+/// Stream events for an app-server notification, for `include_partial_messages`.
+///
+/// Text and reasoning deltas become content block deltas keyed by item id;
+/// `item/completed` closes the item's block and `turn/completed` ends the
+/// message. Call before [`parse_app_server_notification`] so the events
+/// precede the completed message.
+pub(crate) fn app_server_stream_events(
+    method: &str,
+    params: &Value,
+    emitter: &mut DeltaEmitter,
+) -> Vec<Message> {
+    if let Some(thread_id) = params.get("threadId").and_then(|v| v.as_str()) {
+        emitter.set_session_id(thread_id);
+    }
+    let item_id = |v: &Value| {
+        v.get("itemId")
+            .or_else(|| v.get("id"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    let delta = params.get("delta").and_then(|v| v.as_str()).unwrap_or("");
+
+    match method {
+        "item/agentMessage/delta" => emitter.delta(&item_id(params), BlockKind::Text, delta),
+        "item/reasoning/summaryTextDelta" | "item/reasoning/textDelta" => {
+            emitter.delta(&item_id(params), BlockKind::Thinking, delta)
+        }
+        "item/completed" => {
+            let item = params.get("item").unwrap_or(params);
+            emitter.close_block(&item_id(item)).0
+        }
+        "turn/completed" => emitter.finish(params.get("usage").map(parse_usage)),
+        _ => Vec::new(),
+    }
+}
+
 fn parse_turn_completed(params: &Value) -> Result<Option<Message>> {
-    let usage = params.get("usage").cloned();
+    let usage = params.get("usage").map(parse_usage);
     let thread_id = params
         .get("threadId")
         .and_then(|v| v.as_str())
@@ -446,6 +486,30 @@ fn parse_turn_completed(params: &Value) -> Result<Option<Message>> {
     })))
 }
 
+/// Codex token counts, in either the exec (`snake_case`) or app-server
+/// (`camelCase`) spelling.
+///
+/// Codex counts cached tokens as part of `input_tokens`; they are split out
+/// here so the fields of [`Usage`] stay disjoint.
+pub(crate) fn parse_usage(usage: &Value) -> Usage {
+    let count = |snake: &str, camel: &str| {
+        usage
+            .get(snake)
+            .or_else(|| usage.get(camel))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    };
+    let input = count("input_tokens", "inputTokens");
+    let cached = count("cached_input_tokens", "cachedInputTokens");
+    Usage {
+        input_tokens: input.saturating_sub(cached),
+        output_tokens: count("output_tokens", "outputTokens"),
+        cache_read_input_tokens: cached,
+        cache_creation_input_tokens: 0,
+        reasoning_output_tokens: count("reasoning_output_tokens", "reasoningOutputTokens"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -524,6 +588,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_should_parse_dynamic_tool_call_item_completed() {
+        let params = json!({
+            "item": {
+                "type": "dynamicToolCall",
+                "id": "call-1",
+                "tool": "mcp__calc__add",
+                "arguments": {"a": 1, "b": 2},
+                "contentItems": [{"type": "inputText", "text": "3"}],
+                "success": true
+            }
+        });
+        let msg = parse_app_server_notification("item/completed", &params).unwrap();
+        let Some(Message::Assistant(a)) = msg else {
+            panic!("expected AssistantMessage");
+        };
+        match &a.content[..] {
+            [ContentBlock::ToolUse(u), ContentBlock::ToolResult(r)] => {
+                assert_eq!(u.name, "mcp__calc__add");
+                assert_eq!(u.input["b"], 2);
+                assert_eq!(r.tool_use_id, "call-1");
+                assert_eq!(r.is_error, Some(false));
+            }
+            other => panic!("expected tool use and result, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_should_parse_turn_completed_as_result() {
         let params = json!({
@@ -535,7 +626,8 @@ mod tests {
         match msg {
             Message::Result(r) => {
                 assert_eq!(r.session_id, "t-456");
-                assert!(r.usage.is_some());
+                let usage = r.usage.expect("usage");
+                assert_eq!((usage.input_tokens, usage.output_tokens), (100, 50));
             }
             _ => panic!("expected ResultMessage"),
         }
@@ -656,7 +748,8 @@ mod tests {
         let msg = msg.expect("should parse");
         match msg {
             Message::Result(r) => {
-                assert!(r.usage.is_some());
+                let usage = r.usage.expect("usage");
+                assert_eq!((usage.input_tokens, usage.output_tokens), (100, 50));
             }
             _ => panic!("expected ResultMessage"),
         }
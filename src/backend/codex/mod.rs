@@ -5,13 +5,18 @@
 //! - Multi-turn: `codex app-server` (long-lived JSON-RPC 2.0 subprocess)
 
 pub mod app_server;
+mod dynamic_tools;
 pub mod exec_transport;
 pub mod jsonrpc;
 pub mod message_parser;
 
+pub use app_server::{CodexThreadInfo, list_threads};
+
 use crate::backend::{Backend, Capabilities, Session};
 use crate::error::{Error, Result};
+use crate::internal::sdk_mcp::sdk_mcp_servers;
 use crate::options::AgentOptions;
+use crate::transport::{Transport, is_remote};
 use crate::types::{Message, Prompt};
 use async_trait::async_trait;
 use futures::Stream;
@@ -22,7 +27,7 @@ fn codex_capabilities() -> Capabilities {
         control_protocol: false,
         tool_approval: true,
         hooks: false,
-        sdk_mcp_routing: false,
+        sdk_mcp_routing: true,
         persistent_session: true,
         interrupt: true,
         runtime_config_changes: false,
@@ -42,6 +47,23 @@ impl CodexBackend {
             capabilities: codex_capabilities(),
         }
     }
+
+    /// [`Backend::validate_options`], plus the options that only one-shot
+    /// queries support.
+    fn validate_session_options(&self, options: &AgentOptions) -> Result<()> {
+        self.validate_options(options)?;
+        let exec_only = exec_transport::exec_only_extra_args(options);
+        if exec_only.is_empty() {
+            return Ok(());
+        }
+        Err(Error::UnsupportedOptions {
+            backend: "Codex".to_string(),
+            options: exec_only
+                .into_iter()
+                .map(|key| format!("extra_args.{} (codex exec only)", key))
+                .collect(),
+        })
+    }
 }
 
 impl Default for CodexBackend {
@@ -69,8 +91,8 @@ impl Backend for CodexBackend {
         if options.hooks.is_some() {
             unsupported.push("hooks".to_string());
         }
-        if options.fork_session {
-            unsupported.push("fork_session".to_string());
+        if options.fork_session && options.resume.is_none() && !options.continue_conversation {
+            unsupported.push("fork_session (requires resume or continue_conversation)".to_string());
         }
         if options.setting_sources.is_some() {
             unsupported.push("setting_sources".to_string());
@@ -81,6 +103,9 @@ impl Backend for CodexBackend {
         if options.permission_prompt_tool_name.is_some() {
             unsupported.push("permission_prompt_tool_name".to_string());
         }
+        if options.max_budget_usd.is_some() && options.price_table.is_none() {
+            unsupported.push("max_budget_usd (requires price_table)".to_string());
+        }
 
         if unsupported.is_empty() {
             Ok(())
@@ -98,6 +123,13 @@ impl Backend for CodexBackend {
         options: &AgentOptions,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
         self.validate_options(options)?;
+        // `codex exec` reaches SDK MCP servers over a loopback bridge.
+        if is_remote(options) && sdk_mcp_servers(options).is_some() {
+            return Err(Error::UnsupportedOptions {
+                backend: "Codex".to_string(),
+                options: vec!["mcp_servers (SDK servers require a local CLI)".to_string()],
+            });
+        }
         Ok(exec_transport::one_shot_query(prompt, options))
     }
 
@@ -106,8 +138,19 @@ impl Backend for CodexBackend {
         options: &AgentOptions,
         prompt: Option<Prompt>,
     ) -> Result<Box<dyn Session + Send>> {
-        self.validate_options(options)?;
+        self.validate_session_options(options)?;
         let session = app_server::CodexSession::new(options, prompt).await?;
         Ok(Box::new(session))
     }
+
+    async fn create_session_with_transport(
+        &self,
+        options: &AgentOptions,
+        prompt: Option<Prompt>,
+        transport: Box<dyn Transport + Send>,
+    ) -> Result<Box<dyn Session + Send>> {
+        self.validate_session_options(options)?;
+        let session = app_server::CodexSession::with_transport(transport, options, prompt).await?;
+        Ok(Box::new(session))
+    }
 }
@@ -0,0 +1,220 @@
+//! MCP servers for the Cursor Agent CLI.
+//!
+//! The CLI has no flag or environment override for MCP servers; it reads
+//! them from the workspace's `.cursor/mcp.json`. [`CursorMcpConfig`] writes
+//! [`AgentOptions::mcp_servers`] to that file for the lifetime of a query
+//! or session and removes it afterwards. It never touches a file it did not
+//! create: an existing `mcp.json` is an error, and a lock file next to it
+//! keeps two sessions in the same workspace apart.
+//!
+//! Secrets stay out of the workspace: stdio `env` values, HTTP `headers`
+//! and the [`McpBridge`] token serving SDK MCP servers are written as
+//! `${env:NAME}` references, and the values go into the CLI's environment
+//! through [`CursorMcpConfig::env`].
+
+use crate::error::{Error, Result};
+use crate::internal::mcp_bridge::{BRIDGE_TOKEN_ENV, McpBridge};
+use crate::internal::sdk_mcp::sdk_mcp_servers;
+use crate::options::{AgentOptions, McpServerConfig, McpServersConfig};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The session's servers in the workspace's `.cursor/mcp.json`.
+#[derive(Debug)]
+pub(crate) struct CursorMcpConfig {
+    /// `None` once removed.
+    files: Option<InstalledFiles>,
+    env: Vec<(String, String)>,
+    bridge: Option<McpBridge>,
+}
+
+#[derive(Debug)]
+struct InstalledFiles {
+    config: PathBuf,
+    lock: PathBuf,
+    /// `.cursor` directory created for the files.
+    created_dir: Option<PathBuf>,
+}
+
+impl InstalledFiles {
+    /// Take the lock and create an empty config, refusing to replace a
+    /// config of the user's.
+    fn create(self) -> Result<Self> {
+        std::fs::create_dir_all(self.config.parent().expect("in .cursor"))?;
+        let mut lock = match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&self.lock)
+        {
+            Ok(lock) => lock,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(Error::Other(format!(
+                    "{} is in use by another session (remove {} if none is running)",
+                    self.config.display(),
+                    self.lock.display()
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let _ = writeln!(lock, "{}", std::process::id());
+        // Past this point the lock is ours, so failures must release it.
+        let created = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&self.config);
+        match created {
+            Ok(_) => Ok(self),
+            Err(e) => {
+                let _ = std::fs::remove_file(&self.lock);
+                if let Some(ref dir) = self.created_dir {
+                    let _ = std::fs::remove_dir(dir);
+                }
+                Err(if e.kind() == std::io::ErrorKind::AlreadyExists {
+                    Error::Other(format!(
+                        "{} already exists; Cursor reads mcp_servers from that file, so move \
+                         its servers into mcp_servers or remove it",
+                        self.config.display()
+                    ))
+                } else {
+                    e.into()
+                })
+            }
+        }
+    }
+
+    fn remove(self) {
+        for path in [&self.config, &self.lock] {
+            if let Err(e) = std::fs::remove_file(path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                tracing::warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+        if let Some(ref dir) = self.created_dir {
+            // Only succeeds if nothing else was put there meanwhile.
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+}
+
+impl CursorMcpConfig {
+    /// Install the options' MCP servers, or do nothing if there are none.
+    pub(crate) async fn install(options: &AgentOptions) -> Result<Option<Self>> {
+        let Some(McpServersConfig::Dict(servers)) = options.mcp_servers.as_ref() else {
+            return Ok(None);
+        };
+        if servers.is_empty() {
+            return Ok(None);
+        }
+        let workspace = match options.cwd {
+            Some(ref cwd) => cwd.clone(),
+            None => std::env::current_dir()?,
+        };
+        let dir = workspace.join(".cursor");
+        let created_dir = (!dir.is_dir()).then(|| dir.clone());
+        let files = InstalledFiles {
+            config: dir.join("mcp.json"),
+            lock: dir.join("mcp.json.lock"),
+            created_dir,
+        };
+        let files = tokio::task::spawn_blocking(move || files.create())
+            .await
+            .map_err(|e| Error::Other(format!("Failed to install Cursor MCP config: {}", e)))??;
+        // From here on, dropping `installed` removes the files.
+        let mut installed = Self {
+            files: Some(files),
+            env: Vec::new(),
+            bridge: None,
+        };
+        if let Some(sdk_servers) = sdk_mcp_servers(options) {
+            installed.bridge = Some(McpBridge::start(sdk_servers).await?);
+        }
+
+        let mut entries = Map::new();
+        for (name, server) in servers {
+            let entry = match server {
+                McpServerConfig::Stdio(c) => {
+                    let mut entry = json!({"command": c.command});
+                    if let Some(ref args) = c.args {
+                        entry["args"] = json!(args);
+                    }
+                    if let Some(ref env) = c.env {
+                        entry["env"] = installed.secrets(env);
+                    }
+                    entry
+                }
+                McpServerConfig::Sse(c) => installed.url_entry(&c.url, c.headers.as_ref()),
+                McpServerConfig::Http(c) => installed.url_entry(&c.url, c.headers.as_ref()),
+                McpServerConfig::Sdk(_) => {
+                    let bridge = installed
+                        .bridge
+                        .as_ref()
+                        .expect("bridge serves every SDK server");
+                    json!({
+                        "url": bridge.url(name),
+                        "headers": {"Authorization": format!("Bearer ${{env:{}}}", BRIDGE_TOKEN_ENV)},
+                    })
+                }
+            };
+            entries.insert(name.clone(), entry);
+        }
+        if let Some(ref bridge) = installed.bridge {
+            installed
+                .env
+                .push((BRIDGE_TOKEN_ENV.to_string(), bridge.token().to_string()));
+        }
+
+        let config = serde_json::to_vec_pretty(&json!({"mcpServers": entries}))?;
+        let path = installed.files.as_ref().expect("installed").config.clone();
+        tokio::fs::write(&path, config).await?;
+        Ok(Some(installed))
+    }
+
+    /// Environment variables the config refers to, for the CLI's process.
+    pub(crate) fn env(&self) -> Vec<(String, String)> {
+        self.env.clone()
+    }
+
+    /// Remove the installed files.
+    pub(crate) async fn remove(mut self) {
+        if let Some(files) = self.files.take() {
+            let _ = tokio::task::spawn_blocking(move || files.remove()).await;
+        }
+    }
+
+    /// `values` as `${env:NAME}` references to new variables of [`Self::env`].
+    fn secrets(&mut self, values: &HashMap<String, String>) -> Value {
+        let mut refs = Map::new();
+        for (key, value) in values {
+            let var = format!("AGENT_SDK_MCP_SECRET_{}", self.env.len());
+            refs.insert(key.clone(), json!(format!("${{env:{}}}", var)));
+            self.env.push((var, value.clone()));
+        }
+        Value::Object(refs)
+    }
+
+    fn url_entry(&mut self, url: &str, headers: Option<&HashMap<String, String>>) -> Value {
+        let mut entry = json!({"url": url});
+        if let Some(headers) = headers {
+            entry["headers"] = self.secrets(headers);
+        }
+        entry
+    }
+}
+
+impl Drop for CursorMcpConfig {
+    fn drop(&mut self) {
+        let Some(files) = self.files.take() else {
+            return;
+        };
+        // Off the async worker threads where possible.
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(move || files.remove());
+            }
+            Err(_) => files.remove(),
+        }
+    }
+}
@@ -12,9 +12,18 @@
 //! | `{ type: "tool_call", subtype: "started" }` | `AssistantMessage { content: [ToolUseBlock] }` |
 //! | `{ type: "tool_call", subtype: "completed" }` | `AssistantMessage { content: [ToolResultBlock] }` |
 //! | `{ type: "result" }` | `ResultMessage` |
+//!
+//! With `--stream-partial-output` (used for `include_partial_messages`),
+//! `assistant` and `thinking` events carry chunks instead of whole blocks;
+//! [`CursorEventParser`] turns those into [`StreamEvent`]s plus one
+//! assembled `AssistantMessage` per block.
 
 use crate::error::Result;
+use crate::internal::cost::CostEstimator;
+use crate::internal::stream_delta::{BlockKind, DeltaEmitter};
+use crate::options::AgentOptions;
 use crate::types::*;
+use crate::usage::Usage;
 use serde_json::Value;
 
 /// Parse a Cursor Agent stream-json event into a [`Message`].
@@ -37,6 +46,118 @@ pub fn parse_cursor_event(data: &Value) -> Result<Option<Message>> {
     }
 }
 
+/// Per-process Cursor event parser.
+///
+/// In partial mode, text and thinking chunks are emitted as stream events;
+/// each block is closed, and emitted as an `AssistantMessage`, as soon as a
+/// different kind of event arrives. Results are priced with the model from
+/// the `init` event.
+#[derive(Debug, Default)]
+pub(crate) struct CursorEventParser {
+    partial: bool,
+    emitter: DeltaEmitter,
+    model: String,
+    cost: CostEstimator,
+}
+
+const TEXT_BLOCK: &str = "text";
+const THINKING_BLOCK: &str = "thinking";
+
+impl CursorEventParser {
+    pub(crate) fn new(options: &AgentOptions) -> Self {
+        Self {
+            partial: options.include_partial_messages,
+            cost: CostEstimator::new(options),
+            ..Default::default()
+        }
+    }
+
+    /// Parse one event into zero or more messages.
+    pub(crate) fn parse(&mut self, data: &Value) -> Result<Vec<Message>> {
+        if data.get("type").and_then(|v| v.as_str()) == Some("system")
+            && let Some(model) = data.get("model").and_then(|v| v.as_str())
+        {
+            self.cost.set_model(model);
+        }
+        let mut out = self.parse_events(data)?;
+        for msg in &mut out {
+            self.cost.apply(msg);
+        }
+        Ok(out)
+    }
+
+    fn parse_events(&mut self, data: &Value) -> Result<Vec<Message>> {
+        if !self.partial {
+            return Ok(parse_cursor_event(data)?.into_iter().collect());
+        }
+
+        if let Some(id) = data
+            .get("chatId")
+            .or_else(|| data.get("session_id"))
+            .and_then(|v| v.as_str())
+        {
+            self.emitter.set_session_id(id);
+        }
+
+        let event_type = data.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let mut out = Vec::new();
+        match event_type {
+            "assistant" => {
+                let Some(Message::Assistant(chunk)) = parse_assistant_event(data)? else {
+                    return Ok(out);
+                };
+                if !chunk.model.is_empty() {
+                    self.model = chunk.model;
+                }
+                self.close(THINKING_BLOCK, &mut out);
+                for block in chunk.content {
+                    if let ContentBlock::Text(t) = block {
+                        out.extend(self.emitter.delta(TEXT_BLOCK, BlockKind::Text, &t.text));
+                    }
+                }
+            }
+            "thinking" => {
+                self.close(TEXT_BLOCK, &mut out);
+                if data.get("subtype").and_then(|v| v.as_str()) == Some("completed") {
+                    self.close(THINKING_BLOCK, &mut out);
+                } else if let Some(Message::Assistant(chunk)) = parse_thinking_event(data)? {
+                    for block in chunk.content {
+                        if let ContentBlock::Thinking(t) = block {
+                            out.extend(self.emitter.delta(
+                                THINKING_BLOCK,
+                                BlockKind::Thinking,
+                                &t.thinking,
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => {
+                self.close(THINKING_BLOCK, &mut out);
+                self.close(TEXT_BLOCK, &mut out);
+                if event_type == "result" {
+                    out.extend(self.emitter.finish(data.get("usage").map(parse_usage)));
+                }
+                out.extend(parse_cursor_event(data)?);
+            }
+        }
+        Ok(out)
+    }
+
+    fn close(&mut self, key: &str, out: &mut Vec<Message>) {
+        let (events, block) = self.emitter.close_block(key);
+        out.extend(events);
+        if let Some(block) = block {
+            out.push(Message::Assistant(AssistantMessage {
+                content: vec![block],
+                model: self.model.clone(),
+                parent_tool_use_id: None,
+                error: None,
+            }));
+        }
+    }
+}
+
 fn parse_system_event(data: &Value) -> Result<Option<Message>> {
     let subtype = data
         .get("subtype")
@@ -226,7 +347,7 @@ fn parse_result_event(data: &Value) -> Result<Option<Message>> {
         num_turns,
         session_id,
         total_cost_usd: data.get("total_cost_usd").and_then(|v| v.as_f64()),
-        usage: data.get("usage").cloned(),
+        usage: data.get("usage").map(parse_usage),
         result: data
             .get("result")
             .and_then(|v| v.as_str())
@@ -235,6 +356,24 @@ fn parse_result_event(data: &Value) -> Result<Option<Message>> {
     })))
 }
 
+/// Cursor `usage` object (`inputTokens`, `cacheReadTokens`, ...).
+fn parse_usage(usage: &Value) -> Usage {
+    let count = |camel: &str, snake: &str| {
+        usage
+            .get(camel)
+            .or_else(|| usage.get(snake))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    };
+    Usage {
+        input_tokens: count("inputTokens", "input_tokens"),
+        output_tokens: count("outputTokens", "output_tokens"),
+        cache_read_input_tokens: count("cacheReadTokens", "cache_read_input_tokens"),
+        cache_creation_input_tokens: count("cacheWriteTokens", "cache_creation_input_tokens"),
+        reasoning_output_tokens: count("reasoningTokens", "reasoning_output_tokens"),
+    }
+}
+
 fn parse_user_event(data: &Value) -> Result<Option<Message>> {
     let message = data.get("message").unwrap_or(data);
     let content = match message.get("content") {
@@ -374,4 +513,77 @@ mod tests {
         let msg = parse_cursor_event(&data).unwrap();
         assert!(msg.is_none());
     }
+
+    #[test]
+    fn test_should_assemble_partial_chunks_into_stream_events() {
+        let mut parser = CursorEventParser::new(
+            &AgentOptions::builder()
+                .include_partial_messages(true)
+                .build(),
+        );
+        let mut messages = Vec::new();
+        for event in [
+            json!({"type": "system", "subtype": "init", "chatId": "c-1"}),
+            json!({"type": "thinking", "subtype": "delta", "text": "hmm"}),
+            json!({"type": "assistant", "text": "Hel", "model": "gpt-5"}),
+            json!({"type": "assistant", "text": "lo"}),
+            json!({"type": "result", "session_id": "c-1"}),
+        ] {
+            messages.extend(parser.parse(&event).unwrap());
+        }
+
+        let deltas: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::StreamEvent(e) => {
+                    assert_eq!(e.session_id, "c-1");
+                    e.delta()
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            deltas[1..6],
+            [
+                StreamDelta::ContentBlockStart {
+                    index: 0,
+                    block: StreamBlockStart::Thinking {
+                        thinking: String::new()
+                    }
+                },
+                StreamDelta::ThinkingDelta {
+                    index: 0,
+                    thinking: "hmm".to_string()
+                },
+                StreamDelta::ContentBlockStop { index: 0 },
+                StreamDelta::ContentBlockStart {
+                    index: 1,
+                    block: StreamBlockStart::Text {
+                        text: String::new()
+                    }
+                },
+                StreamDelta::TextDelta {
+                    index: 1,
+                    text: "Hel".to_string()
+                },
+            ]
+        );
+        assert!(matches!(deltas[0], StreamDelta::MessageStart { .. }));
+        assert_eq!(deltas.last(), Some(&StreamDelta::MessageStop));
+
+        let assistant: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Assistant(a) => Some(a),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(assistant.len(), 2);
+        assert!(
+            matches!(&assistant[0].content[0], ContentBlock::Thinking(t) if t.thinking == "hmm")
+        );
+        assert!(matches!(&assistant[1].content[0], ContentBlock::Text(t) if t.text == "Hello"));
+        assert_eq!(assistant[1].model, "gpt-5");
+        assert!(matches!(messages.last(), Some(Message::Result(_))));
+    }
 }
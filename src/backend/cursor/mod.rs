@@ -4,13 +4,15 @@
 //! - One-shot: `agent --print --output-format stream-json <prompt>`
 //! - Multi-turn: spawn-per-turn with `agent --print --resume <chatId>`
 
+mod mcp_config;
 pub mod message_parser;
 pub mod session;
 pub mod transport;
 
 use crate::backend::{Backend, Capabilities, Session};
 use crate::error::{Error, Result};
-use crate::options::AgentOptions;
+use crate::options::{AgentOptions, McpServersConfig};
+use crate::transport::is_remote;
 use crate::types::{Message, Prompt};
 use async_trait::async_trait;
 use futures::Stream;
@@ -21,7 +23,7 @@ fn cursor_capabilities() -> Capabilities {
         control_protocol: false,
         tool_approval: false,
         hooks: false,
-        sdk_mcp_routing: false,
+        sdk_mcp_routing: true,
         persistent_session: false,
         interrupt: false,
         runtime_config_changes: false,
@@ -71,8 +73,14 @@ impl Backend for CursorBackend {
         if options.hooks.is_some() {
             unsupported.push("hooks".to_string());
         }
-        if options.mcp_servers.is_some() {
-            unsupported.push("mcp_servers".to_string());
+        match options.mcp_servers {
+            Some(McpServersConfig::Path(_)) => {
+                unsupported.push("mcp_servers (path)".to_string());
+            }
+            Some(McpServersConfig::Dict(_)) if is_remote(options) => {
+                unsupported.push("mcp_servers (requires a local CLI)".to_string());
+            }
+            _ => {}
         }
         if options.fork_session {
             unsupported.push("fork_session".to_string());
@@ -89,6 +97,9 @@ impl Backend for CursorBackend {
         if options.output_format.is_some() {
             unsupported.push("output_format (structured output)".to_string());
         }
+        if options.max_budget_usd.is_some() && options.price_table.is_none() {
+            unsupported.push("max_budget_usd (requires price_table)".to_string());
+        }
 
         if unsupported.is_empty() {
             Ok(())
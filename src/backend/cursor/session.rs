@@ -1,27 +1,27 @@
 //! Cursor Agent spawn-per-turn session management.
 //!
 //! Cursor Agent CLI does not support a long-lived server mode. Multi-turn
-//! sessions are achieved by spawning a new process for each turn using
-//! `agent --print --resume <chatId>`.
+//! sessions are achieved by launching a new process for each turn using
+//! `agent --print --resume <chatId>`, through
+//! [`AgentOptions::launcher`](crate::options::AgentOptions::launcher).
 
+use crate::backend::InterruptHandle;
 use crate::error::{Error, Result};
+use crate::internal::fanout::Fanout;
 use crate::options::AgentOptions;
+use crate::transport::{LaunchSpec, launch};
 use crate::types::{Message, Prompt};
 use async_stream::stream;
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use std::pin::Pin;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Child;
-use tokio::sync::{broadcast, oneshot};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{Notify, oneshot};
 use tokio::task::JoinHandle;
 
+use super::mcp_config::CursorMcpConfig;
 use super::message_parser;
-use super::transport::find_cursor_cli;
-
-const MESSAGE_BUFFER_SIZE: usize = 100;
-const CHAT_ID_WAIT_SECS: u64 = 5;
-const CLOSE_TIMEOUT_SECS: u64 = 5;
+use super::transport::{find_cursor_cli, has_mcp_servers};
 
 /// Internal control message for the cursor session.
 #[derive(Debug, Clone)]
@@ -40,10 +40,16 @@ pub struct CursorSession {
     cli_path: String,
     options: AgentOptions,
     chat_id: Option<String>,
-    message_tx: broadcast::Sender<SessionMessage>,
-    active_process: Option<Child>,
-    read_task: Option<JoinHandle<()>>,
+    message_tx: Fanout<SessionMessage>,
+    /// Reader for the active turn; owns its transport and yields the error
+    /// that ended it, if any.
+    read_task: Option<JoinHandle<Option<Error>>>,
+    /// Wakes the active reader to kill its process.
+    stop_turn: Arc<Notify>,
     has_started_turn: bool,
+    turn_finished: Arc<AtomicBool>,
+    /// Installed until the session is closed or dropped.
+    mcp_config: Option<CursorMcpConfig>,
 }
 
 impl std::fmt::Debug for CursorSession {
@@ -52,7 +58,7 @@ impl std::fmt::Debug for CursorSession {
             .field("cli_path", &self.cli_path)
             .field("chat_id", &self.chat_id)
             .field("has_started_turn", &self.has_started_turn)
-            .field("turn_running", &self.active_process.is_some())
+            .field("turn_running", &self.read_task.is_some())
             .finish_non_exhaustive()
     }
 }
@@ -61,16 +67,19 @@ impl CursorSession {
     /// Create a new Cursor session, optionally running an initial prompt.
     pub async fn new(options: &AgentOptions, prompt: Option<Prompt>) -> Result<Self> {
         let cli_path = find_cursor_cli(options)?;
-        let (message_tx, _) = broadcast::channel(MESSAGE_BUFFER_SIZE);
+        let message_tx = Fanout::new();
+        let mcp_config = CursorMcpConfig::install(options).await?;
 
         let session = Self {
             cli_path,
             options: options.clone(),
             chat_id: None,
             message_tx,
-            active_process: None,
             read_task: None,
+            stop_turn: Arc::new(Notify::new()),
             has_started_turn: false,
+            turn_finished: Arc::new(AtomicBool::new(false)),
+            mcp_config,
         };
 
         // Keep connect semantics consistent across backends:
@@ -90,48 +99,50 @@ impl CursorSession {
     }
 
     async fn sync_completed_turn_state(&mut self) -> Result<()> {
-        let Some(process) = self.active_process.as_mut() else {
+        let Some(mut handle) = self.read_task.take() else {
             return Ok(());
         };
 
-        match process.try_wait() {
-            Ok(Some(status)) => {
-                if !status.success() {
-                    let exit_code = status.code().unwrap_or(-1);
-                    let _ = self.message_tx.send(SessionMessage::Error(format!(
-                        "Process exited with code {}",
-                        exit_code
-                    )));
-                    self.active_process = None;
-                    if let Some(handle) = self.read_task.take() {
-                        let _ = handle.await;
-                    }
-                    return Err(Error::Process {
-                        exit_code,
-                        stderr: None,
-                    });
-                }
-                self.active_process = None;
-                if let Some(handle) = self.read_task.take() {
-                    let _ = handle.await;
+        let outcome = if handle.is_finished() {
+            handle.await
+        } else if self.turn_finished.load(Ordering::SeqCst) {
+            // The result has been delivered and the CLI is shutting down;
+            // give it a moment instead of rejecting the next turn.
+            match tokio::time::timeout(self.options.timeouts.shutdown_grace, &mut handle).await {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    self.stop_turn(handle).await;
+                    return Ok(());
                 }
             }
-            Ok(None) => {}
-            Err(e) => {
-                return Err(Error::Other(format!(
-                    "Failed to check Cursor process status: {}",
-                    e
-                )));
-            }
+        } else {
+            self.read_task = Some(handle);
+            return Ok(());
+        };
+
+        match outcome {
+            Ok(Some(e)) => Err(e),
+            Ok(None) => Ok(()),
+            Err(e) => Err(Error::Other(format!("Cursor reader task failed: {}", e))),
         }
+    }
 
-        Ok(())
+    /// Kill the active turn's process and wait for its reader.
+    async fn stop_turn(&self, mut handle: JoinHandle<Option<Error>>) {
+        self.stop_turn.notify_waiters();
+        if tokio::time::timeout(self.options.timeouts.shutdown_grace, &mut handle)
+            .await
+            .is_err()
+        {
+            handle.abort();
+            let _ = handle.await;
+        }
     }
 
     /// Spawn a new agent process for one turn.
     async fn run_turn(&mut self, prompt: &str) -> Result<()> {
         self.sync_completed_turn_state().await?;
-        if self.active_process.is_some() {
+        if self.read_task.is_some() {
             return Err(Error::Other(
                 "Previous Cursor turn is still running. Wait for receive_response() to complete."
                     .to_string(),
@@ -155,6 +166,10 @@ impl CursorSession {
             cmd_args.push(chat_id.clone());
         }
 
+        if self.options.include_partial_messages {
+            cmd_args.push("--stream-partial-output".to_string());
+        }
+
         if let Some(ref m) = self.options.model {
             cmd_args.push("--model".to_string());
             cmd_args.push(m.clone());
@@ -173,6 +188,10 @@ impl CursorSession {
             }
         }
 
+        if has_mcp_servers(&self.options) {
+            cmd_args.push("--approve-mcps".to_string());
+        }
+
         for (key, value) in &self.options.extra_args {
             if let Some(v) = value {
                 cmd_args.push(format!("--{}", key));
@@ -185,52 +204,46 @@ impl CursorSession {
         // Prompt goes last
         cmd_args.push(prompt.to_string());
 
-        let mut child_cmd = tokio::process::Command::new(&self.cli_path);
-        child_cmd
-            .args(&cmd_args)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null());
-
-        if let Some(ref cwd) = self.options.cwd {
-            child_cmd.current_dir(cwd);
-        }
-
-        for (k, v) in &self.options.env {
-            child_cmd.env(k, v);
-        }
-
-        let mut process = child_cmd.spawn().map_err(|e| {
-            Error::CliNotFound(format!(
-                "Cursor Agent CLI not found at: {} - {}",
-                self.cli_path, e
-            ))
-        })?;
-
-        let stdout = process
-            .stdout
-            .take()
-            .ok_or_else(|| Error::Other("Failed to capture stdout".to_string()))?;
+        let mut spec =
+            LaunchSpec::from_options(self.cli_path.clone(), &self.options).args(cmd_args);
+        spec.env
+            .extend(self.mcp_config.iter().flat_map(CursorMcpConfig::env));
+        let mut transport = launch(&self.options, spec).await?;
+        let _ = transport.end_input().await;
+        let mut events = transport.read_messages();
 
+        self.message_tx.begin_turn();
         let msg_tx = self.message_tx.clone();
+        let stop_turn = Arc::clone(&self.stop_turn);
+        let turn_finished = Arc::clone(&self.turn_finished);
+        turn_finished.store(false, Ordering::SeqCst);
         let (chat_id_tx, chat_id_rx) = oneshot::channel::<Option<String>>();
+        let mut parser = message_parser::CursorEventParser::new(&self.options);
 
         // Spawn a reader task for this turn.
         let read_task = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
             let mut chat_id_tx = Some(chat_id_tx);
             let mut emitted_chat_id: Option<String> = None;
+            let mut failure = None;
+            let stopped = stop_turn.notified();
+            tokio::pin!(stopped);
+            stopped.as_mut().enable();
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
-
-                let data: serde_json::Value = match serde_json::from_str(line) {
+            loop {
+                let item = tokio::select! {
+                    item = events.next() => item,
+                    _ = &mut stopped => break,
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                let data = match item {
                     Ok(d) => d,
-                    Err(_) => continue,
+                    Err(e) => {
+                        msg_tx.send(SessionMessage::Error(e.to_string()));
+                        failure = Some(e);
+                        break;
+                    }
                 };
 
                 let extracted_chat_id =
@@ -255,31 +268,33 @@ impl CursorSession {
                     }
                 }
 
-                match message_parser::parse_cursor_event(&data) {
-                    Ok(Some(msg)) => {
-                        let _ = msg_tx.send(SessionMessage::SdkMessage(msg));
+                match parser.parse(&data) {
+                    Ok(messages) => {
+                        for msg in messages {
+                            if matches!(&msg, Message::Result(_)) {
+                                turn_finished.store(true, Ordering::SeqCst);
+                            }
+                            msg_tx.send(SessionMessage::SdkMessage(msg));
+                        }
                     }
-                    Ok(None) => {}
                     Err(e) => {
-                        let _ = msg_tx.send(SessionMessage::Error(format!("Parse error: {}", e)));
+                        msg_tx.send(SessionMessage::Error(format!("Parse error: {}", e)));
                     }
                 }
             }
             if let Some(tx) = chat_id_tx.take() {
                 let _ = tx.send(emitted_chat_id);
             }
+            let _ = transport.close().await;
+            failure
         });
 
-        self.active_process = Some(process);
         self.read_task = Some(read_task);
         self.has_started_turn = true;
 
         if self.chat_id.is_none()
-            && let Ok(Ok(Some(id))) = tokio::time::timeout(
-                std::time::Duration::from_secs(CHAT_ID_WAIT_SECS),
-                chat_id_rx,
-            )
-            .await
+            && let Ok(Ok(Some(id))) =
+                tokio::time::timeout(self.options.timeouts.initialize, chat_id_rx).await
         {
             self.chat_id = Some(id);
         }
@@ -288,6 +303,32 @@ impl CursorSession {
     }
 }
 
+impl CursorSession {
+    /// Messages from the start of the current turn until the session ends.
+    fn messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+        let mut rx = self.message_tx.subscribe();
+
+        let stream = stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(Some(SessionMessage::SdkMessage(msg))) => yield Ok(msg),
+                    Ok(Some(SessionMessage::End)) | Ok(None) => break,
+                    Ok(Some(SessionMessage::Error(e))) => {
+                        yield Err(Error::Other(e));
+                        break;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
+            }
+        };
+
+        Box::pin(stream)
+    }
+}
+
 #[async_trait::async_trait]
 impl crate::backend::Session for CursorSession {
     async fn send_message(&mut self, prompt: Prompt, _session_id: &str) -> Result<()> {
@@ -303,23 +344,11 @@ impl crate::backend::Session for CursorSession {
     }
 
     fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
-        let mut rx = self.message_tx.subscribe();
-
-        let stream = stream! {
-            loop {
-                match rx.recv().await {
-                    Ok(SessionMessage::SdkMessage(msg)) => yield Ok(msg),
-                    Ok(SessionMessage::End) => break,
-                    Ok(SessionMessage::Error(e)) => {
-                        yield Err(Error::Other(e));
-                        break;
-                    }
-                    Err(_) => break,
-                }
-            }
-        };
+        self.messages()
+    }
 
-        Box::pin(stream)
+    fn subscribe(&self) -> Option<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        Some(self.messages())
     }
 
     fn receive_response(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
@@ -328,19 +357,25 @@ impl crate::backend::Session for CursorSession {
         let stream = stream! {
             loop {
                 match rx.recv().await {
-                    Ok(SessionMessage::SdkMessage(msg)) => {
+                    Ok(Some(SessionMessage::SdkMessage(msg))) => {
                         let is_result = matches!(&msg, Message::Result(_));
+                        if is_result {
+                            rx.end_replay();
+                        }
                         yield Ok(msg);
                         if is_result {
                             break;
                         }
                     }
-                    Ok(SessionMessage::End) => break,
-                    Ok(SessionMessage::Error(e)) => {
+                    Ok(Some(SessionMessage::End)) | Ok(None) => break,
+                    Ok(Some(SessionMessage::Error(e))) => {
                         yield Err(Error::Other(e));
                         break;
                     }
-                    Err(_) => break,
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
                 }
             }
         };
@@ -368,49 +403,27 @@ impl crate::backend::Session for CursorSession {
             .map(|id| serde_json::json!({"chatId": id}))
     }
 
-    async fn close(&mut self) -> Result<()> {
-        if let Some(mut process) = self.active_process.take() {
-            let wait_result = tokio::time::timeout(
-                std::time::Duration::from_secs(CLOSE_TIMEOUT_SECS),
-                process.wait(),
-            )
-            .await;
-
-            match wait_result {
-                Ok(Ok(status)) => {
-                    if !status.success() {
-                        let _ = self.message_tx.send(SessionMessage::Error(format!(
-                            "Process exited with code {}",
-                            status.code().unwrap_or(-1)
-                        )));
-                    }
-                }
-                Ok(Err(e)) => {
-                    return Err(Error::Other(format!(
-                        "Failed waiting for Cursor process shutdown: {}",
-                        e
-                    )));
-                }
-                Err(_) => {
-                    let _ = process.kill().await;
-                    let _ = process.wait().await;
-                }
-            }
-        }
+    fn interrupt_handle(&self) -> Option<InterruptHandle> {
+        let stop_turn = Arc::clone(&self.stop_turn);
+        Some(InterruptHandle::new(move || {
+            stop_turn.notify_waiters();
+            Box::pin(async { Ok(()) })
+        }))
+    }
 
+    async fn close(&mut self) -> Result<()> {
         if let Some(mut handle) = self.read_task.take()
-            && tokio::time::timeout(
-                std::time::Duration::from_secs(CLOSE_TIMEOUT_SECS),
-                &mut handle,
-            )
-            .await
-            .is_err()
+            && tokio::time::timeout(self.options.timeouts.shutdown_grace, &mut handle)
+                .await
+                .is_err()
         {
-            handle.abort();
-            let _ = handle.await;
+            self.stop_turn(handle).await;
+        }
+        if let Some(config) = self.mcp_config.take() {
+            config.remove().await;
         }
 
-        let _ = self.message_tx.send(SessionMessage::End);
+        self.message_tx.send(SessionMessage::End);
         Ok(())
     }
 }
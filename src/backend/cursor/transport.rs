@@ -1,18 +1,17 @@
 //! One-shot transport for the Cursor Agent CLI.
 //!
-//! Spawns `agent --print --output-format stream-json <prompt>` and reads
+//! Launches `agent --print --output-format stream-json <prompt>` and reads
 //! JSONL events from stdout.
 
 use crate::error::{Error, Result};
-use crate::options::AgentOptions;
+use crate::options::{AgentOptions, McpServersConfig};
+use crate::transport::{LaunchSpec, is_remote, launch};
 use crate::types::{Message, Prompt};
 use async_stream::stream;
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use std::pin::Pin;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
 
+use super::mcp_config::CursorMcpConfig;
 use super::message_parser;
 
 /// Find the Cursor Agent CLI binary.
@@ -21,6 +20,10 @@ pub fn find_cursor_cli(options: &AgentOptions) -> Result<String> {
         return Ok(p.to_string_lossy().to_string());
     }
 
+    if is_remote(options) {
+        return Ok("agent".to_string());
+    }
+
     if let Ok(path) = std::env::var("CURSOR_CLI_PATH") {
         return Ok(path);
     }
@@ -52,6 +55,10 @@ fn build_cursor_command(cli_path: &str, prompt: &str, options: &AgentOptions) ->
         "stream-json".to_string(),
     ];
 
+    if options.include_partial_messages {
+        cmd.push("--stream-partial-output".to_string());
+    }
+
     if let Some(ref m) = options.model {
         cmd.push("--model".to_string());
         cmd.push(m.clone());
@@ -70,6 +77,10 @@ fn build_cursor_command(cli_path: &str, prompt: &str, options: &AgentOptions) ->
         }
     }
 
+    if has_mcp_servers(options) {
+        cmd.push("--approve-mcps".to_string());
+    }
+
     for (key, value) in &options.extra_args {
         if let Some(v) = value {
             cmd.push(format!("--{}", key));
@@ -84,6 +95,11 @@ fn build_cursor_command(cli_path: &str, prompt: &str, options: &AgentOptions) ->
     cmd
 }
 
+/// Whether [`CursorMcpConfig`] installs servers for `options`.
+pub(crate) fn has_mcp_servers(options: &AgentOptions) -> bool {
+    matches!(options.mcp_servers, Some(McpServersConfig::Dict(ref servers)) if !servers.is_empty())
+}
+
 /// Execute a one-shot Cursor Agent query, returning a stream of messages.
 pub fn one_shot_query(
     prompt: Prompt,
@@ -112,74 +128,60 @@ pub fn one_shot_query(
             }
         };
 
-        let cmd = build_cursor_command(&cli_path, &prompt_text, &options);
-
-        let mut child_cmd = Command::new(&cmd[0]);
-        child_cmd
-            .args(&cmd[1..])
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null());
-
-        if let Some(ref cwd) = options.cwd {
-            child_cmd.current_dir(cwd);
-        }
-
-        for (k, v) in &options.env {
-            child_cmd.env(k, v);
-        }
-
-        let mut process = match child_cmd.spawn() {
-            Ok(p) => p,
+        // Kept installed until the stream ends.
+        let mcp_config = match CursorMcpConfig::install(&options).await {
+            Ok(config) => config,
             Err(e) => {
-                yield Err(Error::CliNotFound(format!(
-                    "Cursor Agent CLI not found at: {} - {}",
-                    cli_path, e
-                )));
+                yield Err(e);
                 return;
             }
         };
 
-        let stdout = match process.stdout.take() {
-            Some(s) => s,
-            None => {
-                yield Err(Error::Other("Failed to capture stdout".to_string()));
+        let cmd = build_cursor_command(&cli_path, &prompt_text, &options);
+        let mut spec = LaunchSpec::from_options(cmd[0].clone(), &options).args(cmd[1..].to_vec());
+        spec.env.extend(mcp_config.iter().flat_map(CursorMcpConfig::env));
+
+        let mut transport = match launch(&options, spec).await {
+            Ok(t) => t,
+            Err(e) => {
+                yield Err(e);
                 return;
             }
         };
+        let _ = transport.end_input().await;
 
-        let reader = BufReader::new(stdout);
-        let mut lines = reader.lines();
+        let mut events = transport.read_messages();
         let mut got_result = false;
-
-        while let Ok(Some(line)) = lines.next_line().await {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(data) => {
-                    match message_parser::parse_cursor_event(&data) {
-                        Ok(Some(msg)) => {
-                            if matches!(&msg, Message::Result(_)) {
-                                got_result = true;
-                            }
-                            yield Ok(msg);
-                        }
-                        Ok(None) => continue,
-                        Err(e) => {
-                            yield Err(e);
-                            continue;
+        let mut parser = message_parser::CursorEventParser::new(&options);
+
+        while let Some(item) = events.next().await {
+            let data = match item {
+                Ok(data) => data,
+                Err(e) => {
+                    yield Err(e);
+                    let _ = transport.close().await;
+                    return;
+                }
+            };
+            match parser.parse(&data) {
+                Ok(messages) => {
+                    for msg in messages {
+                        if matches!(&msg, Message::Result(_)) {
+                            got_result = true;
                         }
+                        yield Ok(msg);
                     }
                 }
-                Err(_) => continue,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
             }
         }
-
-        // Wait for process to complete
-        let _ = process.wait().await;
+        let _ = transport.close().await;
+        if let Some(config) = mcp_config {
+            config.remove().await;
+        }
 
         // Emit synthetic result if the CLI didn't produce one
         if !got_result {
@@ -0,0 +1,527 @@
+//! In-memory scripted backend for deterministic tests.
+//!
+//! [`MockBackend`] never spawns a CLI. Each session replays a [`MockScript`]:
+//! every [`send_message`](crate::backend::Session::send_message) consumes the
+//! next [`MockTurn`] and delivers its events to `receive_messages()` /
+//! `receive_response()`. All prompts and control requests are captured by a
+//! shared [`MockRecorder`] so tests can assert on what the SDK sent.
+//!
+//! Select it with [`BackendKind::Mock`](crate::backend::BackendKind::Mock) and
+//! [`AgentOptions::mock`](crate::options::AgentOptions::mock):
+//!
+//! ```
+//! use code_agent_sdk::{AgentOptions, BackendKind, MockScript, MockTurn};
+//!
+//! let script = MockScript::new()
+//!     .turn(MockTurn::new().text("Hello!").result("session-1"));
+//! let options = AgentOptions::builder()
+//!     .backend(BackendKind::Mock)
+//!     .mock(script)
+//!     .build();
+//! assert!(options.mock.is_some());
+//! ```
+
+use crate::backend::{Backend, Capabilities, InterruptHandle, Session};
+use crate::error::{Error, Result};
+use crate::options::AgentOptions;
+use crate::types::{
+    AssistantMessage, ContentBlock, Message, Prompt, ResultMessage, TextBlock, ToolUseBlock,
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn mock_capabilities() -> Capabilities {
+    Capabilities {
+        control_protocol: true,
+        tool_approval: true,
+        hooks: true,
+        sdk_mcp_routing: true,
+        persistent_session: true,
+        interrupt: true,
+        runtime_config_changes: true,
+    }
+}
+
+/// A single scripted event emitted during a turn.
+#[derive(Debug, Clone)]
+pub enum MockEvent {
+    /// Deliver a message to the consumer.
+    Message(Message),
+    /// Deliver an error to the consumer (`Error::Other`).
+    Error(String),
+    /// Pause before delivering the next event.
+    Delay(Duration),
+}
+
+/// The scripted output of one turn.
+#[derive(Debug, Clone, Default)]
+pub struct MockTurn {
+    pub events: Vec<MockEvent>,
+}
+
+impl MockTurn {
+    /// Create an empty turn.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an arbitrary message.
+    pub fn message(mut self, message: Message) -> Self {
+        self.events.push(MockEvent::Message(message));
+        self
+    }
+
+    /// Append an assistant message containing a single text block.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.message(Message::Assistant(AssistantMessage {
+            content: vec![ContentBlock::Text(TextBlock { text: text.into() })],
+            model: "mock".to_string(),
+            parent_tool_use_id: None,
+            error: None,
+        }))
+    }
+
+    /// Append an assistant message containing a single tool use block.
+    pub fn tool_use(
+        self,
+        id: impl Into<String>,
+        name: impl Into<String>,
+        input: serde_json::Value,
+    ) -> Self {
+        self.message(Message::Assistant(AssistantMessage {
+            content: vec![ContentBlock::ToolUse(ToolUseBlock {
+                id: id.into(),
+                name: name.into(),
+                input,
+            })],
+            model: "mock".to_string(),
+            parent_tool_use_id: None,
+            error: None,
+        }))
+    }
+
+    /// Append a successful [`ResultMessage`] that ends the turn.
+    pub fn result(self, session_id: impl Into<String>) -> Self {
+        self.message(Message::Result(ResultMessage {
+            subtype: "success".to_string(),
+            duration_ms: 0,
+            duration_api_ms: 0,
+            is_error: false,
+            num_turns: 1,
+            session_id: session_id.into(),
+            total_cost_usd: None,
+            usage: None,
+            result: None,
+            structured_output: None,
+        }))
+    }
+
+    /// Append an error event.
+    pub fn error(mut self, message: impl Into<String>) -> Self {
+        self.events.push(MockEvent::Error(message.into()));
+        self
+    }
+
+    /// Append a pause, as a CLI that goes quiet mid-turn.
+    pub fn delay(mut self, duration: Duration) -> Self {
+        self.events.push(MockEvent::Delay(duration));
+        self
+    }
+}
+
+/// A prompt as received by the mock backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedPrompt {
+    /// A [`Prompt::Text`] prompt.
+    Text(String),
+    /// All values yielded by a [`Prompt::Stream`] prompt.
+    Stream(Vec<serde_json::Value>),
+}
+
+/// A call made by the SDK against the mock backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+    /// A one-shot `query()` or `Session::send_message`.
+    SendMessage {
+        prompt: RecordedPrompt,
+        session_id: String,
+    },
+    /// A `Session::send_control_request`.
+    ControlRequest(serde_json::Value),
+}
+
+/// Shared log of every call made against a mock session.
+///
+/// Cloning is cheap; all clones observe the same log.
+#[derive(Debug, Clone, Default)]
+pub struct MockRecorder {
+    calls: Arc<Mutex<Vec<MockCall>>>,
+}
+
+impl MockRecorder {
+    /// Create an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All recorded calls, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().expect("mock recorder poisoned").clone()
+    }
+
+    /// Only the prompts passed to `send_message` / `query()`.
+    pub fn prompts(&self) -> Vec<RecordedPrompt> {
+        self.calls()
+            .into_iter()
+            .filter_map(|c| match c {
+                MockCall::SendMessage { prompt, .. } => Some(prompt),
+                MockCall::ControlRequest(_) => None,
+            })
+            .collect()
+    }
+
+    /// Only the control requests, in order.
+    pub fn control_requests(&self) -> Vec<serde_json::Value> {
+        self.calls()
+            .into_iter()
+            .filter_map(|c| match c {
+                MockCall::ControlRequest(r) => Some(r),
+                MockCall::SendMessage { .. } => None,
+            })
+            .collect()
+    }
+
+    fn record(&self, call: MockCall) {
+        self.calls
+            .lock()
+            .expect("mock recorder poisoned")
+            .push(call);
+    }
+}
+
+/// Script replayed by [`MockBackend`].
+///
+/// Every session replays the turns from the start; the [`MockRecorder`] is
+/// shared between the script and all sessions created from it.
+#[derive(Debug, Clone, Default)]
+pub struct MockScript {
+    /// Turns replayed in order, one per `send_message`.
+    pub turns: Vec<MockTurn>,
+    /// Responses to control requests, keyed by `subtype`. Missing subtypes
+    /// respond with `null`.
+    pub control_responses: HashMap<String, serde_json::Value>,
+    /// Value returned by `get_server_info()`.
+    pub server_info: Option<serde_json::Value>,
+    /// Log of all calls made against sessions using this script.
+    pub recorder: MockRecorder,
+}
+
+impl MockScript {
+    /// Create an empty script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a turn.
+    pub fn turn(mut self, turn: MockTurn) -> Self {
+        self.turns.push(turn);
+        self
+    }
+
+    /// Set the response for control requests with the given `subtype`.
+    pub fn control_response(
+        mut self,
+        subtype: impl Into<String>,
+        response: serde_json::Value,
+    ) -> Self {
+        self.control_responses.insert(subtype.into(), response);
+        self
+    }
+
+    /// Set the value returned by `get_server_info()`.
+    pub fn server_info(mut self, info: serde_json::Value) -> Self {
+        self.server_info = Some(info);
+        self
+    }
+
+    /// The recorder shared by this script.
+    pub fn recorder(&self) -> MockRecorder {
+        self.recorder.clone()
+    }
+}
+
+/// Backend that replays a [`MockScript`] instead of driving a CLI.
+#[derive(Debug)]
+pub struct MockBackend {
+    capabilities: Capabilities,
+}
+
+impl MockBackend {
+    /// Create a new mock backend.
+    pub fn new() -> Self {
+        Self {
+            capabilities: mock_capabilities(),
+        }
+    }
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn require_script(options: &AgentOptions) -> Result<MockScript> {
+    options.mock.clone().ok_or_else(|| {
+        Error::Other("Mock backend requires AgentOptions::mock to be set".to_string())
+    })
+}
+
+async fn record_prompt(prompt: Prompt) -> RecordedPrompt {
+    match prompt {
+        Prompt::Text(s) => RecordedPrompt::Text(s),
+        Prompt::Stream(s) => RecordedPrompt::Stream(s.collect().await),
+    }
+}
+
+#[async_trait]
+impl Backend for MockBackend {
+    fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    fn name(&self) -> &str {
+        "Mock"
+    }
+
+    fn validate_options(&self, options: &AgentOptions) -> Result<()> {
+        require_script(options).map(|_| ())
+    }
+
+    fn one_shot_query(
+        &self,
+        prompt: Prompt,
+        options: &AgentOptions,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        let script = require_script(options)?;
+
+        let stream = stream! {
+            let prompt = record_prompt(prompt).await;
+            script.recorder.record(MockCall::SendMessage {
+                prompt,
+                session_id: String::new(),
+            });
+
+            let Some(turn) = script.turns.into_iter().next() else {
+                yield Err(Error::Other("Mock script has no turns".to_string()));
+                return;
+            };
+            for event in turn.events {
+                match event {
+                    MockEvent::Message(m) => {
+                        let is_result = matches!(&m, Message::Result(_));
+                        yield Ok(m);
+                        if is_result {
+                            break;
+                        }
+                    }
+                    MockEvent::Error(e) => yield Err(Error::Other(e)),
+                    MockEvent::Delay(d) => tokio::time::sleep(d).await,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn create_session(
+        &self,
+        options: &AgentOptions,
+        prompt: Option<Prompt>,
+    ) -> Result<Box<dyn Session + Send>> {
+        let script = require_script(options)?;
+        let mut session = MockSession::new(script);
+
+        // Match the Claude backend: stream prompts start immediately,
+        // text prompts are not auto-sent.
+        if let Some(Prompt::Stream(s)) = prompt {
+            session.send_message(Prompt::Stream(s), "").await?;
+        }
+
+        Ok(Box::new(session))
+    }
+}
+
+/// Session replaying a [`MockScript`].
+///
+/// Events are buffered until read, so a turn's output is never lost when
+/// `receive_response()` is called after `send_message()`.
+struct MockSession {
+    script: MockScript,
+    next_turn: usize,
+    event_tx: Option<mpsc::UnboundedSender<MockEvent>>,
+    event_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<MockEvent>>,
+    observers: std::sync::Mutex<Observers>,
+}
+
+/// Streams handed out by [`Session::subscribe`], which see every event
+/// independently of the buffered receive queue.
+#[derive(Default)]
+struct Observers {
+    senders: Vec<mpsc::UnboundedSender<MockEvent>>,
+    /// Events of the current turn, replayed to new observers.
+    turn: Vec<MockEvent>,
+}
+
+impl MockSession {
+    fn new(script: MockScript) -> Self {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        Self {
+            script,
+            next_turn: 0,
+            event_tx: Some(event_tx),
+            event_rx: tokio::sync::Mutex::new(event_rx),
+            observers: std::sync::Mutex::default(),
+        }
+    }
+
+    fn receive(
+        &self,
+        until_result: bool,
+    ) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
+        let stream = stream! {
+            let mut rx = self.event_rx.lock().await;
+            while let Some(event) = rx.recv().await {
+                match event {
+                    MockEvent::Message(m) => {
+                        let is_result = matches!(&m, Message::Result(_));
+                        yield Ok(m);
+                        if until_result && is_result {
+                            break;
+                        }
+                    }
+                    MockEvent::Error(e) => yield Err(Error::Other(e)),
+                    MockEvent::Delay(d) => tokio::time::sleep(d).await,
+                }
+            }
+        };
+
+        Box::pin(stream)
+    }
+}
+
+#[async_trait]
+impl Session for MockSession {
+    async fn send_message(&mut self, prompt: Prompt, session_id: &str) -> Result<()> {
+        let event_tx = self
+            .event_tx
+            .as_ref()
+            .ok_or_else(|| Error::Other("Session closed".to_string()))?;
+
+        let prompt = record_prompt(prompt).await;
+        self.script.recorder.record(MockCall::SendMessage {
+            prompt,
+            session_id: session_id.to_string(),
+        });
+
+        let events = match self.script.turns.get(self.next_turn) {
+            Some(turn) => turn.events.clone(),
+            None => vec![MockEvent::Error(format!(
+                "Mock script exhausted after {} turn(s)",
+                self.script.turns.len()
+            ))],
+        };
+        self.next_turn += 1;
+
+        let mut observers = self.observers.lock().expect("mock observers poisoned");
+        observers.turn.clone_from(&events);
+        observers
+            .senders
+            .retain(|tx| events.iter().all(|event| tx.send(event.clone()).is_ok()));
+        for event in events {
+            let _ = event_tx.send(event);
+        }
+        Ok(())
+    }
+
+    fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
+        self.receive(false)
+    }
+
+    fn receive_response(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
+        self.receive(true)
+    }
+
+    async fn send_control_request(
+        &mut self,
+        request: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        if self.event_tx.is_none() {
+            return Err(Error::Other("Session closed".to_string()));
+        }
+        let subtype = request
+            .get("subtype")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        self.script
+            .recorder
+            .record(MockCall::ControlRequest(request));
+        Ok(self
+            .script
+            .control_responses
+            .get(&subtype)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+
+    async fn get_server_info(&self) -> Option<serde_json::Value> {
+        self.script.server_info.clone()
+    }
+
+    fn interrupt_handle(&self) -> Option<InterruptHandle> {
+        let recorder = self.script.recorder.clone();
+        Some(InterruptHandle::new(move || {
+            recorder.record(MockCall::ControlRequest(
+                serde_json::json!({"subtype": "interrupt"}),
+            ));
+            Box::pin(async { Ok(()) })
+        }))
+    }
+
+    fn subscribe(&self) -> Option<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        if self.event_tx.is_some() {
+            let mut observers = self.observers.lock().expect("mock observers poisoned");
+            for event in &observers.turn {
+                let _ = tx.send(event.clone());
+            }
+            observers.senders.push(tx);
+        }
+        Some(Box::pin(stream! {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    MockEvent::Message(m) => yield Ok(m),
+                    MockEvent::Error(e) => yield Err(Error::Other(e)),
+                    MockEvent::Delay(d) => tokio::time::sleep(d).await,
+                }
+            }
+        }))
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        drop(self.event_tx.take());
+        self.observers
+            .lock()
+            .expect("mock observers poisoned")
+            .senders
+            .clear();
+        Ok(())
+    }
+}
@@ -8,17 +8,22 @@
 pub mod claude;
 pub mod codex;
 pub mod cursor;
+pub mod mock;
 
 use crate::error::Result;
 use crate::options::AgentOptions;
+use crate::transport::Transport;
 use crate::types::{Message, Prompt};
 use async_trait::async_trait;
 use futures::Stream;
+use futures::future::BoxFuture;
 use std::fmt;
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// Selects which CLI backend to use.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[non_exhaustive]
 pub enum BackendKind {
     /// Claude Code CLI (`claude`).
@@ -28,6 +33,8 @@ pub enum BackendKind {
     Codex,
     /// Cursor Agent CLI (`agent`).
     Cursor,
+    /// In-memory scripted backend for tests (see [`mock`]).
+    Mock,
 }
 
 impl fmt::Display for BackendKind {
@@ -36,6 +43,7 @@ impl fmt::Display for BackendKind {
             Self::Claude => write!(f, "Claude"),
             Self::Codex => write!(f, "Codex"),
             Self::Cursor => write!(f, "Cursor"),
+            Self::Mock => write!(f, "Mock"),
         }
     }
 }
@@ -104,6 +112,25 @@ pub trait Backend: Send + Sync + fmt::Debug {
         options: &AgentOptions,
         prompt: Option<Prompt>,
     ) -> Result<Box<dyn Session + Send>>;
+
+    /// Create a multi-turn session over an already connected transport.
+    ///
+    /// Backends with a single long-lived process (Claude, Codex app-server)
+    /// run their protocol over `transport` instead of launching the CLI.
+    /// The default returns
+    /// [`Error::UnsupportedFeature`](crate::error::Error::UnsupportedFeature).
+    async fn create_session_with_transport(
+        &self,
+        options: &AgentOptions,
+        prompt: Option<Prompt>,
+        transport: Box<dyn Transport + Send>,
+    ) -> Result<Box<dyn Session + Send>> {
+        let _ = (options, prompt, transport);
+        Err(crate::error::Error::UnsupportedFeature {
+            feature: "custom transport".to_string(),
+            backend: self.name().to_string(),
+        })
+    }
 }
 
 /// A multi-turn interactive session with a backend.
@@ -113,6 +140,7 @@ pub trait Backend: Send + Sync + fmt::Debug {
 /// - Claude: single long-lived subprocess with stdin/stdout streaming
 /// - Codex: `codex app-server` with JSON-RPC 2.0 protocol
 /// - Cursor: spawn-per-turn with `--resume <chatId>`
+/// - Mock: in-memory replay of a [`mock::MockScript`]
 #[async_trait]
 pub trait Session: Send {
     /// Send a user message in the session.
@@ -136,15 +164,60 @@ pub trait Session: Send {
     /// Get server info from the initialization handshake, if available.
     async fn get_server_info(&self) -> Option<serde_json::Value>;
 
+    /// A handle that interrupts the running turn without borrowing the
+    /// session mutably, used to enforce turn timeouts while a receive
+    /// stream is open. The default returns `None`.
+    fn interrupt_handle(&self) -> Option<InterruptHandle> {
+        None
+    }
+
+    /// Every message of the session from the start of the current turn
+    /// onwards, independently of the `receive_*` streams, used to record
+    /// transcripts. The default returns `None`.
+    fn subscribe(&self) -> Option<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        None
+    }
+
     /// Close the session and release resources.
     async fn close(&mut self) -> Result<()>;
 }
 
+/// Interrupts a session's running turn; see [`Session::interrupt_handle`].
+///
+/// Handles do not keep the session alive: interrupting after the session
+/// has closed is a no-op.
+#[derive(Clone)]
+pub struct InterruptHandle {
+    interrupt: Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>,
+}
+
+impl InterruptHandle {
+    pub fn new(
+        interrupt: impl Fn() -> BoxFuture<'static, Result<()>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            interrupt: Arc::new(interrupt),
+        }
+    }
+
+    /// Ask the backend to stop the running turn.
+    pub async fn interrupt(&self) -> Result<()> {
+        (self.interrupt)().await
+    }
+}
+
+impl fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterruptHandle").finish_non_exhaustive()
+    }
+}
+
 /// Create a backend instance for the given kind.
 pub fn create_backend(kind: BackendKind) -> Box<dyn Backend> {
     match kind {
         BackendKind::Claude => Box::new(claude::ClaudeBackend::new()),
         BackendKind::Codex => Box::new(codex::CodexBackend::new()),
         BackendKind::Cursor => Box::new(cursor::CursorBackend::new()),
+        BackendKind::Mock => Box::new(mock::MockBackend::new()),
     }
 }
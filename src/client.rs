@@ -2,22 +2,47 @@
 
 use crate::backend::{Backend, BackendKind, Session, create_backend};
 use crate::error::{Error, Result};
+use crate::internal::budget::BudgetTracker;
+use crate::internal::transcript::TranscriptRecorder;
+use crate::internal::turn_timer::TurnTimer;
 use crate::options::AgentOptions;
 use crate::transport::Transport;
 use crate::types::{Message, Prompt};
-use futures::Stream;
+use crate::usage::Usage;
+use futures::{Stream, StreamExt};
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// Client for bidirectional, interactive conversations with code agents.
 ///
 /// Supports Claude Code, Codex, and Cursor Agent backends. Methods that
 /// require backend-specific features perform capability checks and return
 /// [`Error::UnsupportedFeature`] when unsupported.
+///
+/// The client tracks usage and cost across turns. Once
+/// [`max_budget_usd`](AgentOptions::max_budget_usd) or
+/// [`max_budget_tokens`](AgentOptions::max_budget_tokens) is reached,
+/// [`query`](Self::query) fails with [`Error::BudgetExceeded`]. Spend is
+/// counted as results are received, so the turn that crosses a ceiling runs
+/// to completion.
+///
+/// [`TimeoutConfig::turn`](crate::options::TimeoutConfig::turn) and
+/// [`TimeoutConfig::idle`](crate::options::TimeoutConfig::idle) are enforced
+/// on the receive streams: when one expires the turn is interrupted and the
+/// stream ends with [`Error::TurnTimeout`].
+///
+/// With [`AgentOptions::session_store`] set, each connection is recorded as
+/// a transcript (see [`transcript_id`](Self::transcript_id)): the user
+/// messages sent and every message the backend produces, whether or not it
+/// is read from a receive stream.
 pub struct AgentSdkClient {
     options: AgentOptions,
     custom_transport: Option<Box<dyn Transport + Send>>,
     backend: Box<dyn Backend>,
     session: Option<Box<dyn Session + Send>>,
+    budget: Arc<BudgetTracker>,
+    turn_timer: Arc<TurnTimer>,
+    recorder: Option<TranscriptRecorder>,
 }
 
 impl AgentSdkClient {
@@ -25,8 +50,11 @@ impl AgentSdkClient {
     ///
     /// # Arguments
     /// * `options` - Configuration options (uses defaults if `None`).
-    /// * `custom_transport` - Optional custom transport (uses subprocess CLI if `None`).
-    ///   Only applicable to the Claude backend.
+    /// * `custom_transport` - Optional connected transport to run the session
+    ///   over instead of launching the CLI. Supported by the Claude and Codex
+    ///   (app-server) backends; use
+    ///   [`AgentOptions::launcher`](crate::options::AgentOptions::launcher) for
+    ///   Cursor, which starts a process per turn.
     pub fn new(
         options: Option<AgentOptions>,
         custom_transport: Option<Box<dyn Transport + Send>>,
@@ -35,11 +63,17 @@ impl AgentSdkClient {
         let kind = options.backend.unwrap_or(BackendKind::Claude);
         let backend = create_backend(kind);
 
+        let budget = Arc::new(BudgetTracker::new(&options));
+        let turn_timer = Arc::new(TurnTimer::new(&options));
+
         Self {
             options,
             custom_transport,
             backend,
             session: None,
+            budget,
+            turn_timer,
+            recorder: None,
         }
     }
 
@@ -65,47 +99,38 @@ impl AgentSdkClient {
             ));
         }
 
-        // For Claude backend with custom transport, use the legacy Query path
-        if self.custom_transport.is_some()
-            && self.options.backend.unwrap_or(BackendKind::Claude) == BackendKind::Claude
+        if self.recorder.is_none()
+            && let Some(store) = &self.options.session_store
         {
-            return self.connect_claude_legacy(prompt).await;
+            self.recorder = Some(TranscriptRecorder::start(
+                Arc::clone(store),
+                self.options.backend.unwrap_or(BackendKind::Claude),
+                self.options.resume.clone(),
+            ));
         }
 
-        let session = self.backend.create_session(&self.options, prompt).await?;
-        self.session = Some(session);
-        Ok(())
-    }
-
-    /// Legacy connect path for Claude with custom transport.
-    async fn connect_claude_legacy(&mut self, prompt: Option<Prompt>) -> Result<()> {
-        use crate::internal::query::Query;
-
-        let mut options = self.options.clone();
-        if options.can_use_tool.is_some() {
-            if options.permission_prompt_tool_name.is_some() {
-                return Err(Error::Other(
-                    "can_use_tool callback cannot be used with permission_prompt_tool_name. \
-                     Please use one or the other."
-                        .to_string(),
-                ));
+        // Text prompts are not sent on connect; streamed user messages are.
+        let prompt = match prompt {
+            Some(p @ Prompt::Stream(_)) => Some(self.track_turns(p)),
+            other => other,
+        };
+        let session = match self.custom_transport.take() {
+            Some(transport) => {
+                self.backend
+                    .create_session_with_transport(&self.options, prompt, transport)
+                    .await?
             }
-            options.permission_prompt_tool_name = Some("stdio".to_string());
+            None => self.backend.create_session(&self.options, prompt).await?,
+        };
+        if let Some(recorder) = &self.recorder
+            && let Some(messages) = session.subscribe()
+        {
+            recorder.follow(messages);
         }
-
-        let transport = self
-            .custom_transport
-            .take()
-            .ok_or_else(|| Error::Other("Custom transport already consumed".to_string()))?;
-
-        let mut query = Query::new(transport, &options);
-        query.initialize(&options).await?;
-
-        if let Some(Prompt::Stream(input_stream)) = prompt {
-            query.stream_input(input_stream).await?;
+        if let Some(messages) = session.subscribe() {
+            self.budget.follow(messages);
         }
-
-        self.session = Some(Box::new(LegacyQuerySession { query }));
+        self.session = Some(session);
         Ok(())
     }
 
@@ -113,24 +138,36 @@ impl AgentSdkClient {
     ///
     /// For `Prompt::Text`: writes a user message with the given session_id.
     /// For `Prompt::Stream`: iterates and writes each message.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BudgetExceeded`] once a session budget is used up.
     pub async fn query(&mut self, prompt: impl Into<Prompt>, session_id: &str) -> Result<()> {
+        if self.session.is_none() {
+            return Err(Error::NotConnected);
+        }
+        self.budget.check()?;
+        let prompt = self.track_turns(prompt.into());
         let session = self.session.as_mut().ok_or(Error::NotConnected)?;
-        session.send_message(prompt.into(), session_id).await
+        session.send_message(prompt, session_id).await
     }
 
     /// Receive all messages (for debugging/monitoring).
     pub fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
         if let Some(ref s) = self.session {
-            s.receive_messages()
+            self.metered(s.as_ref(), s.receive_messages())
         } else {
             Box::pin(futures::stream::once(async { Err(Error::NotConnected) }))
         }
     }
 
     /// Receive messages until the next [`ResultMessage`](crate::types::ResultMessage).
+    ///
+    /// Each result is returned once; calling this again after a result
+    /// continues with the next turn.
     pub fn receive_response(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
         if let Some(ref s) = self.session {
-            s.receive_response()
+            self.metered(s.as_ref(), s.receive_response())
         } else {
             Box::pin(futures::stream::once(async { Err(Error::NotConnected) }))
         }
@@ -223,12 +260,76 @@ impl AgentSdkClient {
         }
     }
 
+    /// Token usage accumulated over the session's completed turns.
+    pub fn total_usage(&self) -> Usage {
+        self.budget.usage()
+    }
+
+    /// Cost accumulated over the session's completed turns, in USD.
+    ///
+    /// Turns whose result carries no cost (see
+    /// [`AgentOptions::price_table`]) count as zero.
+    pub fn total_cost_usd(&self) -> f64 {
+        self.budget.cost_usd()
+    }
+
+    /// Id of the transcript recording the current connection, if a
+    /// [`session_store`](AgentOptions::session_store) is configured.
+    pub fn transcript_id(&self) -> Option<&str> {
+        self.recorder.as_ref().map(|r| r.id())
+    }
+
     /// Disconnect from the agent.
+    ///
+    /// Returns once the transcript, if any, has been written.
     pub async fn disconnect(&mut self) -> Result<()> {
-        if let Some(mut s) = self.session.take() {
-            s.close().await?;
+        let closed = match self.session.take() {
+            Some(mut s) => s.close().await,
+            None => Ok(()),
+        };
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish(self.options.timeouts.shutdown_grace).await;
+        }
+        closed
+    }
+
+    /// Start the turn timer for the user messages in `prompt`, and record
+    /// them in the transcript.
+    fn track_turns(&self, prompt: Prompt) -> Prompt {
+        let prompt = match &self.recorder {
+            Some(recorder) => recorder.record_prompt(prompt),
+            None => prompt,
+        };
+        match prompt {
+            Prompt::Text(text) => {
+                self.turn_timer.start_turn();
+                Prompt::Text(text)
+            }
+            Prompt::Stream(stream) => {
+                let turn_timer = Arc::clone(&self.turn_timer);
+                Prompt::Stream(Box::pin(stream.inspect(move |msg| {
+                    if msg.get("type").and_then(|v| v.as_str()) == Some("user") {
+                        turn_timer.start_turn();
+                    }
+                })))
+            }
         }
-        Ok(())
+    }
+
+    /// Bring the session budget up to date with results from `stream` and
+    /// apply the turn timeouts.
+    fn metered<'a>(
+        &self,
+        session: &(dyn Session + Send),
+        stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send + 'a>>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + 'a>> {
+        let budget = Arc::clone(&self.budget);
+        let stream = Arc::clone(&self.turn_timer).enforce(stream, session.interrupt_handle());
+        Box::pin(stream.inspect(move |item| {
+            if let Ok(Message::Result(_)) = item {
+                budget.catch_up();
+            }
+        }))
     }
 
     /// Check that the backend supports a required capability.
@@ -248,41 +349,3 @@ impl AgentSdkClient {
         }
     }
 }
-
-/// Session wrapper for the legacy Query-based path (custom transport).
-struct LegacyQuerySession {
-    query: crate::internal::query::Query,
-}
-
-#[async_trait::async_trait]
-impl Session for LegacyQuerySession {
-    async fn send_message(&mut self, prompt: Prompt, session_id: &str) -> Result<()> {
-        match prompt {
-            Prompt::Text(text) => self.query.write_user_message(&text, session_id).await,
-            Prompt::Stream(input_stream) => self.query.stream_input(input_stream).await,
-        }
-    }
-
-    fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
-        self.query.receive_messages()
-    }
-
-    fn receive_response(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
-        self.query.receive_response()
-    }
-
-    async fn send_control_request(
-        &mut self,
-        request: serde_json::Value,
-    ) -> Result<serde_json::Value> {
-        self.query.send_control_request(request).await
-    }
-
-    async fn get_server_info(&self) -> Option<serde_json::Value> {
-        self.query.get_server_info().await
-    }
-
-    async fn close(&mut self) -> Result<()> {
-        self.query.close().await
-    }
-}
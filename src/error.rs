@@ -1,5 +1,6 @@
 //! Error types for Code Agent SDK.
 
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -37,8 +38,56 @@ pub enum Error {
         options: Vec<String>,
     },
 
+    #[error("Message stream fell behind; {0} messages were dropped")]
+    Lagged(u64),
+
+    #[error("Session budget exceeded: {0}")]
+    BudgetExceeded(BudgetLimit),
+
+    #[error("Turn timed out: {0}")]
+    TurnTimeout(TurnTimeout),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
     #[error("{0}")]
     Other(String),
 }
 
+/// The session ceiling that was crossed, with the amount spent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetLimit {
+    /// [`AgentOptions::max_budget_usd`](crate::options::AgentOptions::max_budget_usd).
+    Usd { limit: f64, spent: f64 },
+    /// [`AgentOptions::max_budget_tokens`](crate::options::AgentOptions::max_budget_tokens).
+    Tokens { limit: u64, used: u64 },
+}
+
+impl std::fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usd { limit, spent } => write!(f, "spent ${:.4} of ${:.4}", spent, limit),
+            Self::Tokens { limit, used } => write!(f, "used {} of {} tokens", used, limit),
+        }
+    }
+}
+
+/// The turn timeout that expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnTimeout {
+    /// [`TimeoutConfig::turn`](crate::options::TimeoutConfig::turn).
+    Turn(Duration),
+    /// [`TimeoutConfig::idle`](crate::options::TimeoutConfig::idle).
+    Idle(Duration),
+}
+
+impl std::fmt::Display for TurnTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Turn(limit) => write!(f, "no result within {:?}", limit),
+            Self::Idle(limit) => write!(f, "no message for {:?}", limit),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
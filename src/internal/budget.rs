@@ -0,0 +1,144 @@
+//! Session-level spend tracking for [`AgentSdkClient`](crate::client::AgentSdkClient).
+
+use crate::backend::BackendKind;
+use crate::error::{BudgetLimit, Error, Result};
+use crate::options::AgentOptions;
+use crate::types::Message;
+use crate::usage::Usage;
+use futures::{FutureExt, Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Mutex;
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<Message>> + Send>>;
+
+/// Accumulates usage and cost across the turns of a session and enforces
+/// [`AgentOptions::max_budget_usd`] and [`AgentOptions::max_budget_tokens`].
+///
+/// `receive_messages()` and `receive_response()` may observe the same
+/// result, and several turns may be in flight at once. Results are
+/// therefore counted from a [`Session::subscribe`](crate::backend::Session::subscribe)
+/// stream of the tracker's own, which sees each turn's result exactly once;
+/// the `receive_*` streams only prompt it to catch up.
+pub(crate) struct BudgetTracker {
+    max_usd: Option<f64>,
+    max_tokens: Option<u64>,
+    /// Claude reports `total_cost_usd` as a running total for the session.
+    cumulative_cost: bool,
+    results: Mutex<Option<MessageStream>>,
+    state: Mutex<Spend>,
+}
+
+#[derive(Debug, Default)]
+struct Spend {
+    usage: Usage,
+    cost_usd: f64,
+    /// Last running total reported, for backends with `cumulative_cost`.
+    reported_usd: Option<f64>,
+}
+
+impl std::fmt::Debug for BudgetTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BudgetTracker")
+            .field("max_usd", &self.max_usd)
+            .field("max_tokens", &self.max_tokens)
+            .field("cumulative_cost", &self.cumulative_cost)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BudgetTracker {
+    pub(crate) fn new(options: &AgentOptions) -> Self {
+        Self {
+            max_usd: options.max_budget_usd,
+            max_tokens: options.max_budget_tokens,
+            cumulative_cost: options.backend.unwrap_or_default() == BackendKind::Claude,
+            results: Mutex::default(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Count the results of the session's `messages` from now on.
+    pub(crate) fn follow(&self, messages: MessageStream) {
+        *self.results.lock().unwrap() = Some(messages);
+    }
+
+    /// Count the results that have arrived so far, without waiting for more.
+    pub(crate) fn catch_up(&self) {
+        let mut results = self.results.lock().unwrap();
+        let Some(messages) = results.as_mut() else {
+            return;
+        };
+        while let Some(item) = messages.next().now_or_never() {
+            match item {
+                Some(Ok(message)) => self.record(&message),
+                Some(Err(e)) => tracing::warn!("Budget tracking missed messages: {}", e),
+                None => {
+                    *results = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Add the usage and cost of `message` if it is a result.
+    fn record(&self, message: &Message) {
+        let Message::Result(result) = message else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if let Some(usage) = result.usage {
+            state.usage += usage;
+        }
+        let cost = match result.total_cost_usd {
+            Some(total) if self.cumulative_cost => {
+                // A smaller total means the CLI started over, e.g. on resume.
+                let previous = state.reported_usd.replace(total).unwrap_or(0.0);
+                Some(if total >= previous {
+                    total - previous
+                } else {
+                    total
+                })
+            }
+            cost => cost,
+        };
+        match cost {
+            Some(cost) => state.cost_usd += cost,
+            None if self.max_usd.is_some() => {
+                tracing::warn!("Result has no cost; it does not count towards max_budget_usd");
+            }
+            None => {}
+        }
+    }
+
+    /// Fail with [`Error::BudgetExceeded`] once a ceiling has been reached.
+    pub(crate) fn check(&self) -> Result<()> {
+        self.catch_up();
+        let state = self.state.lock().unwrap();
+        if let Some(limit) = self.max_usd
+            && state.cost_usd >= limit
+        {
+            return Err(Error::BudgetExceeded(BudgetLimit::Usd {
+                limit,
+                spent: state.cost_usd,
+            }));
+        }
+        let used = state.usage.total_tokens();
+        if let Some(limit) = self.max_tokens
+            && used >= limit
+        {
+            return Err(Error::BudgetExceeded(BudgetLimit::Tokens { limit, used }));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn usage(&self) -> Usage {
+        self.catch_up();
+        self.state.lock().unwrap().usage
+    }
+
+    pub(crate) fn cost_usd(&self) -> f64 {
+        self.catch_up();
+        self.state.lock().unwrap().cost_usd
+    }
+}
@@ -0,0 +1,41 @@
+//! Cancellation signals handed to `can_use_tool` and hook callbacks.
+
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Session- and turn-scoped cancellation.
+///
+/// Every callback gets a child of the current turn's token, which is itself
+/// a child of the session's: interrupting the turn cancels the callbacks
+/// running for it, closing the session cancels all of them.
+#[derive(Debug)]
+pub(crate) struct CancelScope {
+    session: CancellationToken,
+    turn: Mutex<CancellationToken>,
+}
+
+impl CancelScope {
+    pub(crate) fn new() -> Self {
+        let session = CancellationToken::new();
+        let turn = Mutex::new(session.child_token());
+        Self { session, turn }
+    }
+
+    /// A token for one callback invocation.
+    pub(crate) fn callback_token(&self) -> CancellationToken {
+        self.turn.lock().unwrap().child_token()
+    }
+
+    /// Cancel the callbacks of the current turn; later callbacks get fresh
+    /// tokens.
+    pub(crate) fn cancel_turn(&self) {
+        let mut turn = self.turn.lock().unwrap();
+        turn.cancel();
+        *turn = self.session.child_token();
+    }
+
+    /// Cancel every callback, now and later.
+    pub(crate) fn close(&self) {
+        self.session.cancel();
+    }
+}
@@ -5,9 +5,12 @@
 
 use crate::backend::{BackendKind, create_backend};
 use crate::error::Result;
+use crate::internal::transcript::TranscriptRecorder;
+use crate::internal::turn_timer::TurnTimer;
 use crate::options::AgentOptions;
 use crate::types::{Message, Prompt};
-use futures::Stream;
+use async_stream::stream;
+use futures::{Stream, StreamExt};
 use std::pin::Pin;
 
 pub struct InternalClient;
@@ -25,10 +28,32 @@ impl InternalClient {
         let kind = options.backend.unwrap_or(BackendKind::Claude);
         let backend = create_backend(kind);
 
-        match backend.one_shot_query(prompt, &options) {
-            Ok(stream) => stream,
-            Err(e) => Box::pin(futures::stream::once(async move { Err(e) })),
-        }
+        let Some(store) = options.session_store.clone() else {
+            return match backend.one_shot_query(prompt, &options) {
+                Ok(stream) => TurnTimer::enforce_one_shot(&options, stream),
+                Err(e) => Box::pin(futures::stream::once(async move { Err(e) })),
+            };
+        };
+
+        // Recorded as one transcript; the recorder is started on first
+        // poll, since the caller need not be inside a runtime yet.
+        Box::pin(stream! {
+            let recorder = TranscriptRecorder::start(store, kind, options.resume.clone());
+            let prompt = recorder.record_prompt(prompt);
+            match backend.one_shot_query(prompt, &options) {
+                Ok(messages) => {
+                    let mut messages = TurnTimer::enforce_one_shot(&options, messages);
+                    while let Some(item) = messages.next().await {
+                        if let Ok(message) = &item {
+                            recorder.record(message);
+                        }
+                        yield item;
+                    }
+                }
+                Err(e) => yield Err(e),
+            }
+            recorder.finish(options.timeouts.shutdown_grace).await;
+        })
     }
 }
 
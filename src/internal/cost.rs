@@ -0,0 +1,45 @@
+//! Cost estimation for results from backends that don't report cost.
+
+use crate::options::AgentOptions;
+use crate::types::Message;
+use crate::usage::PriceTable;
+
+/// Fills in [`ResultMessage::total_cost_usd`](crate::types::ResultMessage::total_cost_usd)
+/// from the result's usage and the session's model.
+///
+/// The model starts as [`AgentOptions::model`] and is replaced by whatever
+/// the backend reports it actually used.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CostEstimator {
+    table: Option<PriceTable>,
+    model: Option<String>,
+}
+
+impl CostEstimator {
+    pub(crate) fn new(options: &AgentOptions) -> Self {
+        Self {
+            table: options.price_table.clone(),
+            model: options.model.clone(),
+        }
+    }
+
+    /// Record the model reported by the backend.
+    pub(crate) fn set_model(&mut self, model: &str) {
+        if !model.is_empty() {
+            self.model = Some(model.to_string());
+        }
+    }
+
+    /// Estimate the cost of `message` if it is a result without one.
+    pub(crate) fn apply(&self, message: &mut Message) {
+        let Message::Result(result) = message else {
+            return;
+        };
+        if result.total_cost_usd.is_some() {
+            return;
+        }
+        if let (Some(table), Some(model), Some(usage)) = (&self.table, &self.model, &result.usage) {
+            result.total_cost_usd = table.cost_usd(model, usage);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Lossless fan-out of session messages to any number of readers.
+//!
+//! Sessions used to deliver messages over `tokio::sync::broadcast`, which
+//! silently drops messages for receivers that fall more than its capacity
+//! behind. [`Fanout`] instead keeps every message in a shared log until all
+//! subscribers have read it, so a slow `receive_*` stream only costs memory.
+//!
+//! The log also retains the current turn (everything since the last
+//! [`begin_turn`](Fanout::begin_turn)), so a stream created after
+//! `query()` still sees the turn from its first message. Once a
+//! `receive_response()` has read the turn's result it calls
+//! [`Subscriber::end_replay`], and later subscribers start after that
+//! result instead of replaying a finished turn.
+//!
+//! Retention is capped at [`RETAINED_MESSAGES`]. A subscriber that falls
+//! further behind than that receives [`Error::Lagged`] rather than a
+//! truncated stream.
+
+use crate::error::{Error, Result};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Maximum number of messages retained for slow subscribers.
+pub(crate) const RETAINED_MESSAGES: usize = 10_000;
+
+/// Sending half; also hands out [`Subscriber`]s.
+///
+/// Subscribers see the end of the stream once every `Fanout` handle has
+/// been dropped.
+pub(crate) struct Fanout<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    notify: Notify,
+    senders: AtomicUsize,
+}
+
+struct State<T> {
+    log: VecDeque<T>,
+    /// Sequence number of `log[0]`.
+    first: u64,
+    /// Sequence number of the first message new subscribers see: the start
+    /// of the current turn, or the end of its delivered part.
+    turn_start: u64,
+    /// Next sequence number to read, per subscriber.
+    cursors: HashMap<u64, u64>,
+    next_subscriber: u64,
+    capacity: usize,
+    closed: bool,
+}
+
+impl<T> State<T> {
+    fn end(&self) -> u64 {
+        self.first + self.log.len() as u64
+    }
+
+    /// Drop messages that every subscriber has read and that precede the
+    /// current turn, and anything beyond the retention cap.
+    fn trim(&mut self) {
+        let needed = self
+            .cursors
+            .values()
+            .copied()
+            .fold(self.turn_start, u64::min);
+        let keep_from = needed.max(self.end().saturating_sub(self.capacity as u64));
+        while self.first < keep_from && self.log.pop_front().is_some() {
+            self.first += 1;
+        }
+    }
+}
+
+impl<T: Clone> Fanout<T> {
+    pub(crate) fn new() -> Self {
+        Self::with_capacity(RETAINED_MESSAGES)
+    }
+
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    log: VecDeque::new(),
+                    first: 0,
+                    turn_start: 0,
+                    cursors: HashMap::new(),
+                    next_subscriber: 0,
+                    capacity,
+                    closed: false,
+                }),
+                notify: Notify::new(),
+                senders: AtomicUsize::new(1),
+            }),
+        }
+    }
+
+    /// Deliver `item` to all current and late subscribers of this turn.
+    pub(crate) fn send(&self, item: T) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+            state.log.push_back(item);
+            state.trim();
+        }
+        self.shared.notify.notify_waiters();
+    }
+
+    /// Start a new turn; earlier messages are no longer replayed to new
+    /// subscribers.
+    pub(crate) fn begin_turn(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.turn_start = state.end();
+        state.trim();
+    }
+
+    /// Subscribe from the start of the current turn.
+    pub(crate) fn subscribe(&self) -> Subscriber<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        let from = state.turn_start.max(state.first);
+        self.add_subscriber(&mut state, from)
+    }
+
+    /// Subscribe to messages sent from now on, e.g. before writing a
+    /// request whose response is awaited.
+    pub(crate) fn subscribe_new(&self) -> Subscriber<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        let from = state.end();
+        self.add_subscriber(&mut state, from)
+    }
+
+    fn add_subscriber(&self, state: &mut State<T>, from: u64) -> Subscriber<T> {
+        let id = state.next_subscriber;
+        state.next_subscriber += 1;
+        state.cursors.insert(id, from);
+        Subscriber {
+            shared: Arc::clone(&self.shared),
+            id,
+        }
+    }
+}
+
+impl<T> Clone for Fanout<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Fanout<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.state.lock().unwrap().closed = true;
+            self.shared.notify.notify_waiters();
+        }
+    }
+}
+
+/// Receiving half with its own read position.
+pub(crate) struct Subscriber<T> {
+    shared: Arc<Shared<T>>,
+    id: u64,
+}
+
+impl<T: Clone> Subscriber<T> {
+    /// The next message, or `None` once the stream is closed and drained.
+    ///
+    /// Returns [`Error::Lagged`] if messages this subscriber had not read
+    /// were dropped by the retention cap; reading continues after the gap.
+    pub(crate) async fn recv(&mut self) -> Result<Option<T>> {
+        loop {
+            let notified = self.shared.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                let cursor = state.cursors[&self.id];
+                if cursor < state.first {
+                    let first = state.first;
+                    state.cursors.insert(self.id, first);
+                    return Err(Error::Lagged(first - cursor));
+                }
+                if cursor < state.end() {
+                    let item = state.log[(cursor - state.first) as usize].clone();
+                    state.cursors.insert(self.id, cursor + 1);
+                    if cursor == state.first {
+                        state.trim();
+                    }
+                    return Ok(Some(item));
+                }
+                if state.closed {
+                    return Ok(None);
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+impl<T> Subscriber<T> {
+    /// Stop replaying what this subscriber has read to new subscribers,
+    /// e.g. after reading the result that ends a response.
+    pub(crate) fn end_replay(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        let cursor = state.cursors[&self.id];
+        if cursor > state.turn_start {
+            state.turn_start = cursor;
+            state.trim();
+        }
+    }
+}
+
+impl<T> Drop for Subscriber<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.cursors.remove(&self.id);
+        state.trim();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_should_replay_current_turn_and_surface_lag() {
+        let fanout = Fanout::with_capacity(3);
+        fanout.send(0);
+        fanout.begin_turn();
+        let mut early = fanout.subscribe();
+        fanout.send(1);
+        fanout.send(2);
+
+        // A late subscriber still sees the whole turn.
+        let mut late = fanout.subscribe();
+        assert_eq!(late.recv().await.unwrap(), Some(1));
+        assert_eq!(early.recv().await.unwrap(), Some(1));
+
+        // `early` is at 2; pushing past the cap drops it.
+        for i in 3..7 {
+            fanout.send(i);
+        }
+        assert!(matches!(early.recv().await, Err(Error::Lagged(2))));
+        assert_eq!(early.recv().await.unwrap(), Some(4));
+
+        drop(fanout);
+        let mut rest = Vec::new();
+        while let Some(i) = early.recv().await.unwrap() {
+            rest.push(i);
+        }
+        assert_eq!(rest, [5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_should_not_replay_delivered_messages() {
+        let fanout = Fanout::new();
+        fanout.begin_turn();
+        fanout.send(1);
+        fanout.send(2);
+        fanout.send(3);
+
+        let mut response = fanout.subscribe();
+        assert_eq!(response.recv().await.unwrap(), Some(1));
+        assert_eq!(response.recv().await.unwrap(), Some(2));
+        response.end_replay();
+
+        let mut next = fanout.subscribe();
+        assert_eq!(next.recv().await.unwrap(), Some(3));
+    }
+}
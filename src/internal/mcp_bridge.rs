@@ -0,0 +1,421 @@
+//! Loopback MCP endpoint for in-process SDK MCP servers.
+//!
+//! CLIs without a channel back to the SDK reach [`McpSdkConfig`] tools as
+//! ordinary MCP servers: [`McpBridge`] serves each of them over the
+//! streamable HTTP transport at `http://127.0.0.1:<port>/mcp/<server>`,
+//! answering every POSTed JSON-RPC request with a single JSON response. A
+//! GET opens a server-sent event stream carrying
+//! `notifications/tools/list_changed` when tools are added or removed.
+//! The backend points the CLI's MCP configuration at [`McpBridge::url`].
+//!
+//! Every request must carry the bridge's random bearer token, which the
+//! backend hands the CLI through [`BRIDGE_TOKEN_ENV`] so it never appears
+//! in arguments or files. Requests with an `Origin` header or a `Host`
+//! other than the bridge's own address are refused, so a web page cannot
+//! reach the tools through DNS rebinding.
+
+use crate::error::{Error, Result};
+use crate::internal::sdk_mcp;
+use crate::options::McpSdkConfig;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Largest request body accepted.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Environment variable holding the bridge's token in the CLI's process.
+///
+/// Programs the agent starts inherit it, so it stays outside
+/// [`ENV_PREFIX`](crate::profile::ENV_PREFIX), whose variables
+/// [`ProfileLoader`](crate::ProfileLoader) rejects unless it knows them.
+pub(crate) const BRIDGE_TOKEN_ENV: &str = "AGENT_SDK_MCP_TOKEN";
+
+/// Serves SDK MCP servers on a loopback port until dropped.
+#[derive(Debug)]
+pub(crate) struct McpBridge {
+    addr: SocketAddr,
+    servers: Vec<String>,
+    token: Arc<str>,
+    task: JoinHandle<()>,
+}
+
+impl McpBridge {
+    /// Listen on an ephemeral loopback port.
+    pub(crate) async fn start(servers: Arc<HashMap<String, McpSdkConfig>>) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let token: Arc<str> = new_token()?.into();
+        let mut names: Vec<_> = servers.keys().cloned().collect();
+        names.sort();
+        let auth = Arc::new(Auth {
+            token: Arc::clone(&token),
+            hosts: [
+                format!("127.0.0.1:{}", addr.port()),
+                format!("localhost:{}", addr.port()),
+            ],
+        });
+        let task = tokio::spawn(async move {
+            // Dropped with the task, closing every connection.
+            let mut connections = JoinSet::new();
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            connections.spawn(serve_connection(
+                                stream,
+                                Arc::clone(&servers),
+                                Arc::clone(&auth),
+                            ));
+                        }
+                        Err(_) => break,
+                    },
+                    Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                }
+            }
+        });
+        Ok(Self {
+            addr,
+            servers: names,
+            token,
+            task,
+        })
+    }
+
+    /// Names of the served servers, sorted.
+    pub(crate) fn servers(&self) -> &[String] {
+        &self.servers
+    }
+
+    /// Bearer token every request must carry.
+    pub(crate) fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Endpoint of `server`.
+    pub(crate) fn url(&self, server: &str) -> String {
+        format!("http://{}/mcp/{}", self.addr, encode_path_segment(server))
+    }
+}
+
+impl Drop for McpBridge {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// What a request must present to be served.
+struct Auth {
+    token: Arc<str>,
+    /// `Host` values naming the bridge.
+    hosts: [String; 2],
+}
+
+impl Auth {
+    /// The error response for a request that may not be served.
+    fn check(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        if request.origin {
+            return Some(HttpResponse::empty("403 Forbidden"));
+        }
+        if !request
+            .host
+            .as_deref()
+            .is_some_and(|host| self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host)))
+        {
+            return Some(HttpResponse::empty("421 Misdirected Request"));
+        }
+        let presented = request
+            .authorization
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if !constant_time_eq(presented.as_bytes(), self.token.as_bytes()) {
+            let mut response = HttpResponse::empty("401 Unauthorized");
+            response.headers.push(("WWW-Authenticate", "Bearer"));
+            return Some(response);
+        }
+        None
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    host: Option<String>,
+    authorization: Option<String>,
+    /// Whether an `Origin` header was sent, as browsers do.
+    origin: bool,
+    body: Vec<u8>,
+    close: bool,
+}
+
+struct HttpResponse {
+    status: &'static str,
+    headers: Vec<(&'static str, &'static str)>,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn empty(status: &'static str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn json(status: &'static str, body: &Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", "application/json")],
+            body: body.to_string().into_bytes(),
+        }
+    }
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    servers: Arc<HashMap<String, McpSdkConfig>>,
+    auth: Arc<Auth>,
+) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    loop {
+        let request = match read_request(&mut reader).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::debug!("MCP bridge: bad request: {}", e);
+                let _ = write_response(&mut write, HttpResponse::empty("400 Bad Request")).await;
+                return;
+            }
+        };
+        if let Some(refusal) = auth.check(&request) {
+            tracing::debug!("MCP bridge: refused {} {}", request.method, request.path);
+            let _ = write_response(&mut write, refusal).await;
+            return;
+        }
+        if request.method == "GET"
+            && let Some(server) = find_server(&request.path, &servers)
+        {
+            if let Err(e) = stream_events(&mut write, server).await {
+                tracing::debug!("MCP bridge: event stream closed: {}", e);
+            }
+            return;
+        }
+        let response = respond(&request, &servers).await;
+        if write_response(&mut write, response).await.is_err() || request.close {
+            return;
+        }
+    }
+}
+
+/// The next request on the connection, or `None` at its end.
+async fn read_request<R: tokio::io::AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> std::io::Result<Option<HttpRequest>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        host: None,
+        authorization: None,
+        origin: false,
+        body: Vec::new(),
+        close: parts.next() == Some("HTTP/1.0"),
+    };
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(invalid("connection closed in headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(invalid("malformed header"));
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| invalid("invalid Content-Length"))?;
+        } else if name.eq_ignore_ascii_case("host") {
+            request.host = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("authorization") {
+            request.authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("origin") {
+            request.origin = true;
+        } else if name.eq_ignore_ascii_case("connection") {
+            request.close = value.eq_ignore_ascii_case("close");
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(invalid("chunked requests are not supported"));
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(invalid("request body too large"));
+    }
+    request.body = vec![0; content_length];
+    reader.read_exact(&mut request.body).await?;
+    Ok(Some(request))
+}
+
+async fn write_response<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    response: HttpResponse,
+) -> std::io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(&response.body).await?;
+    writer.flush().await
+}
+
+async fn respond(request: &HttpRequest, servers: &HashMap<String, McpSdkConfig>) -> HttpResponse {
+    let Some(server) = find_server(&request.path, servers) else {
+        return HttpResponse::empty("404 Not Found");
+    };
+    if request.method != "POST" {
+        let mut response = HttpResponse::empty("405 Method Not Allowed");
+        response.headers.push(("Allow", "GET, POST"));
+        return response;
+    }
+
+    let body: Value = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(e) => {
+            let error = json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32700, "message": format!("Parse error: {}", e)},
+            });
+            return HttpResponse::json("400 Bad Request", &error);
+        }
+    };
+    match body {
+        Value::Array(batch) => {
+            let mut responses = Vec::new();
+            for message in &batch {
+                responses.extend(handle_message(server, message).await);
+            }
+            if responses.is_empty() {
+                HttpResponse::empty("202 Accepted")
+            } else {
+                HttpResponse::json("200 OK", &Value::Array(responses))
+            }
+        }
+        message => match handle_message(server, &message).await {
+            Some(response) => HttpResponse::json("200 OK", &response),
+            None => HttpResponse::empty("202 Accepted"),
+        },
+    }
+}
+
+fn find_server<'a>(
+    path: &str,
+    servers: &'a HashMap<String, McpSdkConfig>,
+) -> Option<&'a McpSdkConfig> {
+    let name = path.strip_prefix("/mcp/")?;
+    servers
+        .iter()
+        .find(|(n, _)| encode_path_segment(n) == name)
+        .map(|(_, server)| server)
+}
+
+/// Answer a GET with a server-sent event stream of `server`'s
+/// notifications, open until the client goes away.
+async fn stream_events<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    server: &McpSdkConfig,
+) -> std::io::Result<()> {
+    let mut changes = server.registry.subscribe();
+    writer
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+              Cache-Control: no-cache\r\n\r\n",
+        )
+        .await?;
+    writer.flush().await?;
+    loop {
+        match changes.recv().await {
+            Ok(()) | Err(RecvError::Lagged(_)) => {
+                let event = json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"});
+                writer
+                    .write_all(format!("event: message\ndata: {}\n\n", event).as_bytes())
+                    .await?;
+                writer.flush().await?;
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// The response to a JSON-RPC request; notifications and responses get
+/// none.
+async fn handle_message(server: &McpSdkConfig, message: &Value) -> Option<Value> {
+    let method = message.get("method")?.as_str().unwrap_or("");
+    let id = message.get("id")?.clone();
+    let params = message.get("params").cloned().unwrap_or(json!({}));
+    Some(
+        match sdk_mcp::handle_method(server, method, &params).await {
+            Ok(mut result) => {
+                // `stream_events` pushes tool list changes.
+                if method == "initialize" {
+                    result["capabilities"]["tools"]["listChanged"] = json!(true);
+                }
+                json!({"jsonrpc": "2.0", "id": id, "result": result})
+            }
+            Err(e) => json!({"jsonrpc": "2.0", "id": id, "error": e.to_json()}),
+        },
+    )
+}
+
+/// 32 random bytes, hex-encoded.
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| Error::Other(format!("No randomness for MCP bridge token: {}", e)))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Compare without leaking how long a matching prefix is.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Percent-encode everything but unreserved characters.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
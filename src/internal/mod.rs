@@ -1,3 +1,12 @@
+pub(crate) mod budget;
+pub(crate) mod cancel;
 pub mod client;
+pub(crate) mod cost;
+pub(crate) mod fanout;
+pub(crate) mod mcp_bridge;
 pub mod message_parser;
 pub mod query;
+pub(crate) mod sdk_mcp;
+pub(crate) mod stream_delta;
+pub(crate) mod transcript;
+pub(crate) mod turn_timer;
@@ -1,23 +1,28 @@
 //! Query - control protocol handler for bidirectional streaming.
 
+use crate::backend::InterruptHandle;
 use crate::error::{Error, Result};
+use crate::internal::cancel::CancelScope;
+use crate::internal::fanout::Fanout;
 use crate::internal::message_parser::parse_message;
+use crate::internal::sdk_mcp;
 use crate::options::{
-    HookCallback, HookContext, HookEvent, HookJSONOutput, HookMatcher, McpSdkConfig,
-    PermissionResult, ToolPermissionContext,
+    CanUseToolCallback, HookCallback, HookContext, HookEvent, HookJSONOutput, HookMatcher,
+    McpSdkConfig, PermissionResult, TimeoutConfig, ToolPermissionContext,
 };
 use crate::transport::Transport;
 use crate::types::Message;
 use async_stream::stream;
-use futures::Stream;
-use std::collections::HashMap;
+use futures::future::{AbortHandle, BoxFuture, abortable};
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::sync::{broadcast, mpsc};
-
-const INITIALIZE_TIMEOUT_SECS: u64 = 60;
-const MESSAGE_BUFFER_SIZE: usize = 100;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, Clone)]
 enum ControlMessage {
@@ -29,9 +34,121 @@ enum ControlMessage {
 /// Query handles the control protocol for ClaudeSDKClient.
 pub struct Query {
     write_tx: Option<mpsc::Sender<String>>,
-    message_tx: broadcast::Sender<ControlMessage>,
-    request_counter: AtomicU64,
+    message_tx: Fanout<ControlMessage>,
+    request_counter: Arc<AtomicU64>,
     init_result: tokio::sync::RwLock<Option<serde_json::Value>>,
+    timeouts: TimeoutConfig,
+    /// Tells the write task to close stdin even while the read task still
+    /// holds a sender for control responses.
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    write_task: Option<JoinHandle<()>>,
+    cancel: Arc<CancelScope>,
+}
+
+/// Most control requests answered at once; further ones wait for a slot.
+const MAX_CONCURRENT_CONTROL_REQUESTS: usize = 16;
+
+/// A control request being answered by [`ControlHandlers::respond`].
+struct InFlight {
+    signal: CancellationToken,
+    abort: AbortHandle,
+}
+
+/// Answers the CLI's control requests concurrently, up to
+/// [`MAX_CONCURRENT_CONTROL_REQUESTS`] at a time, writing each response as
+/// soon as it is ready.
+struct ControlDispatcher {
+    handlers: ControlHandlers,
+    cancel: Arc<CancelScope>,
+    in_flight: HashMap<String, InFlight>,
+    /// Resolve to the request id once answered or withdrawn.
+    responses: FuturesUnordered<BoxFuture<'static, String>>,
+    queued: VecDeque<serde_json::Value>,
+}
+
+impl ControlDispatcher {
+    fn new(handlers: ControlHandlers, cancel: Arc<CancelScope>) -> Self {
+        Self {
+            handlers,
+            cancel,
+            in_flight: HashMap::new(),
+            responses: FuturesUnordered::new(),
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Start answering `data`, or queue it if every slot is taken.
+    fn submit(&mut self, data: serde_json::Value) {
+        if self.in_flight.len() >= MAX_CONCURRENT_CONTROL_REQUESTS {
+            self.queued.push_back(data);
+            return;
+        }
+        let request_id = request_id_of(&data);
+        let signal = self.cancel.callback_token();
+        let (response, abort) = abortable(self.handlers.clone().respond(data, signal.clone()));
+        let id = request_id.clone();
+        self.responses.push(Box::pin(async move {
+            let _ = response.await;
+            id
+        }));
+        self.in_flight
+            .insert(request_id, InFlight { signal, abort });
+    }
+
+    /// The CLI withdrew `request_id`: cancel its callback and never answer.
+    fn withdraw(&mut self, request_id: &str) {
+        match self.in_flight.remove(request_id) {
+            Some(request) => {
+                request.signal.cancel();
+                request.abort.abort();
+            }
+            None => self
+                .queued
+                .retain(|queued| request_id_of(queued) != request_id),
+        }
+    }
+
+    /// Wait for a request to finish and hand its slot to the next queued
+    /// one. Never resolves while nothing is in flight.
+    async fn next_finished(&mut self) {
+        let Some(request_id) = self.responses.next().await else {
+            return std::future::pending().await;
+        };
+        self.in_flight.remove(&request_id);
+        if let Some(data) = self.queued.pop_front() {
+            self.submit(data);
+        }
+    }
+}
+
+/// Callbacks answering the CLI's control requests.
+#[derive(Clone)]
+struct ControlHandlers {
+    write_tx: mpsc::Sender<String>,
+    can_use_tool: Option<CanUseToolCallback>,
+    hook_callbacks: Option<Arc<HashMap<String, HookCallback>>>,
+    sdk_mcp_servers: Option<Arc<HashMap<String, McpSdkConfig>>>,
+}
+
+impl ControlHandlers {
+    /// Answer one control request, reporting failures as error responses.
+    async fn respond(self, data: serde_json::Value, signal: CancellationToken) {
+        if let Err(e) = handle_control_request(
+            &data,
+            &self.write_tx,
+            self.can_use_tool.as_ref(),
+            self.hook_callbacks.as_deref(),
+            self.sdk_mcp_servers.as_ref(),
+            signal,
+        )
+        .await
+        {
+            let _ = self
+                .write_tx
+                .send(format_control_error(&request_id_of(&data), &e.to_string()))
+                .await;
+        }
+    }
 }
 
 impl Query {
@@ -39,58 +156,68 @@ impl Query {
         mut transport: Box<dyn Transport + Send>,
         options: &crate::options::AgentOptions,
     ) -> Self {
-        let (message_tx, _) = broadcast::channel(MESSAGE_BUFFER_SIZE);
+        let message_tx = Fanout::new();
         let (write_tx, mut write_rx) = mpsc::channel::<String>(64);
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+        let (read_done_tx, read_done_rx) = oneshot::channel::<()>();
+        let timeouts = options.timeouts;
 
         let mut read_stream = transport.read_messages();
         let msg_tx = message_tx.clone();
-        let can_use_tool = options.can_use_tool.clone();
-        let hook_callbacks = build_hook_callbacks(options.hooks.as_ref());
-        let sdk_mcp_servers = extract_sdk_mcp_servers(options);
 
-        tokio::spawn(async move {
-            while let Some(s) = write_rx.recv().await {
-                let _ = transport.write(&format!("{}\n", s)).await;
+        let write_task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    msg = write_rx.recv() => match msg {
+                        Some(s) => {
+                            let _ = transport.write(&format!("{}\n", s)).await;
+                        }
+                        None => break,
+                    },
+                    _ = &mut shutdown_rx => break,
+                }
             }
             let _ = transport.end_input().await;
+            // Give the CLI a chance to exit on EOF before killing it.
+            let _ = tokio::time::timeout(timeouts.shutdown_grace, read_done_rx).await;
             let _ = transport.close().await;
         });
 
-        let write_tx_for_read = write_tx.clone();
+        let cancel = Arc::new(CancelScope::new());
+        let handlers = ControlHandlers {
+            write_tx: write_tx.clone(),
+            can_use_tool: options.can_use_tool.clone(),
+            hook_callbacks: build_hook_callbacks(options.hooks.as_ref()).map(Arc::new),
+            sdk_mcp_servers: sdk_mcp::sdk_mcp_servers(options),
+        };
+        let cancel_for_read = Arc::clone(&cancel);
         tokio::spawn(async move {
-            use futures::StreamExt;
+            // The loop keeps reading while callbacks run so that messages,
+            // other control requests and cancellations are not held up
+            // behind a slow callback.
+            let mut dispatcher = ControlDispatcher::new(handlers, Arc::clone(&cancel_for_read));
 
-            while let Some(item) = read_stream.next().await {
+            loop {
+                let item = tokio::select! {
+                    item = read_stream.next() => item,
+                    _ = dispatcher.next_finished() => continue,
+                };
+                let Some(item) = item else {
+                    break;
+                };
                 match item {
                     Ok(data) => {
                         let msg_type = data.get("type").and_then(|v| v.as_str());
                         if msg_type == Some("control_cancel_request") {
-                            // Handle cancel requests - currently just ignored
+                            dispatcher.withdraw(&request_id_of(&data));
                             continue;
                         }
                         if msg_type == Some("control_request") {
-                            let request_id = data
-                                .get("request_id")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("")
-                                .to_string();
-                            if let Err(e) = handle_control_request(
-                                &data,
-                                &write_tx_for_read,
-                                can_use_tool.as_ref(),
-                                hook_callbacks.as_ref(),
-                                sdk_mcp_servers.as_ref(),
-                            )
-                            .await
-                            {
-                                let _ = write_tx_for_read
-                                    .send(format_control_error(&request_id, &e.to_string()))
-                                    .await;
-                            }
+                            dispatcher.submit(data);
                             continue;
                         }
                         if msg_type == Some("end") {
-                            let _ = msg_tx.send(ControlMessage::End);
+                            msg_tx.send(ControlMessage::End);
                             break;
                         }
                         if msg_type == Some("error") {
@@ -99,25 +226,31 @@ impl Query {
                                 .and_then(|v| v.as_str())
                                 .unwrap_or("Unknown")
                                 .to_string();
-                            let _ = msg_tx.send(ControlMessage::Error(err));
+                            msg_tx.send(ControlMessage::Error(err));
                             break;
                         }
-                        let _ = msg_tx.send(ControlMessage::Data(data));
+                        msg_tx.send(ControlMessage::Data(data));
                     }
                     Err(e) => {
-                        let _ = msg_tx.send(ControlMessage::Error(e.to_string()));
+                        msg_tx.send(ControlMessage::Error(e.to_string()));
                         break;
                     }
                 }
             }
-            let _ = msg_tx.send(ControlMessage::End);
+            cancel_for_read.close();
+            let _ = read_done_tx.send(());
+            msg_tx.send(ControlMessage::End);
         });
 
         Self {
             write_tx: Some(write_tx),
             message_tx,
-            request_counter: AtomicU64::new(0),
+            request_counter: Arc::new(AtomicU64::new(0)),
             init_result: tokio::sync::RwLock::new(None),
+            timeouts,
+            shutdown_tx: Some(shutdown_tx),
+            write_task: Some(write_task),
+            cancel,
         }
     }
 
@@ -148,7 +281,7 @@ impl Query {
         });
 
         // Subscribe before sending to avoid missing fast responses.
-        let mut rx = self.message_tx.subscribe();
+        let mut rx = self.message_tx.subscribe_new();
         self.write_tx
             .as_ref()
             .ok_or_else(|| Error::Other("Query closed".to_string()))?
@@ -156,12 +289,10 @@ impl Query {
             .await
             .map_err(|_| Error::Other("Write channel closed".to_string()))?;
 
-        let timeout = tokio::time::Duration::from_secs(INITIALIZE_TIMEOUT_SECS);
-
-        tokio::time::timeout(timeout, async {
+        tokio::time::timeout(self.timeouts.initialize, async {
             loop {
                 match rx.recv().await {
-                    Ok(ControlMessage::Data(data)) => {
+                    Ok(Some(ControlMessage::Data(data))) => {
                         let resp = data.get("response").and_then(|v| v.as_object());
                         let req_id = resp
                             .and_then(|r| r.get("request_id"))
@@ -176,15 +307,16 @@ impl Query {
                         }
                         continue;
                     }
-                    Ok(ControlMessage::End) => {
+                    Ok(Some(ControlMessage::End)) => {
                         return Err(Error::Other(
                             "Stream ended before initialize response".to_string(),
                         ));
                     }
-                    Ok(ControlMessage::Error(e)) => {
+                    Ok(Some(ControlMessage::Error(e))) => {
                         return Err(Error::Other(e));
                     }
-                    Err(_) => return Err(Error::Other("Channel closed".to_string())),
+                    Ok(None) => return Err(Error::Other("Channel closed".to_string())),
+                    Err(e) => return Err(e),
                 }
             }
         })
@@ -193,15 +325,14 @@ impl Query {
     }
 
     fn next_request_id(&self) -> String {
-        let n = self.request_counter.fetch_add(1, Ordering::SeqCst);
-        let hash = n.wrapping_mul(2_654_435_761) & 0xFFFF_FFFF;
-        format!("req_{}_{:08x}", n, hash)
+        next_request_id(&self.request_counter)
     }
 
     pub async fn send_control_request(
-        &mut self,
+        &self,
         request: serde_json::Value,
     ) -> Result<serde_json::Value> {
+        let is_interrupt = request.get("subtype").and_then(|v| v.as_str()) == Some("interrupt");
         let request_id = self.next_request_id();
         let full_request = serde_json::json!({
             "type": "control_request",
@@ -210,20 +341,22 @@ impl Query {
         });
 
         // Subscribe before sending to avoid missing fast responses.
-        let mut rx = self.message_tx.subscribe();
+        let mut rx = self.message_tx.subscribe_new();
         self.write_tx
             .as_ref()
             .ok_or_else(|| Error::Other("Query closed".to_string()))?
             .send(serde_json::to_string(&full_request)?)
             .await
             .map_err(|_| Error::Other("Write channel closed".to_string()))?;
+        // Stop callbacks of the interrupted turn once the CLI has been told.
+        if is_interrupt {
+            self.cancel.cancel_turn();
+        }
 
-        let timeout = tokio::time::Duration::from_secs(60);
-
-        tokio::time::timeout(timeout, async {
+        tokio::time::timeout(self.timeouts.control_request, async {
             loop {
                 match rx.recv().await {
-                    Ok(ControlMessage::Data(data)) => {
+                    Ok(Some(ControlMessage::Data(data))) => {
                         let resp = data.get("response").and_then(|v| v.as_object());
                         let req_id = resp
                             .and_then(|r| r.get("request_id"))
@@ -246,13 +379,14 @@ impl Query {
                         }
                         continue;
                     }
-                    Ok(ControlMessage::End) => {
+                    Ok(Some(ControlMessage::End)) => {
                         return Err(Error::Other("Stream ended".to_string()));
                     }
-                    Ok(ControlMessage::Error(e)) => {
+                    Ok(Some(ControlMessage::Error(e))) => {
                         return Err(Error::Other(e));
                     }
-                    Err(_) => return Err(Error::Other("Channel closed".to_string())),
+                    Ok(None) => return Err(Error::Other("Channel closed".to_string())),
+                    Err(e) => return Err(e),
                 }
             }
         })
@@ -260,13 +394,13 @@ impl Query {
         .map_err(|_| Error::ControlTimeout(request_id))?
     }
 
-    pub fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
+    pub fn receive_messages(&self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
         let mut rx = self.message_tx.subscribe();
 
         let stream = stream! {
             loop {
                 match rx.recv().await {
-                    Ok(ControlMessage::Data(data)) => {
+                    Ok(Some(ControlMessage::Data(data))) => {
                         match parse_message(&data) {
                             Ok(Some(m)) => yield Ok(m),
                             Ok(None) => continue, // Forward-compatible: skip unknown types
@@ -276,12 +410,15 @@ impl Query {
                             }
                         }
                     }
-                    Ok(ControlMessage::End) => break,
-                    Ok(ControlMessage::Error(e)) => {
+                    Ok(Some(ControlMessage::End)) | Ok(None) => break,
+                    Ok(Some(ControlMessage::Error(e))) => {
                         yield Err(Error::Other(e));
                         break;
                     }
-                    Err(_) => break,
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
                 }
             }
         };
@@ -295,10 +432,13 @@ impl Query {
         let stream = stream! {
             loop {
                 match rx.recv().await {
-                    Ok(ControlMessage::Data(data)) => {
+                    Ok(Some(ControlMessage::Data(data))) => {
                         match parse_message(&data) {
                             Ok(Some(m)) => {
                                 let is_result = matches!(&m, Message::Result(_));
+                                if is_result {
+                                    rx.end_replay();
+                                }
                                 yield Ok(m);
                                 if is_result {
                                     break;
@@ -311,12 +451,15 @@ impl Query {
                             }
                         }
                     }
-                    Ok(ControlMessage::End) => break,
-                    Ok(ControlMessage::Error(e)) => {
+                    Ok(Some(ControlMessage::End)) | Ok(None) => break,
+                    Ok(Some(ControlMessage::Error(e))) => {
                         yield Err(Error::Other(e));
                         break;
                     }
-                    Err(_) => break,
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
                 }
             }
         };
@@ -335,6 +478,7 @@ impl Query {
             "message": {"role": "user", "content": prompt},
             "parent_tool_use_id": serde_json::Value::Null
         });
+        self.message_tx.begin_turn();
         self.write_tx
             .as_ref()
             .ok_or_else(|| Error::Other("Query closed".to_string()))?
@@ -358,10 +502,11 @@ impl Query {
             .clone()
             .ok_or_else(|| Error::Other("Query closed".to_string()))?;
 
+        self.message_tx.begin_turn();
+
         // Spawn a background task to iterate the stream and write messages
         let write_tx_for_close = self.write_tx.take();
         tokio::spawn(async move {
-            use futures::StreamExt;
             let mut stream = input_stream;
             while let Some(msg) = stream.next().await {
                 if let Ok(json_str) = serde_json::to_string(&msg)
@@ -385,12 +530,68 @@ impl Query {
         Ok(())
     }
 
+    /// Send `interrupt` control requests without waiting for the response.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        let write_tx = self.write_tx.as_ref().map(mpsc::Sender::downgrade);
+        let request_counter = Arc::clone(&self.request_counter);
+        let cancel = Arc::clone(&self.cancel);
+        InterruptHandle::new(move || {
+            let cancel = Arc::clone(&cancel);
+            let write_tx = write_tx.as_ref().and_then(mpsc::WeakSender::upgrade);
+            let request = serde_json::json!({
+                "type": "control_request",
+                "request_id": next_request_id(&request_counter),
+                "request": {"subtype": "interrupt"}
+            });
+            Box::pin(async move {
+                if let Some(tx) = write_tx {
+                    let _ = tx.send(serde_json::to_string(&request)?).await;
+                }
+                cancel.cancel_turn();
+                Ok(())
+            })
+        })
+    }
+
+    /// Close stdin, give the CLI [`TimeoutConfig::shutdown_grace`] to exit
+    /// and then kill it.
     pub async fn close(&mut self) -> Result<()> {
+        self.cancel.close();
         drop(self.write_tx.take());
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        if let Some(mut handle) = self.write_task.take()
+            && tokio::time::timeout(2 * self.timeouts.shutdown_grace, &mut handle)
+                .await
+                .is_err()
+        {
+            handle.abort();
+            let _ = handle.await;
+        }
         Ok(())
     }
 }
 
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.cancel.close();
+    }
+}
+
+fn request_id_of(data: &serde_json::Value) -> String {
+    data.get("request_id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn next_request_id(counter: &AtomicU64) -> String {
+    let n = counter.fetch_add(1, Ordering::SeqCst);
+    let hash = n.wrapping_mul(2_654_435_761) & 0xFFFF_FFFF;
+    format!("req_{}_{:08x}", n, hash)
+}
+
 fn build_hooks_config_for_initialize(
     hooks: Option<&HashMap<HookEvent, Vec<HookMatcher>>>,
 ) -> serde_json::Value {
@@ -522,9 +723,10 @@ fn parse_permission_suggestions(
 async fn handle_control_request(
     data: &serde_json::Value,
     write_tx: &mpsc::Sender<String>,
-    can_use_tool: Option<&crate::options::CanUseToolCallback>,
+    can_use_tool: Option<&CanUseToolCallback>,
     hook_callbacks: Option<&HashMap<String, HookCallback>>,
     sdk_mcp_servers: Option<&Arc<HashMap<String, McpSdkConfig>>>,
+    signal: CancellationToken,
 ) -> Result<()> {
     let request_id = data
         .get("request_id")
@@ -555,7 +757,7 @@ async fn handle_control_request(
             let suggestions =
                 parse_permission_suggestions(request_data.get("permission_suggestions"));
             let ctx = ToolPermissionContext {
-                signal: None,
+                signal,
                 suggestions,
             };
             let result = cb(tool_name, original_input.clone(), ctx).await;
@@ -609,7 +811,7 @@ async fn handle_control_request(
                 .get("tool_use_id")
                 .and_then(|v| v.as_str())
                 .map(String::from);
-            let ctx = HookContext { signal: None };
+            let ctx = HookContext { signal };
             let output = cb(input, tool_use_id, ctx).await?;
             hook_output_to_json(&output)
         }
@@ -637,32 +839,6 @@ async fn handle_control_request(
     Ok(())
 }
 
-/// Extract SDK MCP server configs from options for in-process routing.
-fn extract_sdk_mcp_servers(
-    options: &crate::options::AgentOptions,
-) -> Option<Arc<HashMap<String, McpSdkConfig>>> {
-    let servers = match options.mcp_servers.as_ref()? {
-        crate::options::McpServersConfig::Dict(dict) => dict,
-        crate::options::McpServersConfig::Path(_) => return None,
-    };
-
-    let sdk_servers: HashMap<String, McpSdkConfig> = servers
-        .iter()
-        .filter_map(|(name, config)| match config {
-            crate::options::McpServerConfig::Sdk(sdk_config) => {
-                Some((name.clone(), sdk_config.clone()))
-            }
-            _ => None,
-        })
-        .collect();
-
-    if sdk_servers.is_empty() {
-        None
-    } else {
-        Some(Arc::new(sdk_servers))
-    }
-}
-
 /// Handle an incoming SDK MCP JSONRPC request by routing to the appropriate in-process server.
 async fn handle_sdk_mcp_request(
     request_data: &serde_json::Map<String, serde_json::Value>,
@@ -694,79 +870,19 @@ async fn handle_sdk_mcp_request(
         .cloned()
         .unwrap_or(serde_json::json!({}));
 
-    let jsonrpc_result = match method {
-        "initialize" => {
-            serde_json::json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": {
-                    "tools": {}
-                },
-                "serverInfo": {
-                    "name": server.name,
-                    "version": server.version
-                }
-            })
-        }
-        "notifications/initialized" => {
-            // Acknowledge with empty result
-            serde_json::Value::Object(serde_json::Map::new())
-        }
-        "tools/list" => {
-            let tools: Vec<serde_json::Value> = server
-                .tools
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "name": t.name,
-                        "description": t.description,
-                        "inputSchema": t.input_schema,
-                    })
-                })
-                .collect();
-            serde_json::json!({ "tools": tools })
-        }
-        "tools/call" => {
-            let tool_name = params
-                .get("name")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| Error::Other("tools/call missing tool name".to_string()))?;
-            let arguments = params
-                .get("arguments")
-                .cloned()
-                .unwrap_or(serde_json::json!({}));
-
-            let tool = server
-                .tools
-                .iter()
-                .find(|t| t.name == tool_name)
-                .ok_or_else(|| {
-                    Error::Other(format!(
-                        "Tool '{}' not found in server '{}'",
-                        tool_name, server_name
-                    ))
-                })?;
-
-            match (tool.handler)(arguments).await {
-                Ok(result) => result,
-                Err(e) => {
-                    serde_json::json!({
-                        "content": [{"type": "text", "text": e.to_string()}],
-                        "isError": true,
-                    })
-                }
-            }
-        }
-        _ => {
-            return Err(Error::Other(format!("Unsupported MCP method: {}", method)));
-        }
-    };
-
     // Wrap in JSONRPC response envelope
-    let jsonrpc_response = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": jsonrpc_id,
-        "result": jsonrpc_result,
-    });
+    let jsonrpc_response = match sdk_mcp::handle_method(server, method, &params).await {
+        Ok(result) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": jsonrpc_id,
+            "result": result,
+        }),
+        Err(e) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": jsonrpc_id,
+            "error": e.to_json(),
+        }),
+    };
 
     // Return mcp_response wrapped result
     Ok(serde_json::json!({
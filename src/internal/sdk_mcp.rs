@@ -0,0 +1,552 @@
+//! MCP methods of in-process SDK MCP servers ([`McpSdkConfig`]).
+//!
+//! Shared by every route a backend has to the servers: Claude's
+//! `mcp_message` control requests, Codex dynamic tools and the loopback
+//! [`McpBridge`](super::mcp_bridge::McpBridge).
+
+use crate::mcp::{SdkMcpResourceHandler, ToolResult};
+use crate::options::{AgentOptions, McpSdkConfig, McpServerConfig, McpServersConfig, SdkMcpTool};
+use crate::schema;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Protocol versions the servers speak, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// A JSON-RPC error from an MCP method.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct McpError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+}
+
+impl McpError {
+    fn method_not_found(method: &str) -> Self {
+        Self {
+            code: -32601,
+            message: format!("Method not found: {}", method),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: -32602,
+            message: message.into(),
+        }
+    }
+
+    fn internal(message: impl Into<String>) -> Self {
+        Self {
+            code: -32603,
+            message: message.into(),
+        }
+    }
+
+    fn resource_not_found(uri: &str) -> Self {
+        Self {
+            code: -32002,
+            message: format!("Resource not found: {}", uri),
+        }
+    }
+
+    /// The `error` member of a JSON-RPC response.
+    pub(crate) fn to_json(&self) -> Value {
+        json!({"code": self.code, "message": self.message})
+    }
+}
+
+impl std::fmt::Display for McpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The SDK MCP servers in [`AgentOptions::mcp_servers`] by name, or `None`
+/// if there are none.
+pub(crate) fn sdk_mcp_servers(
+    options: &AgentOptions,
+) -> Option<Arc<HashMap<String, McpSdkConfig>>> {
+    let servers = match options.mcp_servers.as_ref()? {
+        McpServersConfig::Dict(dict) => dict,
+        McpServersConfig::Path(_) => return None,
+    };
+
+    let sdk_servers: HashMap<String, McpSdkConfig> = servers
+        .iter()
+        .filter_map(|(name, config)| match config {
+            McpServerConfig::Sdk(sdk_config) => Some((name.clone(), sdk_config.clone())),
+            _ => None,
+        })
+        .collect();
+
+    if sdk_servers.is_empty() {
+        None
+    } else {
+        Some(Arc::new(sdk_servers))
+    }
+}
+
+/// The result of MCP `method` on `server`.
+pub(crate) async fn handle_method(
+    server: &McpSdkConfig,
+    method: &str,
+    params: &Value,
+) -> std::result::Result<Value, McpError> {
+    match method {
+        "initialize" => {
+            let requested = params.get("protocolVersion").and_then(|v| v.as_str());
+            let version = requested
+                .filter(|v| PROTOCOL_VERSIONS.contains(v))
+                .unwrap_or(PROTOCOL_VERSIONS[0]);
+            // Routes that deliver `notifications/tools/list_changed` add
+            // `listChanged` themselves.
+            let mut capabilities = json!({"tools": {}});
+            if !server.resources.is_empty() || !server.resource_templates.is_empty() {
+                capabilities["resources"] = json!({});
+            }
+            if !server.prompts.is_empty() {
+                capabilities["prompts"] = json!({});
+            }
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": capabilities,
+                "serverInfo": {
+                    "name": server.name,
+                    "version": server.version
+                }
+            }))
+        }
+        // Acknowledge with empty result
+        "ping" => Ok(json!({})),
+        m if m.starts_with("notifications/") => Ok(json!({})),
+        "tools/list" => {
+            let tools: Vec<Value> = server.active_tools().iter().map(tool_spec).collect();
+            Ok(json!({ "tools": tools }))
+        }
+        "tools/call" => {
+            let tool_name = string_param(params, "name")?;
+            let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+            let tool = server
+                .active_tools()
+                .into_iter()
+                .find(|t| t.name == tool_name)
+                .ok_or_else(|| {
+                    McpError::invalid_params(format!(
+                        "Tool '{}' not found in server '{}'",
+                        tool_name, server.name
+                    ))
+                })?;
+            Ok(call_tool(&tool, arguments).await)
+        }
+        "resources/list" => {
+            let resources: Vec<Value> = server
+                .resources
+                .iter()
+                .map(|r| {
+                    let mut spec = json!({"uri": r.uri, "name": r.name});
+                    describe(&mut spec, &r.description, &r.mime_type);
+                    spec
+                })
+                .collect();
+            Ok(json!({ "resources": resources }))
+        }
+        "resources/templates/list" => {
+            let templates: Vec<Value> = server
+                .resource_templates
+                .iter()
+                .map(|t| {
+                    let mut spec = json!({"uriTemplate": t.uri_template, "name": t.name});
+                    describe(&mut spec, &t.description, &t.mime_type);
+                    spec
+                })
+                .collect();
+            Ok(json!({ "resourceTemplates": templates }))
+        }
+        "resources/read" => {
+            let uri = string_param(params, "uri")?;
+            let (handler, vars): (&SdkMcpResourceHandler, _) =
+                match server.resources.iter().find(|r| r.uri == uri) {
+                    Some(resource) => (&resource.handler, HashMap::new()),
+                    None => server
+                        .resource_templates
+                        .iter()
+                        .find_map(|t| Some((&t.handler, t.matches(uri)?)))
+                        .ok_or_else(|| McpError::resource_not_found(uri))?,
+                };
+            let contents = handler(uri.to_string(), vars)
+                .await
+                .map_err(|e| McpError::internal(e.to_string()))?;
+            Ok(json!({ "contents": contents }))
+        }
+        "prompts/list" => {
+            let prompts: Vec<Value> = server
+                .prompts
+                .iter()
+                .map(|p| {
+                    let mut spec = json!({"name": p.name, "arguments": p.arguments});
+                    if let Some(ref description) = p.description {
+                        spec["description"] = json!(description);
+                    }
+                    spec
+                })
+                .collect();
+            Ok(json!({ "prompts": prompts }))
+        }
+        "prompts/get" => {
+            let name = string_param(params, "name")?;
+            let prompt = server
+                .prompts
+                .iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| McpError::invalid_params(format!("Unknown prompt: {}", name)))?;
+            let arguments: HashMap<String, String> = params
+                .get("arguments")
+                .and_then(|v| v.as_object())
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| {
+                    let value = v.as_str().map_or_else(|| v.to_string(), str::to_string);
+                    (k.clone(), value)
+                })
+                .collect();
+            if let Some(missing) = prompt
+                .arguments
+                .iter()
+                .find(|a| a.required && !arguments.contains_key(&a.name))
+            {
+                return Err(McpError::invalid_params(format!(
+                    "Missing required argument: {}",
+                    missing.name
+                )));
+            }
+            let messages = (prompt.handler)(arguments)
+                .await
+                .map_err(|e| McpError::internal(e.to_string()))?;
+            let mut result = json!({ "messages": messages });
+            if let Some(ref description) = prompt.description {
+                result["description"] = json!(description);
+            }
+            Ok(result)
+        }
+        _ => Err(McpError::method_not_found(method)),
+    }
+}
+
+/// A `tools/list` entry.
+fn tool_spec(tool: &SdkMcpTool) -> Value {
+    let mut spec = json!({
+        "name": tool.name,
+        "description": tool.description,
+        "inputSchema": tool.input_schema,
+    });
+    if let Some(ref schema) = tool.output_schema {
+        spec["outputSchema"] = schema.clone();
+    }
+    if let Some(ref annotations) = tool.annotations {
+        if let Some(ref title) = annotations.title {
+            spec["title"] = json!(title);
+        }
+        spec["annotations"] = json!(annotations);
+    }
+    spec
+}
+
+fn describe(spec: &mut Value, description: &Option<String>, mime_type: &Option<String>) {
+    if let Some(description) = description {
+        spec["description"] = json!(description);
+    }
+    if let Some(mime_type) = mime_type {
+        spec["mimeType"] = json!(mime_type);
+    }
+}
+
+fn string_param<'a>(params: &'a Value, key: &str) -> std::result::Result<&'a str, McpError> {
+    params
+        .get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| McpError::invalid_params(format!("Missing parameter: {}", key)))
+}
+
+/// Run `tool`, reporting arguments that fail its input schema and handler
+/// errors as `isError` results. Structured content without content blocks
+/// is also given as JSON text.
+pub(crate) async fn call_tool(tool: &SdkMcpTool, arguments: Value) -> Value {
+    let violations = schema::validate(&tool.input_schema, &arguments);
+    if !violations.is_empty() {
+        let lines: Vec<String> = violations.iter().map(|v| format!("- {}", v)).collect();
+        return ToolResult::error(format!(
+            "Invalid arguments for tool '{}':\n{}",
+            tool.name,
+            lines.join("\n")
+        ))
+        .into();
+    }
+    match (tool.handler)(arguments).await {
+        Ok(mut result) => {
+            if let Some(structured) = result.get("structuredContent")
+                && result.get("content").is_none()
+            {
+                result["content"] = json!([{"type": "text", "text": structured.to_string()}]);
+            }
+            result
+        }
+        Err(e) => json!({
+            "content": [{"type": "text", "text": e.to_string()}],
+            "isError": true,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::{
+        PromptMessage, ResourceContents, SdkMcpPrompt, SdkMcpResource, SdkMcpResourceTemplate,
+        ToolAnnotations, ToolResult,
+    };
+    use crate::{create_sdk_mcp_server, sdk_mcp_tool};
+
+    fn server() -> McpSdkConfig {
+        let stats = sdk_mcp_tool("stats", "Word count", json!({"type": "object"}), |_| {
+            Box::pin(async { Ok(json!({"structuredContent": {"words": 2}})) })
+        })
+        .with_output_schema(json!({"type": "object", "properties": {"words": {"type": "integer"}}}))
+        .with_annotations(ToolAnnotations {
+            title: Some("Stats".to_string()),
+            read_only_hint: Some(true),
+            ..Default::default()
+        });
+        create_sdk_mcp_server("docs", "1.0.0", vec![stats])
+            .resource(
+                SdkMcpResource::new("docs://readme", "README", |uri, _| {
+                    Box::pin(async move { Ok(vec![ResourceContents::text(uri, "hello world")]) })
+                })
+                .mime_type("text/markdown"),
+            )
+            .resource_template(SdkMcpResourceTemplate::new(
+                "docs://pages/{page}",
+                "Page",
+                |uri, vars| {
+                    Box::pin(async move {
+                        Ok(vec![ResourceContents::text(
+                            uri,
+                            format!("page {}", vars["page"]),
+                        )])
+                    })
+                },
+            ))
+            .prompt(
+                SdkMcpPrompt::new("summarize", |args| {
+                    Box::pin(async move {
+                        Ok(vec![PromptMessage::user(format!(
+                            "Summarize {}",
+                            args["topic"]
+                        ))])
+                    })
+                })
+                .description("Summarize a topic")
+                .argument("topic", "What to summarize", true),
+            )
+    }
+
+    #[tokio::test]
+    async fn test_should_negotiate_protocol_and_advertise_capabilities() {
+        let server = server();
+        let init = handle_method(
+            &server,
+            "initialize",
+            &json!({"protocolVersion": "2025-03-26"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(init["protocolVersion"], "2025-03-26");
+        assert!(init["capabilities"]["tools"].is_object());
+        assert!(init["capabilities"]["tools"].get("listChanged").is_none());
+        assert!(init["capabilities"]["resources"].is_object());
+        assert!(init["capabilities"]["prompts"].is_object());
+
+        let init = handle_method(
+            &server,
+            "initialize",
+            &json!({"protocolVersion": "1999-01-01"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(init["protocolVersion"], PROTOCOL_VERSIONS[0]);
+
+        let err = handle_method(&server, "sampling/createMessage", &json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, -32601);
+    }
+
+    #[tokio::test]
+    async fn test_should_list_tool_metadata_and_fill_structured_content_text() {
+        let server = server();
+        let list = handle_method(&server, "tools/list", &json!({}))
+            .await
+            .unwrap();
+        let tool = &list["tools"][0];
+        assert_eq!(tool["title"], "Stats");
+        assert_eq!(tool["annotations"]["readOnlyHint"], true);
+        assert_eq!(
+            tool["outputSchema"]["properties"]["words"]["type"],
+            "integer"
+        );
+
+        let call = handle_method(&server, "tools/call", &json!({"name": "stats"}))
+            .await
+            .unwrap();
+        assert_eq!(call["structuredContent"]["words"], 2);
+        assert_eq!(call["content"][0]["text"], "{\"words\":2}");
+    }
+
+    #[tokio::test]
+    async fn test_should_read_resources_and_templates() {
+        let server = server();
+        let list = handle_method(&server, "resources/list", &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(
+            list["resources"],
+            json!([{"uri": "docs://readme", "name": "README", "mimeType": "text/markdown"}])
+        );
+        let templates = handle_method(&server, "resources/templates/list", &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(
+            templates["resourceTemplates"][0]["uriTemplate"],
+            "docs://pages/{page}"
+        );
+
+        let read = handle_method(&server, "resources/read", &json!({"uri": "docs://readme"}))
+            .await
+            .unwrap();
+        assert_eq!(read["contents"][0]["text"], "hello world");
+        let read = handle_method(
+            &server,
+            "resources/read",
+            &json!({"uri": "docs://pages/intro"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(read["contents"][0]["text"], "page intro");
+        let err = handle_method(&server, "resources/read", &json!({"uri": "docs://other"}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, -32002);
+    }
+
+    #[tokio::test]
+    async fn test_should_get_prompts_with_required_arguments() {
+        let server = server();
+        let list = handle_method(&server, "prompts/list", &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(list["prompts"][0]["arguments"][0]["required"], true);
+
+        let prompt = handle_method(
+            &server,
+            "prompts/get",
+            &json!({"name": "summarize", "arguments": {"topic": "MCP"}}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(prompt["description"], "Summarize a topic");
+        assert_eq!(
+            prompt["messages"],
+            json!([{"role": "user", "content": {"type": "text", "text": "Summarize MCP"}}])
+        );
+        let err = handle_method(&server, "prompts/get", &json!({"name": "summarize"}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, -32602);
+    }
+
+    #[tokio::test]
+    async fn test_should_add_and_remove_tools_at_runtime() {
+        let server = server();
+        let clone = server.clone();
+        let mut changes = server.registry.subscribe();
+        clone
+            .add_tool(sdk_mcp_tool(
+                "echo",
+                "Echo",
+                json!({"type": "object"}),
+                |args| Box::pin(async move { Ok(ToolResult::text(args.to_string()).into()) }),
+            ))
+            .unwrap();
+        changes.try_recv().expect("list_changed after add");
+        assert!(clone.remove_tool("stats").unwrap());
+        assert!(!clone.remove_tool("stats").unwrap());
+
+        // A session that cannot apply changes holds them off.
+        let pin = server.registry.pin("Codex");
+        assert!(matches!(
+            clone.remove_tool("echo"),
+            Err(crate::error::Error::UnsupportedFeature { .. })
+        ));
+        drop(pin);
+
+        let list = handle_method(&server, "tools/list", &json!({}))
+            .await
+            .unwrap();
+        let names: Vec<_> = list["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| &t["name"])
+            .collect();
+        assert_eq!(names, ["echo"]);
+        let err = handle_method(&server, "tools/call", &json!({"name": "stats"}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, -32602);
+    }
+
+    #[tokio::test]
+    async fn test_should_reject_arguments_that_fail_the_input_schema() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let add = sdk_mcp_tool(
+            "add",
+            "Add",
+            json!({
+                "type": "object",
+                "properties": {"a": {"type": "number"}, "b": {"type": "number", "minimum": 0}},
+                "required": ["a", "b"]
+            }),
+            move |_| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Box::pin(async { Ok(ToolResult::text("ok").into()) })
+            },
+        );
+        let server = create_sdk_mcp_server("calc", "1.0.0", vec![add]);
+
+        let call = handle_method(
+            &server,
+            "tools/call",
+            &json!({"name": "add", "arguments": {"a": "1", "b": -2}}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(call["isError"], true);
+        assert_eq!(
+            call["content"][0]["text"],
+            "Invalid arguments for tool 'add':\n- /a: expected number, got string\n- /b: must be >= 0"
+        );
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let call = handle_method(
+            &server,
+            "tools/call",
+            &json!({"name": "add", "arguments": {"a": 1, "b": 2}}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(call["content"][0]["text"], "ok");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+}
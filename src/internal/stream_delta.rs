@@ -0,0 +1,160 @@
+//! Normalization of backend partial output into [`StreamDelta`] events.
+//!
+//! Claude emits Anthropic-format stream events natively. Codex and Cursor
+//! only emit bare text chunks, so their sessions feed those chunks through a
+//! [`DeltaEmitter`], which supplies the surrounding `message_start`,
+//! `content_block_start`/`stop` and `message_stop` events and wraps
+//! everything in [`Message::StreamEvent`].
+
+use crate::types::{
+    ContentBlock, Message, StreamBlockStart, StreamDelta, StreamEvent, TextBlock, ThinkingBlock,
+};
+use crate::usage::Usage;
+
+/// Kind of a block assembled from bare text chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockKind {
+    Text,
+    Thinking,
+}
+
+#[derive(Debug)]
+struct OpenBlock {
+    key: String,
+    index: usize,
+    kind: BlockKind,
+    text: String,
+}
+
+/// Turns keyed text chunks into a well-formed stream event sequence.
+///
+/// Each `key` (e.g. a Codex item id) maps to one content block. Blocks are
+/// opened on their first chunk and closed explicitly or when the message
+/// finishes.
+#[derive(Debug, Default)]
+pub(crate) struct DeltaEmitter {
+    session_id: String,
+    sequence: u64,
+    in_message: bool,
+    next_index: usize,
+    open: Vec<OpenBlock>,
+}
+
+impl DeltaEmitter {
+    /// Session id stamped on subsequent events.
+    pub(crate) fn set_session_id(&mut self, session_id: impl Into<String>) {
+        self.session_id = session_id.into();
+    }
+
+    /// Append `text` to the block for `key`, opening it (and the message)
+    /// if needed.
+    pub(crate) fn delta(&mut self, key: &str, kind: BlockKind, text: &str) -> Vec<Message> {
+        let mut out = Vec::new();
+        if text.is_empty() {
+            return out;
+        }
+        if !self.in_message {
+            self.in_message = true;
+            self.push(
+                &mut out,
+                StreamDelta::MessageStart {
+                    id: None,
+                    model: None,
+                },
+            );
+        }
+        let pos = match self.open.iter().position(|b| b.key == key) {
+            Some(pos) => pos,
+            None => {
+                let index = self.next_index;
+                self.next_index += 1;
+                let block = match kind {
+                    BlockKind::Text => StreamBlockStart::Text {
+                        text: String::new(),
+                    },
+                    BlockKind::Thinking => StreamBlockStart::Thinking {
+                        thinking: String::new(),
+                    },
+                };
+                self.push(&mut out, StreamDelta::ContentBlockStart { index, block });
+                self.open.push(OpenBlock {
+                    key: key.to_string(),
+                    index,
+                    kind,
+                    text: String::new(),
+                });
+                self.open.len() - 1
+            }
+        };
+        let block = &mut self.open[pos];
+        block.text.push_str(text);
+        let delta = match block.kind {
+            BlockKind::Text => StreamDelta::TextDelta {
+                index: block.index,
+                text: text.to_string(),
+            },
+            BlockKind::Thinking => StreamDelta::ThinkingDelta {
+                index: block.index,
+                thinking: text.to_string(),
+            },
+        };
+        self.push(&mut out, delta);
+        out
+    }
+
+    /// Close the block for `key`, returning the events and the assembled block.
+    pub(crate) fn close_block(&mut self, key: &str) -> (Vec<Message>, Option<ContentBlock>) {
+        let mut out = Vec::new();
+        let Some(pos) = self.open.iter().position(|b| b.key == key) else {
+            return (out, None);
+        };
+        let block = self.open.remove(pos);
+        self.push(
+            &mut out,
+            StreamDelta::ContentBlockStop { index: block.index },
+        );
+        let content = match block.kind {
+            BlockKind::Text => ContentBlock::Text(TextBlock { text: block.text }),
+            BlockKind::Thinking => ContentBlock::Thinking(ThinkingBlock {
+                thinking: block.text,
+                signature: String::new(),
+            }),
+        };
+        (out, Some(content))
+    }
+
+    /// Close all open blocks and end the message, if one was started.
+    pub(crate) fn finish(&mut self, usage: Option<Usage>) -> Vec<Message> {
+        let mut out = Vec::new();
+        if !self.in_message {
+            return out;
+        }
+        for block in std::mem::take(&mut self.open) {
+            self.push(
+                &mut out,
+                StreamDelta::ContentBlockStop { index: block.index },
+            );
+        }
+        self.push(
+            &mut out,
+            StreamDelta::MessageDelta {
+                stop_reason: Some("end_turn".to_string()),
+                usage: usage.and_then(|u| serde_json::to_value(u).ok()),
+            },
+        );
+        self.push(&mut out, StreamDelta::MessageStop);
+        self.in_message = false;
+        self.next_index = 0;
+        out
+    }
+
+    fn push(&mut self, out: &mut Vec<Message>, delta: StreamDelta) {
+        self.sequence += 1;
+        out.push(Message::StreamEvent(StreamEvent {
+            uuid: format!("{}-delta-{}", self.session_id, self.sequence),
+            session_id: self.session_id.clone(),
+            event: delta.to_event(),
+            parent_tool_use_id: None,
+        }));
+    }
+}
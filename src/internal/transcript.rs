@@ -0,0 +1,222 @@
+//! Recording sessions into a [`SessionStore`].
+
+use crate::backend::BackendKind;
+use crate::error::Result;
+use crate::internal::message_parser::parse_message;
+use crate::session_store::{SessionStore, TranscriptEntry, new_transcript_id, now_ms};
+use crate::types::{Message, Prompt, UserContent, UserMessage};
+use futures::task::noop_waker_ref;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<Message>> + Send>>;
+
+/// The session stream being recorded, shared between the writer task and
+/// the senders that drain it.
+type Following = Arc<Mutex<Option<MessageStream>>>;
+
+enum Input {
+    Message(u64, Message),
+    /// A session stream is now followed.
+    Follow,
+    /// Record what the followed stream has left, then stop.
+    Finish,
+}
+
+/// Records the messages of one transcript.
+///
+/// Messages are appended by a background task, so recording never blocks
+/// the session. Sent messages are queued after everything the followed
+/// session stream has produced so far, and the task empties that queue
+/// before reading the stream further, so entries keep the order in which
+/// messages were sent and received. Entries carry the backend session id
+/// seen most recently, starting from
+/// [`AgentOptions::resume`](crate::options::AgentOptions::resume).
+pub(crate) struct TranscriptRecorder {
+    id: String,
+    tx: mpsc::UnboundedSender<Input>,
+    following: Following,
+    writer: JoinHandle<()>,
+}
+
+impl TranscriptRecorder {
+    /// Start a new transcript. Must be called within a Tokio runtime.
+    pub(crate) fn start(
+        store: Arc<dyn SessionStore>,
+        backend: BackendKind,
+        session_id: Option<String>,
+    ) -> Self {
+        let id = new_transcript_id();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let following = Following::default();
+        let writer = tokio::spawn(write_transcript(
+            store,
+            id.clone(),
+            backend,
+            session_id,
+            rx,
+            Arc::clone(&following),
+        ));
+        Self {
+            id,
+            tx,
+            following,
+            writer,
+        }
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Record a received message.
+    pub(crate) fn record(&self, message: &Message) {
+        let _ = self.tx.send(Input::Message(now_ms(), message.clone()));
+    }
+
+    /// Record every message of a session, as yielded by
+    /// [`Session::subscribe`](crate::backend::Session::subscribe).
+    pub(crate) fn follow(&self, messages: MessageStream) {
+        *self.following.lock().unwrap() = Some(messages);
+        let _ = self.tx.send(Input::Follow);
+    }
+
+    /// Record the user messages of an outgoing prompt: a text prompt now,
+    /// streamed messages as they are sent.
+    pub(crate) fn record_prompt(&self, prompt: Prompt) -> Prompt {
+        match prompt {
+            Prompt::Text(text) => {
+                record_sent(
+                    &self.tx,
+                    &self.following,
+                    Message::User(UserMessage {
+                        content: UserContent::String(text.clone()),
+                        uuid: None,
+                        parent_tool_use_id: None,
+                        tool_use_result: None,
+                    }),
+                );
+                Prompt::Text(text)
+            }
+            Prompt::Stream(stream) => {
+                let tx = self.tx.clone();
+                let following = Arc::clone(&self.following);
+                Prompt::Stream(Box::pin(stream.inspect(move |data| {
+                    if data.get("type").and_then(|v| v.as_str()) == Some("user")
+                        && let Ok(Some(message)) = parse_message(data)
+                    {
+                        record_sent(&tx, &following, message);
+                    }
+                })))
+            }
+        }
+    }
+
+    /// Wait for the followed stream to end and everything recorded to be
+    /// appended, for at most `grace`. Messages recorded afterwards are
+    /// dropped.
+    pub(crate) async fn finish(self, grace: Duration) {
+        let _ = self.tx.send(Input::Finish);
+        let mut writer = self.writer;
+        if tokio::time::timeout(grace, &mut writer).await.is_err() {
+            writer.abort();
+        }
+    }
+}
+
+/// Queue what the followed stream has ready, then the sent `message`.
+fn record_sent(tx: &mpsc::UnboundedSender<Input>, following: &Following, message: Message) {
+    let mut following = following.lock().unwrap();
+    let mut cx = Context::from_waker(noop_waker_ref());
+    // Polling with a no-op waker may displace the writer's; the send below
+    // wakes it again.
+    while let Poll::Ready(Some(received)) = poll_following(&mut following, &mut cx) {
+        let _ = tx.send(Input::Message(now_ms(), received));
+    }
+    let _ = tx.send(Input::Message(now_ms(), message));
+}
+
+/// Next message of the followed stream; `None` once it has ended or if
+/// there is none. Errors are skipped.
+fn poll_following(
+    following: &mut Option<MessageStream>,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Message>> {
+    let Some(stream) = following else {
+        return Poll::Ready(None);
+    };
+    loop {
+        match stream.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(message))) => return Poll::Ready(Some(message)),
+            Poll::Ready(Some(Err(_))) => continue,
+            Poll::Ready(None) => {
+                *following = None;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+}
+
+async fn write_transcript(
+    store: Arc<dyn SessionStore>,
+    transcript_id: String,
+    backend: BackendKind,
+    mut session_id: Option<String>,
+    mut rx: mpsc::UnboundedReceiver<Input>,
+    following: Following,
+) {
+    let mut finishing = false;
+    loop {
+        let is_following = following.lock().unwrap().is_some();
+        let (timestamp_ms, message) = tokio::select! {
+            biased;
+            input = rx.recv(), if !finishing => match input {
+                Some(Input::Message(timestamp_ms, message)) => (timestamp_ms, message),
+                Some(Input::Follow) => continue,
+                Some(Input::Finish) | None => {
+                    finishing = true;
+                    continue;
+                }
+            },
+            received = std::future::poll_fn(|cx| {
+                poll_following(&mut following.lock().unwrap(), cx)
+            }), if is_following || finishing => match received {
+                Some(message) => (now_ms(), message),
+                None if finishing => break,
+                None => continue,
+            },
+        };
+
+        if let Some(id) = reported_session_id(&message) {
+            session_id = Some(id.to_string());
+        }
+        let entry = TranscriptEntry {
+            backend,
+            session_id: session_id.clone(),
+            timestamp_ms,
+            message,
+        };
+        if let Err(e) = store.append(&transcript_id, &entry).await {
+            tracing::warn!("Failed to record transcript {}: {}", transcript_id, e);
+        }
+    }
+}
+
+/// The backend session, thread or chat id a message reports, if any.
+fn reported_session_id(message: &Message) -> Option<&str> {
+    let id = match message {
+        Message::System(system) => ["session_id", "threadId", "chatId"]
+            .iter()
+            .find_map(|key| system.data.get(*key).and_then(|v| v.as_str())),
+        Message::Result(result) => Some(result.session_id.as_str()),
+        Message::StreamEvent(event) => Some(event.session_id.as_str()),
+        _ => None,
+    };
+    id.filter(|id| !id.is_empty())
+}
@@ -0,0 +1,154 @@
+//! Turn and idle timeouts for [`AgentSdkClient`](crate::client::AgentSdkClient)
+//! and one-shot [`query()`](crate::query).
+
+use crate::backend::InterruptHandle;
+use crate::error::{Error, Result, TurnTimeout};
+use crate::options::AgentOptions;
+use crate::types::Message;
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant};
+
+/// Tracks when the turn in flight started and enforces
+/// [`TimeoutConfig::turn`](crate::options::TimeoutConfig::turn) and
+/// [`TimeoutConfig::idle`](crate::options::TimeoutConfig::idle) on receive
+/// streams.
+///
+/// As with [`BudgetTracker`](super::budget::BudgetTracker), each turn ends
+/// once, on whichever stream sees its result first.
+#[derive(Debug)]
+pub(crate) struct TurnTimer {
+    turn: Option<Duration>,
+    idle: Option<Duration>,
+    pending: Mutex<u64>,
+    /// Start of the turn in flight, if any.
+    started: watch::Sender<Option<Instant>>,
+}
+
+impl TurnTimer {
+    pub(crate) fn new(options: &AgentOptions) -> Self {
+        Self {
+            turn: options.timeouts.turn,
+            idle: options.timeouts.idle,
+            pending: Mutex::new(0),
+            started: watch::Sender::new(None),
+        }
+    }
+
+    /// Note that a user message was sent; its turn starts now unless an
+    /// earlier one is still running.
+    pub(crate) fn start_turn(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending += 1;
+        if *pending == 1 {
+            self.started.send_replace(Some(Instant::now()));
+        }
+    }
+
+    /// End the turn in flight; the next queued turn, if any, starts now.
+    fn finish_turn(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        if *pending == 0 {
+            return;
+        }
+        *pending -= 1;
+        self.started.send_replace((*pending > 0).then(Instant::now));
+    }
+
+    /// Forget all turns in flight after a timeout.
+    fn abandon(&self) {
+        *self.pending.lock().unwrap() = 0;
+        self.started.send_replace(None);
+    }
+
+    /// The earliest expiring timeout for the turn in flight.
+    fn deadline(&self) -> Option<(Instant, TurnTimeout)> {
+        let started = (*self.started.borrow())?;
+        let turn = self
+            .turn
+            .map(|limit| (started + limit, TurnTimeout::Turn(limit)));
+        let idle = self
+            .idle
+            .map(|limit| (Instant::now() + limit, TurnTimeout::Idle(limit)));
+        match (turn, idle) {
+            (Some(a), Some(b)) => Some(if a.0 <= b.0 { a } else { b }),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Apply the timeouts to a one-shot query, whose single turn starts when
+    /// the stream is first polled. On expiry the query's stream is dropped,
+    /// which kills its CLI process, and the stream ends with
+    /// [`Error::TurnTimeout`].
+    pub(crate) fn enforce_one_shot(
+        options: &AgentOptions,
+        stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+        let timer = Arc::new(Self::new(options));
+        if timer.turn.is_none() && timer.idle.is_none() {
+            return stream;
+        }
+        let start = Arc::clone(&timer);
+        let started = Box::pin(stream! {
+            start.start_turn();
+            let mut stream = stream;
+            while let Some(item) = stream.next().await {
+                yield item;
+            }
+        });
+        timer.enforce(started, None)
+    }
+
+    /// Apply the timeouts to `stream`. On expiry the turn is interrupted
+    /// through `interrupt` and the stream ends with [`Error::TurnTimeout`].
+    pub(crate) fn enforce<'a>(
+        self: Arc<Self>,
+        stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send + 'a>>,
+        interrupt: Option<InterruptHandle>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + 'a>> {
+        if self.turn.is_none() && self.idle.is_none() {
+            return stream;
+        }
+        let mut started = self.started.subscribe();
+        Box::pin(stream! {
+            let mut stream = stream;
+            loop {
+                let deadline = self.deadline();
+                let expired = async {
+                    match deadline {
+                        Some((at, _)) => tokio::time::sleep_until(at).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    item = stream.next() => {
+                        let Some(item) = item else {
+                            break;
+                        };
+                        if let Ok(Message::Result(_)) = &item {
+                            self.finish_turn();
+                        }
+                        yield item;
+                    }
+                    _ = expired => {
+                        self.abandon();
+                        if let Some(ref handle) = interrupt
+                            && let Err(e) = handle.interrupt().await
+                        {
+                            tracing::warn!("Failed to interrupt timed out turn: {}", e);
+                        }
+                        if let Some((_, timeout)) = deadline {
+                            yield Err(Error::TurnTimeout(timeout));
+                        }
+                        break;
+                    }
+                    // A turn started or ended elsewhere; recompute the deadline.
+                    _ = started.changed() => {}
+                }
+            }
+        })
+    }
+}
@@ -3,27 +3,49 @@
 //! Multi-backend SDK supporting Claude Code, Codex, and Cursor Agent CLIs.
 //! See [arch-rust.md](../docs/arch-rust.md) for architecture design.
 
+// Lets `#[derive(ToolSchema)]` name this crate from inside it.
+extern crate self as code_agent_sdk;
+
+pub mod accumulator;
 pub mod backend;
 pub mod client;
 pub mod error;
 pub mod internal;
+pub mod mcp;
 pub mod options;
+pub mod profile;
+pub mod schema;
+pub mod session_store;
 pub mod transport;
 pub mod types;
+pub mod usage;
 
 // Primary exports
 pub use backend::BackendKind;
+pub use backend::claude::history::{ClaudeHistory, ClaudeSessionInfo};
+pub use backend::codex::CodexThreadInfo;
+pub use backend::mock::{MockCall, MockEvent, MockRecorder, MockScript, MockTurn, RecordedPrompt};
 pub use client::AgentSdkClient;
-pub use error::{Error, Result};
+pub use error::{BudgetLimit, Error, Result, TurnTimeout};
 pub use internal::message_parser::parse_message;
 pub use options::{
     AgentDefinition, AgentModel, AgentOptions, AgentOptionsBuilder, AssistantMessageError,
-    CodexOptions, CursorOptions, Effort, HookEvent, HookMatcher, McpHttpConfig, McpSdkConfig,
-    McpServerConfig, McpServersConfig, McpSseConfig, McpStdioConfig, PermissionMode,
-    PermissionResult, PermissionResultAllow, PermissionResultDeny, SandboxSettings, SdkBeta,
-    SdkMcpTool, SdkMcpToolHandler, SdkPluginConfig, SettingSource, ToolPermissionContext,
+    CodexOptions, ContainerMount, ContainerOptions, ContainerRuntime, CursorOptions, Effort,
+    HookEvent, HookMatcher, McpHttpConfig, McpSdkConfig, McpServerConfig, McpServersConfig,
+    McpSseConfig, McpStdioConfig, PermissionMode, PermissionResult, PermissionResultAllow,
+    PermissionResultDeny, SandboxSettings, SdkBeta, SdkMcpTool, SdkMcpToolHandler, SdkPluginConfig,
+    SettingSource, TimeoutConfig, ToolPermissionContext,
 };
+pub use profile::ProfileLoader;
+pub use session_store::{JsonlSessionStore, SessionStore, TranscriptEntry};
+pub use tokio_util::sync::CancellationToken;
 pub use types::*;
+pub use usage::{ModelPrice, PriceTable, Usage};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
 
 /// Create an SDK MCP server configuration with tools for in-process execution.
 ///
@@ -54,6 +76,10 @@ pub fn create_sdk_mcp_server(name: &str, version: &str, tools: Vec<SdkMcpTool>)
         name: name.to_string(),
         version: version.to_string(),
         tools,
+        resources: Vec::new(),
+        resource_templates: Vec::new(),
+        prompts: Vec::new(),
+        registry: Default::default(),
     }
 }
 
@@ -79,6 +105,8 @@ where
         name: name.to_string(),
         description: description.to_string(),
         input_schema,
+        output_schema: None,
+        annotations: None,
         handler: std::sync::Arc::new(handler),
     }
 }
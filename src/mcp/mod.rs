@@ -0,0 +1,656 @@
+//! Resources, prompts and content types of in-process SDK MCP servers.
+//!
+//! Tools are defined with [`sdk_mcp_tool`](crate::sdk_mcp_tool), or typed
+//! with [`SdkTool`] and a derived [`ToolSchema`]; the types here add the
+//! rest of the MCP server features to an
+//! [`McpSdkConfig`](crate::options::McpSdkConfig):
+//!
+//! ```rust
+//! use code_agent_sdk::create_sdk_mcp_server;
+//! use code_agent_sdk::mcp::{PromptMessage, ResourceContents, SdkMcpPrompt, SdkMcpResource};
+//!
+//! let server = create_sdk_mcp_server("docs", "1.0.0", vec![])
+//!     .resource(
+//!         SdkMcpResource::new("docs://readme", "README", |uri, _| {
+//!             Box::pin(async move { Ok(vec![ResourceContents::text(uri, "# Docs")]) })
+//!         })
+//!         .mime_type("text/markdown"),
+//!     )
+//!     .prompt(
+//!         SdkMcpPrompt::new("summarize", |args| {
+//!             Box::pin(async move {
+//!                 let topic = args.get("topic").cloned().unwrap_or_default();
+//!                 Ok(vec![PromptMessage::user(format!("Summarize {topic}"))])
+//!             })
+//!         })
+//!         .argument("topic", "What to summarize", true),
+//!     );
+//! assert_eq!(server.resources.len(), 1);
+//! ```
+
+mod typed;
+
+pub use typed::{SdkTool, ToolOutput, ToolSchema};
+
+use crate::error::{Error, Result};
+use crate::options::SdkMcpTool;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Handler for reading a resource. Receives the requested URI and, for
+/// templates, the values of the template's variables.
+pub type SdkMcpResourceHandler = Arc<
+    dyn Fn(
+            String,
+            HashMap<String, String>,
+        ) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Handler for getting a prompt. Receives the prompt's arguments.
+pub type SdkMcpPromptHandler = Arc<
+    dyn Fn(
+            HashMap<String, String>,
+        ) -> Pin<Box<dyn Future<Output = Result<Vec<PromptMessage>>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// A resource with a fixed URI.
+#[derive(Clone)]
+pub struct SdkMcpResource {
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    /// Called with the URI and no variables.
+    pub handler: SdkMcpResourceHandler,
+}
+
+impl SdkMcpResource {
+    pub fn new<F>(uri: impl Into<String>, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(
+                String,
+                HashMap<String, String>,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            uri: uri.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
+impl std::fmt::Debug for SdkMcpResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SdkMcpResource")
+            .field("uri", &self.uri)
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("mime_type", &self.mime_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Resources whose URIs match a template such as `file:///{path}`.
+///
+/// Each `{name}` matches up to the literal text that follows it, or the
+/// rest of the URI if it comes last.
+#[derive(Clone)]
+pub struct SdkMcpResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    /// Called with the URI and the values of the template's variables.
+    pub handler: SdkMcpResourceHandler,
+}
+
+impl SdkMcpResourceTemplate {
+    pub fn new<F>(uri_template: impl Into<String>, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(
+                String,
+                HashMap<String, String>,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            uri_template: uri_template.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// The variables of `uri` if it matches the template.
+    pub fn matches(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut vars = HashMap::new();
+        let mut rest = uri;
+        let mut template = self.uri_template.as_str();
+        while !template.is_empty() {
+            let Some(open) = template.find('{') else {
+                return (rest == template).then_some(vars);
+            };
+            rest = rest.strip_prefix(&template[..open])?;
+            let close = open + template[open..].find('}')?;
+            let name = &template[open + 1..close];
+            template = &template[close + 1..];
+            let literal = &template[..template.find('{').unwrap_or(template.len())];
+            let end = if literal.is_empty() {
+                rest.len()
+            } else {
+                rest.find(literal)?
+            };
+            if end == 0 {
+                return None;
+            }
+            vars.insert(name.to_string(), rest[..end].to_string());
+            rest = &rest[end..];
+        }
+        rest.is_empty().then_some(vars)
+    }
+}
+
+impl std::fmt::Debug for SdkMcpResourceTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SdkMcpResourceTemplate")
+            .field("uri_template", &self.uri_template)
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("mime_type", &self.mime_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A prompt template the client can fill in and get.
+#[derive(Clone)]
+pub struct SdkMcpPrompt {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+    pub handler: SdkMcpPromptHandler,
+}
+
+impl SdkMcpPrompt {
+    pub fn new<F>(name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(
+                HashMap<String, String>,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<PromptMessage>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            name: name.into(),
+            description: None,
+            arguments: Vec::new(),
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Declare an argument. Gets without a required argument are rejected
+    /// before the handler runs.
+    pub fn argument(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        required: bool,
+    ) -> Self {
+        self.arguments.push(PromptArgument {
+            name: name.into(),
+            description: Some(description.into()),
+            required,
+        });
+        self
+    }
+}
+
+impl std::fmt::Debug for SdkMcpPrompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SdkMcpPrompt")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("arguments", &self.arguments)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptMessage {
+    /// `"user"` or `"assistant"`.
+    pub role: String,
+    pub content: McpContent,
+}
+
+impl PromptMessage {
+    pub fn user(content: impl Into<McpContent>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<McpContent>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+/// A content block of a tool result or prompt message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        /// Base64-encoded image data.
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Audio {
+        /// Base64-encoded audio data.
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    /// Resource contents embedded in the result.
+    Resource {
+        resource: ResourceContents,
+    },
+    /// A resource the client can read.
+    ResourceLink {
+        uri: String,
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+    },
+}
+
+impl McpContent {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    pub fn image(data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self::Image {
+            data: data.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+
+    pub fn resource(resource: ResourceContents) -> Self {
+        Self::Resource { resource }
+    }
+}
+
+impl From<String> for McpContent {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<&str> for McpContent {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+/// The contents of a resource, as text or base64-encoded bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResourceContents {
+    Text {
+        uri: String,
+        #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        text: String,
+    },
+    Blob {
+        uri: String,
+        #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        blob: String,
+    },
+}
+
+impl ResourceContents {
+    pub fn text(uri: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Text {
+            uri: uri.into(),
+            mime_type: None,
+            text: text.into(),
+        }
+    }
+
+    pub fn blob(uri: impl Into<String>, blob: impl Into<String>) -> Self {
+        Self::Blob {
+            uri: uri.into(),
+            mime_type: None,
+            blob: blob.into(),
+        }
+    }
+
+    pub fn with_mime_type(mut self, value: impl Into<String>) -> Self {
+        match self {
+            Self::Text {
+                ref mut mime_type, ..
+            }
+            | Self::Blob {
+                ref mut mime_type, ..
+            } => *mime_type = Some(value.into()),
+        }
+        self
+    }
+}
+
+/// Hints about a tool's behavior. Clients must not rely on them for
+/// security decisions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+/// The result of a tool call. Converts into the JSON a
+/// [`SdkMcpToolHandler`](crate::options::SdkMcpToolHandler) returns.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolResult {
+    pub content: Vec<McpContent>,
+    /// Output matching the tool's output schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl ToolResult {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![McpContent::text(text)],
+            ..Default::default()
+        }
+    }
+
+    /// Structured output, also given as JSON text for clients that don't
+    /// read structured content.
+    pub fn structured(value: Value) -> Self {
+        Self {
+            content: vec![McpContent::text(value.to_string())],
+            structured_content: Some(value),
+            is_error: false,
+        }
+    }
+
+    /// A failure the model can see and react to.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            is_error: true,
+            ..Self::text(message)
+        }
+    }
+
+    pub fn with_content(mut self, content: impl Into<McpContent>) -> Self {
+        self.content.push(content.into());
+        self
+    }
+}
+
+impl From<ToolResult> for Value {
+    fn from(result: ToolResult) -> Self {
+        serde_json::to_value(result).unwrap_or(Value::Null)
+    }
+}
+
+/// Tools added to and removed from a running SDK MCP server. Shared by all
+/// clones of its [`McpSdkConfig`](crate::options::McpSdkConfig).
+#[derive(Clone)]
+pub(crate) struct ToolRegistry {
+    state: Arc<Mutex<RegistryState>>,
+    changed: broadcast::Sender<()>,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    added: Vec<SdkMcpTool>,
+    removed: HashSet<String>,
+    /// Backends of running sessions that cannot pick up changes.
+    pinned_by: Vec<&'static str>,
+}
+
+impl RegistryState {
+    fn check_unpinned(&self, feature: &str) -> Result<()> {
+        match self.pinned_by.first() {
+            Some(backend) => Err(Error::UnsupportedFeature {
+                feature: format!("{} while a session is running", feature),
+                backend: backend.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Keeps a registry's tools fixed until dropped; see [`ToolRegistry::pin`].
+pub(crate) struct RegistryPin {
+    state: Arc<Mutex<RegistryState>>,
+    backend: &'static str,
+}
+
+impl std::fmt::Debug for RegistryPin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegistryPin")
+            .field("backend", &self.backend)
+            .finish()
+    }
+}
+
+impl Drop for RegistryPin {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = state.pinned_by.iter().position(|b| *b == self.backend) {
+            state.pinned_by.remove(i);
+        }
+    }
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self {
+            state: Arc::default(),
+            changed: broadcast::channel(16).0,
+        }
+    }
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("ToolRegistry")
+            .field("added", &state.added.len())
+            .field("removed", &state.removed)
+            .field("pinned_by", &state.pinned_by)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    pub(crate) fn add(&self, tool: SdkMcpTool) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.check_unpinned("add_tool")?;
+        state.removed.remove(&tool.name);
+        state.added.retain(|t| t.name != tool.name);
+        state.added.push(tool);
+        drop(state);
+        let _ = self.changed.send(());
+        Ok(())
+    }
+
+    pub(crate) fn remove(&self, name: &str, registered: bool) -> Result<bool> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.check_unpinned("remove_tool")?;
+        let before = state.added.len();
+        state.added.retain(|t| t.name != name);
+        let removed =
+            state.added.len() != before || (registered && state.removed.insert(name.to_string()));
+        drop(state);
+        if removed {
+            let _ = self.changed.send(());
+        }
+        Ok(removed)
+    }
+
+    /// Refuse changes while the returned pin is alive, for a `backend`
+    /// session that registered the tools once and cannot update them.
+    pub(crate) fn pin(&self, backend: &'static str) -> RegistryPin {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.pinned_by.push(backend);
+        RegistryPin {
+            state: Arc::clone(&self.state),
+            backend,
+        }
+    }
+
+    /// `registered` with the changes applied.
+    pub(crate) fn apply(&self, registered: &[SdkMcpTool]) -> Vec<SdkMcpTool> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        registered
+            .iter()
+            .filter(|t| !state.removed.contains(&t.name))
+            .filter(|t| !state.added.iter().any(|a| a.name == t.name))
+            .chain(state.added.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<()> {
+        self.changed.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(uri_template: &str) -> SdkMcpResourceTemplate {
+        SdkMcpResourceTemplate::new(uri_template, "t", |_, _| Box::pin(async { Ok(vec![]) }))
+    }
+
+    #[test]
+    fn test_should_match_uri_templates() {
+        let vars = template("repo://{owner}/{name}/readme")
+            .matches("repo://rust-lang/rust/readme")
+            .unwrap();
+        assert_eq!(vars["owner"], "rust-lang");
+        assert_eq!(vars["name"], "rust");
+
+        let vars = template("file:///{path}")
+            .matches("file:///a/b.txt")
+            .unwrap();
+        assert_eq!(vars["path"], "a/b.txt");
+
+        assert!(
+            template("repo://{owner}/readme")
+                .matches("repo:///readme")
+                .is_none()
+        );
+        assert!(
+            template("repo://{owner}/readme")
+                .matches("repo://x/license")
+                .is_none()
+        );
+        assert!(
+            template("static://x")
+                .matches("static://x")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_should_serialize_tool_results() {
+        let result: Value = ToolResult::structured(serde_json::json!({"sum": 3}))
+            .with_content(McpContent::resource(
+                ResourceContents::text("calc://last", "3").with_mime_type("text/plain"),
+            ))
+            .into();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "content": [
+                    {"type": "text", "text": "{\"sum\":3}"},
+                    {"type": "resource", "resource": {"uri": "calc://last", "mimeType": "text/plain", "text": "3"}}
+                ],
+                "structuredContent": {"sum": 3}
+            })
+        );
+        let error: Value = ToolResult::error("boom").into();
+        assert_eq!(error["isError"], true);
+    }
+}
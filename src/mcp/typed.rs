@@ -0,0 +1,454 @@
+//! Typed SDK MCP tools.
+//!
+//! A tool's input is a struct deriving [`ToolSchema`] and
+//! [`Deserialize`](serde::Deserialize); its behavior is an [`SdkTool`]
+//! impl. The input schema is generated from the struct, so it cannot drift
+//! from what the handler accepts:
+//!
+//! ```rust
+//! use code_agent_sdk::mcp::{SdkTool, ToolSchema};
+//! use code_agent_sdk::{Result, create_sdk_mcp_server};
+//! use serde::Deserialize;
+//!
+//! /// Add two numbers.
+//! #[derive(Deserialize, ToolSchema)]
+//! struct Add {
+//!     /// First addend.
+//!     a: f64,
+//!     /// Second addend.
+//!     b: f64,
+//! }
+//!
+//! impl SdkTool for Add {
+//!     const NAME: &'static str = "add";
+//!     type Output = f64;
+//!
+//!     async fn call(self) -> Result<f64> {
+//!         Ok(self.a + self.b)
+//!     }
+//! }
+//!
+//! let server = create_sdk_mcp_server("calc", "1.0.0", vec![Add::mcp_tool()]);
+//! assert_eq!(server.tools[0].description, "Add two numbers.");
+//! assert_eq!(server.tools[0].input_schema["required"], serde_json::json!(["a", "b"]));
+//! ```
+
+use super::ToolResult;
+use crate::error::Result;
+use crate::options::SdkMcpTool;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub use code_agent_sdk_derive::ToolSchema;
+
+/// A type with a JSON Schema.
+///
+/// `#[derive(ToolSchema)]` supports structs with named fields, newtype
+/// structs and enums of unit variants, following the serde attributes
+/// that change the JSON shape: `rename`, `rename_all`, `default`, `skip`,
+/// `flatten` and `deny_unknown_fields`. Doc comments become descriptions.
+/// `Option` and `#[serde(default)]` fields are not required. Further
+/// keywords are added with `#[schema(...)]`, keys in snake_case:
+///
+/// ```rust
+/// # use code_agent_sdk::mcp::ToolSchema;
+/// #[derive(ToolSchema)]
+/// struct Search {
+///     #[schema(min_length = 1, pattern = "^[^*]+$")]
+///     query: String,
+///     #[schema(minimum = 1, maximum = 50)]
+///     limit: Option<u32>,
+/// }
+/// let schema = Search::schema();
+/// assert_eq!(schema["properties"]["query"]["minLength"], 1);
+/// ```
+///
+/// serde does not support `flatten` in a struct with
+/// `deny_unknown_fields`, so the derive rejects that combination:
+///
+/// ```compile_fail
+/// # use code_agent_sdk::mcp::ToolSchema;
+/// # #[derive(serde::Deserialize, ToolSchema)]
+/// # struct Paging {
+/// #     offset: u32,
+/// # }
+/// #[derive(serde::Deserialize, ToolSchema)]
+/// #[serde(deny_unknown_fields)]
+/// struct Search {
+///     query: String,
+///     #[serde(flatten)]
+///     paging: Paging,
+/// }
+/// ```
+pub trait ToolSchema {
+    fn schema() -> Value;
+}
+
+/// A typed SDK MCP tool: `Self` is the tool's input.
+pub trait SdkTool: ToolSchema + DeserializeOwned + Send + 'static {
+    /// Tool name (used in `mcp__<server>__<name>` format).
+    const NAME: &'static str;
+    /// Tool description. Defaults to the input type's doc comment.
+    const DESCRIPTION: &'static str = "";
+
+    type Output: ToolOutput;
+
+    fn call(self) -> impl Future<Output = Result<Self::Output>> + Send;
+
+    /// This tool as an [`SdkMcpTool`]. Arguments that don't deserialize
+    /// into `Self` are returned to the model as an `isError` result naming
+    /// the offending field; a handler error is reported the same way.
+    fn mcp_tool() -> SdkMcpTool
+    where
+        Self: Sized,
+    {
+        let input_schema = Self::schema();
+        let description = if Self::DESCRIPTION.is_empty() {
+            input_schema["description"]
+                .as_str()
+                .unwrap_or("")
+                .to_string()
+        } else {
+            Self::DESCRIPTION.to_string()
+        };
+        SdkMcpTool {
+            name: Self::NAME.to_string(),
+            description,
+            input_schema,
+            output_schema: Self::Output::output_schema(),
+            annotations: None,
+            handler: Arc::new(|arguments| {
+                Box::pin(async move {
+                    let input: Self = match serde_path_to_error::deserialize(arguments) {
+                        Ok(input) => input,
+                        Err(e) => {
+                            return Ok(
+                                ToolResult::error(format!("Invalid arguments: {}", e)).into()
+                            );
+                        }
+                    };
+                    Ok(input.call().await?.into_result())
+                })
+            }),
+        }
+    }
+}
+
+/// What a typed tool returns.
+///
+/// Implemented for [`ToolResult`], which is passed through, and for every
+/// `Serialize + ToolSchema` type: strings become text content, values with
+/// an object schema become structured content with that schema as the
+/// tool's output schema, and anything else is returned as JSON text.
+pub trait ToolOutput {
+    fn output_schema() -> Option<Value>;
+
+    /// The tool result JSON.
+    fn into_result(self) -> Value;
+}
+
+impl ToolOutput for ToolResult {
+    fn output_schema() -> Option<Value> {
+        None
+    }
+
+    fn into_result(self) -> Value {
+        self.into()
+    }
+}
+
+impl<T: Serialize + ToolSchema> ToolOutput for T {
+    fn output_schema() -> Option<Value> {
+        let schema = T::schema();
+        (schema["type"] == "object").then_some(schema)
+    }
+
+    fn into_result(self) -> Value {
+        match serde_json::to_value(&self) {
+            Ok(Value::String(text)) => ToolResult::text(text).into(),
+            Ok(value) if value.is_object() && Self::output_schema().is_some() => {
+                ToolResult::structured(value).into()
+            }
+            Ok(value) => ToolResult::text(value.to_string()).into(),
+            Err(e) => ToolResult::error(format!("Failed to serialize output: {}", e)).into(),
+        }
+    }
+}
+
+macro_rules! schema_impl {
+    ($schema:tt => $($ty:ty),+) => {
+        $(impl ToolSchema for $ty {
+            fn schema() -> Value {
+                json!($schema)
+            }
+        })+
+    };
+}
+
+schema_impl!({"type": "boolean"} => bool);
+schema_impl!({"type": "string"} => String, char, PathBuf);
+schema_impl!({"type": "integer"} => i8, i16, i32, i64, i128, isize);
+schema_impl!({"type": "integer", "minimum": 0} => u8, u16, u32, u64, u128, usize);
+schema_impl!({"type": "number"} => f32, f64);
+schema_impl!({"type": "null"} => ());
+schema_impl!({} => Value);
+
+impl ToolSchema for str {
+    fn schema() -> Value {
+        String::schema()
+    }
+}
+
+impl<T: ToolSchema + ?Sized> ToolSchema for &T {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: ToolSchema + ?Sized> ToolSchema for Box<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: ToolSchema + ?Sized> ToolSchema for Arc<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+/// The schema of `T` that also accepts `null`.
+impl<T: ToolSchema> ToolSchema for Option<T> {
+    fn schema() -> Value {
+        let mut schema = T::schema();
+        match schema.get("type").cloned() {
+            Some(Value::String(ty)) => schema["type"] = json!([ty, "null"]),
+            Some(_) => {}
+            None if schema.as_object().is_some_and(|s| s.is_empty()) => {}
+            None => schema = json!({"anyOf": [schema, {"type": "null"}]}),
+        }
+        schema
+    }
+}
+
+impl<T: ToolSchema> ToolSchema for Vec<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: ToolSchema> ToolSchema for [T] {
+    fn schema() -> Value {
+        Vec::<T>::schema()
+    }
+}
+
+impl<T: ToolSchema, const N: usize> ToolSchema for [T; N] {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema(), "minItems": N, "maxItems": N})
+    }
+}
+
+impl<T: ToolSchema, S> ToolSchema for HashSet<T, S> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema(), "uniqueItems": true})
+    }
+}
+
+impl<T: ToolSchema> ToolSchema for BTreeSet<T> {
+    fn schema() -> Value {
+        HashSet::<T>::schema()
+    }
+}
+
+impl<T: ToolSchema, S> ToolSchema for HashMap<String, T, S> {
+    fn schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::schema()})
+    }
+}
+
+impl<T: ToolSchema> ToolSchema for BTreeMap<String, T> {
+    fn schema() -> Value {
+        HashMap::<String, T>::schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::sdk_mcp::call_tool;
+    use serde::Deserialize;
+
+    /// Where to search.
+    #[derive(Debug, Deserialize, Serialize, ToolSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum Scope {
+        Workspace,
+        #[serde(rename = "home-dir")]
+        HomeDir,
+    }
+
+    #[derive(Debug, Deserialize, ToolSchema)]
+    struct Paging {
+        /// Results to skip.
+        #[serde(default)]
+        offset: u32,
+    }
+
+    /// Search files.
+    #[derive(Debug, Deserialize, ToolSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Search {
+        /// Text to find.
+        #[schema(min_length = 1)]
+        query_text: String,
+        scope: Scope,
+        max_results: Option<u8>,
+        tags: Vec<String>,
+        #[serde(flatten)]
+        paging: Paging,
+        #[serde(skip)]
+        _cache: (),
+    }
+
+    #[derive(Debug, Serialize, ToolSchema)]
+    struct Hits {
+        count: usize,
+    }
+
+    impl SdkTool for Search {
+        const NAME: &'static str = "search";
+        type Output = Hits;
+
+        async fn call(self) -> Result<Hits> {
+            if self.query_text == "fail" {
+                return Err(crate::Error::Other("index offline".to_string()));
+            }
+            let found = match self.scope {
+                Scope::Workspace => self.tags.len() + self.paging.offset as usize,
+                Scope::HomeDir => 0,
+            };
+            Ok(Hits {
+                count: self.max_results.map_or(found, |max| found.min(max.into())),
+            })
+        }
+    }
+
+    #[test]
+    fn test_should_derive_schema_from_serde_shape() {
+        assert_eq!(
+            Search::schema(),
+            json!({
+                "type": "object",
+                "description": "Search files.",
+                "properties": {
+                    "queryText": {"type": "string", "description": "Text to find.", "minLength": 1},
+                    "scope": {
+                        "type": "string",
+                        "enum": ["workspace", "home-dir"],
+                        "description": "Where to search."
+                    },
+                    "maxResults": {"type": ["integer", "null"], "minimum": 0},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "offset": {"type": "integer", "minimum": 0, "description": "Results to skip."}
+                },
+                "required": ["queryText", "scope", "tags"]
+            })
+        );
+    }
+
+    #[derive(Debug, Deserialize, ToolSchema)]
+    struct Range {
+        start: u32,
+        #[serde(default)]
+        end: u32,
+    }
+
+    #[derive(Debug, Deserialize, ToolSchema)]
+    struct Slice {
+        name: String,
+        #[serde(flatten)]
+        range: Range,
+    }
+
+    #[test]
+    fn test_should_merge_flattened_fields_into_schema() {
+        assert_eq!(
+            Slice::schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "start": {"type": "integer", "minimum": 0},
+                    "end": {"type": "integer", "minimum": 0}
+                },
+                "required": ["name", "start"]
+            })
+        );
+        let slice: Slice = serde_json::from_value(json!({"name": "a", "start": 2})).unwrap();
+        assert_eq!(
+            (slice.name.as_str(), slice.range.start, slice.range.end),
+            ("a", 2, 0)
+        );
+    }
+
+    #[derive(Debug, Deserialize, ToolSchema)]
+    #[serde(deny_unknown_fields)]
+    struct Strict {
+        id: u32,
+    }
+
+    #[test]
+    fn test_should_close_schema_of_deny_unknown_fields_struct() {
+        assert_eq!(Strict::schema()["additionalProperties"], false);
+        let strict: Strict = serde_json::from_value(json!({"id": 1})).unwrap();
+        assert_eq!(strict.id, 1);
+        assert!(serde_json::from_value::<Strict>(json!({"id": 1, "extra": 0})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_should_run_typed_tool_with_structured_output() {
+        let tool = Search::mcp_tool();
+        assert_eq!(tool.description, "Search files.");
+        assert_eq!(
+            tool.output_schema,
+            Some(json!({
+                "type": "object",
+                "properties": {"count": {"type": "integer", "minimum": 0}},
+                "required": ["count"]
+            }))
+        );
+
+        let result = call_tool(
+            &tool,
+            json!({"queryText": "x", "scope": "workspace", "tags": ["a", "b"], "offset": 1}),
+        )
+        .await;
+        assert_eq!(result["structuredContent"], json!({"count": 3}));
+        assert!(result.get("isError").is_none());
+
+        let result = call_tool(
+            &tool,
+            json!({"queryText": "x", "scope": "attic", "tags": []}),
+        )
+        .await;
+        assert_eq!(result["isError"], true);
+        assert_eq!(
+            result["content"][0]["text"],
+            "Invalid arguments for tool 'search':\n- /scope: must be one of [\"workspace\",\"home-dir\"]"
+        );
+
+        let result = call_tool(
+            &tool,
+            json!({"queryText": "fail", "scope": "workspace", "tags": []}),
+        )
+        .await;
+        assert_eq!(result["content"][0]["text"], "index offline");
+        assert_eq!(result["isError"], true);
+    }
+}
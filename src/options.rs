@@ -9,8 +9,17 @@ use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 use crate::backend::BackendKind;
+use crate::backend::mock::MockScript;
+use crate::mcp::{
+    SdkMcpPrompt, SdkMcpResource, SdkMcpResourceTemplate, ToolAnnotations, ToolRegistry,
+};
+use crate::session_store::SessionStore;
+use crate::transport::ProcessLauncher;
+use crate::usage::PriceTable;
 
 /// Permission modes for tool execution control.
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -310,7 +319,8 @@ impl From<String> for AssistantMessageError {
 }
 
 /// Codex-specific options.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CodexOptions {
     /// Approval policy: `"auto-edit"`, `"full-auto"`, or `"suggest"`.
     pub approval_policy: Option<String>,
@@ -319,7 +329,8 @@ pub struct CodexOptions {
 }
 
 /// Cursor Agent-specific options.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CursorOptions {
     /// Force-approve all tool calls (`--force` / `--yolo`).
     pub force_approve: bool,
@@ -329,12 +340,195 @@ pub struct CursorOptions {
     pub trust_workspace: bool,
 }
 
+/// Container engine used by [`ContainerOptions`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    Podman,
+    /// Another docker-compatible CLI (name or path).
+    Other(String),
+}
+
+impl ContainerRuntime {
+    /// The engine's CLI binary.
+    pub fn program(&self) -> &str {
+        match self {
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+            Self::Other(program) => program,
+        }
+    }
+}
+
+/// A bind mount into the container.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerMount {
+    /// Path on the host.
+    pub source: PathBuf,
+    /// Path inside the container.
+    pub target: PathBuf,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Options for running the backend CLI in a container.
+///
+/// The working directory (`cwd`, or the current directory) and every entry
+/// of `add_dirs` are bind-mounted at the same path inside the container, so
+/// paths in prompts and tool calls stay valid. The image must provide the
+/// backend CLI on its `PATH` (or set `cli_path` to its location in the image).
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContainerOptions {
+    pub runtime: ContainerRuntime,
+    /// Image to run, e.g. `"ghcr.io/acme/agent:latest"`.
+    pub image: String,
+    /// Additional bind mounts.
+    pub mounts: Vec<ContainerMount>,
+    /// Network mode (`--network`), e.g. `"none"` or `"host"`.
+    pub network: Option<String>,
+    /// CPU limit (`--cpus`).
+    pub cpus: Option<f64>,
+    /// Memory limit (`--memory`), e.g. `"4g"`.
+    pub memory: Option<String>,
+    /// Process count limit (`--pids-limit`).
+    pub pids_limit: Option<u32>,
+    /// User to run as inside the container (`--user`).
+    pub user: Option<String>,
+    /// Extra arguments for `run`, inserted before the image.
+    pub extra_args: Vec<String>,
+}
+
+impl ContainerOptions {
+    /// Run `image` with the default runtime and no limits.
+    pub fn new(image: impl Into<String>) -> Self {
+        Self {
+            image: image.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a read-write bind mount.
+    pub fn mount(mut self, source: impl Into<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        self.mounts.push(ContainerMount {
+            source: source.into(),
+            target: target.into(),
+            read_only: false,
+        });
+        self
+    }
+
+    /// Add a read-only bind mount.
+    pub fn mount_read_only(
+        mut self,
+        source: impl Into<PathBuf>,
+        target: impl Into<PathBuf>,
+    ) -> Self {
+        self.mounts.push(ContainerMount {
+            source: source.into(),
+            target: target.into(),
+            read_only: true,
+        });
+        self
+    }
+}
+
+/// Timeouts applied by every backend.
+///
+/// `turn` and `idle` are enforced on the streams returned by
+/// [`AgentSdkClient::receive_messages`](crate::client::AgentSdkClient::receive_messages)
+/// and [`receive_response`](crate::client::AgentSdkClient::receive_response)
+/// while a turn is in flight, and on the stream of a one-shot
+/// [`query()`](crate::query). When either expires the turn is interrupted
+/// (a one-shot query's CLI is killed) and the stream ends with
+/// [`Error::TurnTimeout`](crate::error::Error::TurnTimeout).
+///
+/// Serialized as milliseconds (`initialize_ms`, `turn_ms`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// Wait for the initialize handshake (Claude, Codex) or for the first
+    /// turn to report its chat id (Cursor).
+    #[serde(rename = "initialize_ms", with = "duration_ms")]
+    pub initialize: Duration,
+    /// Wait for the response to a control request (Claude).
+    #[serde(rename = "control_request_ms", with = "duration_ms")]
+    pub control_request: Duration,
+    /// Ceiling for a whole turn, from `query()` to its result.
+    #[serde(rename = "turn_ms", with = "duration_ms::option")]
+    pub turn: Option<Duration>,
+    /// Ceiling for the gap between two messages of a turn.
+    #[serde(rename = "idle_ms", with = "duration_ms::option")]
+    pub idle: Option<Duration>,
+    /// Time a CLI is given to exit after its input is closed before it is
+    /// killed.
+    #[serde(rename = "shutdown_grace_ms", with = "duration_ms")]
+    pub shutdown_grace: Duration,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            initialize: Duration::from_secs(60),
+            control_request: Duration::from_secs(60),
+            turn: None,
+            idle: None,
+            shutdown_grace: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Durations as whole milliseconds.
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub(crate) fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_millis)
+    }
+
+    pub(super) mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub(crate) fn serialize<S: Serializer>(
+            d: &Option<Duration>,
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            match d {
+                Some(d) => s.serialize_some(&(d.as_millis() as u64)),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            d: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            Ok(Option::<u64>::deserialize(d)?.map(Duration::from_millis))
+        }
+    }
+}
+
 /// Agent options for all backends.
 ///
 /// This is the primary configuration struct. Use [`BackendKind`] to select
 /// the target CLI backend. Options not applicable to the selected backend
 /// are validated at runtime and produce [`Error::UnsupportedOptions`](crate::error::Error::UnsupportedOptions).
-#[derive(Clone, Default)]
+///
+/// Options serialize with serde, e.g. to persist a configuration or pass it
+/// to another service. Callbacks (`can_use_tool`, `hooks`, `stderr`), the
+/// `launcher`, the `session_store`, SDK MCP tool handlers and the `mock`
+/// script are skipped; re-attach them after deserializing with
+/// [`into_builder`](Self::into_builder).
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AgentOptions {
     /// Which backend to use. Defaults to [`BackendKind::Claude`].
     pub backend: Option<BackendKind>,
@@ -346,12 +540,21 @@ pub struct AgentOptions {
     pub model: Option<String>,
     pub fallback_model: Option<String>,
     pub max_turns: Option<u32>,
+    /// Dollar ceiling for the session. Forwarded to the Claude CLI and also
+    /// enforced by [`AgentSdkClient`](crate::client::AgentSdkClient) on every
+    /// backend (Codex and Cursor need a [`price_table`](Self::price_table)).
     pub max_budget_usd: Option<f64>,
+    /// Token ceiling for the session, enforced by
+    /// [`AgentSdkClient`](crate::client::AgentSdkClient).
+    pub max_budget_tokens: Option<u64>,
     pub continue_conversation: bool,
     pub resume: Option<String>,
     pub cwd: Option<PathBuf>,
     pub cli_path: Option<PathBuf>,
     pub env: HashMap<String, String>,
+    /// Extra `--flag [value]` arguments for the CLI. Codex sessions run
+    /// `codex app-server`, which only gets the `config`, `enable` and
+    /// `disable` flags; the rest apply to one-shot `codex exec` queries.
     pub extra_args: HashMap<String, Option<String>>,
     pub add_dirs: Vec<PathBuf>,
     pub mcp_servers: Option<McpServersConfig>,
@@ -371,13 +574,32 @@ pub struct AgentOptions {
     pub user: Option<String>,
     pub agents: Option<HashMap<String, AgentDefinition>>,
     pub thinking: Option<ThinkingConfig>,
+    #[serde(skip)]
     pub can_use_tool: Option<CanUseToolCallback>,
+    #[serde(skip)]
     pub hooks: Option<HashMap<HookEvent, Vec<HookMatcher>>>,
+    #[serde(skip)]
     pub stderr: Option<StderrCallback>,
+    /// Launches the backend CLI (defaults to a local subprocess).
+    #[serde(skip)]
+    pub launcher: Option<Arc<dyn ProcessLauncher>>,
+    /// Run the backend CLI inside a container (exclusive with `launcher`).
+    pub container: Option<ContainerOptions>,
+    /// Prices used to estimate `total_cost_usd` when the backend does not
+    /// report it.
+    pub price_table: Option<PriceTable>,
+    /// Handshake, control request, turn and shutdown timeouts.
+    pub timeouts: TimeoutConfig,
     /// Codex-specific options.
     pub codex: Option<CodexOptions>,
     /// Cursor Agent-specific options.
     pub cursor: Option<CursorOptions>,
+    /// Scripted responses for [`BackendKind::Mock`].
+    #[serde(skip)]
+    pub mock: Option<MockScript>,
+    /// Where to record session transcripts; see [`crate::session_store`].
+    #[serde(skip)]
+    pub session_store: Option<Arc<dyn SessionStore>>,
 }
 
 impl std::fmt::Debug for AgentOptions {
@@ -401,6 +623,14 @@ impl std::fmt::Debug for AgentOptions {
                 &self.hooks.as_ref().map(|h| h.keys().collect::<Vec<_>>()),
             )
             .field("stderr", &self.stderr.as_ref().map(|_| "<callback>"))
+            .field("launcher", &self.launcher)
+            .field("container", &self.container)
+            .field("price_table", &self.price_table)
+            .field("timeouts", &self.timeouts)
+            .field(
+                "session_store",
+                &self.session_store.as_ref().map(|_| "<store>"),
+            )
             .finish_non_exhaustive()
     }
 }
@@ -426,16 +656,34 @@ pub struct SdkMcpTool {
     pub description: String,
     /// JSON Schema for the tool's input parameters.
     pub input_schema: serde_json::Value,
+    /// JSON Schema of the tool's `structuredContent`.
+    pub output_schema: Option<serde_json::Value>,
+    /// Behavior hints shown to the client.
+    pub annotations: Option<ToolAnnotations>,
     /// Async handler invoked when the tool is called.
     pub handler: SdkMcpToolHandler,
 }
 
+impl SdkMcpTool {
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+}
+
 impl std::fmt::Debug for SdkMcpTool {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SdkMcpTool")
             .field("name", &self.name)
             .field("description", &self.description)
             .field("input_schema", &self.input_schema)
+            .field("output_schema", &self.output_schema)
+            .field("annotations", &self.annotations)
             .field("handler", &"<handler>")
             .finish()
     }
@@ -447,6 +695,8 @@ impl Clone for SdkMcpTool {
             name: self.name.clone(),
             description: self.description.clone(),
             input_schema: self.input_schema.clone(),
+            output_schema: self.output_schema.clone(),
+            annotations: self.annotations.clone(),
             handler: Arc::clone(&self.handler),
         }
     }
@@ -465,7 +715,9 @@ pub type CanUseToolCallback = Arc<
 
 #[derive(Debug, Clone, Default)]
 pub struct ToolPermissionContext {
-    pub signal: Option<()>,
+    /// Cancelled when the CLI withdraws the request, the turn is
+    /// interrupted or the session closes.
+    pub signal: CancellationToken,
     pub suggestions: Vec<PermissionUpdate>,
 }
 
@@ -587,9 +839,11 @@ pub type HookCallback = Arc<
         + Sync,
 >;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct HookContext {
-    pub signal: Option<()>,
+    /// Cancelled when the CLI withdraws the request, the turn is
+    /// interrupted or the session closes.
+    pub signal: CancellationToken,
 }
 
 #[derive(Debug, Clone)]
@@ -608,7 +862,8 @@ pub enum HookJSONOutput {
     },
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AgentDefinition {
     pub description: String,
     pub prompt: String,
@@ -618,14 +873,16 @@ pub struct AgentDefinition {
     pub model: Option<AgentModel>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum ThinkingConfig {
     Adaptive,
     Enabled { budget_tokens: u32 },
     Disabled,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
 pub struct SandboxSettings {
     pub enabled: Option<bool>,
     pub auto_allow_bash_if_sandboxed: Option<bool>,
@@ -636,7 +893,8 @@ pub struct SandboxSettings {
     pub enable_weaker_nested_sandbox: Option<bool>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
 pub struct SandboxNetworkConfig {
     pub allow_unix_sockets: Option<Vec<String>>,
     pub allow_all_unix_sockets: Option<bool>,
@@ -645,19 +903,23 @@ pub struct SandboxNetworkConfig {
     pub socks_proxy_port: Option<u16>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SandboxIgnoreViolations {
     pub file: Option<Vec<String>>,
     pub network: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
 pub enum McpServersConfig {
     Dict(HashMap<String, McpServerConfig>),
     Path(String),
 }
 
-#[derive(Debug, Clone)]
+/// Serialized in the `.mcp.json` shape, e.g. `{"type": "stdio", "command": ...}`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum McpServerConfig {
     Stdio(McpStdioConfig),
     Sse(McpSseConfig),
@@ -665,43 +927,117 @@ pub enum McpServerConfig {
     Sdk(McpSdkConfig),
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct McpStdioConfig {
     pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub args: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct McpSseConfig {
     pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct McpHttpConfig {
     pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct McpSdkConfig {
     /// Server name.
     pub name: String,
     /// Server version.
     pub version: String,
-    /// Tools registered on this SDK MCP server.
+    /// Tools registered on this SDK MCP server. Not serialized.
+    #[serde(skip)]
     pub tools: Vec<SdkMcpTool>,
+    /// Resources with fixed URIs. Not serialized.
+    #[serde(skip)]
+    pub resources: Vec<SdkMcpResource>,
+    /// Resources read through URI templates. Not serialized.
+    #[serde(skip)]
+    pub resource_templates: Vec<SdkMcpResourceTemplate>,
+    /// Prompts offered by this server. Not serialized.
+    #[serde(skip)]
+    pub prompts: Vec<SdkMcpPrompt>,
+    #[serde(skip)]
+    pub(crate) registry: ToolRegistry,
+}
+
+impl McpSdkConfig {
+    pub fn resource(mut self, resource: SdkMcpResource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn resource_template(mut self, template: SdkMcpResourceTemplate) -> Self {
+        self.resource_templates.push(template);
+        self
+    }
+
+    pub fn prompt(mut self, prompt: SdkMcpPrompt) -> Self {
+        self.prompts.push(prompt);
+        self
+    }
+
+    /// Add `tool` to the running server, replacing any tool of the same
+    /// name. Clients connected through the loopback bridge are sent
+    /// `notifications/tools/list_changed`.
+    ///
+    /// Takes effect for every clone of this config, including the one in
+    /// the session's options.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedFeature`](crate::error::Error::UnsupportedFeature)
+    /// while a Codex app-server session uses the server: its tools are
+    /// registered with the thread when it starts and cannot change.
+    pub fn add_tool(&self, tool: SdkMcpTool) -> crate::error::Result<()> {
+        self.registry.add(tool)
+    }
+
+    /// Remove the tool named `name` from the running server. Returns
+    /// whether there was one.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`add_tool`](Self::add_tool).
+    pub fn remove_tool(&self, name: &str) -> crate::error::Result<bool> {
+        let registered = self.tools.iter().any(|t| t.name == name);
+        self.registry.remove(name, registered)
+    }
+
+    /// The tools the server offers now: [`tools`](Self::tools) with the
+    /// changes made by [`add_tool`](Self::add_tool) and
+    /// [`remove_tool`](Self::remove_tool).
+    pub fn active_tools(&self) -> Vec<SdkMcpTool> {
+        self.registry.apply(&self.tools)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SdkPluginConfig {
     /// Plugin type. Currently only "local" is supported.
+    #[serde(rename = "type")]
     pub type_: String,
     pub path: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
 pub enum ToolsConfig {
     List(Vec<String>),
     Preset { preset: String },
@@ -725,7 +1061,8 @@ impl From<HashMap<String, McpServerConfig>> for McpServersConfig {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
 pub enum SystemPromptConfig {
     String(String),
     Preset {
@@ -787,6 +1124,11 @@ impl AgentOptionsBuilder {
         self
     }
 
+    pub fn max_budget_tokens(mut self, tokens: u64) -> Self {
+        self.options.max_budget_tokens = Some(tokens);
+        self
+    }
+
     pub fn cwd(mut self, path: impl Into<PathBuf>) -> Self {
         self.options.cwd = Some(path.into());
         self
@@ -807,6 +1149,11 @@ impl AgentOptionsBuilder {
         self
     }
 
+    pub fn fork_session(mut self, fork_session: bool) -> Self {
+        self.options.fork_session = fork_session;
+        self
+    }
+
     pub fn build(self) -> AgentOptions {
         self.options
     }
@@ -858,6 +1205,36 @@ impl AgentOptionsBuilder {
         self
     }
 
+    /// Run the backend CLI through `launcher` instead of a local subprocess.
+    pub fn launcher(mut self, launcher: impl ProcessLauncher + 'static) -> Self {
+        self.options.launcher = Some(Arc::new(launcher));
+        self
+    }
+
+    /// Run the backend CLI inside a docker/podman container.
+    pub fn container(mut self, container: ContainerOptions) -> Self {
+        self.options.container = Some(container);
+        self
+    }
+
+    /// Estimate cost from token usage for backends that don't report it.
+    pub fn price_table(mut self, prices: PriceTable) -> Self {
+        self.options.price_table = Some(prices);
+        self
+    }
+
+    /// Record every session's transcript into `store`.
+    pub fn session_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.options.session_store = Some(store);
+        self
+    }
+
+    /// Override the default timeouts.
+    pub fn timeouts(mut self, timeouts: TimeoutConfig) -> Self {
+        self.options.timeouts = timeouts;
+        self
+    }
+
     pub fn mcp_servers(mut self, servers: impl Into<McpServersConfig>) -> Self {
         self.options.mcp_servers = Some(servers.into());
         self
@@ -918,6 +1295,12 @@ impl AgentOptionsBuilder {
         self
     }
 
+    /// Set the script replayed by [`BackendKind::Mock`].
+    pub fn mock(mut self, script: MockScript) -> Self {
+        self.options.mock = Some(script);
+        self
+    }
+
     pub fn setting_sources(mut self, sources: impl IntoIterator<Item = SettingSource>) -> Self {
         self.options.setting_sources = Some(sources.into_iter().collect());
         self
@@ -950,4 +1333,10 @@ impl AgentOptions {
     pub fn builder() -> AgentOptionsBuilder {
         AgentOptionsBuilder::new()
     }
+
+    /// Continue building from these options, e.g. to re-attach callbacks
+    /// after deserializing.
+    pub fn into_builder(self) -> AgentOptionsBuilder {
+        AgentOptionsBuilder { options: self }
+    }
 }
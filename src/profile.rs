@@ -0,0 +1,320 @@
+//! Loading [`AgentOptions`] from profile files and the environment.
+//!
+//! A profile file is TOML (`.toml`) or JSON (`.json`). Its top level holds
+//! the base options, in the same shape as `AgentOptions`' serde
+//! representation; named profiles under `profiles` are layered on top:
+//!
+//! ```toml
+//! model = "claude-sonnet-4-5"
+//! allowed_tools = ["Read", "Grep"]
+//!
+//! [sandbox]
+//! enabled = true
+//!
+//! [profiles.review]
+//! permission_mode = "plan"
+//!
+//! [profiles.ci]
+//! backend = "codex"
+//! codex = { sandbox_mode = "read-only" }
+//! ```
+//!
+//! Options are resolved in three layers: the base options, then the
+//! selected profile, then `CODE_AGENT_*` environment variables (see
+//! [`ENV_OVERRIDES`]). Tables are merged key by key; any other value
+//! replaces the one below it. `CODE_AGENT_PROFILE` selects the profile when
+//! none is given explicitly.
+//!
+//! Unknown keys, unknown `CODE_AGENT_*` variables and options the selected
+//! backend does not support are errors.
+
+use crate::backend::{BackendKind, create_backend};
+use crate::error::{Error, Result};
+use crate::options::AgentOptions;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the environment variables read by [`ProfileLoader`].
+pub const ENV_PREFIX: &str = "CODE_AGENT_";
+
+/// Environment variable selecting the profile.
+pub const PROFILE_ENV: &str = "CODE_AGENT_PROFILE";
+
+/// How an override's value is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvValue {
+    /// Taken as is.
+    String,
+    /// Parsed as a number.
+    Number,
+    /// Comma-separated; surrounding whitespace and empty items are dropped.
+    List,
+}
+
+/// Environment variables overriding a profile: variable, option and format.
+pub const ENV_OVERRIDES: &[(&str, &str, EnvValue)] = &[
+    ("CODE_AGENT_BACKEND", "backend", EnvValue::String),
+    ("CODE_AGENT_MODEL", "model", EnvValue::String),
+    (
+        "CODE_AGENT_FALLBACK_MODEL",
+        "fallback_model",
+        EnvValue::String,
+    ),
+    (
+        "CODE_AGENT_PERMISSION_MODE",
+        "permission_mode",
+        EnvValue::String,
+    ),
+    (
+        "CODE_AGENT_SYSTEM_PROMPT",
+        "system_prompt",
+        EnvValue::String,
+    ),
+    ("CODE_AGENT_EFFORT", "effort", EnvValue::String),
+    ("CODE_AGENT_MAX_TURNS", "max_turns", EnvValue::Number),
+    (
+        "CODE_AGENT_MAX_BUDGET_USD",
+        "max_budget_usd",
+        EnvValue::Number,
+    ),
+    (
+        "CODE_AGENT_MAX_BUDGET_TOKENS",
+        "max_budget_tokens",
+        EnvValue::Number,
+    ),
+    ("CODE_AGENT_ALLOWED_TOOLS", "allowed_tools", EnvValue::List),
+    (
+        "CODE_AGENT_DISALLOWED_TOOLS",
+        "disallowed_tools",
+        EnvValue::List,
+    ),
+    ("CODE_AGENT_CWD", "cwd", EnvValue::String),
+    ("CODE_AGENT_CLI_PATH", "cli_path", EnvValue::String),
+];
+
+/// Resolves [`AgentOptions`] from a profile file and environment overrides.
+///
+/// ```no_run
+/// use code_agent_sdk::ProfileLoader;
+///
+/// let options = ProfileLoader::new()
+///     .file("agents.toml")
+///     .profile("review")
+///     .load()?;
+/// # Ok::<(), code_agent_sdk::Error>(())
+/// ```
+///
+/// Callbacks are not part of a profile; attach them with
+/// [`AgentOptions::into_builder`]. [`BackendKind::Mock`] options are not
+/// validated, since their script can only be attached in code.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileLoader {
+    file: Option<PathBuf>,
+    profile: Option<String>,
+    env: Option<HashMap<String, String>>,
+}
+
+impl ProfileLoader {
+    /// A loader with no file, reading overrides from the process environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read base options and profiles from `path`.
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Apply the named profile; takes precedence over `CODE_AGENT_PROFILE`.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Read overrides from `vars` instead of the process environment.
+    pub fn env<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env = Some(
+            vars.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Resolve and validate the options.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Config`] if the file cannot be read or parsed, the profile
+    /// does not exist, or a key or override is unknown or malformed;
+    /// [`Error::UnsupportedOptions`] if the backend does not support the
+    /// resulting options.
+    pub fn load(self) -> Result<AgentOptions> {
+        let env = match self.env {
+            Some(env) => env,
+            None => std::env::vars().collect(),
+        };
+        let requested = self.profile.clone();
+        let profile = self.profile.or_else(|| env.get(PROFILE_ENV).cloned());
+
+        let mut source = Vec::new();
+        let mut value = Value::Object(Map::new());
+        if let Some(path) = &self.file {
+            source.push(path.display().to_string());
+            let mut file = read_file(path)?;
+            let profiles = file.remove("profiles");
+            value = Value::Object(file);
+            if let Some(name) = &profile {
+                source.push(format!("profile '{}'", name));
+                merge(&mut value, select_profile(path, profiles, name)?);
+            }
+        } else if let Some(name) = requested {
+            return Err(Error::Config(format!(
+                "profile '{}' requested but no profile file given",
+                name
+            )));
+        }
+        let overrides = env_overrides(&env)?;
+        if !overrides.is_empty() {
+            source.push("environment".to_string());
+            merge(&mut value, Value::Object(overrides));
+        }
+
+        let options: AgentOptions = serde_path_to_error::deserialize(value).map_err(|e| {
+            let source = if source.is_empty() {
+                "defaults".to_string()
+            } else {
+                source.join(", ")
+            };
+            Error::Config(format!("{}: {}: {}", source, e.path(), e.inner()))
+        })?;
+
+        let backend = options.backend.unwrap_or_default();
+        if backend != BackendKind::Mock {
+            create_backend(backend).validate_options(&options)?;
+        }
+        Ok(options)
+    }
+}
+
+impl AgentOptions {
+    /// Load the base options of a profile file, with environment overrides
+    /// and the profile named by `CODE_AGENT_PROFILE`, if set.
+    ///
+    /// See [`ProfileLoader`] for the file format and layering.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        ProfileLoader::new().file(path.as_ref()).load()
+    }
+
+    /// Load the named profile from a profile file, with environment
+    /// overrides.
+    pub fn from_profile(path: impl AsRef<Path>, profile: &str) -> Result<Self> {
+        ProfileLoader::new()
+            .file(path.as_ref())
+            .profile(profile)
+            .load()
+    }
+}
+
+fn read_file(path: &Path) -> Result<Map<String, Value>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+    let parsed = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str::<toml::Table>(&text)
+            .map_err(|e| e.to_string())
+            .and_then(|table| serde_json::to_value(table).map_err(|e| e.to_string())),
+        Some("json") => serde_json::from_str::<Value>(&text).map_err(|e| e.to_string()),
+        _ => Err("expected a .toml or .json file".to_string()),
+    };
+    match parsed.map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))? {
+        Value::Object(map) => Ok(map),
+        _ => Err(Error::Config(format!(
+            "{}: expected a table of options",
+            path.display()
+        ))),
+    }
+}
+
+fn select_profile(path: &Path, profiles: Option<Value>, name: &str) -> Result<Value> {
+    let mut profiles = match profiles {
+        Some(Value::Object(profiles)) => profiles,
+        None => Map::new(),
+        Some(_) => {
+            return Err(Error::Config(format!(
+                "{}: `profiles` must be a table",
+                path.display()
+            )));
+        }
+    };
+    if let Some(profile) = profiles.remove(name) {
+        return Ok(profile);
+    }
+    let mut available: Vec<_> = profiles.keys().map(String::as_str).collect();
+    available.sort_unstable();
+    Err(Error::Config(format!(
+        "{}: no profile '{}' (available: {})",
+        path.display(),
+        name,
+        if available.is_empty() {
+            "none".to_string()
+        } else {
+            available.join(", ")
+        }
+    )))
+}
+
+fn env_overrides(env: &HashMap<String, String>) -> Result<Map<String, Value>> {
+    let mut overrides = Map::new();
+    let mut vars: Vec<_> = env
+        .iter()
+        .filter(|(var, _)| var.starts_with(ENV_PREFIX) && var.as_str() != PROFILE_ENV)
+        .collect();
+    vars.sort();
+    for (var, raw) in vars {
+        let Some(&(_, key, kind)) = ENV_OVERRIDES.iter().find(|(name, _, _)| name == var) else {
+            return Err(Error::Config(format!(
+                "unknown environment variable {}",
+                var
+            )));
+        };
+        let value = match kind {
+            EnvValue::String => Value::String(raw.clone()),
+            EnvValue::Number => raw
+                .trim()
+                .parse::<serde_json::Number>()
+                .map(Value::Number)
+                .map_err(|_| Error::Config(format!("{}: expected a number, got {:?}", var, raw)))?,
+            EnvValue::List => raw
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        };
+        overrides.insert(key.to_string(), value);
+    }
+    Ok(overrides)
+}
+
+/// Merge `overlay` into `base`: tables key by key, other values replaced.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
@@ -0,0 +1,558 @@
+//! JSON Schema validation.
+//!
+//! SDK MCP servers check every `tools/call` against the tool's
+//! `input_schema` before running its handler, returning the violations to
+//! the model as an error result so it can correct the call. The same
+//! validator checks structured output against
+//! [`AgentOptions::output_format`](crate::options::AgentOptions::output_format):
+//!
+//! ```
+//! use code_agent_sdk::schema::{validate, validate_structured_output};
+//! use serde_json::json;
+//!
+//! let schema = json!({
+//!     "type": "object",
+//!     "properties": {"name": {"type": "string", "minLength": 1}},
+//!     "required": ["name"]
+//! });
+//! let violations = validate(&schema, &json!({"name": ""}));
+//! assert_eq!(violations[0].to_string(), "/name: must be at least 1 characters long");
+//!
+//! let output_format = json!({"type": "json_schema", "schema": schema});
+//! assert!(validate_structured_output(&output_format, &json!({"name": "x"})).is_empty());
+//! ```
+//!
+//! A subset of draft 2020-12 is supported: `type`, `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `patternProperties`,
+//! `minProperties`/`maxProperties`, `items`, `prefixItems`,
+//! `minItems`/`maxItems`, `uniqueItems`, `minLength`/`maxLength`,
+//! `pattern`, `minimum`/`maximum` and their exclusive forms, `multipleOf`,
+//! `allOf`/`anyOf`/`oneOf`/`not`, `if`/`then`/`else` and local `$ref`s.
+//! Other keywords, including `format`, are ignored.
+
+use regex_lite::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// `$ref`s followed without descending into the instance before giving up,
+/// so a self-referencing schema cannot recurse forever.
+const MAX_REF_DEPTH: usize = 32;
+
+/// A way an instance fails its schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending value; empty for the instance itself.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Every way `instance` fails `schema`; empty if it is valid.
+pub fn validate(schema: &Value, instance: &Value) -> Vec<SchemaViolation> {
+    let mut validator = Validator {
+        root: schema,
+        violations: Vec::new(),
+    };
+    validator.check(schema, instance, "", 0);
+    validator.violations
+}
+
+/// Every way `output` fails the schema of a `{"type": "json_schema",
+/// "schema": ...}` output format. Formats without a schema accept anything.
+pub fn validate_structured_output(output_format: &Value, output: &Value) -> Vec<SchemaViolation> {
+    match output_format.get("schema") {
+        Some(schema) if output_format["type"] == "json_schema" => validate(schema, output),
+        _ => Vec::new(),
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    violations: Vec<SchemaViolation>,
+}
+
+impl<'a> Validator<'a> {
+    fn fail(&mut self, path: &str, message: String) {
+        self.violations.push(SchemaViolation {
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn is_valid(&self, schema: &Value, instance: &Value, refs: usize) -> bool {
+        let mut sub = Validator {
+            root: self.root,
+            violations: Vec::new(),
+        };
+        sub.check(schema, instance, "", refs);
+        sub.violations.is_empty()
+    }
+
+    fn check(&mut self, schema: &'a Value, instance: &Value, path: &str, refs: usize) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.fail(path, "no value is allowed here".to_string()),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str)
+            && refs < MAX_REF_DEPTH
+            && let Some(target) = self.resolve(reference)
+        {
+            self.check(target, instance, path, refs + 1);
+        }
+
+        if let Some(expected) = schema.get("type")
+            && !type_matches(expected, instance)
+        {
+            let expected = match expected {
+                Value::Array(types) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            // Keywords for other types don't apply, so stop at the type.
+            return self.fail(
+                path,
+                format!("expected {}, got {}", expected, type_name(instance)),
+            );
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum")
+            && !allowed.iter().any(|v| json_eq(v, instance))
+        {
+            self.fail(
+                path,
+                format!("must be one of {}", Value::from(allowed.clone())),
+            );
+        }
+        if let Some(expected) = schema.get("const")
+            && !json_eq(expected, instance)
+        {
+            self.fail(path, format!("must be {}", expected));
+        }
+
+        match instance {
+            Value::Object(object) => self.check_object(schema, object, path),
+            Value::Array(items) => self.check_array(schema, items, path),
+            Value::String(s) => self.check_string(schema, s, path),
+            Value::Number(n) => {
+                if let Some(n) = n.as_f64() {
+                    self.check_number(schema, n, path);
+                }
+            }
+            _ => {}
+        }
+
+        self.check_combinators(schema, instance, path, refs);
+    }
+
+    fn check_object(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    self.fail(path, format!("missing required property '{}'", name));
+                }
+            }
+        }
+        if let Some(min) = schema.get("minProperties").and_then(Value::as_u64)
+            && (object.len() as u64) < min
+        {
+            self.fail(path, format!("must have at least {} properties", min));
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64)
+            && object.len() as u64 > max
+        {
+            self.fail(path, format!("must have at most {} properties", max));
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let patterns: Vec<(Regex, &Value)> = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(pattern, schema)| Some((compile(pattern)?, schema)))
+            .collect();
+        for (name, value) in object {
+            let value_path = format!("{}/{}", path, escape_pointer(name));
+            let mut matched = false;
+            if let Some(property) = properties.and_then(|p| p.get(name)) {
+                matched = true;
+                self.check(property, value, &value_path, 0);
+            }
+            for (regex, property) in &patterns {
+                if regex.is_match(name) {
+                    matched = true;
+                    self.check(property, value, &value_path, 0);
+                }
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) if !matched => {
+                    self.fail(path, format!("unexpected property '{}'", name));
+                }
+                Some(additional) if !matched => self.check(additional, value, &value_path, 0),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_array(&mut self, schema: &'a Map<String, Value>, items: &[Value], path: &str) {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+            && (items.len() as u64) < min
+        {
+            self.fail(path, format!("must have at least {} items", min));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+            && items.len() as u64 > max
+        {
+            self.fail(path, format!("must have at most {} items", max));
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true))
+            && items
+                .iter()
+                .enumerate()
+                .any(|(i, a)| items[..i].iter().any(|b| json_eq(a, b)))
+        {
+            self.fail(path, "items must be unique".to_string());
+        }
+
+        // Draft 2020-12 `prefixItems`, or the older array form of `items`.
+        let (prefix, rest) = match (schema.get("prefixItems"), schema.get("items")) {
+            (Some(Value::Array(prefix)), rest) => (prefix.as_slice(), rest),
+            (None, Some(Value::Array(prefix))) => (prefix.as_slice(), None),
+            (_, rest) => (&[][..], rest),
+        };
+        for (i, item) in items.iter().enumerate() {
+            let item_schema = match prefix.get(i) {
+                Some(schema) => schema,
+                None => match rest {
+                    Some(schema) => schema,
+                    None => break,
+                },
+            };
+            self.check(item_schema, item, &format!("{}/{}", path, i), 0);
+        }
+    }
+
+    fn check_string(&mut self, schema: &Map<String, Value>, s: &str, path: &str) {
+        let len = s.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+            && len < min
+        {
+            self.fail(path, format!("must be at least {} characters long", min));
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+            && len > max
+        {
+            self.fail(path, format!("must be at most {} characters long", max));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str)
+            && let Some(regex) = compile(pattern)
+            && !regex.is_match(s)
+        {
+            self.fail(path, format!("must match pattern '{}'", pattern));
+        }
+    }
+
+    fn check_number(&mut self, schema: &Map<String, Value>, n: f64, path: &str) {
+        let bound = |key: &str| schema.get(key).and_then(|v| Some((v.as_f64()?, v)));
+        if let Some((min, shown)) = bound("minimum")
+            && n < min
+        {
+            self.fail(path, format!("must be >= {}", shown));
+        }
+        if let Some((min, shown)) = bound("exclusiveMinimum")
+            && n <= min
+        {
+            self.fail(path, format!("must be > {}", shown));
+        }
+        if let Some((max, shown)) = bound("maximum")
+            && n > max
+        {
+            self.fail(path, format!("must be <= {}", shown));
+        }
+        if let Some((max, shown)) = bound("exclusiveMaximum")
+            && n >= max
+        {
+            self.fail(path, format!("must be < {}", shown));
+        }
+        if let Some((factor, shown)) = bound("multipleOf")
+            && factor > 0.0
+        {
+            let quotient = n / factor;
+            if (quotient - quotient.round()).abs() > 1e-9 {
+                self.fail(path, format!("must be a multiple of {}", shown));
+            }
+        }
+    }
+
+    fn check_combinators(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        instance: &Value,
+        path: &str,
+        refs: usize,
+    ) {
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for sub in all {
+                self.check(sub, instance, path, refs);
+            }
+        }
+        if let Some(Value::Array(any)) = schema.get("anyOf")
+            && !any.iter().any(|sub| self.is_valid(sub, instance, refs))
+        {
+            self.fail(path, "must match at least one schema in anyOf".to_string());
+        }
+        if let Some(Value::Array(one)) = schema.get("oneOf") {
+            let matched = one
+                .iter()
+                .filter(|sub| self.is_valid(sub, instance, refs))
+                .count();
+            if matched != 1 {
+                self.fail(
+                    path,
+                    format!(
+                        "must match exactly one schema in oneOf, matched {}",
+                        matched
+                    ),
+                );
+            }
+        }
+        if let Some(not) = schema.get("not")
+            && self.is_valid(not, instance, refs)
+        {
+            self.fail(path, "must not match the schema in not".to_string());
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = if self.is_valid(condition, instance, refs) {
+                schema.get("then")
+            } else {
+                schema.get("else")
+            };
+            if let Some(branch) = branch {
+                self.check(branch, instance, path, refs);
+            }
+        }
+    }
+
+    /// The schema a local `$ref` such as `#/$defs/item` points at.
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+}
+
+fn type_matches(expected: &Value, instance: &Value) -> bool {
+    match expected {
+        Value::String(ty) => match ty.as_str() {
+            "integer" => instance
+                .as_f64()
+                .is_some_and(|n| n.fract() == 0.0 && n.is_finite()),
+            "number" => instance.is_number(),
+            other => type_name(instance) == other,
+        },
+        Value::Array(types) => types.iter().any(|ty| type_matches(ty, instance)),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// JSON equality, under which `1` and `1.0` are the same number.
+fn json_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| json_eq(a, b))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| json_eq(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+/// A schema `pattern`. Patterns the regex engine rejects are not enforced.
+fn compile(pattern: &str) -> Option<Regex> {
+    Regex::new(pattern)
+        .inspect_err(|e| tracing::debug!("Ignoring schema pattern {:?}: {}", pattern, e))
+        .ok()
+}
+
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(schema: Value, instance: Value) -> Vec<String> {
+        validate(&schema, &instance)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_should_report_every_violation_with_its_path() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "minLength": 1, "pattern": "^[a-z]+$"},
+                "limit": {"type": "integer", "minimum": 1, "maximum": 50},
+                "scope": {"enum": ["workspace", "home"]},
+                "tags": {"type": "array", "items": {"type": "string"}, "uniqueItems": true},
+                "filter": {
+                    "type": "object",
+                    "properties": {"path": {"type": "string"}},
+                    "required": ["path"],
+                    "additionalProperties": false
+                }
+            },
+            "required": ["query", "scope"]
+        });
+        assert_eq!(
+            messages(
+                schema.clone(),
+                json!({
+                    "query": "Hi",
+                    "limit": 2.5,
+                    "tags": ["a", 1, "a"],
+                    "filter": {"glob": "*.rs"}
+                })
+            ),
+            vec![
+                "missing required property 'scope'",
+                "/filter: missing required property 'path'",
+                "/filter: unexpected property 'glob'",
+                "/limit: expected integer, got number",
+                "/query: must match pattern '^[a-z]+$'",
+                "/tags: items must be unique",
+                "/tags/1: expected string, got number",
+            ]
+        );
+        assert!(
+            validate(
+                &schema,
+                &json!({"query": "hi", "scope": "home", "limit": 50.0, "filter": {"path": "src"}})
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            messages(schema, json!({"query": "", "scope": "attic", "limit": 0})),
+            vec![
+                "/limit: must be >= 1",
+                "/query: must be at least 1 characters long",
+                "/query: must match pattern '^[a-z]+$'",
+                "/scope: must be one of [\"workspace\",\"home\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_follow_refs_and_combinators() {
+        let schema = json!({
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": {"anyOf": [{"type": "string"}, {"type": "null"}]},
+                        "children": {"type": "array", "items": {"$ref": "#/$defs/node"}}
+                    }
+                }
+            },
+            "$ref": "#/$defs/node"
+        });
+        assert_eq!(
+            messages(
+                schema,
+                json!({"value": null, "children": [{"value": "a"}, {"value": 3}]})
+            ),
+            vec!["/children/1/value: must match at least one schema in anyOf"]
+        );
+
+        let schema = json!({"oneOf": [{"multipleOf": 3}, {"multipleOf": 5}], "not": {"const": 30}});
+        assert!(messages(schema.clone(), json!(9)).is_empty());
+        assert_eq!(
+            messages(schema, json!(15)),
+            vec!["must match exactly one schema in oneOf, matched 2"]
+        );
+
+        let looping = json!({"$defs": {"a": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"});
+        assert!(validate(&looping, &json!(1)).is_empty());
+    }
+
+    #[test]
+    fn test_should_validate_recursive_schema_below_ref_depth_limit() {
+        let schema = json!({
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": {"type": "integer"},
+                        "next": {"$ref": "#/$defs/node"}
+                    }
+                }
+            },
+            "$ref": "#/$defs/node"
+        });
+        let depth = MAX_REF_DEPTH + 8;
+        let mut list = json!({"value": "deep"});
+        for _ in 0..depth {
+            list = json!({"value": 1, "next": list});
+        }
+        assert_eq!(
+            messages(schema, list),
+            vec![format!(
+                "{}/value: expected integer, got string",
+                "/next".repeat(depth)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_should_check_structured_output_against_output_format() {
+        let output_format = json!({
+            "type": "json_schema",
+            "schema": {"type": "object", "required": ["answer"]}
+        });
+        assert_eq!(
+            validate_structured_output(&output_format, &json!({})),
+            vec![SchemaViolation {
+                path: String::new(),
+                message: "missing required property 'answer'".to_string(),
+            }]
+        );
+        assert!(validate_structured_output(&json!({"type": "text"}), &json!(1)).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Transcript persistence.
+//!
+//! Setting [`AgentOptions::session_store`](crate::options::AgentOptions::session_store)
+//! makes [`AgentSdkClient`](crate::client::AgentSdkClient) and
+//! [`query()`](crate::query) record every message of a session, sent and
+//! received, as a [`TranscriptEntry`]. Each connection (or one-shot query)
+//! is one transcript, identified by an id the SDK generates; entries carry
+//! the backend's own session, thread or chat id once it is known.
+//!
+//! [`JsonlSessionStore`] keeps one JSON Lines file per transcript.
+
+use crate::backend::BackendKind;
+use crate::error::{Error, Result};
+use crate::types::Message;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// One recorded message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub backend: BackendKind,
+    /// The backend's session id (Claude), thread id (Codex) or chat id
+    /// (Cursor), once reported; `None` for messages sent before that.
+    pub session_id: Option<String>,
+    /// When the message was sent or received, in milliseconds since the
+    /// Unix epoch.
+    pub timestamp_ms: u64,
+    pub message: Message,
+}
+
+/// Storage for session transcripts.
+///
+/// Appends for a transcript arrive in order from a single task; a failed
+/// append is logged and does not affect the session.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Add `entry` to the end of the transcript `transcript_id`, creating
+    /// it if needed.
+    async fn append(&self, transcript_id: &str, entry: &TranscriptEntry) -> Result<()>;
+
+    /// All entries of a transcript, in the order they were appended.
+    async fn load(&self, transcript_id: &str) -> Result<Vec<TranscriptEntry>>;
+
+    /// Ids of all stored transcripts, oldest first.
+    async fn list(&self) -> Result<Vec<String>>;
+}
+
+/// A new transcript id: creation time, process id and a counter, so ids
+/// sort by creation time and never collide within a store.
+pub(crate) fn new_transcript_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{:013}-{}-{}",
+        now_ms(),
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// [`SessionStore`] writing `<dir>/<transcript id>.jsonl`, one entry per
+/// line.
+#[derive(Debug)]
+pub struct JsonlSessionStore {
+    dir: PathBuf,
+    /// Serializes appends so lines never interleave.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl JsonlSessionStore {
+    /// Store transcripts in `dir`, which is created on first use.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// The directory holding the transcripts.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, transcript_id: &str) -> Result<PathBuf> {
+        let valid = !transcript_id.is_empty()
+            && transcript_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !transcript_id.starts_with('.');
+        if !valid {
+            return Err(Error::Other(format!(
+                "Invalid transcript id: {:?}",
+                transcript_id
+            )));
+        }
+        Ok(self.dir.join(format!("{}.jsonl", transcript_id)))
+    }
+}
+
+#[async_trait]
+impl SessionStore for JsonlSessionStore {
+    async fn append(&self, transcript_id: &str, entry: &TranscriptEntry) -> Result<()> {
+        let path = self.path(transcript_id)?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().await;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn load(&self, transcript_id: &str) -> Result<Vec<TranscriptEntry>> {
+        let path = self.path(transcript_id)?;
+        let text = tokio::fs::read_to_string(&path).await?;
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    Error::MessageParse(format!("{}:{}: {}", path.display(), i + 1, e))
+                })
+            })
+            .collect()
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut dir = match tokio::fs::read_dir(&self.dir).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ids = Vec::new();
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("jsonl")
+                && let Some(id) = path.file_stem().and_then(|s| s.to_str())
+            {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+}
@@ -0,0 +1,285 @@
+//! Run backend CLIs inside a docker/podman container.
+//!
+//! [`ContainerLauncher`] wraps a [`LaunchSpec`] in `docker run -i` (or
+//! `podman run -i`) and reuses [`ProcessTransport`] for the JSON line
+//! plumbing over the container's stdin/stdout. It is normally configured
+//! through [`AgentOptions::container`](crate::options::AgentOptions::container):
+//!
+//! ```no_run
+//! use code_agent_sdk::{AgentOptions, ContainerOptions, PermissionMode};
+//!
+//! let options = AgentOptions::builder()
+//!     .cwd("/work/repo")
+//!     .permission_mode(PermissionMode::BypassPermissions)
+//!     .container(ContainerOptions {
+//!         network: Some("none".to_string()),
+//!         memory: Some("4g".to_string()),
+//!         ..ContainerOptions::new("ghcr.io/acme/claude-code:latest")
+//!     })
+//!     .build();
+//! ```
+
+use crate::error::Result;
+use crate::options::{AgentOptions, ContainerOptions};
+use crate::transport::{LaunchSpec, ProcessLauncher, ProcessTransport, Transport};
+use async_trait::async_trait;
+use futures::Stream;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long to wait for `rm -f` when closing a container.
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CONTAINER_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Launches CLIs in a fresh container per process.
+///
+/// Each launch runs `<runtime> run -i --rm --init` with the working
+/// directory bind-mounted at the same path and used as the container's
+/// working directory. Environment variables from the spec are passed with
+/// `-e`. The container is named so that [`Transport::close`] can remove it
+/// even if the engine does not forward the kill.
+#[derive(Debug, Clone)]
+pub struct ContainerLauncher {
+    options: ContainerOptions,
+    /// Host directories mounted at the same path (`cwd` aside).
+    shared_dirs: Vec<PathBuf>,
+}
+
+impl ContainerLauncher {
+    /// Launcher for `options`.
+    pub fn new(options: ContainerOptions) -> Self {
+        Self {
+            options,
+            shared_dirs: Vec::new(),
+        }
+    }
+
+    /// Launcher for `container` that also shares the agent's `add_dirs`.
+    pub(crate) fn for_agent(container: &ContainerOptions, agent: &AgentOptions) -> Self {
+        Self {
+            options: container.clone(),
+            shared_dirs: agent.add_dirs.clone(),
+        }
+    }
+
+    /// The local `run` invocation that executes `spec` in a container.
+    pub fn container_spec(&self, spec: &LaunchSpec) -> LaunchSpec {
+        self.run_spec(spec, &next_container_name())
+    }
+
+    fn run_spec(&self, spec: &LaunchSpec, name: &str) -> LaunchSpec {
+        let opts = &self.options;
+        let mut args = vec![
+            "run".to_string(),
+            "-i".to_string(),
+            "--rm".to_string(),
+            "--init".to_string(),
+            "--name".to_string(),
+            name.to_string(),
+        ];
+
+        let workdir = spec.cwd.clone().or_else(|| std::env::current_dir().ok());
+        let mut shared: Vec<&PathBuf> = workdir.iter().collect();
+        for dir in &self.shared_dirs {
+            if !shared.contains(&dir) {
+                shared.push(dir);
+            }
+        }
+        for dir in shared {
+            let dir = dir.to_string_lossy();
+            args.push("-v".to_string());
+            args.push(format!("{}:{}", dir, dir));
+        }
+        for mount in &opts.mounts {
+            args.push("-v".to_string());
+            args.push(format!(
+                "{}:{}{}",
+                mount.source.to_string_lossy(),
+                mount.target.to_string_lossy(),
+                if mount.read_only { ":ro" } else { "" }
+            ));
+        }
+        if let Some(ref dir) = workdir {
+            args.push("-w".to_string());
+            args.push(dir.to_string_lossy().to_string());
+        }
+
+        if let Some(ref network) = opts.network {
+            args.push("--network".to_string());
+            args.push(network.clone());
+        }
+        if let Some(cpus) = opts.cpus {
+            args.push("--cpus".to_string());
+            args.push(cpus.to_string());
+        }
+        if let Some(ref memory) = opts.memory {
+            args.push("--memory".to_string());
+            args.push(memory.clone());
+        }
+        if let Some(pids) = opts.pids_limit {
+            args.push("--pids-limit".to_string());
+            args.push(pids.to_string());
+        }
+        if let Some(ref user) = opts.user {
+            args.push("--user".to_string());
+            args.push(user.clone());
+        }
+        // Values are forwarded from the runtime client's environment so
+        // that they stay out of its command line.
+        for (key, _) in &spec.env {
+            args.push("-e".to_string());
+            args.push(key.clone());
+        }
+        args.extend(opts.extra_args.iter().cloned());
+
+        args.push(opts.image.clone());
+        args.push(spec.program.clone());
+        args.extend(spec.args.iter().cloned());
+
+        let mut run = LaunchSpec::new(opts.runtime.program())
+            .args(args)
+            .stderr(spec.stderr.clone())
+            .max_buffer_size(spec.max_buffer_size);
+        run.env = spec.env.clone();
+        run
+    }
+}
+
+fn next_container_name() -> String {
+    format!(
+        "code-agent-sdk-{}-{}",
+        std::process::id(),
+        CONTAINER_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+#[async_trait]
+impl ProcessLauncher for ContainerLauncher {
+    async fn launch(&self, spec: LaunchSpec) -> Result<Box<dyn Transport + Send>> {
+        let name = next_container_name();
+        let mut inner = ProcessTransport::new(self.run_spec(&spec, &name));
+        inner.connect().await?;
+        Ok(Box::new(ContainerTransport {
+            inner,
+            runtime: self.options.runtime.program().to_string(),
+            name,
+        }))
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+/// [`ProcessTransport`] for a `run -i` client that removes its container
+/// on close.
+struct ContainerTransport {
+    inner: ProcessTransport,
+    runtime: String,
+    name: String,
+}
+
+#[async_trait]
+impl Transport for ContainerTransport {
+    async fn connect(&mut self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        self.inner.write(data).await
+    }
+
+    fn read_messages(&mut self) -> Pin<Box<dyn Stream<Item = Result<serde_json::Value>> + Send>> {
+        self.inner.read_messages()
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let result = self.inner.close().await;
+        // Killing the client does not stop the container; remove it.
+        // Failure is expected when `--rm` already cleaned it up.
+        let remove = Command::new(&self.runtime)
+            .args(["rm", "-f", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        let _ = tokio::time::timeout(REMOVE_TIMEOUT, remove).await;
+        result
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        self.inner.end_input().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ContainerRuntime;
+
+    #[test]
+    fn test_should_build_run_command_with_shared_dirs_and_limits() {
+        let container = ContainerOptions {
+            runtime: ContainerRuntime::Podman,
+            network: Some("none".to_string()),
+            cpus: Some(1.5),
+            memory: Some("2g".to_string()),
+            pids_limit: Some(256),
+            ..ContainerOptions::new("agent-image:1").mount_read_only("/host/cache", "/cache")
+        };
+        let agent = AgentOptions::builder()
+            .add_dir("/work/repo")
+            .add_dir("/work/shared")
+            .build();
+        let spec = LaunchSpec::new("codex")
+            .args(["exec", "hi"])
+            .env("KEY", "v")
+            .cwd(Some("/work/repo".into()));
+
+        let run = ContainerLauncher::for_agent(&container, &agent).run_spec(&spec, "c1");
+        assert_eq!(run.program, "podman");
+        assert_eq!(run.env, [("KEY".to_string(), "v".to_string())]);
+        assert_eq!(
+            run.args,
+            [
+                "run",
+                "-i",
+                "--rm",
+                "--init",
+                "--name",
+                "c1",
+                "-v",
+                "/work/repo:/work/repo",
+                "-v",
+                "/work/shared:/work/shared",
+                "-v",
+                "/host/cache:/cache:ro",
+                "-w",
+                "/work/repo",
+                "--network",
+                "none",
+                "--cpus",
+                "1.5",
+                "--memory",
+                "2g",
+                "--pids-limit",
+                "256",
+                "-e",
+                "KEY",
+                "agent-image:1",
+                "codex",
+                "exec",
+                "hi",
+            ]
+        );
+    }
+}
@@ -1,7 +1,19 @@
 //! Transport implementations for Claude SDK.
 
+mod container;
+mod process;
+mod recording;
+mod ssh;
 mod subprocess_cli;
 
+pub use container::ContainerLauncher;
+pub(crate) use process::{JsonLines, configured_launcher, is_remote, launch};
+pub use process::{LaunchSpec, LocalLauncher, ProcessLauncher, ProcessTransport};
+pub use recording::{
+    CassetteEntry, Direction, RecordingTransport, ReplayMismatch, ReplayTransport, ReplayVerifier,
+    read_cassette,
+};
+pub use ssh::SshLauncher;
 pub use subprocess_cli::SubprocessCliTransport;
 
 use crate::error::Result;
@@ -0,0 +1,472 @@
+//! Process launching for backend CLIs.
+//!
+//! Backends describe the CLI invocation they need as a [`LaunchSpec`] and
+//! hand it to a [`ProcessLauncher`], which returns a connected [`Transport`]
+//! speaking newline-delimited JSON. The default [`LocalLauncher`] spawns a
+//! local subprocess via [`ProcessTransport`]; custom launchers can run the
+//! CLI elsewhere (remote host, container) or return a test double.
+
+use crate::error::{Error, Result};
+use crate::options::StderrCallback;
+use crate::transport::{ContainerLauncher, Transport};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::Stream;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, watch};
+
+/// Lines of stderr kept for [`Error::Process`].
+const STDERR_TAIL_LINES: usize = 50;
+/// How long to wait for stderr to drain after the process exits.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// Largest JSON message buffered when [`LaunchSpec::max_buffer_size`] is unset.
+pub(crate) const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024; // 1MB
+
+/// A CLI invocation to launch.
+#[derive(Clone, Default)]
+pub struct LaunchSpec {
+    /// Program to run (a path or a name resolved by the launcher).
+    pub program: String,
+    /// Arguments, excluding the program.
+    pub args: Vec<String>,
+    /// Extra environment variables for the process.
+    pub env: Vec<(String, String)>,
+    /// Working directory for the process.
+    pub cwd: Option<PathBuf>,
+    /// Called with each line the process writes to stderr.
+    pub stderr: Option<StderrCallback>,
+    /// Largest JSON message, in bytes, to buffer from stdout (1MB if unset).
+    pub max_buffer_size: Option<usize>,
+}
+
+impl std::fmt::Debug for LaunchSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LaunchSpec")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field("cwd", &self.cwd)
+            .field("stderr", &self.stderr.as_ref().map(|_| "<callback>"))
+            .field("max_buffer_size", &self.max_buffer_size)
+            .finish()
+    }
+}
+
+impl LaunchSpec {
+    /// Create a spec for `program` with no arguments.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Append arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set an environment variable.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Spec for `program` with the `cwd`, `env`, `stderr` and
+    /// `max_buffer_size` from `options`.
+    pub(crate) fn from_options(
+        program: impl Into<String>,
+        options: &crate::options::AgentOptions,
+    ) -> Self {
+        let mut spec = Self::new(program)
+            .cwd(options.cwd.clone())
+            .stderr(options.stderr.clone())
+            .max_buffer_size(options.max_buffer_size);
+        spec.env
+            .extend(options.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        spec
+    }
+
+    /// Set the working directory.
+    pub fn cwd(mut self, cwd: Option<PathBuf>) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    /// Set the stderr line callback.
+    pub fn stderr(mut self, callback: Option<StderrCallback>) -> Self {
+        self.stderr = callback;
+        self
+    }
+
+    /// Set the largest JSON message to buffer from stdout.
+    pub fn max_buffer_size(mut self, size: Option<usize>) -> Self {
+        self.max_buffer_size = size;
+        self
+    }
+}
+
+/// Reassembles JSON messages that a CLI split across stdout lines.
+#[derive(Debug)]
+pub(crate) struct JsonLines {
+    buffer: String,
+    max_size: usize,
+}
+
+impl JsonLines {
+    pub(crate) fn new(max_size: Option<usize>) -> Self {
+        Self {
+            buffer: String::new(),
+            max_size: max_size.unwrap_or(DEFAULT_MAX_BUFFER_SIZE),
+        }
+    }
+
+    /// Add a line of stdout, returning the message it completes.
+    ///
+    /// Lines that are not JSON are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error once a message grows past the maximum buffer size.
+    pub(crate) fn push(&mut self, line: &str) -> Result<Option<serde_json::Value>> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let continued = !self.buffer.is_empty();
+        self.buffer.push_str(line);
+        if self.buffer.len() > self.max_size {
+            self.buffer.clear();
+            return Err(Error::Other(format!(
+                "JSON message exceeded maximum buffer size of {} bytes",
+                self.max_size
+            )));
+        }
+        match serde_json::from_str(&self.buffer) {
+            Ok(data) => {
+                self.buffer.clear();
+                Ok(Some(data))
+            }
+            Err(e) if e.is_eof() => Ok(None),
+            Err(_) => {
+                self.buffer.clear();
+                // The line may start a message of its own.
+                if continued { self.push(line) } else { Ok(None) }
+            }
+        }
+    }
+}
+
+/// Starts backend CLI processes.
+///
+/// Set via [`AgentOptions::launcher`](crate::options::AgentOptions::launcher)
+/// to change where and how every backend runs its CLI.
+#[async_trait]
+pub trait ProcessLauncher: Send + Sync + std::fmt::Debug {
+    /// Launch `spec` and return a connected transport.
+    ///
+    /// Transports must report a non-zero exit as an [`Error::Process`] item
+    /// at the end of [`Transport::read_messages`].
+    async fn launch(&self, spec: LaunchSpec) -> Result<Box<dyn Transport + Send>>;
+
+    /// Whether the CLI runs somewhere the local filesystem cannot see.
+    ///
+    /// Remote launchers skip local CLI discovery: `cli_path` is used as-is
+    /// and otherwise the bare program name is resolved by the remote side.
+    fn is_remote(&self) -> bool {
+        false
+    }
+}
+
+/// Launches CLIs as local subprocesses. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalLauncher;
+
+#[async_trait]
+impl ProcessLauncher for LocalLauncher {
+    async fn launch(&self, spec: LaunchSpec) -> Result<Box<dyn Transport + Send>> {
+        let mut transport = ProcessTransport::new(spec);
+        transport.connect().await?;
+        Ok(Box::new(transport))
+    }
+}
+
+/// Process exit state shared between the waiter task and readers.
+#[derive(Debug, Clone)]
+enum ExitState {
+    Running,
+    Exited {
+        code: Option<i32>,
+        stderr: Option<String>,
+    },
+    /// Killed by [`Transport::close`]; not reported as an error.
+    Killed,
+}
+
+/// Newline-delimited JSON transport over a local subprocess.
+///
+/// Messages split across stdout lines are reassembled, up to
+/// [`LaunchSpec::max_buffer_size`]; other lines that are not valid JSON are
+/// skipped. When stdout ends, the read stream waits for the process and
+/// yields [`Error::Process`] (with the tail of stderr) if it exited
+/// unsuccessfully.
+pub struct ProcessTransport {
+    spec: LaunchSpec,
+    stdin: Option<ChildStdin>,
+    stdout: Option<ChildStdout>,
+    exit_rx: Option<watch::Receiver<ExitState>>,
+    kill_tx: Option<oneshot::Sender<()>>,
+    ready: bool,
+}
+
+impl std::fmt::Debug for ProcessTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessTransport")
+            .field("spec", &self.spec)
+            .field("ready", &self.ready)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProcessTransport {
+    /// Create an unconnected transport for `spec`.
+    pub fn new(spec: LaunchSpec) -> Self {
+        Self {
+            spec,
+            stdin: None,
+            stdout: None,
+            exit_rx: None,
+            kill_tx: None,
+            ready: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for ProcessTransport {
+    async fn connect(&mut self) -> Result<()> {
+        if self.exit_rx.is_some() {
+            return Ok(());
+        }
+
+        let mut cmd = Command::new(&self.spec.program);
+        cmd.args(&self.spec.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        for (k, v) in &self.spec.env {
+            cmd.env(k, v);
+        }
+        if let Some(ref cwd) = self.spec.cwd {
+            cmd.current_dir(cwd);
+        }
+
+        let mut child = cmd.spawn().map_err(|e| {
+            if let Some(ref cwd) = self.spec.cwd
+                && !cwd.exists()
+            {
+                return Error::Other(format!(
+                    "Working directory does not exist: {}",
+                    cwd.display()
+                ));
+            }
+            Error::CliNotFound(format!("Failed to start {}: {}", self.spec.program, e))
+        })?;
+
+        self.stdin = child.stdin.take();
+        self.stdout = child.stdout.take();
+
+        let tail = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_task = child.stderr.take().map(|stderr| {
+            let tail = Arc::clone(&tail);
+            let callback = self.spec.stderr.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    if let Some(ref cb) = callback {
+                        cb(line);
+                    }
+                    if let Ok(mut tail) = tail.lock() {
+                        if tail.len() == STDERR_TAIL_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line.to_string());
+                    }
+                }
+            })
+        });
+
+        let (exit_tx, exit_rx) = watch::channel(ExitState::Running);
+        let (kill_tx, kill_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status,
+                _ = kill_rx => {
+                    let _ = child.kill().await;
+                    let _ = exit_tx.send_replace(ExitState::Killed);
+                    return;
+                }
+            };
+            if let Some(task) = stderr_task {
+                let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, task).await;
+            }
+            let stderr = tail
+                .lock()
+                .ok()
+                .filter(|t| !t.is_empty())
+                .map(|t| t.iter().cloned().collect::<Vec<_>>().join("\n"));
+            exit_tx.send_replace(ExitState::Exited {
+                code: status.ok().and_then(|s| s.code()),
+                stderr,
+            });
+        });
+
+        self.exit_rx = Some(exit_rx);
+        self.kill_tx = Some(kill_tx);
+        self.ready = true;
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        if !self.ready {
+            return Err(Error::Other("Transport not ready for writing".to_string()));
+        }
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| Error::Other("Stdin not available".to_string()))?;
+        stdin.write_all(data.as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    fn read_messages(&mut self) -> Pin<Box<dyn Stream<Item = Result<serde_json::Value>> + Send>> {
+        let stdout = self.stdout.take();
+        let exit_rx = self.exit_rx.clone();
+        let mut json_lines = JsonLines::new(self.spec.max_buffer_size);
+
+        Box::pin(stream! {
+            let (Some(stdout), Some(mut exit_rx)) = (stdout, exit_rx) else {
+                yield Err(Error::Other("Not connected".to_string()));
+                return;
+            };
+
+            let mut lines = BufReader::new(stdout).lines();
+            let mut watch_kill = true;
+            loop {
+                tokio::select! {
+                    line = lines.next_line() => match line {
+                        Ok(Some(line)) => match json_lines.push(&line) {
+                            Ok(Some(data)) => yield Ok(data),
+                            Ok(None) => {}
+                            Err(e) => {
+                                yield Err(e);
+                                return;
+                            }
+                        },
+                        Ok(None) => break,
+                        Err(e) => {
+                            yield Err(Error::Connection(e));
+                            return;
+                        }
+                    },
+                    // A killed process may leave children holding stdout open.
+                    killed = async {
+                        exit_rx.wait_for(|s| matches!(s, ExitState::Killed)).await.is_ok()
+                    }, if watch_kill => {
+                        if killed {
+                            return;
+                        }
+                        watch_kill = false;
+                    }
+                }
+            }
+
+            let state = exit_rx
+                .wait_for(|s| !matches!(s, ExitState::Running))
+                .await
+                .map(|s| s.clone());
+            if let Ok(ExitState::Exited { code, stderr }) = state
+                && code != Some(0)
+            {
+                yield Err(Error::Process {
+                    exit_code: code.unwrap_or(-1),
+                    stderr,
+                });
+            }
+        })
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.ready = false;
+        self.stdin = None;
+        self.stdout = None;
+        if let Some(kill_tx) = self.kill_tx.take() {
+            let _ = kill_tx.send(());
+        }
+        if let Some(mut exit_rx) = self.exit_rx.take() {
+            let _ = exit_rx.wait_for(|s| !matches!(s, ExitState::Running)).await;
+        }
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        if let Some(mut stdin) = self.stdin.take() {
+            let _ = stdin.shutdown().await;
+        }
+        Ok(())
+    }
+}
+
+/// Launcher configured in `options`, or `None` for the default.
+pub(crate) fn configured_launcher(
+    options: &crate::options::AgentOptions,
+) -> Result<Option<Arc<dyn ProcessLauncher>>> {
+    match (&options.launcher, &options.container) {
+        (Some(_), Some(_)) => Err(Error::Other(
+            "AgentOptions::launcher and AgentOptions::container cannot both be set".to_string(),
+        )),
+        (Some(launcher), None) => Ok(Some(Arc::clone(launcher))),
+        (None, Some(container)) => Ok(Some(Arc::new(ContainerLauncher::for_agent(
+            container, options,
+        )))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Launch `spec` with the launcher configured in `options`.
+pub(crate) async fn launch(
+    options: &crate::options::AgentOptions,
+    spec: LaunchSpec,
+) -> Result<Box<dyn Transport + Send>> {
+    match configured_launcher(options)? {
+        Some(launcher) => launcher.launch(spec).await,
+        None => LocalLauncher.launch(spec).await,
+    }
+}
+
+/// Whether CLI discovery should be skipped because the CLI is remote.
+pub(crate) fn is_remote(options: &crate::options::AgentOptions) -> bool {
+    options.container.is_some() || options.launcher.as_ref().is_some_and(|l| l.is_remote())
+}
@@ -0,0 +1,396 @@
+//! Record-and-replay transports for session transcripts.
+//!
+//! [`RecordingTransport`] wraps any [`Transport`] and appends every JSON
+//! line written to and read from it to a JSONL *cassette*. A
+//! [`ReplayTransport`] plays a cassette back without a CLI: reads are
+//! released only once the SDK has written everything that preceded them in
+//! the recording, and each write is compared against the recorded one.
+//!
+//! ```no_run
+//! use code_agent_sdk::backend::claude::transport::ClaudeCliTransport;
+//! use code_agent_sdk::transport::{RecordingTransport, ReplayTransport, Transport};
+//! use code_agent_sdk::{AgentOptions, AgentSdkClient};
+//!
+//! # async fn example() -> code_agent_sdk::Result<()> {
+//! // Capture a real session once...
+//! let options = AgentOptions::default();
+//! let mut transport =
+//!     RecordingTransport::new(ClaudeCliTransport::new(options.clone())?, "session.jsonl")?;
+//! transport.connect().await?;
+//! let mut client = AgentSdkClient::new(Some(options), Some(Box::new(transport)));
+//! client.connect(None).await?;
+//! // ... drive the session, then disconnect.
+//!
+//! // ...and replay it in a test.
+//! let replay = ReplayTransport::from_file("session.jsonl")?;
+//! let verifier = replay.verifier();
+//! let mut client = AgentSdkClient::new(None, Some(Box::new(replay)));
+//! client.connect(None).await?;
+//! // ... drive the same session, then:
+//! verifier.verify()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::transport::Transport;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::watch;
+
+/// Direction of a recorded line, from the SDK's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Written by the SDK to the CLI.
+    Write,
+    /// Read by the SDK from the CLI.
+    Read,
+}
+
+/// One line of a cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    /// Milliseconds since the recording started.
+    pub elapsed_ms: u64,
+    pub direction: Direction,
+    /// The JSON message (non-JSON writes are stored as a string).
+    pub data: serde_json::Value,
+}
+
+impl CassetteEntry {
+    /// A line the CLI sends, at the start of the recording.
+    pub fn read(data: serde_json::Value) -> Self {
+        Self {
+            elapsed_ms: 0,
+            direction: Direction::Read,
+            data,
+        }
+    }
+
+    /// A line the SDK sends, at the start of the recording.
+    pub fn write(data: serde_json::Value) -> Self {
+        Self {
+            elapsed_ms: 0,
+            direction: Direction::Write,
+            data,
+        }
+    }
+}
+
+/// Load all entries from a JSONL cassette file.
+pub fn read_cassette(path: impl AsRef<Path>) -> Result<Vec<CassetteEntry>> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .map_err(|e| Error::Other(format!("Failed to open cassette {}: {}", path.display(), e)))?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line)?);
+    }
+    Ok(entries)
+}
+
+/// Split raw transport output into JSON values, one per non-empty line.
+fn split_lines(data: &str) -> Vec<serde_json::Value> {
+    data.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            serde_json::from_str(l).unwrap_or_else(|_| serde_json::Value::String(l.to_string()))
+        })
+        .collect()
+}
+
+struct CassetteWriter {
+    file: File,
+    started: Instant,
+}
+
+impl CassetteWriter {
+    fn append(&mut self, direction: Direction, data: serde_json::Value) {
+        let entry = CassetteEntry {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            direction,
+            data,
+        };
+        if let Ok(line) = serde_json::to_string(&entry) {
+            let _ = writeln!(self.file, "{}", line);
+            let _ = self.file.flush();
+        }
+    }
+}
+
+/// Transport wrapper that records all traffic to a JSONL cassette.
+///
+/// Every call is forwarded to the wrapped transport unchanged.
+pub struct RecordingTransport {
+    inner: Box<dyn Transport + Send>,
+    writer: Arc<Mutex<CassetteWriter>>,
+}
+
+impl RecordingTransport {
+    /// Wrap `inner`, truncating or creating the cassette at `path`.
+    pub fn new(inner: impl Transport + 'static, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| {
+            Error::Other(format!(
+                "Failed to create cassette {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(Self {
+            inner: Box::new(inner),
+            writer: Arc::new(Mutex::new(CassetteWriter {
+                file,
+                started: Instant::now(),
+            })),
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn connect(&mut self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        if let Ok(mut writer) = self.writer.lock() {
+            for value in split_lines(data) {
+                writer.append(Direction::Write, value);
+            }
+        }
+        self.inner.write(data).await
+    }
+
+    fn read_messages(&mut self) -> Pin<Box<dyn Stream<Item = Result<serde_json::Value>> + Send>> {
+        let writer = Arc::clone(&self.writer);
+        Box::pin(self.inner.read_messages().inspect(move |item| {
+            if let Ok(value) = item
+                && let Ok(mut writer) = writer.lock()
+            {
+                writer.append(Direction::Read, value.clone());
+            }
+        }))
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        self.inner.end_input().await
+    }
+}
+
+/// A write that did not match the cassette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayMismatch {
+    /// Position of the write among all SDK writes (0-based).
+    pub write_index: usize,
+    /// The recorded write, or `None` if the cassette had no more writes.
+    pub expected: Option<serde_json::Value>,
+    /// What the SDK actually wrote.
+    pub actual: serde_json::Value,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    writes_seen: usize,
+    mismatches: Vec<ReplayMismatch>,
+}
+
+/// Handle for checking a [`ReplayTransport`] after the SDK is done with it.
+#[derive(Clone)]
+pub struct ReplayVerifier {
+    state: Arc<Mutex<ReplayState>>,
+    expected_writes: Arc<Vec<serde_json::Value>>,
+}
+
+impl ReplayVerifier {
+    /// Writes that differed from the cassette so far.
+    pub fn mismatches(&self) -> Vec<ReplayMismatch> {
+        self.state
+            .lock()
+            .map(|s| s.mismatches.clone())
+            .unwrap_or_default()
+    }
+
+    /// Recorded writes the SDK has not sent (yet).
+    pub fn pending_writes(&self) -> Vec<serde_json::Value> {
+        let seen = self.state.lock().map(|s| s.writes_seen).unwrap_or(0);
+        self.expected_writes.iter().skip(seen).cloned().collect()
+    }
+
+    /// Fail if any write mismatched or any recorded write was never sent.
+    pub fn verify(&self) -> Result<()> {
+        let mismatches = self.mismatches();
+        if let Some(first) = mismatches.first() {
+            return Err(Error::Other(format!(
+                "Replay mismatch at write {} ({} total): expected {}, got {}",
+                first.write_index,
+                mismatches.len(),
+                first
+                    .expected
+                    .as_ref()
+                    .map_or_else(|| "end of cassette".to_string(), |v| v.to_string()),
+                first.actual
+            )));
+        }
+        let pending = self.pending_writes();
+        if !pending.is_empty() {
+            return Err(Error::Other(format!(
+                "Replay incomplete: {} recorded write(s) never sent, next: {}",
+                pending.len(),
+                pending[0]
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How far the SDK has got through a [`ReplayTransport`].
+#[derive(Debug, Default)]
+struct Progress {
+    writes_seen: usize,
+    input_ended: bool,
+    closed: bool,
+}
+
+/// Transport that plays back a cassette recorded by [`RecordingTransport`].
+///
+/// Recorded reads are delivered in order, each one held back until the SDK
+/// has written every line that preceded it in the recording. Writes are
+/// compared structurally (key order does not matter) against the recorded
+/// writes; use [`verifier`](Self::verifier) to inspect the outcome.
+pub struct ReplayTransport {
+    entries: Arc<Vec<CassetteEntry>>,
+    verifier: ReplayVerifier,
+    progress: watch::Sender<Progress>,
+    ready: bool,
+}
+
+impl ReplayTransport {
+    /// Replay the cassette stored at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_entries(read_cassette(path)?))
+    }
+
+    /// Replay in-memory cassette entries.
+    pub fn from_entries(entries: Vec<CassetteEntry>) -> Self {
+        let expected_writes = entries
+            .iter()
+            .filter(|e| e.direction == Direction::Write)
+            .map(|e| e.data.clone())
+            .collect();
+        let (progress, _) = watch::channel(Progress::default());
+        Self {
+            entries: Arc::new(entries),
+            verifier: ReplayVerifier {
+                state: Arc::new(Mutex::new(ReplayState::default())),
+                expected_writes: Arc::new(expected_writes),
+            },
+            progress,
+            ready: false,
+        }
+    }
+
+    /// Handle for checking the SDK's writes against the cassette.
+    pub fn verifier(&self) -> ReplayVerifier {
+        self.verifier.clone()
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn connect(&mut self) -> Result<()> {
+        self.ready = true;
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        let mut state = self
+            .verifier
+            .state
+            .lock()
+            .map_err(|_| Error::Other("Replay state poisoned".to_string()))?;
+        let mut first_error = None;
+        for actual in split_lines(data) {
+            let write_index = state.writes_seen;
+            let expected = self.verifier.expected_writes.get(write_index).cloned();
+            if expected.as_ref() != Some(&actual) {
+                let mismatch = ReplayMismatch {
+                    write_index,
+                    expected,
+                    actual,
+                };
+                first_error.get_or_insert_with(|| {
+                    Error::Other(format!("Replay mismatch at write {}", mismatch.write_index))
+                });
+                state.mismatches.push(mismatch);
+            }
+            state.writes_seen += 1;
+        }
+        let writes_seen = state.writes_seen;
+        drop(state);
+        self.progress.send_modify(|p| p.writes_seen = writes_seen);
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn read_messages(&mut self) -> Pin<Box<dyn Stream<Item = Result<serde_json::Value>> + Send>> {
+        let entries = Arc::clone(&self.entries);
+        let mut progress = self.progress.subscribe();
+
+        Box::pin(stream! {
+            let mut writes_before = 0;
+            for entry in entries.iter() {
+                if entry.direction == Direction::Write {
+                    writes_before += 1;
+                    continue;
+                }
+                // Reads that wait for writes end once the SDK closes stdin.
+                let released = progress
+                    .wait_for(|p| p.closed || p.input_ended || p.writes_seen >= writes_before)
+                    .await
+                    .map(|p| !p.closed && p.writes_seen >= writes_before)
+                    .unwrap_or(false);
+                if !released {
+                    break;
+                }
+                yield Ok(entry.data.clone());
+            }
+        })
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.ready = false;
+        self.progress.send_modify(|p| p.closed = true);
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        self.progress.send_modify(|p| p.input_ended = true);
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! Run backend CLIs on a remote host over `ssh`.
+//!
+//! [`SshLauncher`] turns a [`LaunchSpec`] into a single remote shell command
+//! (`cd <cwd> && exec env K=V <program> <args>`) and runs it through the
+//! local `ssh` client, reusing [`ProcessTransport`] for the JSON line
+//! plumbing. Paths in the spec (`cwd`, `cli_path`, `add_dirs`) are
+//! interpreted on the remote host.
+//!
+//! The spec's environment, including [`AgentOptions::env`], is part of that
+//! command line by default. Its values are therefore visible to other users
+//! in the process list (`ps`, `/proc/*/cmdline`) of both the local and the
+//! remote host. Keep secrets such as API keys out of it: configure them on
+//! the remote host, or use [`SshLauncher::send_env`] where the server
+//! accepts them.
+//!
+//! [`AgentOptions::env`]: crate::AgentOptions::env
+//!
+//! ```no_run
+//! use code_agent_sdk::transport::SshLauncher;
+//! use code_agent_sdk::{AgentOptions, BackendKind};
+//!
+//! let options = AgentOptions::builder()
+//!     .backend(BackendKind::Codex)
+//!     .cwd("/srv/checkout")
+//!     .launcher(SshLauncher::new("build-box").user("ci").port(2222))
+//!     .build();
+//! ```
+
+use crate::error::Result;
+use crate::transport::{LaunchSpec, ProcessLauncher, ProcessTransport, Transport};
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+/// Launches CLIs on a remote host through the local `ssh` client.
+///
+/// Authentication is left to `ssh` (agent, keys, `~/.ssh/config`); the
+/// launcher runs in batch mode so a missing credential fails instead of
+/// prompting. The remote exit code and stderr surface as
+/// [`Error::Process`](crate::Error::Process); `ssh` itself exits with 255
+/// when the connection fails.
+#[derive(Debug, Clone)]
+pub struct SshLauncher {
+    host: String,
+    user: Option<String>,
+    port: Option<u16>,
+    identity_file: Option<PathBuf>,
+    options: Vec<(String, String)>,
+    ssh_program: String,
+    send_env: bool,
+}
+
+impl SshLauncher {
+    /// Launch on `host` (a hostname or an alias from `~/.ssh/config`).
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            user: None,
+            port: None,
+            identity_file: None,
+            options: Vec::new(),
+            ssh_program: "ssh".to_string(),
+            send_env: false,
+        }
+    }
+
+    /// Log in as `user`.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Connect to `port` instead of the default.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Authenticate with this private key (`ssh -i`).
+    pub fn identity_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.identity_file = Some(path.into());
+        self
+    }
+
+    /// Pass an `ssh -o key=value` option.
+    pub fn option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.push((key.into(), value.into()));
+        self
+    }
+
+    /// Use a different local `ssh` binary.
+    pub fn ssh_program(mut self, program: impl Into<String>) -> Self {
+        self.ssh_program = program.into();
+        self
+    }
+
+    /// Forward the spec's environment with `ssh -o SendEnv=NAME` instead of
+    /// on the remote command line, so values stay out of process lists.
+    ///
+    /// The values are set in the local `ssh` process's environment. The
+    /// server must list each name in `AcceptEnv`; `sshd` silently drops
+    /// the others.
+    pub fn send_env(mut self) -> Self {
+        self.send_env = true;
+        self
+    }
+
+    /// The local `ssh` invocation that runs `spec` remotely.
+    pub fn ssh_spec(&self, spec: &LaunchSpec) -> LaunchSpec {
+        let mut args = vec![
+            "-T".to_string(),
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+        ];
+        if let Some(port) = self.port {
+            args.push("-p".to_string());
+            args.push(port.to_string());
+        }
+        if let Some(ref identity) = self.identity_file {
+            args.push("-i".to_string());
+            args.push(identity.to_string_lossy().to_string());
+        }
+        for (key, value) in &self.options {
+            args.push("-o".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+        if self.send_env {
+            for (key, _) in &spec.env {
+                args.push("-o".to_string());
+                args.push(format!("SendEnv={}", key));
+            }
+        }
+        args.push(match self.user {
+            Some(ref user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        });
+        args.push("--".to_string());
+        args.push(remote_command(spec, !self.send_env));
+
+        let mut ssh = LaunchSpec::new(self.ssh_program.clone())
+            .args(args)
+            .stderr(spec.stderr.clone())
+            .max_buffer_size(spec.max_buffer_size);
+        if self.send_env {
+            ssh.env = spec.env.clone();
+        }
+        ssh
+    }
+}
+
+#[async_trait]
+impl ProcessLauncher for SshLauncher {
+    async fn launch(&self, spec: LaunchSpec) -> Result<Box<dyn Transport + Send>> {
+        let mut transport = ProcessTransport::new(self.ssh_spec(&spec));
+        transport.connect().await?;
+        Ok(Box::new(transport))
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+/// Shell command line for `spec`, quoted for a POSIX remote shell. With
+/// `inline_env`, the environment is set by `env K=V` on the command line.
+fn remote_command(spec: &LaunchSpec, inline_env: bool) -> String {
+    let mut parts = Vec::new();
+    if let Some(ref cwd) = spec.cwd {
+        parts.push("cd".to_string());
+        parts.push(shell_quote(&cwd.to_string_lossy()));
+        parts.push("&&".to_string());
+    }
+    parts.push("exec".to_string());
+    if inline_env && !spec.env.is_empty() {
+        parts.push("env".to_string());
+        for (key, value) in &spec.env {
+            parts.push(shell_quote(&format!("{}={}", key, value)));
+        }
+    }
+    parts.push(shell_quote(&spec.program));
+    parts.extend(spec.args.iter().map(|a| shell_quote(a)));
+    parts.join(" ")
+}
+
+/// Quote `s` as a single word for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    let is_plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    if is_plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
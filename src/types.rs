@@ -1,5 +1,7 @@
 //! Type definitions for Code Agent SDK.
 
+use crate::backend::claude::message_parser::{message_to_json, parse_message};
+use crate::usage::Usage;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
@@ -28,7 +30,9 @@ pub struct ToolUseBlock {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolResultBlock {
     pub tool_use_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub content: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
 }
 
@@ -86,7 +90,7 @@ pub struct ResultMessage {
     pub num_turns: u32,
     pub session_id: String,
     pub total_cost_usd: Option<f64>,
-    pub usage: Option<serde_json::Value>,
+    pub usage: Option<Usage>,
     pub result: Option<String>,
     pub structured_output: Option<serde_json::Value>,
 }
@@ -95,10 +99,199 @@ pub struct ResultMessage {
 pub struct StreamEvent {
     pub uuid: String,
     pub session_id: String,
+    /// Raw stream event in the Anthropic Messages streaming format.
     pub event: serde_json::Value,
     pub parent_tool_use_id: Option<String>,
 }
 
+impl StreamEvent {
+    /// The event as a typed [`StreamDelta`], if it is a known event type.
+    pub fn delta(&self) -> Option<StreamDelta> {
+        StreamDelta::from_event(&self.event)
+    }
+}
+
+// ============ Streaming Deltas ============
+
+/// The kind of content block opened by [`StreamDelta::ContentBlockStart`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamBlockStart {
+    Text { text: String },
+    Thinking { thinking: String },
+    ToolUse { id: String, name: String },
+}
+
+/// Typed partial-message event, shared by all backends.
+///
+/// With `include_partial_messages`, every backend emits
+/// [`Message::StreamEvent`]s whose `event` follows the Anthropic Messages
+/// streaming format; Codex and Cursor partial output is normalized into the
+/// same shape. A message is `MessageStart`, then for each block
+/// `ContentBlockStart`, deltas and `ContentBlockStop`, then `MessageDelta`
+/// and `MessageStop`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamDelta {
+    MessageStart {
+        id: Option<String>,
+        model: Option<String>,
+    },
+    ContentBlockStart {
+        index: usize,
+        block: StreamBlockStart,
+    },
+    TextDelta {
+        index: usize,
+        text: String,
+    },
+    ThinkingDelta {
+        index: usize,
+        thinking: String,
+    },
+    SignatureDelta {
+        index: usize,
+        signature: String,
+    },
+    /// A fragment of a tool call's JSON input.
+    InputJsonDelta {
+        index: usize,
+        partial_json: String,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        stop_reason: Option<String>,
+        usage: Option<serde_json::Value>,
+    },
+    MessageStop,
+}
+
+impl StreamDelta {
+    /// Parse a raw stream event. Returns `None` for unknown event types.
+    pub fn from_event(event: &serde_json::Value) -> Option<Self> {
+        let str_field = |v: &serde_json::Value, key: &str| {
+            v.get(key).and_then(|v| v.as_str()).map(String::from)
+        };
+        let index = event
+            .get("index")
+            .and_then(|v| v.as_u64())
+            .unwrap_or_default() as usize;
+
+        match event.get("type")?.as_str()? {
+            "message_start" => {
+                let message = event.get("message").unwrap_or(&serde_json::Value::Null);
+                Some(Self::MessageStart {
+                    id: str_field(message, "id"),
+                    model: str_field(message, "model"),
+                })
+            }
+            "content_block_start" => {
+                let block = event.get("content_block")?;
+                let block = match block.get("type")?.as_str()? {
+                    "text" => StreamBlockStart::Text {
+                        text: str_field(block, "text").unwrap_or_default(),
+                    },
+                    "thinking" => StreamBlockStart::Thinking {
+                        thinking: str_field(block, "thinking").unwrap_or_default(),
+                    },
+                    "tool_use" | "server_tool_use" => StreamBlockStart::ToolUse {
+                        id: str_field(block, "id").unwrap_or_default(),
+                        name: str_field(block, "name").unwrap_or_default(),
+                    },
+                    _ => return None,
+                };
+                Some(Self::ContentBlockStart { index, block })
+            }
+            "content_block_delta" => {
+                let delta = event.get("delta")?;
+                match delta.get("type")?.as_str()? {
+                    "text_delta" => Some(Self::TextDelta {
+                        index,
+                        text: str_field(delta, "text")?,
+                    }),
+                    "thinking_delta" => Some(Self::ThinkingDelta {
+                        index,
+                        thinking: str_field(delta, "thinking")?,
+                    }),
+                    "signature_delta" => Some(Self::SignatureDelta {
+                        index,
+                        signature: str_field(delta, "signature")?,
+                    }),
+                    "input_json_delta" => Some(Self::InputJsonDelta {
+                        index,
+                        partial_json: str_field(delta, "partial_json")?,
+                    }),
+                    _ => None,
+                }
+            }
+            "content_block_stop" => Some(Self::ContentBlockStop { index }),
+            "message_delta" => Some(Self::MessageDelta {
+                stop_reason: event.get("delta").and_then(|d| str_field(d, "stop_reason")),
+                usage: event.get("usage").cloned(),
+            }),
+            "message_stop" => Some(Self::MessageStop),
+            _ => None,
+        }
+    }
+
+    /// Encode as a raw stream event (the inverse of [`from_event`](Self::from_event)).
+    pub fn to_event(&self) -> serde_json::Value {
+        use serde_json::json;
+        match self {
+            Self::MessageStart { id, model } => json!({
+                "type": "message_start",
+                "message": {"id": id, "type": "message", "role": "assistant", "model": model, "content": []}
+            }),
+            Self::ContentBlockStart { index, block } => {
+                let block = match block {
+                    StreamBlockStart::Text { text } => json!({"type": "text", "text": text}),
+                    StreamBlockStart::Thinking { thinking } => {
+                        json!({"type": "thinking", "thinking": thinking})
+                    }
+                    StreamBlockStart::ToolUse { id, name } => {
+                        json!({"type": "tool_use", "id": id, "name": name, "input": {}})
+                    }
+                };
+                json!({"type": "content_block_start", "index": index, "content_block": block})
+            }
+            Self::TextDelta { index, text } => json!({
+                "type": "content_block_delta", "index": index,
+                "delta": {"type": "text_delta", "text": text}
+            }),
+            Self::ThinkingDelta { index, thinking } => json!({
+                "type": "content_block_delta", "index": index,
+                "delta": {"type": "thinking_delta", "thinking": thinking}
+            }),
+            Self::SignatureDelta { index, signature } => json!({
+                "type": "content_block_delta", "index": index,
+                "delta": {"type": "signature_delta", "signature": signature}
+            }),
+            Self::InputJsonDelta {
+                index,
+                partial_json,
+            } => json!({
+                "type": "content_block_delta", "index": index,
+                "delta": {"type": "input_json_delta", "partial_json": partial_json}
+            }),
+            Self::ContentBlockStop { index } => {
+                json!({"type": "content_block_stop", "index": index})
+            }
+            Self::MessageDelta { stop_reason, usage } => json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": stop_reason},
+                "usage": usage
+            }),
+            Self::MessageStop => json!({"type": "message_stop"}),
+        }
+    }
+}
+
+/// A message from any backend.
+///
+/// Serializes in the Claude CLI's `stream-json` format, tagged by `type`
+/// (see [`message_to_json`]), and deserializes through [`parse_message`],
+/// so persisted messages round-trip and recorded Claude output can be read
+/// directly.
 #[derive(Debug, Clone)]
 pub enum Message {
     User(UserMessage),
@@ -108,6 +301,28 @@ pub enum Message {
     StreamEvent(StreamEvent),
 }
 
+impl Serialize for Message {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        message_to_json(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as _;
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        parse_message(&value)
+            .map_err(D::Error::custom)?
+            .ok_or_else(|| {
+                D::Error::custom(format!(
+                    "unknown message type {}",
+                    value.get("type").unwrap_or(&serde_json::Value::Null)
+                ))
+            })
+    }
+}
+
 /// Prompt type supporting both string and async stream inputs.
 ///
 /// Matches the Python SDK's `str | AsyncIterable` parameter type.
@@ -0,0 +1,156 @@
+//! Token usage and cost estimation.
+//!
+//! Every backend reports token counts in its own shape; they are normalized
+//! into [`Usage`] on [`ResultMessage::usage`](crate::types::ResultMessage::usage).
+//! Claude also reports `total_cost_usd`. For the other backends, set a
+//! [`PriceTable`] on [`AgentOptions::price_table`](crate::options::AgentOptions::price_table)
+//! and the SDK fills `total_cost_usd` in from the usage and the turn's model:
+//!
+//! ```
+//! use code_agent_sdk::{AgentOptions, BackendKind, ModelPrice, PriceTable};
+//!
+//! let prices = PriceTable::new()
+//!     .price("gpt-5", ModelPrice::new(1.25, 10.0).cache_read(0.125))
+//!     .price("gpt-5-mini", ModelPrice::new(0.25, 2.0).cache_read(0.025));
+//!
+//! let options = AgentOptions::builder()
+//!     .backend(BackendKind::Codex)
+//!     .model("gpt-5-mini")
+//!     .price_table(prices)
+//!     .build();
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::{Add, AddAssign};
+
+/// Token counts for one turn, normalized across backends.
+///
+/// The counts are disjoint: `input_tokens` excludes cached input, so the
+/// total prompt size is `input_tokens + cache_read_input_tokens +
+/// cache_creation_input_tokens`. `reasoning_output_tokens` is the part of
+/// `output_tokens` spent on reasoning, and is zero when not reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    /// Uncached input tokens.
+    pub input_tokens: u64,
+    /// Output tokens, including reasoning.
+    pub output_tokens: u64,
+    /// Input tokens served from the prompt cache.
+    pub cache_read_input_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    pub cache_creation_input_tokens: u64,
+    /// Output tokens spent on reasoning.
+    pub reasoning_output_tokens: u64,
+}
+
+impl Usage {
+    /// All input and output tokens.
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.cache_read_input_tokens
+            + self.cache_creation_input_tokens
+            + self.output_tokens
+    }
+}
+
+impl Add for Usage {
+    type Output = Usage;
+
+    fn add(mut self, rhs: Usage) -> Usage {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, rhs: Usage) {
+        self.input_tokens += rhs.input_tokens;
+        self.output_tokens += rhs.output_tokens;
+        self.cache_read_input_tokens += rhs.cache_read_input_tokens;
+        self.cache_creation_input_tokens += rhs.cache_creation_input_tokens;
+        self.reasoning_output_tokens += rhs.reasoning_output_tokens;
+    }
+}
+
+/// Per-model prices in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    pub cache_read_per_mtok: f64,
+    pub cache_write_per_mtok: f64,
+}
+
+impl ModelPrice {
+    /// Input and output prices; cached input is billed as regular input
+    /// unless overridden.
+    pub fn new(input_per_mtok: f64, output_per_mtok: f64) -> Self {
+        Self {
+            input_per_mtok,
+            output_per_mtok,
+            cache_read_per_mtok: input_per_mtok,
+            cache_write_per_mtok: input_per_mtok,
+        }
+    }
+
+    /// Price for input read from the prompt cache.
+    pub fn cache_read(mut self, per_mtok: f64) -> Self {
+        self.cache_read_per_mtok = per_mtok;
+        self
+    }
+
+    /// Price for input written to the prompt cache.
+    pub fn cache_write(mut self, per_mtok: f64) -> Self {
+        self.cache_write_per_mtok = per_mtok;
+        self
+    }
+
+    /// Cost of `usage` in USD.
+    pub fn cost_usd(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input_per_mtok
+            + usage.output_tokens as f64 * self.output_per_mtok
+            + usage.cache_read_input_tokens as f64 * self.cache_read_per_mtok
+            + usage.cache_creation_input_tokens as f64 * self.cache_write_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Model prices used to estimate cost for backends that don't report it.
+///
+/// Entries are keyed by model name prefix; the longest matching prefix wins,
+/// so `"gpt-5"` covers dated snapshots while `"gpt-5-mini"` can still be
+/// priced separately.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriceTable {
+    prices: BTreeMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    /// An empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Price models starting with `model_prefix`.
+    pub fn price(mut self, model_prefix: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(model_prefix.into(), price);
+        self
+    }
+
+    /// The price for `model`, if any entry matches.
+    pub fn lookup(&self, model: &str) -> Option<&ModelPrice> {
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| price)
+    }
+
+    /// Estimated cost of `usage` on `model` in USD.
+    pub fn cost_usd(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.lookup(model).map(|price| price.cost_usd(usage))
+    }
+}
@@ -0,0 +1,38 @@
+//! Cassette fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use code_agent_sdk::transport::CassetteEntry;
+use serde_json::{Value, json};
+
+/// A successful `control_response` to `request_id`.
+pub fn control_response(request_id: &str, response: Value) -> Value {
+    json!({
+        "type": "control_response",
+        "response": {"subtype": "success", "request_id": request_id, "response": response}
+    })
+}
+
+/// The initialize handshake of a Claude session, answered with `response`.
+pub fn claude_initialize(response: Value) -> Vec<CassetteEntry> {
+    vec![
+        CassetteEntry::write(json!({
+            "type": "control_request",
+            "request_id": "req_0_00000000",
+            "request": {"subtype": "initialize", "hooks": null, "agents": null}
+        })),
+        CassetteEntry::read(control_response("req_0_00000000", response)),
+    ]
+}
+
+/// The initialize handshake of a Codex app-server session.
+pub fn codex_handshake() -> Vec<CassetteEntry> {
+    vec![
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": {"clientName": "code-agent-sdk", "clientVersion": env!("CARGO_PKG_VERSION")}
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}})),
+    ]
+}
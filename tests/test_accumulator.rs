@@ -0,0 +1,176 @@
+use code_agent_sdk::accumulator::{MessageAccumulator, PartialEvent, accumulate};
+use code_agent_sdk::{
+    AssistantMessage, ContentBlock, Error, Message, StreamBlockStart, StreamDelta, StreamEvent,
+    TextBlock,
+};
+use futures::StreamExt;
+use serde_json::json;
+
+fn event(delta: StreamDelta) -> code_agent_sdk::Result<Message> {
+    Ok(Message::StreamEvent(StreamEvent {
+        uuid: "u".to_string(),
+        session_id: "s".to_string(),
+        event: delta.to_event(),
+        parent_tool_use_id: Some("parent-1".to_string()),
+    }))
+}
+
+fn tool_use_stream() -> Vec<code_agent_sdk::Result<Message>> {
+    vec![
+        event(StreamDelta::MessageStart {
+            id: Some("msg_1".to_string()),
+            model: Some("claude-sonnet-4-5".to_string()),
+        }),
+        event(StreamDelta::ContentBlockStart {
+            index: 0,
+            block: StreamBlockStart::Text {
+                text: String::new(),
+            },
+        }),
+        event(StreamDelta::TextDelta {
+            index: 0,
+            text: "Listing".to_string(),
+        }),
+        event(StreamDelta::ContentBlockStop { index: 0 }),
+        event(StreamDelta::ContentBlockStart {
+            index: 1,
+            block: StreamBlockStart::ToolUse {
+                id: "tu_1".to_string(),
+                name: "Bash".to_string(),
+            },
+        }),
+        event(StreamDelta::InputJsonDelta {
+            index: 1,
+            partial_json: r#"{"command": "ls -"#.to_string(),
+        }),
+        event(StreamDelta::InputJsonDelta {
+            index: 1,
+            partial_json: r#"la", "timeout": 5"#.to_string(),
+        }),
+        event(StreamDelta::InputJsonDelta {
+            index: 1,
+            partial_json: "}".to_string(),
+        }),
+        event(StreamDelta::ContentBlockStop { index: 1 }),
+        event(StreamDelta::MessageDelta {
+            stop_reason: Some("tool_use".to_string()),
+            usage: None,
+        }),
+        event(StreamDelta::MessageStop),
+        Ok(Message::Assistant(AssistantMessage {
+            content: vec![ContentBlock::Text(TextBlock {
+                text: "Listing".to_string(),
+            })],
+            model: "claude-sonnet-4-5".to_string(),
+            parent_tool_use_id: None,
+            error: None,
+        })),
+    ]
+}
+
+#[tokio::test]
+async fn test_should_assemble_snapshots_and_completed_blocks() {
+    let events: Vec<PartialEvent> = accumulate(futures::stream::iter(tool_use_stream()))
+        .map(|e| e.expect("no errors expected"))
+        .collect()
+        .await;
+
+    let snapshots: Vec<&AssistantMessage> = events
+        .iter()
+        .filter_map(|e| match e {
+            PartialEvent::Snapshot(m) => Some(m),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(snapshots.len(), 6);
+    assert!(
+        snapshots.iter().all(|s| s.model == "claude-sonnet-4-5"
+            && s.parent_tool_use_id.as_deref() == Some("parent-1"))
+    );
+    assert!(matches!(&snapshots[1].content[..], [ContentBlock::Text(t)] if t.text == "Listing"));
+
+    // Tool input is parsed as it streams in.
+    let inputs: Vec<_> = snapshots[3..]
+        .iter()
+        .map(|s| match &s.content[1] {
+            ContentBlock::ToolUse(t) => t.input.clone(),
+            other => panic!("expected tool use, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        inputs,
+        [
+            json!({"command": "ls -"}),
+            json!({"command": "ls -la", "timeout": 5}),
+            json!({"command": "ls -la", "timeout": 5}),
+        ]
+    );
+
+    let completed: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            PartialEvent::BlockComplete { index, block } => Some((*index, block)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(completed.len(), 2);
+    assert!(matches!(completed[0], (0, ContentBlock::Text(t)) if t.text == "Listing"));
+    assert!(matches!(
+        completed[1],
+        (1, ContentBlock::ToolUse(t)) if t.id == "tu_1" && t.name == "Bash" && t.input["command"] == "ls -la"
+    ));
+
+    // The backend's final message is passed through untouched.
+    assert!(matches!(
+        events.last(),
+        Some(PartialEvent::Message(Message::Assistant(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_should_pass_through_errors_and_other_messages() {
+    let input = vec![
+        Ok(Message::StreamEvent(StreamEvent {
+            uuid: "u".to_string(),
+            session_id: "s".to_string(),
+            event: json!({"type": "ping"}),
+            parent_tool_use_id: None,
+        })),
+        Err(Error::Other("boom".to_string())),
+    ];
+    let events: Vec<_> = accumulate(futures::stream::iter(input)).collect().await;
+
+    assert!(matches!(
+        &events[0],
+        Ok(PartialEvent::Message(Message::StreamEvent(_)))
+    ));
+    assert!(matches!(&events[1], Err(Error::Other(m)) if m == "boom"));
+}
+
+#[test]
+fn test_should_open_implicit_blocks_for_unstarted_deltas() {
+    let mut accumulator = MessageAccumulator::new();
+    assert!(accumulator.snapshot().is_none());
+
+    accumulator.apply(&StreamDelta::ThinkingDelta {
+        index: 0,
+        thinking: "Hmm".to_string(),
+    });
+    accumulator.apply(&StreamDelta::SignatureDelta {
+        index: 0,
+        signature: "sig".to_string(),
+    });
+    accumulator.apply(&StreamDelta::TextDelta {
+        index: 1,
+        text: "Hi".to_string(),
+    });
+
+    let snapshot = accumulator.snapshot().expect("message in progress");
+    assert!(
+        matches!(&snapshot.content[0], ContentBlock::Thinking(t) if t.thinking == "Hmm" && t.signature == "sig")
+    );
+    assert!(matches!(&snapshot.content[1], ContentBlock::Text(t) if t.text == "Hi"));
+
+    accumulator.apply(&StreamDelta::MessageStop);
+    assert!(accumulator.snapshot().is_none());
+}
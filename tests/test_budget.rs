@@ -0,0 +1,263 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, Direction, ReplayTransport};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, BudgetLimit, Error, Message, MockScript, MockTurn,
+    ModelPrice, PriceTable, ResultMessage, Usage,
+};
+use futures::StreamExt;
+use serde_json::json;
+
+use common::{claude_initialize, codex_handshake};
+
+fn result(cost: Option<f64>, input_tokens: u64, output_tokens: u64) -> Message {
+    Message::Result(ResultMessage {
+        subtype: "success".to_string(),
+        duration_ms: 0,
+        duration_api_ms: 0,
+        is_error: false,
+        num_turns: 1,
+        session_id: "s-1".to_string(),
+        total_cost_usd: cost,
+        usage: Some(Usage {
+            input_tokens,
+            output_tokens,
+            ..Default::default()
+        }),
+        result: None,
+        structured_output: None,
+    })
+}
+
+async fn run_turn(client: &mut AgentSdkClient, prompt: &str) {
+    client.query(prompt, "default").await.expect("query failed");
+    let messages: Vec<_> = client.receive_response().collect().await;
+    assert!(matches!(messages.last(), Some(Ok(Message::Result(_)))));
+}
+
+#[tokio::test]
+async fn test_should_refuse_queries_after_token_ceiling() {
+    let script = MockScript::new()
+        .turn(MockTurn::new().text("one").message(result(None, 400, 200)))
+        .turn(MockTurn::new().text("two").message(result(None, 300, 300)))
+        .turn(MockTurn::new().text("three").result("s-1"));
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Mock)
+        .mock(script)
+        .max_budget_tokens(1000)
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    run_turn(&mut client, "first").await;
+    run_turn(&mut client, "second").await;
+
+    let err = client.query("third", "default").await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::BudgetExceeded(BudgetLimit::Tokens {
+                limit: 1000,
+                used: 1200
+            })
+        ),
+        "{err:?}"
+    );
+    assert_eq!(client.total_usage().input_tokens, 700);
+    assert_eq!(client.total_usage().output_tokens, 500);
+}
+
+#[tokio::test]
+async fn test_should_refuse_queries_after_dollar_ceiling() {
+    let script = MockScript::new()
+        .turn(MockTurn::new().message(result(Some(0.3), 10, 10)))
+        .turn(MockTurn::new().message(result(Some(0.25), 10, 10)))
+        .turn(MockTurn::new().result("s-1"));
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Mock)
+        .mock(script)
+        .max_budget_usd(0.5)
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    run_turn(&mut client, "first").await;
+    run_turn(&mut client, "second").await;
+
+    let err = client.query("third", "default").await.unwrap_err();
+    match err {
+        Error::BudgetExceeded(BudgetLimit::Usd { limit, spent }) => {
+            assert_eq!(limit, 0.5);
+            assert!((spent - 0.55).abs() < 1e-9);
+        }
+        other => panic!("expected BudgetExceeded, got {other:?}"),
+    }
+    assert!((client.total_cost_usd() - 0.55).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_should_count_each_codex_turn_once_across_streams() {
+    let mut entries = codex_handshake();
+    entries.extend([
+        CassetteEntry::write(
+            json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {"model": "gpt-5"}}),
+        ),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "t-1"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "t-1", "input": [{"role": "user", "content": "hi"}]}
+        })),
+        CassetteEntry {
+            elapsed_ms: 50,
+            direction: Direction::Read,
+            data: json!({
+                "jsonrpc": "2.0", "method": "turn/completed",
+                "params": {"threadId": "t-1", "usage": {"inputTokens": 100_000, "outputTokens": 20_000}}
+            }),
+        },
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .model("gpt-5")
+        .price_table(PriceTable::new().price("gpt-5", ModelPrice::new(1.25, 10.0)))
+        .max_budget_usd(0.3)
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("hi", "default").await.expect("query failed");
+    {
+        let all = client
+            .receive_messages()
+            .take_while(|m| futures::future::ready(!matches!(m, Ok(Message::Result(_)))))
+            .collect::<Vec<_>>();
+        let response = client.receive_response().collect::<Vec<_>>();
+        let _ = tokio::join!(all, response);
+    }
+
+    // 100k * $1.25/M + 20k * $10/M, counted once.
+    assert!((client.total_cost_usd() - 0.325).abs() < 1e-9);
+    assert_eq!(client.total_usage().total_tokens(), 120_000);
+    assert!(matches!(
+        client.query("again", "default").await,
+        Err(Error::BudgetExceeded(BudgetLimit::Usd { .. }))
+    ));
+    client.disconnect().await.expect("disconnect failed");
+}
+
+#[tokio::test]
+async fn test_should_require_price_table_for_codex_dollar_budget() {
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .max_budget_usd(1.0)
+        .build();
+    let replay = ReplayTransport::from_entries(Vec::new());
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    match client.connect(None).await {
+        Err(Error::UnsupportedOptions { backend, options }) => {
+            assert_eq!(backend, "Codex");
+            assert_eq!(options, ["max_budget_usd (requires price_table)"]);
+        }
+        other => panic!("expected UnsupportedOptions, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_should_count_claude_running_cost_once() {
+    let user = |content: &str| {
+        CassetteEntry::write(json!({
+            "type": "user",
+            "session_id": "default",
+            "message": {"role": "user", "content": content},
+            "parent_tool_use_id": null
+        }))
+    };
+    let claude_result = |total_cost_usd: f64| {
+        CassetteEntry::read(json!({
+            "type": "result", "subtype": "success", "duration_ms": 0, "duration_api_ms": 0,
+            "is_error": false, "num_turns": 1, "session_id": "s-1",
+            "total_cost_usd": total_cost_usd,
+            "usage": {"input_tokens": 10, "output_tokens": 10}
+        }))
+    };
+    let mut entries = claude_initialize(json!({}));
+    entries.extend([
+        user("first"),
+        claude_result(0.3),
+        user("second"),
+        claude_result(0.45),
+        user("third"),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let options = AgentOptions::builder().max_budget_usd(0.5).build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    run_turn(&mut client, "first").await;
+    run_turn(&mut client, "second").await;
+
+    // The second result reports $0.45 for the session so far, not per turn.
+    assert!((client.total_cost_usd() - 0.45).abs() < 1e-9);
+    assert_eq!(client.total_usage().total_tokens(), 40);
+    client
+        .query("third", "default")
+        .await
+        .expect("under budget");
+    client.disconnect().await.expect("disconnect failed");
+}
+
+#[tokio::test]
+async fn test_should_count_each_turn_once_with_two_turns_in_flight() {
+    let turn_start = |id: u64, content: &str| {
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": id, "method": "turn/start",
+            "params": {"threadId": "t-1", "input": [{"role": "user", "content": content}]}
+        }))
+    };
+    let turn_completed = |input_tokens: u64| {
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {"threadId": "t-1", "usage": {"inputTokens": input_tokens, "outputTokens": 0}}
+        }))
+    };
+    let mut entries = codex_handshake();
+    entries.extend([
+        CassetteEntry::write(
+            json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {"model": "gpt-5"}}),
+        ),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "t-1"}})),
+        turn_start(3, "one"),
+        turn_start(4, "two"),
+        turn_completed(100_000),
+        turn_completed(200_000),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .model("gpt-5")
+        .price_table(PriceTable::new().price("gpt-5", ModelPrice::new(1.25, 10.0)))
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("one", "default").await.expect("query failed");
+    client.query("two", "default").await.expect("query failed");
+    {
+        // Both streams see the first result while the second turn is pending.
+        let all = client
+            .receive_messages()
+            .filter(|m| futures::future::ready(matches!(m, Ok(Message::Result(_)))))
+            .take(2)
+            .collect::<Vec<_>>();
+        let response = client.receive_response().collect::<Vec<_>>();
+        let (all, _) = tokio::join!(all, response);
+        assert_eq!(all.len(), 2);
+    }
+
+    // 100k and 200k input tokens at $1.25/M, each counted once.
+    assert!((client.total_cost_usd() - 0.375).abs() < 1e-9);
+    assert_eq!(client.total_usage().total_tokens(), 300_000);
+    client.disconnect().await.expect("disconnect failed");
+}
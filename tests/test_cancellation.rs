@@ -0,0 +1,172 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, ReplayTransport, Transport};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, CancellationToken, PermissionResult, PermissionResultDeny,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use common::{claude_initialize, control_response};
+
+/// Initialize, send "rm -rf build" and receive a permission request for it.
+fn permission_request_entries() -> Vec<CassetteEntry> {
+    let mut entries = claude_initialize(json!({}));
+    entries.extend([
+        CassetteEntry::write(json!({
+            "type": "user",
+            "session_id": "default",
+            "message": {"role": "user", "content": "rm -rf build"},
+            "parent_tool_use_id": null
+        })),
+        CassetteEntry::read(json!({
+            "type": "control_request",
+            "request_id": "cli_req_0",
+            "request": {
+                "subtype": "can_use_tool",
+                "tool_name": "Bash",
+                "input": {"command": "rm -rf build"}
+            }
+        })),
+    ]);
+    entries
+}
+
+fn result_message() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 10,
+        "duration_api_ms": 5,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "session_123"
+    })
+}
+
+/// A `can_use_tool` callback that reports its signal and waits for it to
+/// be cancelled, then denies.
+fn waiting_for_cancel(signals: mpsc::UnboundedSender<CancellationToken>) -> AgentOptions {
+    AgentOptions::builder()
+        .can_use_tool(Arc::new(move |_tool, _input, ctx| {
+            let _ = signals.send(ctx.signal.clone());
+            Box::pin(async move {
+                ctx.signal.cancelled().await;
+                PermissionResult::Deny(PermissionResultDeny {
+                    message: "cancelled".to_string(),
+                    interrupt: false,
+                })
+            })
+        }))
+        .build()
+}
+
+async fn connected_client(options: AgentOptions, mut replay: ReplayTransport) -> AgentSdkClient {
+    replay.connect().await.expect("transport connect failed");
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client
+}
+
+async fn next_signal(
+    signals: &mut mpsc::UnboundedReceiver<CancellationToken>,
+) -> CancellationToken {
+    tokio::time::timeout(Duration::from_secs(2), signals.recv())
+        .await
+        .expect("callback was not invoked")
+        .unwrap()
+}
+
+async fn assert_cancelled(signal: &CancellationToken) {
+    tokio::time::timeout(Duration::from_secs(2), signal.cancelled())
+        .await
+        .expect("signal was not cancelled");
+}
+
+#[tokio::test]
+async fn test_should_cancel_callback_on_control_cancel_request() {
+    let mut entries = permission_request_entries();
+    entries.extend([
+        // Released once the callback is running.
+        CassetteEntry::write(json!({
+            "type": "control_request",
+            "request_id": "req_1_9e3779b1",
+            "request": {"subtype": "set_model", "model": "opus"}
+        })),
+        CassetteEntry::read(json!({"type": "control_cancel_request", "request_id": "cli_req_0"})),
+        CassetteEntry::read(control_response("req_1_9e3779b1", json!({}))),
+        CassetteEntry::read(result_message()),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    let (signals_tx, mut signals) = mpsc::unbounded_channel();
+
+    let mut client = connected_client(waiting_for_cancel(signals_tx), replay).await;
+    client.query("rm -rf build", "default").await.unwrap();
+    let signal = next_signal(&mut signals).await;
+    assert!(!signal.is_cancelled());
+
+    client.set_model(Some("opus")).await.unwrap();
+    assert_cancelled(&signal).await;
+    let messages: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(messages.len(), 1);
+
+    // A withdrawn request gets no response.
+    client.disconnect().await.unwrap();
+    verifier.verify().unwrap();
+}
+
+#[tokio::test]
+async fn test_should_cancel_callback_on_interrupt() {
+    let mut entries = permission_request_entries();
+    entries.extend([
+        CassetteEntry::write(json!({
+            "type": "control_request",
+            "request_id": "req_1_9e3779b1",
+            "request": {"subtype": "interrupt"}
+        })),
+        CassetteEntry::read(control_response("req_1_9e3779b1", json!({}))),
+        CassetteEntry::write(control_response(
+            "cli_req_0",
+            json!({"behavior": "deny", "message": "cancelled"}),
+        )),
+        CassetteEntry::read(result_message()),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    let (signals_tx, mut signals) = mpsc::unbounded_channel();
+
+    let mut client = connected_client(waiting_for_cancel(signals_tx), replay).await;
+    client.query("rm -rf build", "default").await.unwrap();
+    let signal = next_signal(&mut signals).await;
+
+    client.interrupt().await.unwrap();
+    assert_cancelled(&signal).await;
+    let messages: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(messages.len(), 1);
+
+    client.disconnect().await.unwrap();
+    verifier.verify().unwrap();
+}
+
+#[tokio::test]
+async fn test_should_cancel_callback_on_close() {
+    let mut entries = permission_request_entries();
+    // Keeps the CLI's stdout open until the SDK closes stdin.
+    entries.extend([
+        CassetteEntry::write(json!({"type": "never_sent"})),
+        CassetteEntry::read(result_message()),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let (signals_tx, mut signals) = mpsc::unbounded_channel();
+
+    let mut client = connected_client(waiting_for_cancel(signals_tx), replay).await;
+    client.query("rm -rf build", "default").await.unwrap();
+    let signal = next_signal(&mut signals).await;
+
+    client.disconnect().await.unwrap();
+    assert_cancelled(&signal).await;
+}
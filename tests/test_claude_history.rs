@@ -0,0 +1,203 @@
+use code_agent_sdk::{ClaudeHistory, ContentBlock, Message, ModelPrice, PriceTable, UserContent};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+
+const CWD: &str = "/work/my.project";
+
+/// A history directory unique to this test, holding `sessions` for [`CWD`].
+fn history(name: &str, sessions: &[(&str, Vec<Value>)]) -> ClaudeHistory {
+    let root: PathBuf = std::env::temp_dir().join(format!(
+        "code-agent-sdk-{}-history-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_dir_all(&root);
+    let history = ClaudeHistory::with_projects_dir(&root);
+    let dir = history.project_dir(CWD);
+    assert_eq!(dir, root.join("-work-my-project"));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (id, entries) in sessions {
+        let lines: Vec<_> = entries.iter().map(Value::to_string).collect();
+        std::fs::write(dir.join(format!("{}.jsonl", id)), lines.join("\n") + "\n").unwrap();
+    }
+    history
+}
+
+fn user(text: Value, timestamp: &str) -> Value {
+    json!({
+        "type": "user",
+        "message": {"role": "user", "content": text},
+        "uuid": format!("u-{}", timestamp),
+        "sessionId": "s",
+        "timestamp": timestamp,
+        "gitBranch": "main",
+        "isSidechain": false
+    })
+}
+
+fn assistant(id: &str, block: Value, usage: Value, timestamp: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"id": id, "role": "assistant", "model": "claude-sonnet-4-5", "content": [block], "usage": usage},
+        "timestamp": timestamp,
+        "isSidechain": false
+    })
+}
+
+fn session_with_tool_use() -> Vec<Value> {
+    let usage = json!({"input_tokens": 10, "output_tokens": 20, "cache_read_input_tokens": 100});
+    vec![
+        json!({"type": "summary", "summary": "List the files", "leafUuid": "u-3"}),
+        json!({"type": "queue-operation", "operation": "enqueue", "timestamp": "2026-01-01T10:00:00.000Z"}),
+        user(json!("List the files"), "2026-01-01T10:00:01.000Z"),
+        // One API message, written once per content block.
+        assistant(
+            "msg-1",
+            json!({"type": "text", "text": "Listing"}),
+            usage.clone(),
+            "2026-01-01T10:00:02.000Z",
+        ),
+        assistant(
+            "msg-1",
+            json!({"type": "tool_use", "id": "tu-1", "name": "Bash", "input": {"command": "ls"}}),
+            usage,
+            "2026-01-01T10:00:03.000Z",
+        ),
+        {
+            let mut result = user(
+                json!([{"type": "tool_result", "tool_use_id": "tu-1", "content": "a.rs"}]),
+                "2026-01-01T10:00:04.000Z",
+            );
+            result["toolUseResult"] = json!({"stdout": "a.rs"});
+            result
+        },
+        {
+            let mut meta = user(
+                json!("Caveat: local command output"),
+                "2026-01-01T10:00:05.000Z",
+            );
+            meta["isMeta"] = json!(true);
+            meta
+        },
+        {
+            let mut sidechain = user(json!("Subagent task"), "2026-01-01T10:00:06.000Z");
+            sidechain["isSidechain"] = json!(true);
+            sidechain
+        },
+        assistant(
+            "msg-2",
+            json!({"type": "text", "text": "a.rs"}),
+            json!({"input_tokens": 5, "output_tokens": 5}),
+            "2026-01-01T10:00:07.000Z",
+        ),
+        json!({"type": "cost-state", "totalCostUSD": 0.0123}),
+    ]
+}
+
+fn older_session() -> Vec<Value> {
+    let mut answer = assistant(
+        "msg-9",
+        json!({"type": "text", "text": "Cleared"}),
+        json!({"input_tokens": 1000000, "output_tokens": 0}),
+        "2025-12-31T09:00:01.000Z",
+    );
+    answer["costUSD"] = json!(0.5);
+    vec![
+        user(
+            json!("<command-name>/clear</command-name>"),
+            "2025-12-31T09:00:00.000Z",
+        ),
+        user(
+            json!([{"type": "text", "text": "Fix the build"}]),
+            "2025-12-31T09:00:00.500Z",
+        ),
+        answer,
+    ]
+}
+
+#[tokio::test]
+async fn test_should_list_sessions_with_metadata() {
+    let history = history(
+        "list",
+        &[
+            ("older", older_session()),
+            ("latest", session_with_tool_use()),
+        ],
+    );
+
+    let sessions = history.list_sessions(CWD).await.unwrap();
+    let ids: Vec<_> = sessions.iter().map(|s| s.session_id.as_str()).collect();
+    assert_eq!(ids, ["latest", "older"]);
+
+    let latest = &sessions[0];
+    assert_eq!(latest.summary.as_deref(), Some("List the files"));
+    assert_eq!(latest.first_prompt.as_deref(), Some("List the files"));
+    assert_eq!(
+        latest.last_activity.as_deref(),
+        Some("2026-01-01T10:00:07.000Z")
+    );
+    assert_eq!(latest.git_branch.as_deref(), Some("main"));
+    assert_eq!(latest.model.as_deref(), Some("claude-sonnet-4-5"));
+    assert_eq!(latest.message_count, 5);
+    // msg-1's usage counts once.
+    assert_eq!(latest.usage.input_tokens, 15);
+    assert_eq!(latest.usage.output_tokens, 25);
+    assert_eq!(latest.usage.cache_read_input_tokens, 100);
+    assert_eq!(latest.total_cost_usd, Some(0.0123));
+
+    let older = &sessions[1];
+    assert_eq!(older.first_prompt.as_deref(), Some("Fix the build"));
+    assert_eq!(older.total_cost_usd, Some(0.5));
+
+    assert!(
+        history
+            .list_sessions("/elsewhere")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_should_read_conversation_messages() {
+    let history = history("read", &[("latest", session_with_tool_use())]);
+
+    let messages = history.read_session(CWD, "latest").await.unwrap();
+
+    assert_eq!(messages.len(), 5);
+    assert!(matches!(
+        &messages[0],
+        Message::User(u) if matches!(&u.content, UserContent::String(t) if t == "List the files")
+    ));
+    assert!(matches!(
+        &messages[2],
+        Message::Assistant(a) if matches!(&a.content[..], [ContentBlock::ToolUse(t)] if t.name == "Bash")
+    ));
+    let Message::User(result) = &messages[3] else {
+        panic!("expected the tool result, got {:?}", messages[3]);
+    };
+    assert_eq!(result.tool_use_result, Some(json!({"stdout": "a.rs"})));
+    assert!(matches!(&messages[4], Message::Assistant(_)));
+}
+
+#[tokio::test]
+async fn test_should_estimate_cost_and_reject_unknown_sessions() {
+    let mut entries = older_session();
+    entries[2].as_object_mut().unwrap().remove("costUSD");
+    let history = history("estimate", &[("unpriced", entries)]);
+
+    let info = history.session_info(CWD, "unpriced").await.unwrap();
+    assert_eq!(info.total_cost_usd, None);
+
+    let priced = history
+        .clone()
+        .price_table(PriceTable::new().price("claude-sonnet", ModelPrice::new(3.0, 15.0)))
+        .session_info(CWD, "unpriced")
+        .await
+        .unwrap();
+    assert_eq!(priced.total_cost_usd, Some(3.0));
+
+    let err = history.read_session(CWD, "missing").await.unwrap_err();
+    assert!(err.to_string().contains("not found"), "{}", err);
+    assert!(history.read_session(CWD, "../other").await.is_err());
+}
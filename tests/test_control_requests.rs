@@ -0,0 +1,104 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, ReplayTransport, Transport};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, Message, PermissionResult, PermissionResultAllow,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+use common::{claude_initialize, control_response};
+
+fn permission_request(request_id: &str, tool_name: &str, input: Value) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": request_id,
+        "request": {"subtype": "can_use_tool", "tool_name": tool_name, "input": input}
+    })
+}
+
+#[tokio::test]
+async fn test_should_answer_control_requests_concurrently() {
+    let mut entries = claude_initialize(json!({}));
+    entries.extend([
+        CassetteEntry::write(json!({
+            "type": "user",
+            "session_id": "default",
+            "message": {"role": "user", "content": "clean up"},
+            "parent_tool_use_id": null
+        })),
+        CassetteEntry::read(permission_request(
+            "cli_req_0",
+            "Bash",
+            json!({"command": "rm -rf build"}),
+        )),
+        CassetteEntry::read(permission_request(
+            "cli_req_1",
+            "Read",
+            json!({"file_path": "README.md"}),
+        )),
+        // The second request is answered while the first is still pending.
+        CassetteEntry::write(control_response(
+            "cli_req_1",
+            json!({"behavior": "allow", "updatedInput": {"file_path": "README.md"}}),
+        )),
+        CassetteEntry::read(json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": "Reading"}], "model": "claude"},
+            "parent_tool_use_id": null
+        })),
+        CassetteEntry::write(control_response(
+            "cli_req_0",
+            json!({"behavior": "allow", "updatedInput": {"command": "rm -rf build"}}),
+        )),
+        CassetteEntry::read(json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 10,
+            "duration_api_ms": 5,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "session_123"
+        })),
+    ]);
+    let mut replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    replay.connect().await.unwrap();
+
+    // Bash approval waits for a human, who only answers once the
+    // assistant message has arrived.
+    let approved = Arc::new(Notify::new());
+    let approval = Arc::clone(&approved);
+    let options = AgentOptions::builder()
+        .can_use_tool(Arc::new(move |tool_name, _input, _ctx| {
+            let approval = Arc::clone(&approval);
+            Box::pin(async move {
+                if tool_name == "Bash" {
+                    approval.notified().await;
+                }
+                PermissionResult::Allow(PermissionResultAllow {
+                    updated_input: None,
+                    updated_permissions: None,
+                })
+            })
+        }))
+        .build();
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.unwrap();
+    client.query("clean up", "default").await.unwrap();
+
+    let mut messages = client.receive_response();
+    let first = messages.next().await.unwrap().unwrap();
+    assert!(matches!(first, Message::Assistant(_)));
+    approved.notify_one();
+    assert!(matches!(
+        messages.next().await,
+        Some(Ok(Message::Result(_)))
+    ));
+    drop(messages);
+
+    client.disconnect().await.unwrap();
+    verifier.verify().unwrap();
+}
@@ -0,0 +1,1249 @@
+mod common;
+
+use async_trait::async_trait;
+use code_agent_sdk::backend::claude::transport::ClaudeCliTransport;
+use code_agent_sdk::transport::{
+    CassetteEntry, ContainerLauncher, LaunchSpec, LocalLauncher, ProcessLauncher, ProcessTransport,
+    ReplayTransport, SshLauncher, Transport,
+};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, CodexOptions, ContainerOptions, ContainerRuntime,
+    ContentBlock, Effort, Error, McpServerConfig, McpServersConfig, Message, ProfileLoader,
+    create_sdk_mcp_server, sdk_mcp_tool,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use common::codex_handshake;
+
+/// Launcher that hands out scripted cassettes and records each spec.
+#[derive(Debug, Clone, Default)]
+struct ScriptedLauncher {
+    remote: bool,
+    cassettes: Arc<Mutex<VecDeque<Vec<CassetteEntry>>>>,
+    specs: Arc<Mutex<Vec<LaunchSpec>>>,
+}
+
+impl ScriptedLauncher {
+    fn new(cassettes: Vec<Vec<CassetteEntry>>) -> Self {
+        Self {
+            cassettes: Arc::new(Mutex::new(cassettes.into())),
+            ..Default::default()
+        }
+    }
+
+    fn specs(&self) -> Vec<LaunchSpec> {
+        self.specs.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl ProcessLauncher for ScriptedLauncher {
+    async fn launch(&self, spec: LaunchSpec) -> code_agent_sdk::Result<Box<dyn Transport + Send>> {
+        self.specs.lock().unwrap().push(spec);
+        let entries = self
+            .cassettes
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| Error::Other("no more cassettes".to_string()))?;
+        let mut transport = ReplayTransport::from_entries(entries);
+        transport.connect().await?;
+        Ok(Box::new(transport))
+    }
+
+    fn is_remote(&self) -> bool {
+        self.remote
+    }
+}
+
+fn assistant_texts(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .filter_map(|m| match m {
+            Message::Assistant(a) => Some(a.content.iter().filter_map(|b| match b {
+                ContentBlock::Text(t) => Some(t.text.clone()),
+                _ => None,
+            })),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+async fn run_turn(client: &mut AgentSdkClient, prompt: &str) -> Vec<Message> {
+    client.query(prompt, "default").await.expect("query failed");
+    client
+        .receive_response()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("receive_response failed")
+}
+
+fn cursor_turn(chat_id: &str, text: &str) -> Vec<CassetteEntry> {
+    vec![
+        CassetteEntry::read(json!({"type": "system", "subtype": "init", "chatId": chat_id})),
+        CassetteEntry::read(json!({"type": "assistant", "text": text})),
+        CassetteEntry::read(json!({
+            "type": "result", "subtype": "success", "session_id": chat_id,
+            "is_error": false, "num_turns": 1
+        })),
+    ]
+}
+
+#[tokio::test]
+async fn test_should_launch_cursor_turns_through_custom_launcher() {
+    let launcher = ScriptedLauncher {
+        remote: true,
+        ..ScriptedLauncher::new(vec![
+            cursor_turn("chat-9", "one"),
+            cursor_turn("chat-9", "two"),
+        ])
+    };
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Cursor)
+        .cwd("/remote/work")
+        .env("TOKEN", "abc")
+        .launcher(launcher.clone())
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    assert_eq!(
+        assistant_texts(&run_turn(&mut client, "first").await),
+        ["one"]
+    );
+    assert_eq!(
+        assistant_texts(&run_turn(&mut client, "second").await),
+        ["two"]
+    );
+    client.disconnect().await.expect("disconnect failed");
+
+    let specs = launcher.specs();
+    assert_eq!(specs.len(), 2);
+    // Remote launchers skip local CLI discovery.
+    assert_eq!(specs[0].program, "agent");
+    assert_eq!(
+        specs[0].cwd.as_deref(),
+        Some(std::path::Path::new("/remote/work"))
+    );
+    assert!(
+        specs[0]
+            .env
+            .contains(&("TOKEN".to_string(), "abc".to_string()))
+    );
+    assert_eq!(specs[0].args.last().map(String::as_str), Some("first"));
+    assert!(!specs[0].args.contains(&"--resume".to_string()));
+    let resume = specs[1].args.iter().position(|a| a == "--resume").unwrap();
+    assert_eq!(specs[1].args[resume + 1], "chat-9");
+}
+
+#[tokio::test]
+async fn test_should_run_codex_session_over_custom_transport() {
+    let replay = ReplayTransport::from_entries(vec![
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": {"clientName": "code-agent-sdk", "clientVersion": env!("CARGO_PKG_VERSION")}
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}})),
+        CassetteEntry::write(
+            json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {}}),
+        ),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "thread-7"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "thread-7", "input": [{"role": "user", "content": "hi"}]}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "item/completed",
+            "params": {"item": {"type": "agent_message", "rawText": "hello"}}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {"threadId": "thread-7", "usage": {}}
+        })),
+    ]);
+    let verifier = replay.verifier();
+    let options = AgentOptions::builder().backend(BackendKind::Codex).build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    assert_eq!(
+        client.get_server_info().await.unwrap(),
+        Some(json!({"threadId": "thread-7"}))
+    );
+    let messages = run_turn(&mut client, "hi").await;
+    client.disconnect().await.expect("disconnect failed");
+
+    assert_eq!(assistant_texts(&messages), ["hello"]);
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+    verifier.verify().expect("codex writes should match");
+}
+
+async fn codex_thread_id(options: AgentOptions, entries: Vec<CassetteEntry>) -> Value {
+    let replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    let info = client.get_server_info().await.unwrap().unwrap();
+    client.disconnect().await.expect("disconnect failed");
+    verifier.verify().expect("codex writes should match");
+    info["threadId"].clone()
+}
+
+#[tokio::test]
+async fn test_should_resume_and_fork_codex_threads() {
+    let mut resume = codex_handshake();
+    resume.extend([
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 2, "method": "thread/resume", "params": {"threadId": "thread-7"}})),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"thread": {"id": "thread-7"}, "model": "gpt-5"}})),
+    ]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .resume("thread-7")
+        .build();
+    assert_eq!(codex_thread_id(options, resume).await, "thread-7");
+
+    // continue_conversation forks the most recently updated thread of the
+    // same workspace, paging past threads of other projects.
+    let mut fork_latest = codex_handshake();
+    fork_latest.extend([
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 2, "method": "thread/list", "params": {}})),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 2,
+            "result": {"data": [{"id": "thread-12", "cwd": "/other", "updatedAt": 1700000100}], "nextCursor": "page-2"}
+        })),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 3, "method": "thread/list", "params": {"cursor": "page-2"}})),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 3,
+            "result": {"data": [{"id": "thread-9", "preview": "fix the build", "cwd": "/work", "updatedAt": 1700000000}], "nextCursor": null}
+        })),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 4, "method": "thread/fork", "params": {"threadId": "thread-9", "cwd": "/work"}})),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 4, "result": {"thread": {"id": "thread-10"}}})),
+    ]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .cwd("/work")
+        .continue_conversation(true)
+        .fork_session(true)
+        .build();
+    assert_eq!(codex_thread_id(options, fork_latest).await, "thread-10");
+
+    // Without threads of this workspace to continue, a new one is started.
+    let mut fresh = codex_handshake();
+    fresh.extend([
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 2, "method": "thread/list", "params": {}})),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 2,
+            "result": {"data": [{"id": "thread-12", "cwd": "/other"}]}
+        })),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 3, "method": "thread/start", "params": {"cwd": "/work"}})),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 3, "result": {"threadId": "thread-11"}})),
+    ]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .cwd("/work")
+        .continue_conversation(true)
+        .build();
+    assert_eq!(codex_thread_id(options, fresh).await, "thread-11");
+}
+
+#[tokio::test]
+async fn test_should_forward_codex_settings_to_app_server() {
+    let mut cassette = codex_handshake();
+    cassette.extend([
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 2, "method": "thread/start",
+            "params": {
+                "model": "gpt-5-codex",
+                "cwd": "/work",
+                "sandbox": "workspace-write",
+                "config": {"sandbox_workspace_write.writable_roots": ["/data"]}
+            }
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "thread-7"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "thread-7", "effort": "high", "input": [{"role": "user", "content": "hi"}]}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {"threadId": "thread-7", "usage": {}}
+        })),
+    ]);
+    let launcher = ScriptedLauncher::new(vec![cassette]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .cli_path("codex")
+        .launcher(launcher.clone())
+        .model("gpt-5-codex")
+        .cwd("/work")
+        .effort(Effort::High)
+        .add_dir("/data")
+        .extra_arg("config", Some("hide_agent_reasoning=true".to_string()))
+        .codex(CodexOptions {
+            approval_policy: None,
+            sandbox_mode: Some("workspace-write".to_string()),
+        })
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect failed");
+    let messages = run_turn(&mut client, "hi").await;
+    client.disconnect().await.expect("disconnect failed");
+
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+    assert_eq!(
+        launcher.specs()[0].args,
+        ["app-server", "--config", "hide_agent_reasoning=true"]
+    );
+}
+
+#[tokio::test]
+async fn test_should_reject_exec_only_extra_args_for_codex_sessions() {
+    let launcher = ScriptedLauncher::new(vec![]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .cli_path("codex")
+        .launcher(launcher.clone())
+        .extra_arg("skip-git-repo-check", None)
+        .extra_arg("config", Some("hide_agent_reasoning=true".to_string()))
+        .extra_arg("color", Some("never".to_string()))
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    let err = client.connect(None).await.unwrap_err();
+    match err {
+        Error::UnsupportedOptions { backend, options } => {
+            assert_eq!(backend, "Codex");
+            assert_eq!(
+                options,
+                [
+                    "extra_args.color (codex exec only)",
+                    "extra_args.skip-git-repo-check (codex exec only)"
+                ]
+            );
+        }
+        other => panic!("expected UnsupportedOptions, got {other:?}"),
+    }
+    assert!(launcher.specs().is_empty());
+}
+
+#[tokio::test]
+async fn test_should_run_sdk_mcp_tools_as_codex_dynamic_tools() {
+    let add = sdk_mcp_tool(
+        "add",
+        "Add two numbers",
+        json!({"type": "object", "properties": {"a": {"type": "number"}, "b": {"type": "number"}}}),
+        |args| {
+            Box::pin(async move {
+                let sum = args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0);
+                Ok(json!({"content": [{"type": "text", "text": sum.to_string()}]}))
+            })
+        },
+    );
+    let fail = sdk_mcp_tool("fail", "Always fails", json!({"type": "object"}), |_| {
+        Box::pin(async move { Err(Error::Other("boom".to_string())) })
+    });
+    let server = create_sdk_mcp_server("calc", "1.0.0", vec![add, fail]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .mcp_servers(McpServersConfig::Dict(
+            [("calc".to_string(), McpServerConfig::Sdk(server.clone()))].into(),
+        ))
+        .build();
+
+    let replay = ReplayTransport::from_entries(vec![
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": {
+                "clientName": "code-agent-sdk",
+                "clientVersion": env!("CARGO_PKG_VERSION"),
+                "capabilities": {"experimentalApi": true}
+            }
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 2, "method": "thread/start",
+            "params": {"dynamicTools": [
+                {
+                    "name": "mcp__calc__add",
+                    "description": "Add two numbers",
+                    "inputSchema": {"type": "object", "properties": {"a": {"type": "number"}, "b": {"type": "number"}}}
+                },
+                {"name": "mcp__calc__fail", "description": "Always fails", "inputSchema": {"type": "object"}}
+            ]}
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "thread-7"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "thread-7", "input": [{"role": "user", "content": "add"}]}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 0, "method": "item/tool/call",
+            "params": {"threadId": "thread-7", "callId": "c-1", "tool": "mcp__calc__add", "arguments": {"a": 1, "b": 2}}
+        })),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 0,
+            "result": {"contentItems": [{"type": "inputText", "text": "3"}], "success": true}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "item/tool/call",
+            "params": {"threadId": "thread-7", "callId": "c-2", "tool": "mcp__calc__fail", "arguments": {}}
+        })),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 1,
+            "result": {"contentItems": [{"type": "inputText", "text": "boom"}], "success": false}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {"threadId": "thread-7", "usage": {}}
+        })),
+    ]);
+    let verifier = replay.verifier();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    let messages = run_turn(&mut client, "add").await;
+    // The thread's tools are fixed while the session runs.
+    assert!(matches!(
+        server.remove_tool("fail"),
+        Err(Error::UnsupportedFeature { .. })
+    ));
+    client.disconnect().await.expect("disconnect failed");
+    assert!(
+        server
+            .remove_tool("fail")
+            .expect("unpinned after the session")
+    );
+
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+    verifier.verify().expect("codex writes should match");
+}
+
+#[tokio::test]
+async fn test_should_stream_codex_notifications_while_a_tool_runs() {
+    let release = Arc::new(tokio::sync::Notify::new());
+    let released = Arc::clone(&release);
+    let wait = sdk_mcp_tool(
+        "wait",
+        "Wait to be released",
+        json!({"type": "object"}),
+        move |_| {
+            let released = Arc::clone(&released);
+            Box::pin(async move {
+                released.notified().await;
+                Ok(json!({"content": [{"type": "text", "text": "released"}]}))
+            })
+        },
+    );
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .mcp_servers(McpServersConfig::Dict(
+            [(
+                "slow".to_string(),
+                McpServerConfig::Sdk(create_sdk_mcp_server("slow", "1.0.0", vec![wait])),
+            )]
+            .into(),
+        ))
+        .build();
+
+    let replay = ReplayTransport::from_entries(vec![
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": {
+                "clientName": "code-agent-sdk",
+                "clientVersion": env!("CARGO_PKG_VERSION"),
+                "capabilities": {"experimentalApi": true}
+            }
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 2, "method": "thread/start",
+            "params": {"dynamicTools": [
+                {"name": "mcp__slow__wait", "description": "Wait to be released", "inputSchema": {"type": "object"}}
+            ]}
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "thread-7"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "thread-7", "input": [{"role": "user", "content": "wait"}]}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 0, "method": "item/tool/call",
+            "params": {"threadId": "thread-7", "callId": "c-1", "tool": "mcp__slow__wait", "arguments": {}}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "item/completed",
+            "params": {"item": {"type": "agent_message", "rawText": "still working"}}
+        })),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 0,
+            "result": {"contentItems": [{"type": "inputText", "text": "released"}], "success": true}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {"threadId": "thread-7", "usage": {}}
+        })),
+    ]);
+    let verifier = replay.verifier();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("wait", "default").await.expect("query failed");
+    let mut response = client.receive_response();
+    // Arrives while the tool call is still waiting.
+    let first = tokio::time::timeout(std::time::Duration::from_secs(5), response.next())
+        .await
+        .expect("notification held up by the tool call")
+        .expect("stream ended")
+        .expect("message");
+    assert_eq!(assistant_texts(&[first]), ["still working"]);
+    release.notify_one();
+    let rest: Vec<_> = response.map(|m| m.expect("message")).collect().await;
+    assert!(matches!(rest.last(), Some(Message::Result(_))));
+    client.disconnect().await.expect("disconnect failed");
+    verifier.verify().expect("codex writes should match");
+}
+
+fn echo_server() -> McpServersConfig {
+    let echo = sdk_mcp_tool("echo", "Echo the text", json!({"type": "object"}), |args| {
+        Box::pin(async move { Ok(json!({"content": [{"type": "text", "text": args["text"]}]})) })
+    });
+    McpServersConfig::Dict(
+        [(
+            "tools".to_string(),
+            McpServerConfig::Sdk(create_sdk_mcp_server("tools", "1.0.0", vec![echo])),
+        )]
+        .into(),
+    )
+}
+
+/// The MCP bridge token handed to the CLI of the `n`th launch.
+fn bridge_token(launcher: &ScriptedLauncher, n: usize) -> String {
+    launcher.specs()[n]
+        .env
+        .iter()
+        .find(|(k, _)| k == "AGENT_SDK_MCP_TOKEN")
+        .map(|(_, v)| v.clone())
+        .expect("bridge token in the CLI's environment")
+}
+
+/// POST a JSON-RPC message to an MCP bridge URL, returning the status code
+/// and JSON body.
+async fn post_mcp(url: &str, token: &str, body: Value) -> (u16, Value) {
+    post_mcp_with(url, &format!("Authorization: Bearer {token}\r\n"), body).await
+}
+
+/// POST `body` to `url` with the given extra header lines and no others.
+async fn post_mcp_with(url: &str, headers: &str, body: Value) -> (u16, Value) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let rest = url.strip_prefix("http://").unwrap();
+    let (addr, path) = rest.split_at(rest.find('/').unwrap());
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let body = body.to_string();
+    let headers = if headers.contains("Host:") {
+        headers.to_string()
+    } else {
+        format!("Host: {addr}\r\n{headers}")
+    };
+    let request = format!(
+        "POST {path} HTTP/1.1\r\n{headers}Content-Type: application/json\r\n\
+         Accept: application/json, text/event-stream\r\nConnection: close\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_should_serve_sdk_mcp_servers_to_codex_exec_over_bridge() {
+    let launcher = ScriptedLauncher::new(vec![vec![
+        CassetteEntry::read(json!({"type": "thread.started", "thread_id": "t-1"})),
+        CassetteEntry::read(json!({
+            "type": "item.completed",
+            "item": {"id": "i-1", "type": "agent_message", "text": "done"}
+        })),
+    ]]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .cli_path("codex")
+        .mcp_servers(echo_server())
+        .launcher(launcher.clone())
+        .build();
+
+    let mut stream = Box::pin(code_agent_sdk::query("hi", Some(options)));
+    stream.next().await.unwrap().expect("first message");
+
+    // The bridge serves the server while the query runs.
+    let args = launcher.specs()[0].args.clone();
+    let config = args
+        .iter()
+        .find_map(|a| a.strip_prefix("mcp_servers.\"tools\".url="))
+        .expect("bridge url passed with -c");
+    let url = config.trim_matches('"');
+    assert!(args.contains(
+        &"mcp_servers.\"tools\".bearer_token_env_var=\"AGENT_SDK_MCP_TOKEN\"".to_string()
+    ));
+    let token = bridge_token(&launcher, 0);
+    let (_, init) = post_mcp(
+        url,
+        &token,
+        json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}),
+    )
+    .await;
+    assert_eq!(init["result"]["capabilities"]["tools"]["listChanged"], true);
+    let (status, list) = post_mcp(
+        url,
+        &token,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(list["result"]["tools"][0]["name"], "echo");
+    let (_, call) = post_mcp(
+        url,
+        &token,
+        json!({
+            "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+            "params": {"name": "echo", "arguments": {"text": "ping"}}
+        }),
+    )
+    .await;
+    assert_eq!(call["id"], 2);
+    assert_eq!(call["result"]["content"][0]["text"], "ping");
+    let (status, _) = post_mcp(
+        url,
+        &token,
+        json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+    )
+    .await;
+    assert_eq!(status, 202);
+
+    // Requests without the token, from a browser or for another host are
+    // refused.
+    let list = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"});
+    let (status, _) = post_mcp_with(url, "", list.clone()).await;
+    assert_eq!(status, 401);
+    let (status, _) = post_mcp(url, "not-the-token", list.clone()).await;
+    assert_eq!(status, 401);
+    let auth = format!("Authorization: Bearer {token}\r\n");
+    let (status, _) = post_mcp_with(
+        url,
+        &format!("{auth}Origin: http://evil.example\r\n"),
+        list.clone(),
+    )
+    .await;
+    assert_eq!(status, 403);
+    let (status, _) = post_mcp_with(url, &format!("Host: evil.example\r\n{auth}"), list).await;
+    assert_eq!(status, 421);
+
+    while let Some(item) = stream.next().await {
+        item.expect("query failed");
+    }
+    drop(stream);
+    let addr = url.trim_start_matches("http://").split('/').next().unwrap();
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_should_push_tool_list_changes_over_bridge_event_stream() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    let server = create_sdk_mcp_server("tools", "1.0.0", vec![]);
+    let launcher = ScriptedLauncher::new(vec![vec![CassetteEntry::read(
+        json!({"type": "thread.started", "thread_id": "t-1"}),
+    )]]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .cli_path("codex")
+        .mcp_servers(McpServersConfig::Dict(
+            [("tools".to_string(), McpServerConfig::Sdk(server.clone()))].into(),
+        ))
+        .launcher(launcher.clone())
+        .build();
+    let mut stream = Box::pin(code_agent_sdk::query("hi", Some(options)));
+    stream.next().await.unwrap().expect("first message");
+
+    let url = launcher.specs()[0]
+        .args
+        .iter()
+        .find_map(|a| a.strip_prefix("mcp_servers.\"tools\".url="))
+        .unwrap()
+        .trim_matches('"')
+        .to_string();
+    let token = bridge_token(&launcher, 0);
+    let rest = url.strip_prefix("http://").unwrap();
+    let (addr, path) = rest.split_at(rest.find('/').unwrap());
+    let mut events = tokio::io::BufReader::new(tokio::net::TcpStream::connect(addr).await.unwrap());
+    events
+        .get_mut()
+        .write_all(
+            format!(
+                "GET {path} HTTP/1.1\r\nHost: {addr}\r\nAuthorization: Bearer {token}\r\n\
+                 Accept: text/event-stream\r\n\r\n"
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+    let mut line = String::new();
+    events.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("HTTP/1.1 200"), "{line}");
+    while line != "\r\n" {
+        line.clear();
+        events.read_line(&mut line).await.unwrap();
+    }
+
+    server
+        .add_tool(sdk_mcp_tool(
+            "late",
+            "Added later",
+            json!({"type": "object"}),
+            |_| Box::pin(async { Ok(json!({"content": []})) }),
+        ))
+        .expect("add_tool");
+    let data = loop {
+        line.clear();
+        events.read_line(&mut line).await.unwrap();
+        if let Some(data) = line.strip_prefix("data: ") {
+            break serde_json::from_str::<Value>(data).unwrap();
+        }
+    };
+    assert_eq!(data["method"], "notifications/tools/list_changed");
+    let (_, list) = post_mcp(
+        &url,
+        &token,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+    )
+    .await;
+    assert_eq!(list["result"]["tools"][0]["name"], "late");
+
+    while let Some(item) = stream.next().await {
+        item.expect("query failed");
+    }
+}
+
+#[tokio::test]
+async fn test_should_install_cursor_mcp_config_for_the_query() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let workspace = std::env::temp_dir().join(format!("cursor-mcp-{}-{nanos}", std::process::id()));
+    let config_path = workspace.join(".cursor/mcp.json");
+    std::fs::create_dir_all(&workspace).unwrap();
+
+    let launcher = ScriptedLauncher::new(vec![cursor_turn("chat-1", "done")]);
+    let mut servers = echo_server();
+    if let McpServersConfig::Dict(ref mut dict) = servers {
+        dict.insert(
+            "fs".to_string(),
+            serde_json::from_value(json!({
+                "type": "stdio", "command": "mcp-fs", "args": ["--root", "."],
+                "env": {"FS_TOKEN": "s3cret"}
+            }))
+            .unwrap(),
+        );
+        dict.insert(
+            "docs".to_string(),
+            serde_json::from_value(json!({
+                "type": "http", "url": "https://docs.example/mcp",
+                "headers": {"Authorization": "Bearer docs-key"}
+            }))
+            .unwrap(),
+        );
+    }
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Cursor)
+        .cli_path("agent")
+        .cwd(&workspace)
+        .mcp_servers(servers)
+        .launcher(launcher.clone())
+        .build();
+
+    let mut stream = Box::pin(code_agent_sdk::query("hi", Some(options.clone())));
+    stream.next().await.unwrap().expect("first message");
+
+    // Secrets are passed to the CLI's environment, not written to the file.
+    let written = std::fs::read_to_string(&config_path).unwrap();
+    assert!(!written.contains("s3cret") && !written.contains("docs-key"));
+    let installed: Value = serde_json::from_str(&written).unwrap();
+    let servers = &installed["mcpServers"];
+    let env = &launcher.specs()[0].env;
+    let resolve = |reference: &Value| {
+        let name = reference
+            .as_str()
+            .unwrap()
+            .strip_prefix("${env:")
+            .unwrap()
+            .trim_end_matches('}');
+        env.iter().find(|(k, _)| k == name).unwrap().1.clone()
+    };
+    assert_eq!(servers["fs"]["command"], "mcp-fs");
+    assert_eq!(servers["fs"]["args"], json!(["--root", "."]));
+    assert_eq!(resolve(&servers["fs"]["env"]["FS_TOKEN"]), "s3cret");
+    assert_eq!(servers["docs"]["url"], "https://docs.example/mcp");
+    assert_eq!(
+        resolve(&servers["docs"]["headers"]["Authorization"]),
+        "Bearer docs-key"
+    );
+    assert_eq!(
+        servers["tools"]["headers"]["Authorization"],
+        "Bearer ${env:AGENT_SDK_MCP_TOKEN}"
+    );
+    let url = servers["tools"]["url"].as_str().unwrap();
+    let (_, list) = post_mcp(
+        url,
+        &bridge_token(&launcher, 0),
+        json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+    )
+    .await;
+    assert_eq!(list["result"]["tools"][0]["name"], "echo");
+    assert!(
+        launcher.specs()[0]
+            .args
+            .contains(&"--approve-mcps".to_string())
+    );
+
+    // A second query in the same workspace waits its turn.
+    let mut second = Box::pin(code_agent_sdk::query("hi", Some(options)));
+    let err = second.next().await.unwrap().unwrap_err();
+    assert!(
+        err.to_string().contains("in use by another session"),
+        "{err}"
+    );
+
+    while let Some(item) = stream.next().await {
+        item.expect("query failed");
+    }
+    drop(stream);
+    assert!(!workspace.join(".cursor").exists());
+    std::fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_should_keep_bridge_env_loadable_by_profiles() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let workspace = std::env::temp_dir().join(format!("cursor-env-{}-{nanos}", std::process::id()));
+    std::fs::create_dir_all(&workspace).unwrap();
+
+    let launcher = ScriptedLauncher::new(vec![cursor_turn("chat-1", "done")]);
+    let mut servers = echo_server();
+    if let McpServersConfig::Dict(ref mut dict) = servers {
+        dict.insert(
+            "docs".to_string(),
+            serde_json::from_value(json!({
+                "type": "http", "url": "https://docs.example/mcp",
+                "headers": {"Authorization": "Bearer docs-key"}
+            }))
+            .unwrap(),
+        );
+    }
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Cursor)
+        .cli_path("agent")
+        .cwd(&workspace)
+        .mcp_servers(servers)
+        .launcher(launcher.clone())
+        .build();
+    let messages: Vec<_> = code_agent_sdk::query("hi", Some(options)).collect().await;
+    std::fs::remove_dir_all(&workspace).unwrap();
+    assert!(messages.iter().all(Result::is_ok), "{messages:?}");
+
+    // An SDK program started by the agent inherits the CLI's environment.
+    let env = launcher.specs()[0].env.clone();
+    assert_eq!(env.len(), 2, "{env:?}");
+    ProfileLoader::new()
+        .env(env)
+        .load()
+        .expect("bridge variables are not profile overrides");
+}
+
+#[tokio::test]
+async fn test_should_not_replace_users_cursor_mcp_config() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let workspace = std::env::temp_dir().join(format!("cursor-own-{}-{nanos}", std::process::id()));
+    let config_path = workspace.join(".cursor/mcp.json");
+    std::fs::create_dir_all(workspace.join(".cursor")).unwrap();
+    let original = r#"{"mcpServers": {"docs": {"url": "https://docs.example/mcp"}}}"#;
+    std::fs::write(&config_path, original).unwrap();
+
+    let launcher = ScriptedLauncher::new(vec![cursor_turn("chat-1", "done")]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Cursor)
+        .cli_path("agent")
+        .cwd(&workspace)
+        .mcp_servers(echo_server())
+        .launcher(launcher.clone())
+        .build();
+    let mut stream = Box::pin(code_agent_sdk::query("hi", Some(options)));
+    let err = stream.next().await.unwrap().unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{err}");
+    assert!(launcher.specs().is_empty());
+
+    assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+    assert!(!workspace.join(".cursor/mcp.json.lock").exists());
+    std::fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_should_reject_bridged_mcp_servers_on_remote_launcher() {
+    for backend in [BackendKind::Codex, BackendKind::Cursor] {
+        let options = AgentOptions::builder()
+            .backend(backend)
+            .mcp_servers(echo_server())
+            .launcher(ScriptedLauncher {
+                remote: true,
+                ..Default::default()
+            })
+            .build();
+        let mut stream = Box::pin(code_agent_sdk::query("hi", Some(options)));
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("local CLI"), "{err}");
+    }
+}
+
+#[tokio::test]
+async fn test_should_reject_unknown_codex_thread_and_bare_fork() {
+    let mut missing = codex_handshake();
+    missing.extend([
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 2, "method": "thread/resume", "params": {"threadId": "gone"}})),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 2,
+            "error": {"code": -32600, "message": "no rollout found for thread id gone"}
+        })),
+    ]);
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .resume("gone")
+        .build();
+    let mut client = AgentSdkClient::new(
+        Some(options),
+        Some(Box::new(ReplayTransport::from_entries(missing))),
+    );
+    let err = client.connect(None).await.unwrap_err();
+    assert!(err.to_string().contains("no rollout found"), "{}", err);
+
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .fork_session(true)
+        .build();
+    let mut client = AgentSdkClient::new(Some(options), None);
+    assert!(matches!(
+        client.connect(None).await,
+        Err(Error::UnsupportedOptions { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_should_reject_custom_transport_for_cursor() {
+    let options = AgentOptions::builder().backend(BackendKind::Cursor).build();
+    let replay = ReplayTransport::from_entries(Vec::new());
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+
+    let err = client.connect(None).await.unwrap_err();
+    assert!(
+        matches!(err, Error::UnsupportedFeature { ref backend, .. } if backend == "Cursor"),
+        "{err}"
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_should_report_process_exit_with_stderr() {
+    let spec = LaunchSpec::new("sh").args([
+        "-c",
+        r#"echo '{"type":"ok"}'; echo not-json; echo boom >&2; exit 3"#,
+    ]);
+    let mut transport = ProcessTransport::new(spec);
+    transport.connect().await.expect("spawn failed");
+
+    let items: Vec<_> = transport.read_messages().collect().await;
+    assert_eq!(items.len(), 2, "{items:?}");
+    assert_eq!(items[0].as_ref().unwrap(), &json!({"type": "ok"}));
+    match &items[1] {
+        Err(Error::Process { exit_code, stderr }) => {
+            assert_eq!(*exit_code, 3);
+            assert_eq!(stderr.as_deref(), Some("boom"));
+        }
+        other => panic!("expected process error, got {other:?}"),
+    }
+    transport.close().await.expect("close failed");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_should_forward_env_and_cwd_to_local_process() {
+    let spec = LaunchSpec::new("sh")
+        .args([
+            "-c",
+            r#"printf '{"dir":"%s","var":"%s"}\n' "$PWD" "$GREETING""#,
+        ])
+        .env("GREETING", "hello")
+        .cwd(Some(std::env::temp_dir().canonicalize().unwrap()));
+    let mut transport = ProcessTransport::new(spec);
+    transport.connect().await.expect("spawn failed");
+
+    let items: Vec<_> = transport.read_messages().collect().await;
+    let value = items[0].as_ref().expect("expected JSON line");
+    assert_eq!(value["var"], "hello");
+    assert_eq!(
+        std::path::Path::new(value["dir"].as_str().unwrap())
+            .canonicalize()
+            .unwrap(),
+        std::env::temp_dir().canonicalize().unwrap()
+    );
+    assert_eq!(items.len(), 1);
+    transport.close().await.expect("close failed");
+}
+
+/// Launcher that runs a shell script in place of the requested CLI.
+#[cfg(unix)]
+#[derive(Debug)]
+struct ShellLauncher(&'static str);
+
+#[cfg(unix)]
+#[async_trait]
+impl ProcessLauncher for ShellLauncher {
+    async fn launch(&self, spec: LaunchSpec) -> code_agent_sdk::Result<Box<dyn Transport + Send>> {
+        let spec = LaunchSpec {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), self.0.to_string()],
+            ..spec
+        };
+        LocalLauncher.launch(spec).await
+    }
+}
+
+#[cfg(unix)]
+async fn launched_claude_output(
+    script: &'static str,
+    max_buffer_size: Option<usize>,
+) -> Vec<code_agent_sdk::Result<Value>> {
+    let options = AgentOptions {
+        cli_path: Some("claude".into()),
+        launcher: Some(Arc::new(ShellLauncher(script))),
+        max_buffer_size,
+        ..Default::default()
+    };
+    let mut transport = ClaudeCliTransport::new(options).expect("transport");
+    transport.connect().await.expect("launch failed");
+    let items = transport.read_messages().collect().await;
+    transport.close().await.expect("close failed");
+    items
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_should_reassemble_split_claude_messages_through_launcher() {
+    let items = launched_claude_output(
+        r#"printf '{"type":"system",\n"subtype":"init"}\nnot-json\n{"type":"ok"}\n'"#,
+        None,
+    )
+    .await;
+    let values: Vec<Value> = items.into_iter().map(|item| item.unwrap()).collect();
+    assert_eq!(
+        values,
+        [
+            json!({"type": "system", "subtype": "init"}),
+            json!({"type": "ok"})
+        ]
+    );
+
+    let items = launched_claude_output(
+        r#"printf '{"text":\n"%s"}\n' aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"#,
+        Some(16),
+    )
+    .await;
+    assert_eq!(items.len(), 1, "{items:?}");
+    let err = items[0].as_ref().unwrap_err();
+    assert!(
+        err.to_string().contains("maximum buffer size of 16 bytes"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_should_quote_remote_command_for_ssh() {
+    let spec = LaunchSpec::new("codex")
+        .args(["app-server", "-c", "approval_policy=\"never\"", "it's"])
+        .env("API_KEY", "a b")
+        .cwd(Some("/srv/my repo".into()));
+    let ssh = SshLauncher::new("build-box")
+        .user("ci")
+        .port(2222)
+        .identity_file("/keys/id")
+        .option("StrictHostKeyChecking", "no")
+        .ssh_spec(&spec);
+
+    assert_eq!(ssh.program, "ssh");
+    assert!(ssh.env.is_empty());
+    assert!(ssh.cwd.is_none());
+    assert_eq!(
+        ssh.args,
+        [
+            "-T",
+            "-o",
+            "BatchMode=yes",
+            "-p",
+            "2222",
+            "-i",
+            "/keys/id",
+            "-o",
+            "StrictHostKeyChecking=no",
+            "ci@build-box",
+            "--",
+            r#"cd '/srv/my repo' && exec env 'API_KEY=a b' codex app-server -c 'approval_policy="never"' 'it'\''s'"#,
+        ]
+    );
+    assert!(SshLauncher::new("build-box").is_remote());
+
+    let ssh = SshLauncher::new("build-box").send_env().ssh_spec(&spec);
+    assert_eq!(ssh.env, [("API_KEY".to_string(), "a b".to_string())]);
+    assert_eq!(
+        ssh.args,
+        [
+            "-T",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "SendEnv=API_KEY",
+            "build-box",
+            "--",
+            r#"cd '/srv/my repo' && exec codex app-server -c 'approval_policy="never"' 'it'\''s'"#,
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_should_map_remote_exit_to_process_error() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stand-in for `ssh` that runs the remote command with the local shell.
+    let dir = std::env::temp_dir().join(format!("fake-ssh-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("work dir")).unwrap();
+    let fake_ssh = dir.join("ssh");
+    std::fs::write(
+        &fake_ssh,
+        "#!/bin/sh\nfor last; do :; done\nexec sh -c \"$last\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&fake_ssh, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let launcher = SshLauncher::new("remote").ssh_program(fake_ssh.to_string_lossy());
+    let spec = LaunchSpec::new("sh")
+        .args([
+            "-c",
+            r#"printf '{"dir":"%s","var":"%s"}\n' "$(basename "$PWD")" "$GREETING"; echo 'remote failure' >&2; exit 4"#,
+        ])
+        .env("GREETING", "it's here")
+        .cwd(Some(dir.join("work dir")));
+    let mut transport = launcher.launch(spec).await.expect("launch failed");
+
+    let items: Vec<_> = transport.read_messages().collect().await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(
+        items[0].as_ref().unwrap(),
+        &json!({"dir": "work dir", "var": "it's here"})
+    );
+    match &items[1] {
+        Err(Error::Process { exit_code, stderr }) => {
+            assert_eq!(*exit_code, 4);
+            assert_eq!(stderr.as_deref(), Some("remote failure"));
+        }
+        other => panic!("expected process error, got {other:?}"),
+    }
+    transport.close().await.expect("close failed");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_should_run_spec_in_container_and_remove_it_on_close() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stand-in for `docker` that runs the command locally and logs `rm`.
+    let dir = std::env::temp_dir().join(format!("fake-docker-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("repo")).unwrap();
+    let rm_log = dir.join("rm.log");
+    let fake_docker = dir.join("docker");
+    let script = format!(
+        r#"#!/bin/sh
+if [ "$1" = rm ]; then echo "$2 $3" >> '{}'; exit 0; fi
+shift
+while [ $# -gt 0 ]; do
+  case "$1" in
+    -i|--rm|--init) shift ;;
+    -w) cd "$2"; shift 2 ;;
+    -e) export "$2"; shift 2 ;;
+    -*) shift 2 ;;
+    *) break ;;
+  esac
+done
+shift
+exec "$@"
+"#,
+        rm_log.display()
+    );
+    std::fs::write(&fake_docker, script).unwrap();
+    std::fs::set_permissions(&fake_docker, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let launcher = ContainerLauncher::new(ContainerOptions {
+        runtime: ContainerRuntime::Other(fake_docker.to_string_lossy().to_string()),
+        ..ContainerOptions::new("agent-image")
+    });
+    assert!(launcher.is_remote());
+    let spec = LaunchSpec::new("sh")
+        .args([
+            "-c",
+            r#"printf '{"dir":"%s","var":"%s"}\n' "$(basename "$PWD")" "$GREETING""#,
+        ])
+        .env("GREETING", "hello")
+        .cwd(Some(dir.join("repo")));
+    let mut transport = launcher.launch(spec).await.expect("launch failed");
+
+    let items: Vec<_> = transport.read_messages().collect().await;
+    transport.close().await.expect("close failed");
+    let removed = std::fs::read_to_string(&rm_log).unwrap_or_default();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(items.len(), 1, "{items:?}");
+    assert_eq!(
+        items[0].as_ref().unwrap(),
+        &json!({"dir": "repo", "var": "hello"})
+    );
+    assert!(removed.starts_with("-f code-agent-sdk-"), "{removed}");
+}
+
+#[tokio::test]
+async fn test_should_reject_launcher_combined_with_container() {
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .launcher(ScriptedLauncher::default())
+        .container(ContainerOptions::new("agent-image"))
+        .build();
+    let mut client = AgentSdkClient::new(Some(options), None);
+
+    let err = client.connect(None).await.unwrap_err();
+    assert!(err.to_string().contains("cannot both be set"), "{err}");
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, ReplayTransport};
+use code_agent_sdk::{AgentOptions, AgentSdkClient, BackendKind, Message};
+use futures::StreamExt;
+use serde_json::json;
+use std::time::Duration;
+
+use common::codex_handshake;
+
+/// A Codex turn streaming `chunks` text deltas before completing.
+fn codex_turn_cassette(chunks: usize) -> Vec<CassetteEntry> {
+    let mut entries = codex_handshake();
+    entries.extend([
+        CassetteEntry::write(
+            json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {}}),
+        ),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "t-1"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "t-1", "input": [{"role": "user", "content": "go"}]}
+        })),
+    ]);
+    for i in 0..chunks {
+        entries.push(CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "item/agentMessage/delta",
+            "params": {"threadId": "t-1", "itemId": "m-1", "delta": format!("{i} ")}
+        })));
+    }
+    entries.push(CassetteEntry::read(json!({
+        "jsonrpc": "2.0", "method": "item/completed",
+        "params": {"item": {"type": "agent_message", "id": "m-1", "rawText": "done"}}
+    })));
+    entries.push(CassetteEntry::read(json!({
+        "jsonrpc": "2.0", "method": "turn/completed",
+        "params": {"threadId": "t-1", "usage": {}}
+    })));
+    entries
+}
+
+#[tokio::test]
+async fn test_should_deliver_long_turns_to_slow_and_late_subscribers() {
+    let chunks = 1_000;
+    let replay = ReplayTransport::from_entries(codex_turn_cassette(chunks));
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .include_partial_messages(true)
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("go", "default").await.expect("query failed");
+    let mut early = client.receive_messages();
+
+    // Let the whole turn arrive before anyone reads it; `late` only
+    // subscribes afterwards.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let late: Vec<Message> = client
+        .receive_response()
+        .map(|m| m.expect("late subscriber should not lag"))
+        .collect()
+        .await;
+    let mut early_count = 0;
+    loop {
+        let msg = early.next().await.expect("stream ended early");
+        early_count += 1;
+        if matches!(
+            msg.expect("early subscriber should not lag"),
+            Message::Result(_)
+        ) {
+            break;
+        }
+    }
+    drop(early);
+    client.disconnect().await.expect("disconnect failed");
+
+    // message_start, block start, one delta per chunk, block stop,
+    // message_delta, message_stop, the assistant message and the result.
+    let expected = chunks + 7;
+    assert_eq!(late.len(), expected);
+    assert_eq!(early_count, expected);
+    assert!(matches!(late.last(), Some(Message::Result(_))));
+}
+
+#[tokio::test]
+async fn test_should_not_return_a_stale_result_to_a_repeated_receive_response() {
+    let mut entries = codex_turn_cassette(0);
+    entries.insert(
+        entries.len() - 2,
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 4, "method": "turn/start",
+            "params": {"threadId": "t-1", "input": [{"role": "user", "content": "again"}]}
+        })),
+    );
+    entries.push(CassetteEntry::read(json!({
+        "jsonrpc": "2.0", "method": "item/completed",
+        "params": {"item": {"type": "agent_message", "id": "m-2", "rawText": "done again"}}
+    })));
+    entries.push(CassetteEntry::read(json!({
+        "jsonrpc": "2.0", "method": "turn/completed",
+        "params": {"threadId": "t-1", "usage": {}}
+    })));
+    let replay = ReplayTransport::from_entries(entries);
+    let options = AgentOptions::builder().backend(BackendKind::Codex).build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("go", "default").await.expect("query failed");
+    client
+        .query("again", "default")
+        .await
+        .expect("query failed");
+
+    let text = |messages: &[Message]| {
+        messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Assistant(a) => Some(format!("{:?}", a.content)),
+                _ => None,
+            })
+            .collect::<String>()
+    };
+    let first: Vec<Message> = client
+        .receive_response()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(text(&first).contains("done"));
+    assert!(!text(&first).contains("again"));
+
+    // Both turns' results are already in; the repeat gets the second one.
+    let second: Vec<Message> = client
+        .receive_response()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(text(&second).contains("done again"), "{second:?}");
+    assert!(matches!(second.last(), Some(Message::Result(_))));
+
+    // Nothing is left to replay.
+    let third =
+        tokio::time::timeout(Duration::from_millis(100), client.receive_response().next()).await;
+    assert!(matches!(third, Err(_) | Ok(None)), "{third:?}");
+    client.disconnect().await.expect("disconnect failed");
+}
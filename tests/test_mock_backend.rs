@@ -0,0 +1,172 @@
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, ContentBlock, Error, Message, MockCall, MockScript,
+    MockTurn, Prompt, RecordedPrompt, query,
+};
+use futures::StreamExt;
+use serde_json::json;
+
+fn mock_options(script: MockScript) -> AgentOptions {
+    AgentOptions::builder()
+        .backend(BackendKind::Mock)
+        .mock(script)
+        .build()
+}
+
+fn assistant_text(msg: &Message) -> Option<String> {
+    let Message::Assistant(a) = msg else {
+        return None;
+    };
+    a.content.iter().find_map(|block| match block {
+        ContentBlock::Text(t) => Some(t.text.clone()),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn test_should_replay_first_turn_for_one_shot_query() {
+    let script = MockScript::new().turn(MockTurn::new().text("Hi there").result("s-1"));
+    let recorder = script.recorder();
+
+    let messages: Vec<_> = query("Hello", Some(mock_options(script)))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("mock query failed");
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(assistant_text(&messages[0]).as_deref(), Some("Hi there"));
+    match &messages[1] {
+        Message::Result(r) => assert_eq!(r.session_id, "s-1"),
+        other => panic!("expected result, got {:?}", other),
+    }
+    assert_eq!(
+        recorder.prompts(),
+        vec![RecordedPrompt::Text("Hello".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn test_should_play_one_turn_per_client_query() {
+    let script = MockScript::new()
+        .turn(MockTurn::new().text("first").result("s-1"))
+        .turn(
+            MockTurn::new()
+                .tool_use("tool-1", "Bash", json!({"command": "ls"}))
+                .text("second")
+                .result("s-1"),
+        );
+    let recorder = script.recorder();
+
+    let mut client = AgentSdkClient::new(Some(mock_options(script)), None);
+    client.connect(None).await.expect("connect failed");
+
+    client.query("one", "default").await.expect("query failed");
+    let turn1: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(turn1.len(), 2);
+    assert_eq!(
+        assistant_text(turn1[0].as_ref().unwrap()).as_deref(),
+        Some("first")
+    );
+
+    client.query("two", "s-1").await.expect("query failed");
+    let turn2: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(turn2.len(), 3);
+    assert!(matches!(turn2.last(), Some(Ok(Message::Result(_)))));
+
+    client.disconnect().await.expect("disconnect failed");
+
+    let calls = recorder.calls();
+    assert_eq!(calls.len(), 2);
+    match &calls[1] {
+        MockCall::SendMessage { prompt, session_id } => {
+            assert_eq!(prompt, &RecordedPrompt::Text("two".to_string()));
+            assert_eq!(session_id, "s-1");
+        }
+        other => panic!("expected send_message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_should_record_control_requests_and_return_scripted_responses() {
+    let script = MockScript::new()
+        .control_response("mcp_status", json!({"mcpServers": []}))
+        .server_info(json!({"commands": []}));
+    let recorder = script.recorder();
+
+    let mut client = AgentSdkClient::new(Some(mock_options(script)), None);
+    client.connect(None).await.expect("connect failed");
+
+    client.interrupt().await.expect("interrupt failed");
+    client
+        .set_model(Some("mock-model"))
+        .await
+        .expect("set_model failed");
+    let status = client.get_mcp_status().await.expect("mcp status failed");
+    assert_eq!(status, json!({"mcpServers": []}));
+    assert_eq!(
+        client.get_server_info().await.unwrap(),
+        Some(json!({"commands": []}))
+    );
+
+    let subtypes: Vec<_> = recorder
+        .control_requests()
+        .iter()
+        .map(|r| r["subtype"].as_str().unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(subtypes, vec!["interrupt", "set_model", "mcp_status"]);
+}
+
+#[tokio::test]
+async fn test_should_record_stream_prompt_sent_on_connect() {
+    let script = MockScript::new().turn(MockTurn::new().text("streamed").result("s-1"));
+    let recorder = script.recorder();
+    let input = json!({"type": "user", "message": {"role": "user", "content": "hi"}});
+
+    let mut client = AgentSdkClient::new(Some(mock_options(script)), None);
+    client
+        .connect(Some(Prompt::Stream(Box::pin(futures::stream::iter(vec![
+            input.clone(),
+        ])))))
+        .await
+        .expect("connect failed");
+
+    let messages: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        recorder.prompts(),
+        vec![RecordedPrompt::Stream(vec![input])]
+    );
+}
+
+#[tokio::test]
+async fn test_should_surface_scripted_errors_and_exhaustion() {
+    let script = MockScript::new().turn(MockTurn::new().error("boom").result("s-1"));
+
+    let mut client = AgentSdkClient::new(Some(mock_options(script)), None);
+    client.connect(None).await.expect("connect failed");
+
+    client.query("one", "default").await.unwrap();
+    let turn1: Vec<_> = client.receive_response().collect().await;
+    assert!(matches!(&turn1[0], Err(Error::Other(e)) if e == "boom"));
+    assert!(matches!(&turn1[1], Ok(Message::Result(_))));
+
+    client.query("two", "default").await.unwrap();
+    let next = client.receive_messages().next().await;
+    assert!(
+        matches!(&next, Some(Err(Error::Other(e))) if e.contains("exhausted")),
+        "unexpected: {next:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_should_reject_mock_backend_without_script() {
+    let options = AgentOptions::builder().backend(BackendKind::Mock).build();
+
+    let mut client = AgentSdkClient::new(Some(options.clone()), None);
+    let err = client.connect(None).await.unwrap_err();
+    assert!(err.to_string().contains("mock"), "unexpected error: {err}");
+
+    let results: Vec<_> = query("Hello", Some(options)).collect().await;
+    assert!(matches!(results.as_slice(), [Err(Error::Other(_))]));
+}
@@ -0,0 +1,193 @@
+use code_agent_sdk::{
+    AgentOptions, BackendKind, Error, McpServerConfig, McpServersConfig, PermissionMode,
+    ProfileLoader,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const PROFILES: &str = r#"
+model = "claude-sonnet-4-5"
+allowed_tools = ["Read", "Grep"]
+max_turns = 5
+
+[sandbox]
+enabled = true
+
+[sandbox.network]
+allowLocalBinding = true
+
+[mcp_servers.calc]
+type = "stdio"
+command = "npx"
+args = ["calc-server"]
+
+[profiles.review]
+permission_mode = "plan"
+timeouts = { turn_ms = 600000 }
+
+[profiles.review.sandbox]
+autoAllowBashIfSandboxed = true
+
+[profiles.ci]
+backend = "codex"
+sandbox = {}
+codex = { sandbox_mode = "read-only" }
+"#;
+
+/// Write `contents` to a file unique to this test.
+fn profile_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("code-agent-sdk-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn no_env() -> [(&'static str, &'static str); 0] {
+    []
+}
+
+fn config_error(result: code_agent_sdk::Result<AgentOptions>) -> String {
+    match result {
+        Err(Error::Config(message)) => message,
+        other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_should_layer_base_profile_and_environment() {
+    let path = profile_file("layers.toml", PROFILES);
+
+    let options = ProfileLoader::new()
+        .file(&path)
+        .profile("review")
+        .env([
+            ("CODE_AGENT_MODEL", "claude-opus-4-1"),
+            ("CODE_AGENT_ALLOWED_TOOLS", "Read, Edit,"),
+            ("CODE_AGENT_MAX_TURNS", "12"),
+            ("HOME", "/root"),
+        ])
+        .load()
+        .unwrap();
+
+    assert_eq!(options.model.as_deref(), Some("claude-opus-4-1"));
+    assert_eq!(options.allowed_tools, ["Read", "Edit"]);
+    assert_eq!(options.max_turns, Some(12));
+    assert_eq!(options.permission_mode, Some(PermissionMode::Plan));
+    assert_eq!(options.timeouts.turn, Some(Duration::from_secs(600)));
+    // Tables merge key by key.
+    let sandbox = options.sandbox.unwrap();
+    assert_eq!(sandbox.enabled, Some(true));
+    assert_eq!(sandbox.auto_allow_bash_if_sandboxed, Some(true));
+    assert_eq!(sandbox.network.unwrap().allow_local_binding, Some(true));
+    let Some(McpServersConfig::Dict(servers)) = options.mcp_servers else {
+        panic!("expected MCP servers");
+    };
+    assert!(matches!(&servers["calc"], McpServerConfig::Stdio(c) if c.command == "npx"));
+}
+
+#[test]
+fn test_should_select_profile_from_environment() {
+    let path = profile_file("env-profile.toml", PROFILES);
+
+    let options = ProfileLoader::new()
+        .file(&path)
+        .env([("CODE_AGENT_PROFILE", "ci")])
+        .load()
+        .unwrap();
+
+    assert_eq!(options.backend, Some(BackendKind::Codex));
+    assert_eq!(
+        options.codex.unwrap().sandbox_mode.as_deref(),
+        Some("read-only")
+    );
+    assert_eq!(options.model.as_deref(), Some("claude-sonnet-4-5"));
+}
+
+#[test]
+fn test_should_load_json_profiles() {
+    let path = profile_file(
+        "profiles.json",
+        r#"{"model": "gpt-5", "profiles": {"fast": {"effort": "low"}}}"#,
+    );
+
+    let options = ProfileLoader::new()
+        .file(&path)
+        .profile("fast")
+        .env(no_env())
+        .load()
+        .unwrap();
+
+    assert_eq!(options.model.as_deref(), Some("gpt-5"));
+    assert!(options.effort.is_some());
+}
+
+#[test]
+fn test_should_report_unknown_keys_with_their_path() {
+    let path = profile_file("unknown-key.toml", "modle = \"opus\"\n");
+    let message = config_error(ProfileLoader::new().file(&path).env(no_env()).load());
+    assert!(message.contains("unknown field `modle`"), "{}", message);
+    assert!(message.contains("unknown-key.toml"), "{}", message);
+
+    let path = profile_file(
+        "unknown-nested-key.toml",
+        "[profiles.ci.cursor]\nforce = true\n",
+    );
+    let message = config_error(
+        ProfileLoader::new()
+            .file(&path)
+            .profile("ci")
+            .env(no_env())
+            .load(),
+    );
+    assert!(message.contains("profile 'ci'"), "{}", message);
+    assert!(message.contains("cursor"), "{}", message);
+    assert!(message.contains("unknown field `force`"), "{}", message);
+}
+
+#[test]
+fn test_should_reject_unknown_profiles_and_overrides() {
+    let path = profile_file("errors.toml", PROFILES);
+
+    let message = config_error(
+        ProfileLoader::new()
+            .file(&path)
+            .profile("nightly")
+            .env(no_env())
+            .load(),
+    );
+    assert!(
+        message.contains("no profile 'nightly' (available: ci, review)"),
+        "{}",
+        message
+    );
+
+    let message = config_error(
+        ProfileLoader::new()
+            .file(&path)
+            .env([("CODE_AGENT_MODLE", "opus")])
+            .load(),
+    );
+    assert!(message.contains("CODE_AGENT_MODLE"), "{}", message);
+
+    let message = config_error(
+        ProfileLoader::new()
+            .file(&path)
+            .env([("CODE_AGENT_MAX_TURNS", "many")])
+            .load(),
+    );
+    assert!(message.contains("expected a number"), "{}", message);
+}
+
+#[test]
+fn test_should_validate_options_against_backend() {
+    let path = profile_file(
+        "unsupported.toml",
+        "backend = \"codex\"\nsystem_prompt = \"Be terse\"\n",
+    );
+
+    let err = AgentOptions::from_file(&path).unwrap_err();
+
+    assert!(matches!(
+        err,
+        Error::UnsupportedOptions { ref options, .. } if options == &["system_prompt"]
+    ));
+}
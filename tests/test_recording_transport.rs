@@ -0,0 +1,224 @@
+mod common;
+
+use code_agent_sdk::transport::{
+    CassetteEntry, Direction, RecordingTransport, ReplayTransport, Transport, read_cassette,
+};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, ContentBlock, Message, PermissionResult, PermissionResultAllow,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::{claude_initialize, control_response};
+
+fn initialize_entries() -> Vec<CassetteEntry> {
+    claude_initialize(json!({"commands": []}))
+}
+
+fn user_message(prompt: &str) -> Value {
+    json!({
+        "type": "user",
+        "session_id": "default",
+        "message": {"role": "user", "content": prompt},
+        "parent_tool_use_id": null
+    })
+}
+
+fn assistant_message(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "content": [{"type": "text", "text": text}],
+            "model": "claude-sonnet-4-5"
+        },
+        "parent_tool_use_id": null
+    })
+}
+
+fn result_message() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 10,
+        "duration_api_ms": 5,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "session_123"
+    })
+}
+
+fn weather_cassette() -> Vec<CassetteEntry> {
+    let mut entries = initialize_entries();
+    entries.extend([
+        CassetteEntry::write(user_message("What's the weather?")),
+        CassetteEntry::read(assistant_message("Sunny")),
+        CassetteEntry::read(result_message()),
+    ]);
+    entries
+}
+
+async fn connected_client(
+    options: Option<AgentOptions>,
+    mut transport: impl Transport + 'static,
+) -> AgentSdkClient {
+    transport.connect().await.expect("transport connect failed");
+    let mut client = AgentSdkClient::new(options, Some(Box::new(transport)));
+    client.connect(None).await.expect("connect failed");
+    client
+}
+
+async fn run_turn(client: &mut AgentSdkClient, prompt: &str) -> Vec<Message> {
+    client.query(prompt, "default").await.expect("query failed");
+    client
+        .receive_response()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("receive_response failed")
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock before UNIX_EPOCH")
+        .as_nanos();
+    std::env::temp_dir().join(format!("{name}-{}-{nanos}.jsonl", std::process::id()))
+}
+
+#[tokio::test]
+async fn test_should_replay_cassette_through_client() {
+    let replay = ReplayTransport::from_entries(weather_cassette());
+    let verifier = replay.verifier();
+    let mut client = connected_client(None, replay).await;
+
+    assert_eq!(
+        client.get_server_info().await.unwrap(),
+        Some(json!({"commands": []}))
+    );
+    let messages = run_turn(&mut client, "What's the weather?").await;
+    client.disconnect().await.expect("disconnect failed");
+
+    assert_eq!(messages.len(), 2);
+    match &messages[0] {
+        Message::Assistant(a) => {
+            assert!(matches!(&a.content[0], ContentBlock::Text(t) if t.text == "Sunny"))
+        }
+        other => panic!("expected assistant, got {:?}", other),
+    }
+    assert!(matches!(&messages[1], Message::Result(r) if r.session_id == "session_123"));
+    verifier.verify().expect("replay should match");
+}
+
+#[tokio::test]
+async fn test_should_report_mismatched_writes() {
+    let replay = ReplayTransport::from_entries(weather_cassette());
+    let verifier = replay.verifier();
+    let mut client = connected_client(None, replay).await;
+
+    run_turn(&mut client, "Something else").await;
+    client.disconnect().await.expect("disconnect failed");
+
+    let mismatches = verifier.mismatches();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].write_index, 1);
+    assert_eq!(
+        mismatches[0].expected,
+        Some(user_message("What's the weather?"))
+    );
+    assert_eq!(mismatches[0].actual, user_message("Something else"));
+    assert!(verifier.verify().is_err());
+}
+
+#[tokio::test]
+async fn test_should_report_unsent_writes() {
+    let replay = ReplayTransport::from_entries(weather_cassette());
+    let verifier = replay.verifier();
+    let mut client = connected_client(None, replay).await;
+    client.disconnect().await.expect("disconnect failed");
+
+    assert!(verifier.mismatches().is_empty());
+    assert_eq!(
+        verifier.pending_writes(),
+        vec![user_message("What's the weather?")]
+    );
+    let err = verifier.verify().unwrap_err();
+    assert!(err.to_string().contains("never sent"), "{err}");
+}
+
+#[tokio::test]
+async fn test_should_record_traffic_to_cassette() {
+    let path = temp_path("cassette-record");
+    let recording =
+        RecordingTransport::new(ReplayTransport::from_entries(weather_cassette()), &path)
+            .expect("create recording");
+    let mut client = connected_client(None, recording).await;
+    run_turn(&mut client, "What's the weather?").await;
+    client.disconnect().await.expect("disconnect failed");
+
+    let recorded = read_cassette(&path).expect("read cassette");
+    let _ = std::fs::remove_file(&path);
+
+    let strip = |entries: &[CassetteEntry]| -> Vec<(Direction, Value)> {
+        entries
+            .iter()
+            .map(|e| (e.direction, e.data.clone()))
+            .collect()
+    };
+    assert_eq!(strip(&recorded), strip(&weather_cassette()));
+    assert!(
+        recorded
+            .windows(2)
+            .all(|w| w[0].elapsed_ms <= w[1].elapsed_ms)
+    );
+
+    // The recorded cassette replays cleanly.
+    let replay = ReplayTransport::from_entries(recorded);
+    let verifier = replay.verifier();
+    let mut client = connected_client(None, replay).await;
+    run_turn(&mut client, "What's the weather?").await;
+    client.disconnect().await.expect("disconnect failed");
+    verifier.verify().expect("recorded cassette should replay");
+}
+
+#[tokio::test]
+async fn test_should_replay_permission_control_request() {
+    let mut entries = initialize_entries();
+    entries.extend([
+        CassetteEntry::write(user_message("List files")),
+        CassetteEntry::read(json!({
+            "type": "control_request",
+            "request_id": "cli_req_0",
+            "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {"command": "ls"}}
+        })),
+        CassetteEntry::write(control_response(
+            "cli_req_0",
+            json!({"behavior": "allow", "updatedInput": {"command": "ls -la"}}),
+        )),
+        CassetteEntry::read(assistant_message("a.txt")),
+        CassetteEntry::read(result_message()),
+    ]);
+
+    let options = AgentOptions::builder()
+        .can_use_tool(Arc::new(|_tool, _input, _ctx| {
+            Box::pin(async move {
+                PermissionResult::Allow(PermissionResultAllow {
+                    updated_input: Some(json!({"command": "ls -la"})),
+                    updated_permissions: None,
+                })
+            })
+        }))
+        .build();
+
+    let replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    let mut client = connected_client(Some(options), replay).await;
+    let messages = run_turn(&mut client, "List files").await;
+    client.disconnect().await.expect("disconnect failed");
+
+    assert_eq!(messages.len(), 2);
+    verifier.verify().expect("control response should match");
+}
@@ -0,0 +1,163 @@
+use code_agent_sdk::{
+    AgentOptions, BackendKind, McpServerConfig, McpServersConfig, McpStdioConfig, Message,
+    PermissionMode, PermissionResult, PermissionResultAllow, SandboxSettings, TimeoutConfig,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_should_round_trip_messages_in_wire_format() {
+    let wire = [
+        json!({
+            "type": "user",
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "tu-1", "content": "ok", "is_error": false}
+            ]},
+            "uuid": "u-1",
+            "parent_tool_use_id": null,
+            "tool_use_result": {"stdout": "ok"}
+        }),
+        json!({
+            "type": "user",
+            "message": {"role": "user", "content": "Hello"},
+            "parent_tool_use_id": "tu-0"
+        }),
+        json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+                {"type": "tool_use", "id": "tu-1", "name": "Bash", "input": {"command": "ls"}},
+                {"type": "text", "text": "Done"}
+            ], "model": "claude-sonnet"},
+            "parent_tool_use_id": null,
+            "error": "rate_limit"
+        }),
+        json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "s-1",
+            "tools": ["Bash"]
+        }),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1200,
+            "duration_api_ms": 900,
+            "is_error": false,
+            "num_turns": 2,
+            "session_id": "s-1",
+            "total_cost_usd": 0.25,
+            "usage": {
+                "input_tokens": 100,
+                "output_tokens": 50,
+                "cache_read_input_tokens": 10,
+                "cache_creation_input_tokens": 5,
+                "reasoning_output_tokens": 7
+            },
+            "result": "Done"
+        }),
+        json!({
+            "type": "stream_event",
+            "uuid": "e-1",
+            "session_id": "s-1",
+            "event": {"type": "message_stop"},
+            "parent_tool_use_id": null
+        }),
+    ];
+
+    for data in wire {
+        let message: Message = serde_json::from_value(data.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&message).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_should_reject_unknown_message_type() {
+    let err = serde_json::from_value::<Message>(json!({"type": "telemetry"})).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unknown message type \"telemetry\"")
+    );
+
+    let err = serde_json::from_value::<Message>(json!({"type": "result"})).unwrap_err();
+    assert!(err.to_string().contains("missing 'subtype'"));
+}
+
+#[test]
+fn test_should_round_trip_options_and_reattach_callbacks() {
+    let mut servers = HashMap::new();
+    servers.insert(
+        "calc".to_string(),
+        McpServerConfig::Stdio(McpStdioConfig {
+            command: "npx".to_string(),
+            args: Some(vec!["calc-server".to_string()]),
+            env: None,
+        }),
+    );
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .model("gpt-5")
+        .permission_mode(PermissionMode::AcceptEdits)
+        .allowed_tools(["Read", "Bash"])
+        .env("RUST_LOG", "debug")
+        .mcp_servers(McpServersConfig::Dict(servers))
+        .sandbox(SandboxSettings {
+            enabled: Some(true),
+            ..Default::default()
+        })
+        .timeouts(TimeoutConfig {
+            turn: Some(Duration::from_secs(300)),
+            ..Default::default()
+        })
+        .can_use_tool(Arc::new(|_, _, _| {
+            Box::pin(async {
+                PermissionResult::Allow(PermissionResultAllow {
+                    updated_input: None,
+                    updated_permissions: None,
+                })
+            })
+        }))
+        .stderr(|_| {})
+        .build();
+
+    let value = serde_json::to_value(&options).unwrap();
+    assert_eq!(value["backend"], "codex");
+    assert_eq!(value["timeouts"]["turn_ms"], 300_000);
+    assert_eq!(value["timeouts"]["initialize_ms"], 60_000);
+    assert_eq!(
+        value["mcp_servers"]["calc"],
+        json!({"type": "stdio", "command": "npx", "args": ["calc-server"]})
+    );
+    assert_eq!(value["sandbox"]["enabled"], true);
+    assert!(value.get("can_use_tool").is_none());
+
+    let restored: AgentOptions = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&restored).unwrap(), value);
+    assert_eq!(restored.backend, Some(BackendKind::Codex));
+    assert_eq!(restored.timeouts.turn, Some(Duration::from_secs(300)));
+    assert!(restored.can_use_tool.is_none());
+    assert!(restored.stderr.is_none());
+
+    let reattached = restored
+        .into_builder()
+        .can_use_tool(options.can_use_tool.clone().unwrap())
+        .build();
+    assert!(reattached.can_use_tool.is_some());
+    assert_eq!(reattached.model.as_deref(), Some("gpt-5"));
+}
+
+#[test]
+fn test_should_fill_missing_options_with_defaults() {
+    let options: AgentOptions =
+        serde_json::from_value(json!({"model": "opus", "timeouts": {"idle_ms": 500}})).unwrap();
+
+    assert_eq!(options.model.as_deref(), Some("opus"));
+    assert_eq!(options.timeouts.idle, Some(Duration::from_millis(500)));
+    assert_eq!(
+        options.timeouts.initialize,
+        TimeoutConfig::default().initialize
+    );
+    assert!(options.allowed_tools.is_empty());
+}
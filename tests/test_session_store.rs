@@ -0,0 +1,198 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, ReplayTransport, Transport};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, Error, JsonlSessionStore, Message, MockScript,
+    MockTurn, SessionStore, UserContent, query,
+};
+use futures::StreamExt;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::claude_initialize;
+
+/// A store in a directory unique to this test.
+fn store(name: &str) -> Arc<JsonlSessionStore> {
+    let dir: PathBuf = std::env::temp_dir().join(format!(
+        "code-agent-sdk-{}-transcripts-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    Arc::new(JsonlSessionStore::new(dir))
+}
+
+fn summary(message: &Message) -> String {
+    match message {
+        Message::User(u) => match &u.content {
+            UserContent::String(text) => format!("user:{}", text),
+            UserContent::Blocks(_) => "user:<blocks>".to_string(),
+        },
+        Message::Assistant(_) => "assistant".to_string(),
+        Message::System(s) => format!("system:{}", s.subtype),
+        Message::Result(r) => format!("result:{}", r.session_id),
+        Message::StreamEvent(_) => "stream_event".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_should_record_client_session_including_unread_turns() {
+    let store = store("client");
+    let script = MockScript::new()
+        .turn(MockTurn::new().text("first").result("s-1"))
+        .turn(MockTurn::new().text("second").result("s-1"));
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Mock)
+        .mock(script)
+        .session_store(store.clone())
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), None);
+    assert!(client.transcript_id().is_none());
+    client.connect(None).await.unwrap();
+    let transcript_id = client.transcript_id().unwrap().to_string();
+
+    client.query("one", "default").await.unwrap();
+    let turn: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(turn.len(), 2);
+    // The second turn is never read but still recorded.
+    client.query("two", "s-1").await.unwrap();
+    client.disconnect().await.unwrap();
+
+    assert_eq!(store.list().await.unwrap(), vec![transcript_id.clone()]);
+    let entries = store.load(&transcript_id).await.unwrap();
+    let recorded: Vec<_> = entries
+        .iter()
+        .map(|e| (summary(&e.message), e.session_id.as_deref()))
+        .collect();
+    assert_eq!(
+        recorded,
+        [
+            ("user:one".to_string(), None),
+            ("assistant".to_string(), None),
+            ("result:s-1".to_string(), Some("s-1")),
+            ("user:two".to_string(), Some("s-1")),
+            ("assistant".to_string(), Some("s-1")),
+            ("result:s-1".to_string(), Some("s-1")),
+        ]
+    );
+    assert!(entries.iter().all(|e| e.backend == BackendKind::Mock));
+    assert!(
+        entries
+            .windows(2)
+            .all(|w| w[0].timestamp_ms <= w[1].timestamp_ms)
+    );
+}
+
+#[tokio::test]
+async fn test_should_record_claude_session_id_from_init() {
+    let store = store("claude");
+    let mut entries = claude_initialize(json!({}));
+    entries.extend([
+        CassetteEntry::write(json!({
+            "type": "user",
+            "session_id": "default",
+            "message": {"role": "user", "content": "Hello"},
+            "parent_tool_use_id": null
+        })),
+        CassetteEntry::read(
+            json!({"type": "system", "subtype": "init", "session_id": "session_123"}),
+        ),
+        CassetteEntry::read(json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": "Hi"}], "model": "claude"},
+            "parent_tool_use_id": null
+        })),
+        CassetteEntry::read(json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 10,
+            "duration_api_ms": 5,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "session_123"
+        })),
+    ]);
+    let mut replay = ReplayTransport::from_entries(entries);
+    replay.connect().await.unwrap();
+    let options = AgentOptions::builder().session_store(store.clone()).build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.unwrap();
+    let transcript_id = client.transcript_id().unwrap().to_string();
+    client.query("Hello", "default").await.unwrap();
+    let turn: Vec<_> = client.receive_response().collect().await;
+    assert_eq!(turn.len(), 3);
+    let started = Instant::now();
+    client.disconnect().await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let entries = store.load(&transcript_id).await.unwrap();
+    let recorded: Vec<_> = entries
+        .iter()
+        .map(|e| (summary(&e.message), e.session_id.as_deref()))
+        .collect();
+    assert_eq!(
+        recorded,
+        [
+            ("user:Hello".to_string(), None),
+            ("system:init".to_string(), Some("session_123")),
+            ("assistant".to_string(), Some("session_123")),
+            ("result:session_123".to_string(), Some("session_123")),
+        ]
+    );
+    assert!(entries.iter().all(|e| e.backend == BackendKind::Claude));
+}
+
+#[tokio::test]
+async fn test_should_record_one_shot_query() {
+    let store = store("query");
+    let script = MockScript::new().turn(MockTurn::new().text("Hi there").result("s-2"));
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Mock)
+        .mock(script)
+        .resume("s-2")
+        .session_store(store.clone())
+        .build();
+
+    let messages: Vec<_> = query("Hello", Some(options)).collect().await;
+    assert_eq!(messages.len(), 2);
+
+    let ids = store.list().await.unwrap();
+    assert_eq!(ids.len(), 1);
+    let entries = store.load(&ids[0]).await.unwrap();
+    let recorded: Vec<_> = entries.iter().map(|e| summary(&e.message)).collect();
+    assert_eq!(recorded, ["user:Hello", "assistant", "result:s-2"]);
+    // Resumed sessions are known from the first message.
+    assert!(
+        entries
+            .iter()
+            .all(|e| e.session_id.as_deref() == Some("s-2"))
+    );
+}
+
+#[tokio::test]
+async fn test_should_report_malformed_transcripts_and_ids() {
+    let store = store("malformed");
+    std::fs::create_dir_all(store.dir()).unwrap();
+    std::fs::write(
+        store.dir().join("broken.jsonl"),
+        "{\"backend\":\"claude\",\"session_id\":null,\"timestamp_ms\":1,\
+         \"message\":{\"type\":\"user\",\"message\":{\"role\":\"user\",\"content\":\"hi\"}}}\n\
+         not json\n",
+    )
+    .unwrap();
+    std::fs::write(store.dir().join("notes.txt"), "ignored").unwrap();
+
+    assert_eq!(store.list().await.unwrap(), ["broken"]);
+    match store.load("broken").await {
+        Err(Error::MessageParse(message)) => {
+            assert!(message.contains("broken.jsonl:2"), "{}", message)
+        }
+        other => panic!("expected a parse error, got {:?}", other.map(|e| e.len())),
+    }
+    assert!(store.load("../broken").await.is_err());
+    assert!(store.load("missing").await.is_err());
+}
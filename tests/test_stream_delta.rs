@@ -0,0 +1,227 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, ReplayTransport};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, Message, StreamBlockStart, StreamDelta,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+
+use common::codex_handshake;
+
+fn notification(method: &str, params: Value) -> CassetteEntry {
+    CassetteEntry::read(json!({"jsonrpc": "2.0", "method": method, "params": params}))
+}
+
+/// A Codex app-server session that streams one reasoning and one message item.
+fn codex_streaming_cassette() -> Vec<CassetteEntry> {
+    let mut entries = codex_handshake();
+    entries.extend([
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {}})),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "t-1"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "t-1", "input": [{"role": "user", "content": "hi"}]}
+        })),
+        notification(
+            "item/reasoning/summaryTextDelta",
+            json!({"threadId": "t-1", "itemId": "r1", "delta": "think"}),
+        ),
+        notification(
+            "item/completed",
+            json!({"threadId": "t-1", "item": {"id": "r1", "type": "reasoning", "text": "think"}}),
+        ),
+        notification(
+            "item/agentMessage/delta",
+            json!({"threadId": "t-1", "itemId": "m1", "delta": "Hel"}),
+        ),
+        notification(
+            "item/agentMessage/delta",
+            json!({"threadId": "t-1", "itemId": "m1", "delta": "lo"}),
+        ),
+        notification(
+            "item/completed",
+            json!({"threadId": "t-1", "item": {"id": "m1", "type": "agent_message", "rawText": "Hello"}}),
+        ),
+        notification(
+            "turn/completed",
+            json!({"threadId": "t-1", "usage": {"input_tokens": 3, "output_tokens": 2}}),
+        ),
+    ]);
+    entries
+}
+
+async fn run_codex_turn(include_partial_messages: bool) -> Vec<Message> {
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .include_partial_messages(include_partial_messages)
+        .build();
+    let replay = ReplayTransport::from_entries(codex_streaming_cassette());
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("hi", "default").await.expect("query failed");
+    let messages = client
+        .receive_response()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("receive_response failed");
+    client.disconnect().await.expect("disconnect failed");
+    messages
+}
+
+#[test]
+fn test_should_parse_claude_stream_events() {
+    let cases = [
+        (
+            json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-5", "content": []}}),
+            StreamDelta::MessageStart {
+                id: Some("msg_1".to_string()),
+                model: Some("claude-sonnet-4-5".to_string()),
+            },
+        ),
+        (
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "tu_1", "name": "Bash", "input": {}}}),
+            StreamDelta::ContentBlockStart {
+                index: 1,
+                block: StreamBlockStart::ToolUse {
+                    id: "tu_1".to_string(),
+                    name: "Bash".to_string(),
+                },
+            },
+        ),
+        (
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+            StreamDelta::TextDelta {
+                index: 0,
+                text: "Hi".to_string(),
+            },
+        ),
+        (
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "Hm"}}),
+            StreamDelta::ThinkingDelta {
+                index: 0,
+                thinking: "Hm".to_string(),
+            },
+        ),
+        (
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"co"}}),
+            StreamDelta::InputJsonDelta {
+                index: 1,
+                partial_json: "{\"co".to_string(),
+            },
+        ),
+        (
+            json!({"type": "content_block_stop", "index": 1}),
+            StreamDelta::ContentBlockStop { index: 1 },
+        ),
+        (
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 12}}),
+            StreamDelta::MessageDelta {
+                stop_reason: Some("tool_use".to_string()),
+                usage: Some(json!({"output_tokens": 12})),
+            },
+        ),
+        (json!({"type": "message_stop"}), StreamDelta::MessageStop),
+    ];
+
+    for (event, expected) in cases {
+        assert_eq!(StreamDelta::from_event(&event).as_ref(), Some(&expected));
+        assert_eq!(
+            StreamDelta::from_event(&expected.to_event()),
+            Some(expected)
+        );
+    }
+    assert_eq!(StreamDelta::from_event(&json!({"type": "ping"})), None);
+}
+
+#[tokio::test]
+async fn test_should_normalize_codex_deltas_into_stream_events() {
+    let messages = run_codex_turn(true).await;
+
+    let deltas: Vec<StreamDelta> = messages
+        .iter()
+        .filter_map(|m| match m {
+            Message::StreamEvent(e) => {
+                assert_eq!(e.session_id, "t-1");
+                Some(e.delta().expect("normalized event should parse"))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        deltas,
+        [
+            StreamDelta::MessageStart {
+                id: None,
+                model: None
+            },
+            StreamDelta::ContentBlockStart {
+                index: 0,
+                block: StreamBlockStart::Thinking {
+                    thinking: String::new()
+                },
+            },
+            StreamDelta::ThinkingDelta {
+                index: 0,
+                thinking: "think".to_string()
+            },
+            StreamDelta::ContentBlockStop { index: 0 },
+            StreamDelta::ContentBlockStart {
+                index: 1,
+                block: StreamBlockStart::Text {
+                    text: String::new()
+                },
+            },
+            StreamDelta::TextDelta {
+                index: 1,
+                text: "Hel".to_string()
+            },
+            StreamDelta::TextDelta {
+                index: 1,
+                text: "lo".to_string()
+            },
+            StreamDelta::ContentBlockStop { index: 1 },
+            StreamDelta::MessageDelta {
+                stop_reason: Some("end_turn".to_string()),
+                // Backend usage is normalized to the `Usage` field names.
+                usage: Some(json!({
+                    "input_tokens": 3,
+                    "output_tokens": 2,
+                    "cache_read_input_tokens": 0,
+                    "cache_creation_input_tokens": 0,
+                    "reasoning_output_tokens": 0
+                })),
+            },
+            StreamDelta::MessageStop,
+        ]
+    );
+    // Completed items still arrive as whole assistant messages.
+    let assistant = messages
+        .iter()
+        .filter(|m| matches!(m, Message::Assistant(_)))
+        .count();
+    assert_eq!(assistant, 2);
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+}
+
+#[tokio::test]
+async fn test_should_drop_codex_deltas_without_partial_messages() {
+    let messages = run_codex_turn(false).await;
+
+    assert!(
+        !messages
+            .iter()
+            .any(|m| matches!(m, Message::StreamEvent(_)))
+    );
+    let kinds: Vec<_> = messages
+        .iter()
+        .map(|m| match m {
+            Message::Assistant(_) => "assistant",
+            Message::Result(_) => "result",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(kinds, ["assistant", "assistant", "result"]);
+}
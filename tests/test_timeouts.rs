@@ -0,0 +1,182 @@
+mod common;
+
+use code_agent_sdk::transport::{CassetteEntry, ReplayTransport, Transport};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, Error, Message, MockScript, MockTurn, TimeoutConfig,
+    TurnTimeout,
+};
+use futures::StreamExt;
+use serde_json::json;
+use std::time::Duration;
+
+use common::claude_initialize;
+
+async fn connected_client(options: AgentOptions, mut replay: ReplayTransport) -> AgentSdkClient {
+    replay.connect().await.expect("transport connect failed");
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client
+}
+
+#[tokio::test]
+async fn test_should_interrupt_claude_turn_after_turn_timeout() {
+    let mut entries = claude_initialize(json!({}));
+    entries.extend([
+        CassetteEntry::write(json!({
+            "type": "user",
+            "session_id": "default",
+            "message": {"role": "user", "content": "count forever"},
+            "parent_tool_use_id": null
+        })),
+        CassetteEntry::read(json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": "1, 2, 3"}], "model": "claude"},
+            "parent_tool_use_id": null
+        })),
+        // Only released once the SDK interrupts.
+        CassetteEntry::write(json!({
+            "type": "control_request",
+            "request_id": "req_1_9e3779b1",
+            "request": {"subtype": "interrupt"}
+        })),
+        CassetteEntry::read(json!({
+            "type": "result",
+            "subtype": "error_during_execution",
+            "duration_ms": 200,
+            "duration_api_ms": 150,
+            "is_error": true,
+            "num_turns": 1,
+            "session_id": "session_123"
+        })),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    let options = AgentOptions::builder()
+        .timeouts(TimeoutConfig {
+            turn: Some(Duration::from_millis(200)),
+            ..Default::default()
+        })
+        .build();
+
+    let mut client = connected_client(options, replay).await;
+    client.query("count forever", "default").await.unwrap();
+    let items: Vec<_> = client.receive_response().collect().await;
+
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0], Ok(Message::Assistant(_))));
+    assert!(matches!(
+        items[1],
+        Err(Error::TurnTimeout(TurnTimeout::Turn(limit))) if limit == Duration::from_millis(200)
+    ));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    verifier.verify().expect("interrupt was not sent");
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_should_interrupt_codex_turn_after_idle_timeout() {
+    let entries = vec![
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": {"clientName": "code-agent-sdk", "clientVersion": env!("CARGO_PKG_VERSION")}
+        })),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+        CassetteEntry::write(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}})),
+        CassetteEntry::write(
+            json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {}}),
+        ),
+        CassetteEntry::read(json!({"jsonrpc": "2.0", "id": 2, "result": {"threadId": "t-1"}})),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "t-1", "input": [{"role": "user", "content": "go"}]}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "item/completed",
+            "params": {"item": {"type": "agent_message", "id": "m-1", "rawText": "working"}}
+        })),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 4, "method": "turn/interrupt",
+            "params": {"threadId": "t-1"}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {"threadId": "t-1", "usage": {}}
+        })),
+    ];
+    let replay = ReplayTransport::from_entries(entries);
+    let verifier = replay.verifier();
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .timeouts(TimeoutConfig {
+            turn: Some(Duration::from_secs(30)),
+            idle: Some(Duration::from_millis(200)),
+            ..Default::default()
+        })
+        .build();
+
+    let mut client = connected_client(options, replay).await;
+    client.query("go", "default").await.unwrap();
+    let items: Vec<_> = client.receive_response().collect().await;
+
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0], Ok(Message::Assistant(_))));
+    assert!(matches!(
+        items[1],
+        Err(Error::TurnTimeout(TurnTimeout::Idle(_)))
+    ));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    verifier.verify().expect("interrupt was not sent");
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_should_end_one_shot_query_after_idle_timeout() {
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Mock)
+        .mock(
+            MockScript::new().turn(
+                MockTurn::new()
+                    .text("working")
+                    .delay(Duration::from_secs(30))
+                    .result("s-1"),
+            ),
+        )
+        .timeouts(TimeoutConfig {
+            idle: Some(Duration::from_millis(100)),
+            ..Default::default()
+        })
+        .build();
+
+    let started = std::time::Instant::now();
+    let items: Vec<_> = code_agent_sdk::query("go", Some(options)).collect().await;
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0], Ok(Message::Assistant(_))));
+    assert!(matches!(
+        items[1],
+        Err(Error::TurnTimeout(TurnTimeout::Idle(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_should_time_out_unanswered_control_request() {
+    let mut entries = claude_initialize(json!({}));
+    entries.push(CassetteEntry::write(json!({
+        "type": "control_request",
+        "request_id": "req_1_9e3779b1",
+        "request": {"subtype": "set_model", "model": "opus"}
+    })));
+    let options = AgentOptions::builder()
+        .timeouts(TimeoutConfig {
+            control_request: Duration::from_millis(100),
+            ..Default::default()
+        })
+        .build();
+
+    let mut client = connected_client(options, ReplayTransport::from_entries(entries)).await;
+    let err = client.set_model(Some("opus")).await.unwrap_err();
+
+    assert!(matches!(err, Error::ControlTimeout(ref id) if id == "req_1_9e3779b1"));
+    client.disconnect().await.unwrap();
+}
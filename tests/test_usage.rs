@@ -0,0 +1,180 @@
+mod common;
+
+use async_trait::async_trait;
+use code_agent_sdk::transport::{
+    CassetteEntry, LaunchSpec, ProcessLauncher, ReplayTransport, Transport,
+};
+use code_agent_sdk::{
+    AgentOptions, AgentSdkClient, BackendKind, Message, ModelPrice, PriceTable, ResultMessage,
+    Usage, parse_message, query,
+};
+use futures::StreamExt;
+use serde_json::json;
+use std::sync::Mutex;
+
+use common::codex_handshake;
+
+fn prices() -> PriceTable {
+    PriceTable::new()
+        .price("gpt-5", ModelPrice::new(1.25, 10.0).cache_read(0.125))
+        .price("gpt-5-mini", ModelPrice::new(0.25, 2.0).cache_read(0.025))
+        .price("sonnet-4", ModelPrice::new(3.0, 15.0).cache_read(0.3))
+}
+
+fn last_result(messages: Vec<code_agent_sdk::Result<Message>>) -> ResultMessage {
+    match messages.into_iter().last() {
+        Some(Ok(Message::Result(r))) => r,
+        other => panic!("expected a result, got {other:?}"),
+    }
+}
+
+/// Launcher that replays one cassette without spawning anything.
+#[derive(Debug)]
+struct ReplayLauncher(Mutex<Option<Vec<CassetteEntry>>>);
+
+#[async_trait]
+impl ProcessLauncher for ReplayLauncher {
+    async fn launch(&self, _spec: LaunchSpec) -> code_agent_sdk::Result<Box<dyn Transport + Send>> {
+        let entries = self.0.lock().unwrap().take().unwrap_or_default();
+        let mut transport = ReplayTransport::from_entries(entries);
+        transport.connect().await?;
+        Ok(Box::new(transport))
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_should_price_by_longest_model_prefix() {
+    let usage = Usage {
+        input_tokens: 1_000_000,
+        output_tokens: 100_000,
+        cache_read_input_tokens: 2_000_000,
+        ..Default::default()
+    };
+    let table = prices();
+
+    assert_eq!(table.cost_usd("gpt-5-2025-08-07", &usage), Some(2.5));
+    assert_eq!(table.cost_usd("gpt-5-mini", &usage), Some(0.5));
+    assert_eq!(table.cost_usd("o3", &usage), None);
+    assert_eq!((usage + usage).total_tokens(), 2 * usage.total_tokens());
+}
+
+#[test]
+fn test_should_keep_claude_reported_cost_and_type_usage() {
+    let msg = parse_message(&json!({
+        "type": "result", "subtype": "success", "duration_ms": 10, "duration_api_ms": 8,
+        "is_error": false, "num_turns": 1, "session_id": "s1", "total_cost_usd": 0.042,
+        "usage": {
+            "input_tokens": 12, "output_tokens": 40,
+            "cache_read_input_tokens": 3000, "cache_creation_input_tokens": 500,
+            "service_tier": "standard"
+        }
+    }))
+    .unwrap();
+
+    let Some(Message::Result(result)) = msg else {
+        panic!("expected a result");
+    };
+    assert_eq!(result.total_cost_usd, Some(0.042));
+    assert_eq!(
+        result.usage,
+        Some(Usage {
+            input_tokens: 12,
+            output_tokens: 40,
+            cache_read_input_tokens: 3000,
+            cache_creation_input_tokens: 500,
+            reasoning_output_tokens: 0,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_should_estimate_codex_cost_from_thread_model() {
+    let mut entries = codex_handshake();
+    entries.extend([
+        CassetteEntry::write(
+            json!({"jsonrpc": "2.0", "id": 2, "method": "thread/start", "params": {"model": "gpt-5"}}),
+        ),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "id": 2,
+            "result": {"threadId": "thread-1", "model": "gpt-5-mini-2025-08-07"}
+        })),
+        CassetteEntry::write(json!({
+            "jsonrpc": "2.0", "id": 3, "method": "turn/start",
+            "params": {"threadId": "thread-1", "input": [{"role": "user", "content": "hi"}]}
+        })),
+        CassetteEntry::read(json!({
+            "jsonrpc": "2.0", "method": "turn/completed",
+            "params": {
+                "threadId": "thread-1",
+                "usage": {
+                    "inputTokens": 1_200_000, "cachedInputTokens": 200_000,
+                    "outputTokens": 300_000, "reasoningOutputTokens": 100_000
+                }
+            }
+        })),
+    ]);
+    let replay = ReplayTransport::from_entries(entries);
+    // The thread's reported model overrides the configured one.
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Codex)
+        .model("gpt-5")
+        .price_table(prices())
+        .build();
+
+    let mut client = AgentSdkClient::new(Some(options), Some(Box::new(replay)));
+    client.connect(None).await.expect("connect failed");
+    client.query("hi", "default").await.expect("query failed");
+    let result = last_result(client.receive_response().collect().await);
+    client.disconnect().await.expect("disconnect failed");
+
+    assert_eq!(
+        result.usage,
+        Some(Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 300_000,
+            cache_read_input_tokens: 200_000,
+            cache_creation_input_tokens: 0,
+            reasoning_output_tokens: 100_000,
+        })
+    );
+    let cost = result.total_cost_usd.expect("cost should be estimated");
+    assert!((cost - 0.855).abs() < 1e-9, "{cost}");
+}
+
+#[tokio::test]
+async fn test_should_estimate_cursor_cost_from_init_model() {
+    let launcher = ReplayLauncher(Mutex::new(Some(vec![
+        CassetteEntry::read(
+            json!({"type": "system", "subtype": "init", "chatId": "c1", "model": "sonnet-4.5"}),
+        ),
+        CassetteEntry::read(json!({"type": "assistant", "text": "done"})),
+        CassetteEntry::read(json!({
+            "type": "result", "subtype": "success", "session_id": "c1",
+            "is_error": false, "num_turns": 1,
+            "usage": {"inputTokens": 100_000, "outputTokens": 10_000, "cacheReadTokens": 1_000_000}
+        })),
+    ])));
+    let options = AgentOptions::builder()
+        .backend(BackendKind::Cursor)
+        .launcher(launcher)
+        .price_table(prices())
+        .build();
+
+    let result = last_result(query("hi", Some(options)).collect().await);
+
+    assert_eq!(
+        result.usage,
+        Some(Usage {
+            input_tokens: 100_000,
+            output_tokens: 10_000,
+            cache_read_input_tokens: 1_000_000,
+            ..Default::default()
+        })
+    );
+    let cost = result.total_cost_usd.expect("cost should be estimated");
+    assert!((cost - 0.75).abs() < 1e-9, "{cost}");
+}